@@ -1,15 +1,45 @@
-pub mod error;
-
-use models::types::Role;
+use models::types::{Capability, Resource, Role};
 use serde::{Deserialize, Serialize};
 
 /// User data passed to services through "user" header
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(rename_all = "camelCase")]
 pub struct ReqUser {
     pub id: String,
     pub account_id: String,
-    pub role: models::types::Role,
+    pub role: Role,
+    /// Resources the user can create, check with require_perm
+    #[serde(default)]
+    pub create_perms: Vec<Resource>,
+    #[serde(default)]
+    pub update_perms: Vec<Resource>,
+    #[serde(default)]
+    pub delete_perms: Vec<Resource>,
+    /// What the user's role lets them do, none means the built in capabilities of `role`
+    #[serde(default)]
+    pub capabilities: Option<Vec<Capability>>,
+}
+
+impl ReqUser {
+    pub fn capabilities(&self) -> Vec<Capability> {
+        match &self.capabilities {
+            Some(capabilities) => capabilities.clone(),
+            None => self.role.capabilities(),
+        }
+    }
+}
+
+/// Header internal services use to say which user they are acting for, so the audit log
+/// attributes the change to them
+pub const ACTOR_HEADER: &str = "audit-actor";
+
+/// What a user wants to do to a resource
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    Create,
+    Update,
+    Delete,
 }
 
 /// Takes a value (such as a header) and attempts to deserialize into ReqUser
@@ -29,9 +59,18 @@ pub fn belongs_to_account(req_user: &Option<ReqUser>, expected: &str) -> bool {
     }
 }
 
+/// Account the user's queries should be limited to, none if they can see every account
+pub fn account_scope(req_user: &Option<ReqUser>) -> Option<String> {
+    match req_user {
+        // admin account and internal requests see everything
+        Some(req_user) if req_user.account_id != "admin" => Some(req_user.account_id.clone()),
+        _ => None,
+    }
+}
+
 #[cfg(feature = "actix")]
 impl actix_web::FromRequest for ReqUser {
-    type Error = error::Error;
+    type Error = errors::ApiError;
     type Future = std::future::Ready<Result<Self, Self::Error>>;
 
     fn from_request(
@@ -42,42 +81,58 @@ impl actix_web::FromRequest for ReqUser {
         let value = req.extensions().get::<Option<Self>>().cloned().unwrap();
 
         // convert to result and return
-        std::future::ready(value.ok_or(error::Error {
-            status_code: 200,
-            message: "".into(),
-        }))
+        std::future::ready(value.ok_or_else(errors::ApiError::unauthorized))
     }
 }
 
-/// 0 - any user
-/// 1 - at least moderator
-/// 2 - at least admin
-/// 3 - only owner
-pub fn role_to_number(role: &Role) -> i32 {
-    match role {
-        Role::Owner => 3,
-        Role::Admin => 2,
-        Role::Moderator => 1,
-        Role::User => 0,
+/// Checks if reqUser's role has capability
+pub fn require_cap(req_user: &Option<ReqUser>, capability: Capability) -> bool {
+    match req_user {
+        Some(user) => user.capabilities().contains(&capability),
+        None => true,
     }
 }
 
-/// Checks if reqUser has a role of at least requirement
-pub fn require_role(req_user: &Option<ReqUser>, requirement: Role) -> bool {
+/// Checks if reqUser has every capability given and at least one more,
+/// so nobody can manage a role as powerful as their own
+pub fn outranks(req_user: &Option<ReqUser>, capabilities: &[Capability]) -> bool {
     match req_user {
-        Some(user) => role_to_number(&user.role) >= role_to_number(&requirement),
+        Some(user) => {
+            let own = user.capabilities();
+            capabilities.iter().all(|capability| own.contains(capability))
+                && own.iter().any(|capability| !capabilities.contains(capability))
+        }
         None => true,
     }
 }
 
-/// Checks if reqUser has a role higher than requirement, impossible if requirement is Owner
-pub fn higher_role(req_user: &Option<ReqUser>, requirement: Role) -> bool {
+/// Checks if reqUser has every capability given, for handing out roles or keys no more
+/// powerful than their own
+pub fn covers(req_user: &Option<ReqUser>, capabilities: &[Capability]) -> bool {
     match req_user {
-        Some(user) => role_to_number(&user.role) > role_to_number(&requirement),
+        Some(user) => {
+            let own = user.capabilities();
+            capabilities.iter().all(|capability| own.contains(capability))
+        }
         None => true,
     }
 }
 
+/// Checks if reqUser may do action to resource, roles with AllResources can do anything
+pub fn require_perm(req_user: &Option<ReqUser>, action: Action, resource: Resource) -> bool {
+    match req_user {
+        Some(user) if !require_cap(req_user, Capability::AllResources) => {
+            let perms = match action {
+                Action::Create => &user.create_perms,
+                Action::Update => &user.update_perms,
+                Action::Delete => &user.delete_perms,
+            };
+            perms.contains(&resource)
+        }
+        _ => true,
+    }
+}
+
 #[cfg(feature = "axum")]
 pub struct ExtractReqUser(pub Option<ReqUser>);
 
@@ -87,7 +142,7 @@ impl<B> axum::extract::FromRequest<B> for ExtractReqUser
 where
     B: Send,
 {
-    type Rejection = (axum::http::StatusCode, &'static str);
+    type Rejection = errors::ApiError;
 
     async fn from_request(
         req: &mut axum::extract::RequestParts<B>,
@@ -107,9 +162,66 @@ where
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use models::types::Role;
+
     #[test]
     fn it_works() {
         let result = 2 + 2;
         assert_eq!(result, 4);
     }
+
+    #[test]
+    fn perms() {
+        let user = Some(ReqUser {
+            id: "user".into(),
+            account_id: "account".into(),
+            role: Role::Moderator,
+            create_perms: vec![Resource::Load],
+            update_perms: vec![Resource::Load, Resource::Carrier],
+            delete_perms: vec![],
+            capabilities: None,
+        });
+
+        assert!(require_perm(&user, Action::Create, Resource::Load));
+        assert!(!require_perm(&user, Action::Create, Resource::Carrier));
+        assert!(require_perm(&user, Action::Update, Resource::Carrier));
+        assert!(!require_perm(&user, Action::Delete, Resource::Load));
+
+        // admins don't need perms, internal requests have no user
+        let admin = user.map(|user| ReqUser { role: Role::Admin, ..user });
+        assert!(require_perm(&admin, Action::Delete, Resource::Shipper));
+        assert!(require_perm(&None, Action::Delete, Resource::Shipper));
+    }
+
+    #[test]
+    fn capabilities() {
+        let moderator = Some(ReqUser {
+            id: "user".into(),
+            account_id: "account".into(),
+            role: Role::Moderator,
+            create_perms: vec![],
+            update_perms: vec![],
+            delete_perms: vec![],
+            capabilities: None,
+        });
+
+        // built in roles fall back to their default capabilities
+        assert!(require_cap(&moderator, Capability::ManageUsers));
+        assert!(!require_cap(&moderator, Capability::ManageInstances));
+        assert!(outranks(&moderator, &Role::User.capabilities()));
+        assert!(!outranks(&moderator, &Role::Moderator.capabilities()));
+        assert!(covers(&moderator, &Role::Moderator.capabilities()));
+
+        // custom roles carry their own
+        let dispatcher = moderator.map(|user| ReqUser {
+            capabilities: Some(vec![Capability::ManageInstances]),
+            ..user
+        });
+        assert!(require_cap(&dispatcher, Capability::ManageInstances));
+        assert!(!require_cap(&dispatcher, Capability::ManageUsers));
+        assert!(!outranks(&dispatcher, &Role::Moderator.capabilities()));
+        assert!(!outranks(&dispatcher, &Role::Owner.capabilities()));
+        assert!(require_cap(&None, Capability::TransferOwnership));
+    }
 }
@@ -0,0 +1,227 @@
+use std::fmt::Debug;
+
+use async_trait::async_trait;
+use aws_sdk_elasticbeanstalk::model::{ConfigurationOptionSetting, EnvironmentTier};
+use errors::ApiError;
+
+use crate::{missing, sdk_error};
+
+const SERVICE: &str = "Elastic Beanstalk";
+
+/// What deploys need to know about an environment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Environment {
+    pub id: String,
+    pub name: String,
+    /// Where the environment answers, not there until it has a load balancer
+    pub cname: Option<String>,
+}
+
+/// One option setting, like `DBUser` in `aws:rds:dbinstance`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigOption {
+    pub namespace: String,
+    pub name: String,
+    pub value: String,
+}
+
+impl ConfigOption {
+    pub fn new(namespace: &str, name: &str, value: &str) -> Self {
+        ConfigOption {
+            namespace: namespace.into(),
+            name: name.into(),
+            value: value.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NewEnvironment {
+    pub application_name: String,
+    pub environment_name: String,
+    pub version_label: String,
+    pub solution_stack: String,
+    pub options: Vec<ConfigOption>,
+}
+
+/// Elastic Beanstalk, where instances run
+#[async_trait]
+pub trait Beanstalk: Debug + Send + Sync {
+    /// Labels of the application's versions, newest first
+    async fn version_labels(&self, application: &str) -> Result<Vec<String>, ApiError>;
+
+    async fn solution_stacks(&self) -> Result<Vec<String>, ApiError>;
+
+    /// Starts a web server environment, it keeps coming up after this returns
+    async fn create_environment(&self, env: NewEnvironment) -> Result<Environment, ApiError>;
+
+    async fn find_environment(
+        &self,
+        application: &str,
+        env_id: &str,
+    ) -> Result<Option<Environment>, ApiError>;
+
+    /// The application's environments that aren't terminated
+    async fn environments(&self, application: &str) -> Result<Vec<Environment>, ApiError>;
+
+    /// Deploys the application version to the environment
+    async fn update_environment(&self, env_id: &str, version_label: &str) -> Result<(), ApiError>;
+
+    /// Names of the load balancers in front of the environment
+    async fn load_balancers(&self, env_id: &str) -> Result<Vec<String>, ApiError>;
+
+    /// Force terminates the environment, returning it as it was before
+    async fn terminate_environment(&self, env_id: &str) -> Result<Environment, ApiError>;
+}
+
+fn environment(
+    id: Option<&str>,
+    name: Option<&str>,
+    cname: Option<&str>,
+) -> Result<Environment, ApiError> {
+    Ok(Environment {
+        id: id.ok_or_else(|| missing(SERVICE, "environment id"))?.into(),
+        name: name
+            .ok_or_else(|| missing(SERVICE, "environment name"))?
+            .into(),
+        cname: cname.map(|cname| cname.into()),
+    })
+}
+
+#[async_trait]
+impl Beanstalk for aws_sdk_elasticbeanstalk::Client {
+    async fn version_labels(&self, application: &str) -> Result<Vec<String>, ApiError> {
+        let res = self
+            .describe_application_versions()
+            .application_name(application)
+            .send()
+            .await
+            .map_err(|err| sdk_error(SERVICE, err))?;
+
+        Ok(res
+            .application_versions()
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|version| version.version_label().map(|label| label.into()))
+            .collect())
+    }
+
+    async fn solution_stacks(&self) -> Result<Vec<String>, ApiError> {
+        let res = self
+            .list_available_solution_stacks()
+            .send()
+            .await
+            .map_err(|err| sdk_error(SERVICE, err))?;
+
+        Ok(res.solution_stacks().unwrap_or_default().to_vec())
+    }
+
+    async fn create_environment(&self, env: NewEnvironment) -> Result<Environment, ApiError> {
+        let options = env
+            .options
+            .iter()
+            .map(|option| {
+                ConfigurationOptionSetting::builder()
+                    .namespace(&option.namespace)
+                    .option_name(&option.name)
+                    .value(&option.value)
+                    .build()
+            })
+            .collect();
+
+        let res = self
+            .create_environment()
+            .application_name(env.application_name)
+            .environment_name(env.environment_name)
+            .tier(
+                EnvironmentTier::builder()
+                    .name("WebServer")
+                    .r#type("Standard")
+                    .build(),
+            )
+            .version_label(env.version_label)
+            .solution_stack_name(env.solution_stack)
+            .set_option_settings(Some(options))
+            .send()
+            .await
+            .map_err(|err| sdk_error(SERVICE, err))?;
+
+        environment(res.environment_id(), res.environment_name(), res.cname())
+    }
+
+    async fn find_environment(
+        &self,
+        application: &str,
+        env_id: &str,
+    ) -> Result<Option<Environment>, ApiError> {
+        let res = self
+            .describe_environments()
+            .application_name(application)
+            .environment_ids(env_id)
+            .send()
+            .await
+            .map_err(|err| sdk_error(SERVICE, err))?;
+
+        res.environments()
+            .unwrap_or_default()
+            .first()
+            .map(|env| environment(env.environment_id(), env.environment_name(), env.cname()))
+            .transpose()
+    }
+
+    async fn environments(&self, application: &str) -> Result<Vec<Environment>, ApiError> {
+        let res = self
+            .describe_environments()
+            .application_name(application)
+            .include_deleted(false)
+            .send()
+            .await
+            .map_err(|err| sdk_error(SERVICE, err))?;
+
+        res.environments()
+            .unwrap_or_default()
+            .iter()
+            .map(|env| environment(env.environment_id(), env.environment_name(), env.cname()))
+            .collect()
+    }
+
+    async fn update_environment(&self, env_id: &str, version_label: &str) -> Result<(), ApiError> {
+        self.update_environment()
+            .environment_id(env_id)
+            .version_label(version_label)
+            .send()
+            .await
+            .map_err(|err| sdk_error(SERVICE, err))?;
+
+        Ok(())
+    }
+
+    async fn load_balancers(&self, env_id: &str) -> Result<Vec<String>, ApiError> {
+        let res = self
+            .describe_environment_resources()
+            .environment_id(env_id)
+            .send()
+            .await
+            .map_err(|err| sdk_error(SERVICE, err))?;
+
+        Ok(res
+            .environment_resources()
+            .and_then(|resources| resources.load_balancers())
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|balancer| balancer.name().map(|name| name.into()))
+            .collect())
+    }
+
+    async fn terminate_environment(&self, env_id: &str) -> Result<Environment, ApiError> {
+        let res = self
+            .terminate_environment()
+            .environment_id(env_id)
+            .force_terminate(true)
+            .send()
+            .await
+            .map_err(|err| sdk_error(SERVICE, err))?;
+
+        environment(res.environment_id(), res.environment_name(), res.cname())
+    }
+}
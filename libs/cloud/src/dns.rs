@@ -0,0 +1,78 @@
+use std::fmt::Debug;
+
+use async_trait::async_trait;
+use aws_sdk_route53::model::{
+    AliasTarget, Change, ChangeAction, ChangeBatch, ResourceRecordSet, RrType,
+};
+use errors::ApiError;
+
+use crate::sdk_error;
+
+const SERVICE: &str = "Route53";
+
+/// An A record pointing a name at a load balancer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AliasRecord {
+    pub hosted_zone_id: String,
+    pub name: String,
+    pub target_dns_name: String,
+    /// Zone of the load balancer, not the one the record is in
+    pub target_zone_id: String,
+}
+
+/// Route53, where instance domains point at their environment
+#[async_trait]
+pub trait Dns: Debug + Send + Sync {
+    async fn create_alias(&self, record: &AliasRecord) -> Result<(), ApiError>;
+
+    /// Needs the record exactly as it was created
+    async fn delete_alias(&self, record: &AliasRecord) -> Result<(), ApiError>;
+}
+
+async fn change(
+    client: &aws_sdk_route53::Client,
+    action: ChangeAction,
+    record: &AliasRecord,
+) -> Result<(), ApiError> {
+    client
+        .change_resource_record_sets()
+        .hosted_zone_id(&record.hosted_zone_id)
+        .change_batch(
+            ChangeBatch::builder()
+                .changes(
+                    Change::builder()
+                        .action(action)
+                        .resource_record_set(
+                            ResourceRecordSet::builder()
+                                .name(&record.name)
+                                .r#type(RrType::A)
+                                .alias_target(
+                                    AliasTarget::builder()
+                                        .dns_name(&record.target_dns_name)
+                                        .evaluate_target_health(false)
+                                        .hosted_zone_id(&record.target_zone_id)
+                                        .build(),
+                                )
+                                .build(),
+                        )
+                        .build(),
+                )
+                .build(),
+        )
+        .send()
+        .await
+        .map_err(|err| sdk_error(SERVICE, err))?;
+
+    Ok(())
+}
+
+#[async_trait]
+impl Dns for aws_sdk_route53::Client {
+    async fn create_alias(&self, record: &AliasRecord) -> Result<(), ApiError> {
+        change(self, ChangeAction::Create, record).await
+    }
+
+    async fn delete_alias(&self, record: &AliasRecord) -> Result<(), ApiError> {
+        change(self, ChangeAction::Delete, record).await
+    }
+}
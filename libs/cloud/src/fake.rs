@@ -0,0 +1,367 @@
+//! Every provider kept in memory, for running offline and for tests
+//!
+//! Environments come up with a load balancer and a cname right away, nothing is deployed
+use std::sync::{Mutex, MutexGuard};
+
+use async_trait::async_trait;
+use errors::{ApiError, ErrorCode};
+
+use crate::{
+    beanstalk::{ConfigOption, Environment, NewEnvironment},
+    dns::AliasRecord,
+    Beanstalk, Dns, LoadBalancers, Publisher, Queue,
+};
+
+/// Published to a topic or sent to a queue
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    /// Topic arn or queue url
+    pub to: String,
+    pub body: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListenerAction {
+    /// Arn of the target group
+    Forward(String),
+    RedirectToHttps,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FakeListener {
+    pub arn: String,
+    pub port: u16,
+    pub action: ListenerAction,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FakeBalancer {
+    pub arn: String,
+    pub listeners: Vec<FakeListener>,
+    pub target_group_arn: String,
+    pub security_groups: Vec<String>,
+}
+
+/// An environment the fake created, with what it was created with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FakeEnvironment {
+    pub environment: Environment,
+    pub application_name: String,
+    pub version_label: String,
+    pub options: Vec<ConfigOption>,
+    pub balancer: FakeBalancer,
+    pub terminated: bool,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    created: usize,
+    environments: Vec<FakeEnvironment>,
+    records: Vec<AliasRecord>,
+    published: Vec<Message>,
+    sent: Vec<Message>,
+    failing: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct FakeAws {
+    state: Mutex<State>,
+}
+
+impl FakeAws {
+    /// Every call to the operation fails from now on, it's named like the trait method
+    pub fn fail_on(&self, operation: &str) {
+        self.state.lock().unwrap().failing.push(operation.into());
+    }
+
+    /// Undoes fail_on, the operation works again
+    pub fn recover(&self, operation: &str) {
+        self.state
+            .lock()
+            .unwrap()
+            .failing
+            .retain(|failing| failing != operation);
+    }
+
+    /// Including terminated ones, oldest first
+    pub fn environments(&self) -> Vec<FakeEnvironment> {
+        self.state.lock().unwrap().environments.clone()
+    }
+
+    pub fn records(&self) -> Vec<AliasRecord> {
+        self.state.lock().unwrap().records.clone()
+    }
+
+    pub fn published(&self) -> Vec<Message> {
+        self.state.lock().unwrap().published.clone()
+    }
+
+    pub fn sent(&self) -> Vec<Message> {
+        self.state.lock().unwrap().sent.clone()
+    }
+
+    fn state(&self, operation: &str) -> Result<MutexGuard<'_, State>, ApiError> {
+        let state = self.state.lock().unwrap();
+        if state.failing.iter().any(|failing| failing == operation) {
+            return Err(ApiError::new(500, format!("Fake {} failed.", operation))
+                .with_code(ErrorCode::ServiceUnavailable));
+        }
+        Ok(state)
+    }
+
+    /// For operations that don't touch the state
+    fn check(&self, operation: &str) -> Result<(), ApiError> {
+        self.state(operation).map(|_| ())
+    }
+}
+
+impl State {
+    fn balancer(&mut self, arn: &str) -> Result<&mut FakeBalancer, ApiError> {
+        self.environments
+            .iter_mut()
+            .filter(|env| !env.terminated)
+            .map(|env| &mut env.balancer)
+            .find(|balancer| balancer.arn == arn)
+            .ok_or_else(|| not_found(format!("Load balancer {}", arn)))
+    }
+}
+
+fn not_found(what: String) -> ApiError {
+    ApiError::new(404, format!("{} doesn't exist.", what))
+}
+
+#[async_trait]
+impl Beanstalk for FakeAws {
+    async fn version_labels(&self, _application: &str) -> Result<Vec<String>, ApiError> {
+        self.check("version_labels")?;
+        Ok(vec!["fake-version".into()])
+    }
+
+    async fn solution_stacks(&self) -> Result<Vec<String>, ApiError> {
+        self.check("solution_stacks")?;
+        Ok(vec!["64bit Amazon Linux 2 running Docker".into()])
+    }
+
+    async fn create_environment(&self, env: NewEnvironment) -> Result<Environment, ApiError> {
+        let mut state = self.state("create_environment")?;
+        state.created += 1;
+
+        let balancer_arn = format!(
+            "arn:aws:elasticloadbalancing:fake:loadbalancer/app/{}",
+            env.environment_name
+        );
+        let target_group_arn = format!(
+            "arn:aws:elasticloadbalancing:fake:targetgroup/{}",
+            env.environment_name
+        );
+        let environment = Environment {
+            id: format!("e-fake{:06}", state.created),
+            cname: Some(format!(
+                "{}.fake.elasticbeanstalk.com",
+                env.environment_name
+            )),
+            name: env.environment_name,
+        };
+
+        state.environments.push(FakeEnvironment {
+            environment: environment.clone(),
+            application_name: env.application_name,
+            version_label: env.version_label,
+            options: env.options,
+            balancer: FakeBalancer {
+                listeners: vec![FakeListener {
+                    arn: format!("{}/listener/80", balancer_arn),
+                    port: 80,
+                    action: ListenerAction::Forward(target_group_arn.clone()),
+                }],
+                arn: balancer_arn,
+                target_group_arn,
+                security_groups: vec![],
+            },
+            terminated: false,
+        });
+
+        Ok(environment)
+    }
+
+    async fn find_environment(
+        &self,
+        application: &str,
+        env_id: &str,
+    ) -> Result<Option<Environment>, ApiError> {
+        let state = self.state("find_environment")?;
+        Ok(state
+            .environments
+            .iter()
+            .find(|env| env.application_name == application && env.environment.id == env_id)
+            .map(|env| env.environment.clone()))
+    }
+
+    async fn environments(&self, application: &str) -> Result<Vec<Environment>, ApiError> {
+        let state = self.state("environments")?;
+        Ok(state
+            .environments
+            .iter()
+            .filter(|env| env.application_name == application && !env.terminated)
+            .map(|env| env.environment.clone())
+            .collect())
+    }
+
+    async fn update_environment(&self, env_id: &str, version_label: &str) -> Result<(), ApiError> {
+        let mut state = self.state("update_environment")?;
+        let env = state
+            .environments
+            .iter_mut()
+            .find(|env| env.environment.id == env_id && !env.terminated)
+            .ok_or_else(|| not_found(format!("Environment {}", env_id)))?;
+
+        env.version_label = version_label.into();
+        Ok(())
+    }
+
+    async fn load_balancers(&self, env_id: &str) -> Result<Vec<String>, ApiError> {
+        let state = self.state("load_balancers")?;
+        Ok(state
+            .environments
+            .iter()
+            .filter(|env| env.environment.id == env_id && !env.terminated)
+            .map(|env| env.balancer.arn.clone())
+            .collect())
+    }
+
+    async fn terminate_environment(&self, env_id: &str) -> Result<Environment, ApiError> {
+        let mut state = self.state("terminate_environment")?;
+        let env = state
+            .environments
+            .iter_mut()
+            .find(|env| env.environment.id == env_id && !env.terminated)
+            .ok_or_else(|| not_found(format!("Environment {}", env_id)))?;
+
+        env.terminated = true;
+        Ok(env.environment.clone())
+    }
+}
+
+#[async_trait]
+impl LoadBalancers for FakeAws {
+    async fn listeners(&self, balancer_arn: &str) -> Result<Vec<String>, ApiError> {
+        let mut state = self.state("listeners")?;
+        let balancer = state.balancer(balancer_arn)?;
+        Ok(balancer
+            .listeners
+            .iter()
+            .map(|listener| listener.arn.clone())
+            .collect())
+    }
+
+    async fn target_groups(&self, balancer_arn: &str) -> Result<Vec<String>, ApiError> {
+        let mut state = self.state("target_groups")?;
+        let balancer = state.balancer(balancer_arn)?;
+        Ok(vec![balancer.target_group_arn.clone()])
+    }
+
+    async fn create_https_listener(
+        &self,
+        balancer_arn: &str,
+        target_group_arn: &str,
+        _certificate_arn: &str,
+    ) -> Result<(), ApiError> {
+        let mut state = self.state("create_https_listener")?;
+        let balancer = state.balancer(balancer_arn)?;
+        if balancer
+            .listeners
+            .iter()
+            .any(|listener| listener.port == 443)
+        {
+            return Err(ApiError::new(
+                409,
+                "A listener already uses port 443.".into(),
+            ));
+        }
+
+        balancer.listeners.push(FakeListener {
+            arn: format!("{}/listener/443", balancer_arn),
+            port: 443,
+            action: ListenerAction::Forward(target_group_arn.into()),
+        });
+        Ok(())
+    }
+
+    async fn redirect_to_https(&self, listener_arn: &str) -> Result<(), ApiError> {
+        let mut state = self.state("redirect_to_https")?;
+        let listener = state
+            .environments
+            .iter_mut()
+            .filter(|env| !env.terminated)
+            .flat_map(|env| env.balancer.listeners.iter_mut())
+            .find(|listener| listener.arn == listener_arn)
+            .ok_or_else(|| not_found(format!("Listener {}", listener_arn)))?;
+
+        listener.action = ListenerAction::RedirectToHttps;
+        Ok(())
+    }
+
+    async fn set_security_groups(
+        &self,
+        balancer_arn: &str,
+        groups: &[String],
+    ) -> Result<(), ApiError> {
+        let mut state = self.state("set_security_groups")?;
+        state.balancer(balancer_arn)?.security_groups = groups.to_vec();
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Dns for FakeAws {
+    async fn create_alias(&self, record: &AliasRecord) -> Result<(), ApiError> {
+        let mut state = self.state("create_alias")?;
+        if state
+            .records
+            .iter()
+            .any(|existing| existing.name == record.name)
+        {
+            return Err(ApiError::new(
+                409,
+                format!("{} already has a record.", record.name),
+            ));
+        }
+
+        state.records.push(record.clone());
+        Ok(())
+    }
+
+    async fn delete_alias(&self, record: &AliasRecord) -> Result<(), ApiError> {
+        let mut state = self.state("delete_alias")?;
+        let index = state
+            .records
+            .iter()
+            .position(|existing| existing == record)
+            .ok_or_else(|| not_found(format!("Record for {}", record.name)))?;
+
+        state.records.remove(index);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Publisher for FakeAws {
+    async fn publish(&self, topic_arn: &str, message: String) -> Result<(), ApiError> {
+        self.state("publish")?.published.push(Message {
+            to: topic_arn.into(),
+            body: message,
+        });
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Queue for FakeAws {
+    async fn send_message(&self, queue_url: &str, body: String) -> Result<(), ApiError> {
+        self.state("send_message")?.sent.push(Message {
+            to: queue_url.into(),
+            body,
+        });
+        Ok(())
+    }
+}
@@ -0,0 +1,74 @@
+//! AWS behind traits, so services run against whichever implementation they're handed
+//!
+//! `Aws::from_env` gives the real SDK clients, or a `FakeAws` that keeps everything in memory
+//! when OFFLINE is set so the stack runs without an AWS account
+pub mod beanstalk;
+pub mod dns;
+pub mod fake;
+pub mod load_balancers;
+pub mod messaging;
+
+use std::{fmt::Debug, sync::Arc};
+
+use errors::{ApiError, ErrorCode};
+
+pub use beanstalk::Beanstalk;
+pub use dns::Dns;
+pub use fake::FakeAws;
+pub use load_balancers::LoadBalancers;
+pub use messaging::{Publisher, Queue};
+
+/// Every provider a service might need, cheap to clone
+#[derive(Debug, Clone)]
+pub struct Aws {
+    pub beanstalk: Arc<dyn Beanstalk>,
+    pub dns: Arc<dyn Dns>,
+    pub load_balancers: Arc<dyn LoadBalancers>,
+    pub publisher: Arc<dyn Publisher>,
+    pub queue: Arc<dyn Queue>,
+}
+
+impl Aws {
+    /// SDK clients using the credentials in the environment, or a fresh fake if OFFLINE is set
+    pub async fn from_env() -> Self {
+        if std::env::var_os("OFFLINE").is_some() {
+            log::info!("OFFLINE is set, AWS is faked in memory");
+            return Aws::fake(Arc::new(FakeAws::default()));
+        }
+
+        let config = aws_config::load_from_env().await;
+        Aws {
+            beanstalk: Arc::new(aws_sdk_elasticbeanstalk::Client::new(&config)),
+            dns: Arc::new(aws_sdk_route53::Client::new(&config)),
+            load_balancers: Arc::new(aws_sdk_elasticloadbalancingv2::Client::new(&config)),
+            publisher: Arc::new(aws_sdk_sns::Client::new(&config)),
+            queue: Arc::new(aws_sdk_sqs::Client::new(&config)),
+        }
+    }
+
+    /// Every provider backed by the same fake, keep a handle to it to look at what was called
+    pub fn fake(fake: Arc<FakeAws>) -> Self {
+        Aws {
+            beanstalk: fake.clone(),
+            dns: fake.clone(),
+            load_balancers: fake.clone(),
+            publisher: fake.clone(),
+            queue: fake,
+        }
+    }
+}
+
+/// Logs what the SDK returned and replaces it with an error that's fine to respond with
+fn sdk_error(service: &str, err: impl Debug) -> ApiError {
+    log::error!("[{}] {:?}", service, err);
+    ApiError::new(500, format!("{} couldn't complete the request.", service))
+        .with_code(ErrorCode::ServiceUnavailable)
+}
+
+/// For fields the SDK models as optional but are always there on success
+fn missing(service: &str, field: &str) -> ApiError {
+    sdk_error(service, format!("response has no {}", field))
+}
+
+#[cfg(test)]
+mod tests;
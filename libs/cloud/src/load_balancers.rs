@@ -0,0 +1,141 @@
+use std::fmt::Debug;
+
+use async_trait::async_trait;
+use aws_sdk_elasticloadbalancingv2::model::{
+    Action, ActionTypeEnum, Certificate, ProtocolEnum, RedirectActionConfig,
+    RedirectActionStatusCodeEnum,
+};
+use errors::ApiError;
+
+use crate::sdk_error;
+
+const SERVICE: &str = "Elastic Load Balancing";
+const SSL_POLICY: &str = "ELBSecurityPolicy-2016-08";
+
+/// ELBv2, the load balancers beanstalk puts in front of environments
+#[async_trait]
+pub trait LoadBalancers: Debug + Send + Sync {
+    /// Arns of the balancer's listeners
+    async fn listeners(&self, balancer_arn: &str) -> Result<Vec<String>, ApiError>;
+
+    /// Arns of the balancer's target groups
+    async fn target_groups(&self, balancer_arn: &str) -> Result<Vec<String>, ApiError>;
+
+    /// Adds a listener on 443 that forwards to the target group
+    async fn create_https_listener(
+        &self,
+        balancer_arn: &str,
+        target_group_arn: &str,
+        certificate_arn: &str,
+    ) -> Result<(), ApiError>;
+
+    /// Makes the listener answer with a 301 to https
+    async fn redirect_to_https(&self, listener_arn: &str) -> Result<(), ApiError>;
+
+    async fn set_security_groups(
+        &self,
+        balancer_arn: &str,
+        groups: &[String],
+    ) -> Result<(), ApiError>;
+}
+
+#[async_trait]
+impl LoadBalancers for aws_sdk_elasticloadbalancingv2::Client {
+    async fn listeners(&self, balancer_arn: &str) -> Result<Vec<String>, ApiError> {
+        let res = self
+            .describe_listeners()
+            .load_balancer_arn(balancer_arn)
+            .send()
+            .await
+            .map_err(|err| sdk_error(SERVICE, err))?;
+
+        Ok(res
+            .listeners()
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|listener| listener.listener_arn().map(|arn| arn.into()))
+            .collect())
+    }
+
+    async fn target_groups(&self, balancer_arn: &str) -> Result<Vec<String>, ApiError> {
+        let res = self
+            .describe_target_groups()
+            .load_balancer_arn(balancer_arn)
+            .send()
+            .await
+            .map_err(|err| sdk_error(SERVICE, err))?;
+
+        Ok(res
+            .target_groups()
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|group| group.target_group_arn().map(|arn| arn.into()))
+            .collect())
+    }
+
+    async fn create_https_listener(
+        &self,
+        balancer_arn: &str,
+        target_group_arn: &str,
+        certificate_arn: &str,
+    ) -> Result<(), ApiError> {
+        self.create_listener()
+            .load_balancer_arn(balancer_arn)
+            .port(443)
+            .protocol(ProtocolEnum::Https)
+            .ssl_policy(SSL_POLICY)
+            .default_actions(
+                Action::builder()
+                    .r#type(ActionTypeEnum::Forward)
+                    .target_group_arn(target_group_arn)
+                    .build(),
+            )
+            .certificates(
+                Certificate::builder()
+                    .certificate_arn(certificate_arn)
+                    .build(),
+            )
+            .send()
+            .await
+            .map_err(|err| sdk_error(SERVICE, err))?;
+
+        Ok(())
+    }
+
+    async fn redirect_to_https(&self, listener_arn: &str) -> Result<(), ApiError> {
+        self.modify_listener()
+            .listener_arn(listener_arn)
+            .default_actions(
+                Action::builder()
+                    .r#type(ActionTypeEnum::Redirect)
+                    .redirect_config(
+                        RedirectActionConfig::builder()
+                            .status_code(RedirectActionStatusCodeEnum::Http301)
+                            .port("443")
+                            .protocol("HTTPS")
+                            .build(),
+                    )
+                    .build(),
+            )
+            .send()
+            .await
+            .map_err(|err| sdk_error(SERVICE, err))?;
+
+        Ok(())
+    }
+
+    async fn set_security_groups(
+        &self,
+        balancer_arn: &str,
+        groups: &[String],
+    ) -> Result<(), ApiError> {
+        self.set_security_groups()
+            .load_balancer_arn(balancer_arn)
+            .set_security_groups(Some(groups.to_vec()))
+            .send()
+            .await
+            .map_err(|err| sdk_error(SERVICE, err))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,46 @@
+use std::fmt::Debug;
+
+use async_trait::async_trait;
+use errors::ApiError;
+
+use crate::sdk_error;
+
+/// SNS, how crud starts the deploy lambda
+#[async_trait]
+pub trait Publisher: Debug + Send + Sync {
+    async fn publish(&self, topic_arn: &str, message: String) -> Result<(), ApiError>;
+}
+
+/// SQS, how the lambdas hand an instance to the next step
+#[async_trait]
+pub trait Queue: Debug + Send + Sync {
+    async fn send_message(&self, queue_url: &str, body: String) -> Result<(), ApiError>;
+}
+
+#[async_trait]
+impl Publisher for aws_sdk_sns::Client {
+    async fn publish(&self, topic_arn: &str, message: String) -> Result<(), ApiError> {
+        self.publish()
+            .topic_arn(topic_arn)
+            .message(message)
+            .send()
+            .await
+            .map_err(|err| sdk_error("SNS", err))?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Queue for aws_sdk_sqs::Client {
+    async fn send_message(&self, queue_url: &str, body: String) -> Result<(), ApiError> {
+        self.send_message()
+            .queue_url(queue_url)
+            .message_body(body)
+            .send()
+            .await
+            .map_err(|err| sdk_error("SQS", err))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,110 @@
+use std::sync::Arc;
+
+use crate::{
+    beanstalk::NewEnvironment,
+    dns::AliasRecord,
+    fake::{FakeAws, ListenerAction},
+    Aws,
+};
+
+fn new_environment(name: &str) -> NewEnvironment {
+    NewEnvironment {
+        application_name: "app".into(),
+        environment_name: name.into(),
+        version_label: "v1".into(),
+        solution_stack: "docker".into(),
+        options: vec![],
+    }
+}
+
+#[tokio::test]
+async fn fake_environment_lifecycle() {
+    let fake = Arc::new(FakeAws::default());
+    let aws = Aws::fake(fake.clone());
+
+    let env = aws
+        .beanstalk
+        .create_environment(new_environment("lifecycle"))
+        .await
+        .unwrap();
+    let found = aws
+        .beanstalk
+        .find_environment("app", &env.id)
+        .await
+        .unwrap();
+    assert_eq!(found.as_ref(), Some(&env));
+
+    let balancer = aws
+        .beanstalk
+        .load_balancers(&env.id)
+        .await
+        .unwrap()
+        .remove(0);
+    let http = aws
+        .load_balancers
+        .listeners(&balancer)
+        .await
+        .unwrap()
+        .remove(0);
+    let targets = aws
+        .load_balancers
+        .target_groups(&balancer)
+        .await
+        .unwrap()
+        .remove(0);
+    aws.load_balancers
+        .create_https_listener(&balancer, &targets, "cert")
+        .await
+        .unwrap();
+    aws.load_balancers.redirect_to_https(&http).await.unwrap();
+
+    let listeners = fake.environments()[0].balancer.listeners.clone();
+    assert_eq!(listeners[0].action, ListenerAction::RedirectToHttps);
+    assert_eq!(listeners[1].port, 443);
+    assert_eq!(listeners[1].action, ListenerAction::Forward(targets));
+
+    let record = AliasRecord {
+        hosted_zone_id: "zone".into(),
+        name: "lifecycle.example.com".into(),
+        target_dns_name: env.cname.clone().unwrap(),
+        target_zone_id: "elb".into(),
+    };
+    aws.dns.create_alias(&record).await.unwrap();
+    assert!(aws.dns.create_alias(&record).await.is_err());
+
+    let terminated = aws.beanstalk.terminate_environment(&env.id).await.unwrap();
+    aws.dns.delete_alias(&record).await.unwrap();
+    assert_eq!(terminated, env);
+    assert!(fake.records().is_empty());
+    assert!(fake.environments()[0].terminated);
+
+    // gone once terminated
+    assert!(aws.beanstalk.terminate_environment(&env.id).await.is_err());
+    assert!(aws.load_balancers.listeners(&balancer).await.is_err());
+}
+
+#[tokio::test]
+async fn fake_fails_on_request() {
+    let fake = Arc::new(FakeAws::default());
+    let aws = Aws::fake(fake.clone());
+
+    aws.publisher
+        .publish("topic", "first".into())
+        .await
+        .unwrap();
+    fake.fail_on("publish");
+    let err = aws
+        .publisher
+        .publish("topic", "second".into())
+        .await
+        .unwrap_err();
+    assert_eq!(err.status_code, 500);
+
+    // other operations keep working
+    aws.queue
+        .send_message("queue", "third".into())
+        .await
+        .unwrap();
+    assert_eq!(fake.published().len(), 1);
+    assert_eq!(fake.sent()[0].body, "third");
+}
@@ -0,0 +1,149 @@
+use errors::ApiError;
+use models::{
+    Account, AccountClosure, AccountExport, Instance, ListQuery, NewAccount, NewUser, Page,
+    UpdateAccount, User,
+};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+
+use crate::{json, send, CrudClient, DeleteBody, Route};
+
+const FIND_ALL: Route = Route::get("/accounts");
+const CREATE: Route = Route::post("/accounts");
+const FIND: Route = Route::get("/accounts/{id}");
+const FIND_BY_CUSTOMER: Route = Route::get("/accounts/by-customer/{id}");
+const FIND_BY_SUB: Route = Route::get("/accounts/by-sub/{id}");
+const UPDATE: Route = Route::put("/accounts/{id}");
+const DELETE: Route = Route::delete("/accounts/{id}");
+const RESTORE: Route = Route::post("/accounts/{id}/restore");
+const CLOSURE: Route = Route::get("/accounts/{id}/closure");
+const EXPORT: Route = Route::get("/accounts/{id}/export");
+const USAGE: Route = Route::get("/accounts/{id}/usage");
+const USERS: Route = Route::get("/accounts/{id}/users");
+const INSTANCES: Route = Route::get("/accounts/{id}/instances");
+const REGISTER: Route = Route::post("/register");
+
+#[cfg(test)]
+pub(crate) const ROUTES: &[Route] = &[
+    FIND_ALL,
+    CREATE,
+    FIND,
+    FIND_BY_CUSTOMER,
+    FIND_BY_SUB,
+    UPDATE,
+    DELETE,
+    RESTORE,
+    CLOSURE,
+    EXPORT,
+    USAGE,
+    USERS,
+    INSTANCES,
+    REGISTER,
+];
+
+#[derive(Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Usage {
+    pub instances: i64,
+    pub users: i64,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RegisterBody {
+    pub account: NewAccount,
+    pub user: NewUser,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RegisterResponse {
+    pub account: Account,
+    pub user: User,
+}
+
+/// An export is only downloadable once it's built
+#[derive(Debug)]
+pub enum Export {
+    /// Zip archive of the account's data
+    Archive(Vec<u8>),
+    /// Still building, ask again later
+    Pending(AccountExport),
+}
+
+impl CrudClient {
+    pub async fn register(&self, body: &RegisterBody) -> Result<RegisterResponse, ApiError> {
+        json(self.request(REGISTER, None).json(body)).await
+    }
+
+    pub async fn find_accounts(&self, query: &ListQuery) -> Result<Page<Account>, ApiError> {
+        json(self.request(FIND_ALL, None).query(query)).await
+    }
+
+    pub async fn create_account(&self, account: &NewAccount) -> Result<Account, ApiError> {
+        json(self.request(CREATE, None).json(account)).await
+    }
+
+    pub async fn find_account(&self, id: &str) -> Result<Account, ApiError> {
+        json(self.request(FIND, Some(id))).await
+    }
+
+    /// Finds the account by its stripe customer id
+    pub async fn find_account_by_customer(&self, customer_id: &str) -> Result<Account, ApiError> {
+        json(self.request(FIND_BY_CUSTOMER, Some(customer_id))).await
+    }
+
+    /// Finds the account by its stripe subscription id
+    pub async fn find_account_by_sub(&self, sub_id: &str) -> Result<Account, ApiError> {
+        json(self.request(FIND_BY_SUB, Some(sub_id))).await
+    }
+
+    pub async fn update_account(
+        &self,
+        id: &str,
+        account: &UpdateAccount,
+    ) -> Result<Account, ApiError> {
+        json(self.request(UPDATE, Some(id)).json(account)).await
+    }
+
+    pub async fn delete_account(&self, id: &str) -> Result<DeleteBody, ApiError> {
+        json(self.request(DELETE, Some(id))).await
+    }
+
+    pub async fn restore_account(&self, id: &str) -> Result<Account, ApiError> {
+        json(self.request(RESTORE, Some(id))).await
+    }
+
+    pub async fn find_account_closure(&self, id: &str) -> Result<AccountClosure, ApiError> {
+        json(self.request(CLOSURE, Some(id))).await
+    }
+
+    pub async fn export_account(&self, id: &str) -> Result<Export, ApiError> {
+        let res = send(self.request(EXPORT, Some(id))).await?;
+        if res.status() == StatusCode::ACCEPTED {
+            Ok(Export::Pending(res.json().await?))
+        } else {
+            Ok(Export::Archive(res.bytes().await?.to_vec()))
+        }
+    }
+
+    pub async fn account_usage(&self, id: &str) -> Result<Usage, ApiError> {
+        json(self.request(USAGE, Some(id))).await
+    }
+
+    pub async fn find_account_users(
+        &self,
+        id: &str,
+        query: &ListQuery,
+    ) -> Result<Page<User>, ApiError> {
+        json(self.request(USERS, Some(id)).query(query)).await
+    }
+
+    pub async fn find_account_instances(
+        &self,
+        id: &str,
+        query: &ListQuery,
+    ) -> Result<Page<Instance>, ApiError> {
+        json(self.request(INSTANCES, Some(id)).query(query)).await
+    }
+}
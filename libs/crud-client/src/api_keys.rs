@@ -0,0 +1,35 @@
+use errors::ApiError;
+use models::{ApiKey, ListQuery, NewApiKey, Page};
+use serde::{Deserialize, Serialize};
+
+use crate::{json, CrudClient, Route};
+
+const FIND_ALL: Route = Route::get("/api-keys");
+const CREATE: Route = Route::post("/api-keys");
+const REVOKE: Route = Route::put("/api-keys/{id}/revoke");
+
+#[cfg(test)]
+pub(crate) const ROUTES: &[Route] = &[FIND_ALL, CREATE, REVOKE];
+
+/// Returned once when a key is created, the key can't be seen again after
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CreatedApiKey {
+    pub api_key: ApiKey,
+    pub key: String,
+}
+
+impl CrudClient {
+    pub async fn find_api_keys(&self, query: &ListQuery) -> Result<Page<ApiKey>, ApiError> {
+        json(self.request(FIND_ALL, None).query(query)).await
+    }
+
+    pub async fn create_api_key(&self, api_key: &NewApiKey) -> Result<CreatedApiKey, ApiError> {
+        json(self.request(CREATE, None).json(api_key)).await
+    }
+
+    pub async fn revoke_api_key(&self, id: &str) -> Result<ApiKey, ApiError> {
+        json(self.request(REVOKE, Some(id))).await
+    }
+}
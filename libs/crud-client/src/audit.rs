@@ -0,0 +1,25 @@
+use errors::ApiError;
+use models::{AuditEvent, ListQuery, NewAuditEvent, Page};
+
+use crate::{json, CrudClient, Route};
+
+const CREATE: Route = Route::post("/audit-events");
+const FIND_BY_ACCOUNT: Route = Route::get("/accounts/{id}/audit");
+
+#[cfg(test)]
+pub(crate) const ROUTES: &[Route] = &[CREATE, FIND_BY_ACCOUNT];
+
+impl CrudClient {
+    /// Records a change made outside of crud, such as with Stripe
+    pub async fn create_audit_event(&self, event: &NewAuditEvent) -> Result<AuditEvent, ApiError> {
+        json(self.request(CREATE, None).json(event)).await
+    }
+
+    pub async fn find_account_audit(
+        &self,
+        account_id: &str,
+        query: &ListQuery,
+    ) -> Result<Page<AuditEvent>, ApiError> {
+        json(self.request(FIND_BY_ACCOUNT, Some(account_id)).query(query)).await
+    }
+}
@@ -0,0 +1,206 @@
+use ::auth::ReqUser;
+use errors::ApiError;
+use models::{types::Capability, User};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+
+use crate::{cookies, empty, json, send, CrudClient, Route, WithCookies};
+
+const LOGIN: Route = Route::post("/login");
+const LOGIN_MFA: Route = Route::post("/login/mfa");
+const AUTHENTICATE: Route = Route::get("/authenticate");
+const VERIFY: Route = Route::get("/verify");
+const VERIFY_KEY: Route = Route::get("/verify-key");
+const VERIFY_DEPLOY: Route = Route::get("/verify-deploy");
+const REFRESH: Route = Route::post("/refresh");
+const LOGOUT: Route = Route::post("/logout");
+const REQUEST_PASSWORD_RESET: Route = Route::post("/password-reset/request");
+const CONFIRM_PASSWORD_RESET: Route = Route::post("/password-reset/confirm");
+const ENROLL_MFA: Route = Route::post("/mfa/enroll");
+const VERIFY_MFA: Route = Route::post("/mfa/verify");
+const DISABLE_MFA: Route = Route::post("/mfa/disable");
+
+#[cfg(test)]
+pub(crate) const ROUTES: &[Route] = &[
+    LOGIN,
+    LOGIN_MFA,
+    AUTHENTICATE,
+    VERIFY,
+    VERIFY_KEY,
+    VERIFY_DEPLOY,
+    REFRESH,
+    LOGOUT,
+    REQUEST_PASSWORD_RESET,
+    CONFIRM_PASSWORD_RESET,
+    ENROLL_MFA,
+    VERIFY_MFA,
+    DISABLE_MFA,
+];
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct Login {
+    pub account_id: String,
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct PasswordResetRequest {
+    pub account_id: String,
+    pub username: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct PasswordResetConfirm {
+    pub token: String,
+    pub password: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct MfaCode {
+    pub code: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct MfaRequired {
+    pub mfa_required: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct MfaEnrollment {
+    pub secret: String,
+    pub provisioning_uri: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct RecoveryCodes {
+    pub recovery_codes: Vec<String>,
+}
+
+/// Returned from verify, the gateway reads it as a ReqUser
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct VerifiedUser {
+    #[serde(flatten)]
+    pub user: User,
+    /// What the user's role lets them do
+    pub capabilities: Vec<Capability>,
+}
+
+impl From<VerifiedUser> for ReqUser {
+    fn from(verified: VerifiedUser) -> Self {
+        ReqUser {
+            id: verified.user.id,
+            account_id: verified.user.account_id,
+            role: verified.user.role,
+            create_perms: verified.user.create_perms,
+            update_perms: verified.user.update_perms,
+            delete_perms: verified.user.delete_perms,
+            capabilities: Some(verified.capabilities),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum LoginOutcome {
+    LoggedIn(Box<User>),
+    /// Finish with `login_mfa`, calling with the mfa cookie that was set
+    MfaRequired,
+}
+
+impl CrudClient {
+    pub async fn login(&self, login: &Login) -> Result<WithCookies<LoginOutcome>, ApiError> {
+        let res = send(self.request(LOGIN, None).json(login)).await?;
+        let cookies = cookies(&res);
+        let body = if res.status() == StatusCode::ACCEPTED {
+            LoginOutcome::MfaRequired
+        } else {
+            LoginOutcome::LoggedIn(res.json().await?)
+        };
+        Ok(WithCookies { body, cookies })
+    }
+
+    pub async fn login_mfa(&self, code: &MfaCode) -> Result<WithCookies<User>, ApiError> {
+        let res = send(self.request(LOGIN_MFA, None).json(code)).await?;
+        let cookies = cookies(&res);
+        Ok(WithCookies {
+            body: res.json().await?,
+            cookies,
+        })
+    }
+
+    /// Succeeds if the session cookie is valid
+    pub async fn authenticate(&self) -> Result<(), ApiError> {
+        empty(self.request(AUTHENTICATE, None)).await
+    }
+
+    /// Finds the user the session cookie belongs to
+    pub async fn verify(&self) -> Result<VerifiedUser, ApiError> {
+        json(self.request(VERIFY, None)).await
+    }
+
+    /// Finds the user the api key belongs to
+    pub async fn verify_key(&self) -> Result<ReqUser, ApiError> {
+        json(self.request(VERIFY_KEY, None)).await
+    }
+
+    /// Succeeds if the jwt was signed for an instance deployment
+    pub async fn verify_deploy(&self) -> Result<(), ApiError> {
+        empty(self.request(VERIFY_DEPLOY, None)).await
+    }
+
+    pub async fn refresh(&self) -> Result<WithCookies<()>, ApiError> {
+        let res = send(self.request(REFRESH, None)).await?;
+        Ok(WithCookies {
+            body: (),
+            cookies: cookies(&res),
+        })
+    }
+
+    pub async fn logout(&self) -> Result<WithCookies<()>, ApiError> {
+        let res = send(self.request(LOGOUT, None)).await?;
+        Ok(WithCookies {
+            body: (),
+            cookies: cookies(&res),
+        })
+    }
+
+    pub async fn request_password_reset(
+        &self,
+        request: &PasswordResetRequest,
+    ) -> Result<(), ApiError> {
+        empty(self.request(REQUEST_PASSWORD_RESET, None).json(request)).await
+    }
+
+    pub async fn confirm_password_reset(
+        &self,
+        confirm: &PasswordResetConfirm,
+    ) -> Result<(), ApiError> {
+        empty(self.request(CONFIRM_PASSWORD_RESET, None).json(confirm)).await
+    }
+
+    pub async fn enroll_mfa(&self) -> Result<MfaEnrollment, ApiError> {
+        json(self.request(ENROLL_MFA, None)).await
+    }
+
+    pub async fn verify_mfa(&self, code: &MfaCode) -> Result<RecoveryCodes, ApiError> {
+        json(self.request(VERIFY_MFA, None).json(code)).await
+    }
+
+    pub async fn disable_mfa(&self, code: &MfaCode) -> Result<(), ApiError> {
+        empty(self.request(DISABLE_MFA, None).json(code)).await
+    }
+}
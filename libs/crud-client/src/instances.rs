@@ -0,0 +1,127 @@
+use errors::ApiError;
+use models::{
+    Instance, InstanceHealthCheck, InstanceStatusChange, ListQuery, NewInstance, Page,
+    UpdateInstance,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{empty, json, CrudClient, DeleteBody, Route};
+
+const FIND_ALL: Route = Route::get("/instances");
+const CREATE: Route = Route::post("/instances");
+const FIND: Route = Route::get("/instances/{id}");
+const UPDATE: Route = Route::put("/instances/{id}");
+const DELETE: Route = Route::delete("/instances/{id}");
+const RESTORE: Route = Route::post("/instances/{id}/restore");
+const DEPLOY: Route = Route::put("/instances/{id}/deploy");
+const DEACTIVATE: Route = Route::put("/instances/{id}/deactivate");
+const HEALTH: Route = Route::get("/instances/{id}/health");
+const HISTORY: Route = Route::get("/instances/{id}/history");
+const UPTIME: Route = Route::get("/instances/{id}/uptime");
+const CALLBACK: Route = Route::post("/instances/{id}/callback");
+const FAIL_CALLBACK: Route = Route::post("/instances/{id}/fail-callback");
+
+#[cfg(test)]
+pub(crate) const ROUTES: &[Route] = &[
+    FIND_ALL,
+    CREATE,
+    FIND,
+    UPDATE,
+    DELETE,
+    RESTORE,
+    DEPLOY,
+    DEACTIVATE,
+    HEALTH,
+    HISTORY,
+    UPTIME,
+    CALLBACK,
+    FAIL_CALLBACK,
+];
+
+/// Where instance deploy put the instance
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CallbackParams {
+    pub env_id: String,
+    pub url: String,
+    pub account_id: String,
+}
+
+/// Percent of health checks the instance passed, none for a window without checks
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct Uptime {
+    pub last_day: Option<f64>,
+    pub last_week: Option<f64>,
+    pub last_month: Option<f64>,
+    /// Newest checks first, with how long each took
+    pub recent: Vec<InstanceHealthCheck>,
+}
+
+impl CrudClient {
+    pub async fn find_instances(&self, query: &ListQuery) -> Result<Page<Instance>, ApiError> {
+        json(self.request(FIND_ALL, None).query(query)).await
+    }
+
+    pub async fn create_instance(&self, instance: &NewInstance) -> Result<Instance, ApiError> {
+        json(self.request(CREATE, None).json(instance)).await
+    }
+
+    pub async fn find_instance(&self, id: &str) -> Result<Instance, ApiError> {
+        json(self.request(FIND, Some(id))).await
+    }
+
+    pub async fn update_instance(
+        &self,
+        id: &str,
+        instance: &UpdateInstance,
+    ) -> Result<Instance, ApiError> {
+        json(self.request(UPDATE, Some(id)).json(instance)).await
+    }
+
+    pub async fn delete_instance(&self, id: &str) -> Result<DeleteBody, ApiError> {
+        json(self.request(DELETE, Some(id))).await
+    }
+
+    pub async fn restore_instance(&self, id: &str) -> Result<Instance, ApiError> {
+        json(self.request(RESTORE, Some(id))).await
+    }
+
+    pub async fn deploy_instance(&self, id: &str) -> Result<Instance, ApiError> {
+        json(self.request(DEPLOY, Some(id))).await
+    }
+
+    pub async fn deactivate_instance(&self, id: &str) -> Result<(), ApiError> {
+        empty(self.request(DEACTIVATE, Some(id))).await
+    }
+
+    /// Succeeds if the instance is reachable
+    pub async fn instance_health(&self, id: &str) -> Result<(), ApiError> {
+        empty(self.request(HEALTH, Some(id))).await
+    }
+
+    /// Every status the instance has moved to, newest first
+    pub async fn instance_history(&self, id: &str) -> Result<Vec<InstanceStatusChange>, ApiError> {
+        json(self.request(HISTORY, Some(id))).await
+    }
+
+    pub async fn instance_uptime(&self, id: &str) -> Result<Uptime, ApiError> {
+        json(self.request(UPTIME, Some(id))).await
+    }
+
+    /// Tells crud the instance is deployed, call with the deployment's jwt
+    pub async fn instance_callback(
+        &self,
+        id: &str,
+        params: &CallbackParams,
+    ) -> Result<(), ApiError> {
+        empty(self.request(CALLBACK, Some(id)).json(params)).await
+    }
+
+    /// Tells crud the deployment failed, call with the deployment's jwt
+    pub async fn instance_fail_callback(&self, id: &str) -> Result<(), ApiError> {
+        empty(self.request(FAIL_CALLBACK, Some(id))).await
+    }
+}
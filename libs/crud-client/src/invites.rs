@@ -0,0 +1,63 @@
+use errors::ApiError;
+use models::{types::Resource, Invite, ListQuery, Page, User};
+use serde::{Deserialize, Serialize};
+
+use crate::{json, CrudClient, Route};
+
+const FIND_ALL: Route = Route::get("/invites");
+const CREATE: Route = Route::post("/invites");
+const ACCEPT: Route = Route::post("/invites/accept");
+const REVOKE: Route = Route::put("/invites/{id}/revoke");
+
+#[cfg(test)]
+pub(crate) const ROUTES: &[Route] = &[FIND_ALL, CREATE, ACCEPT, REVOKE];
+
+/// Who to invite and what they can do once they join
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct InviteRequest {
+    pub email: String,
+    /// Defaults to the built in user role
+    #[serde(default)]
+    pub role_id: Option<String>,
+    #[serde(default)]
+    pub instances: Vec<String>,
+    #[serde(default)]
+    pub create_perms: Vec<Resource>,
+    #[serde(default)]
+    pub update_perms: Vec<Resource>,
+    #[serde(default)]
+    pub delete_perms: Vec<Resource>,
+}
+
+/// Invitee's own details, sent with the token from their invite link
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct InviteAcceptance {
+    pub token: String,
+    pub username: String,
+    pub first_name: String,
+    pub last_name: String,
+    pub password: String,
+}
+
+impl CrudClient {
+    pub async fn find_invites(&self, query: &ListQuery) -> Result<Page<Invite>, ApiError> {
+        json(self.request(FIND_ALL, None).query(query)).await
+    }
+
+    pub async fn create_invite(&self, invite: &InviteRequest) -> Result<Invite, ApiError> {
+        json(self.request(CREATE, None).json(invite)).await
+    }
+
+    /// Creates the invitee's user, doesn't need to be called as anyone
+    pub async fn accept_invite(&self, acceptance: &InviteAcceptance) -> Result<User, ApiError> {
+        json(self.request(ACCEPT, None).json(acceptance)).await
+    }
+
+    pub async fn revoke_invite(&self, id: &str) -> Result<Invite, ApiError> {
+        json(self.request(REVOKE, Some(id))).await
+    }
+}
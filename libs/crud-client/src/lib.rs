@@ -0,0 +1,210 @@
+//! Typed client for crud, so services don't build crud urls by hand
+//!
+//! Every crud route has a method here, changing a route's path or body fails to compile in the
+//! services calling it instead of failing at runtime
+pub mod accounts;
+pub mod api_keys;
+pub mod audit;
+pub mod auth;
+pub mod instances;
+pub mod invites;
+pub mod notifications;
+pub mod roles;
+pub mod users;
+
+use ::auth::{ReqUser, ACTOR_HEADER};
+use errors::{ApiError, ErrorCode};
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, COOKIE, SET_COOKIE},
+    Method, RequestBuilder, Response, StatusCode,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct DeleteBody {
+    pub affected: i32,
+}
+
+impl DeleteBody {
+    pub fn new(affected: i32) -> Self {
+        DeleteBody { affected }
+    }
+}
+
+/// Body of a route that starts or ends a session, with the cookies it set
+#[derive(Debug)]
+pub struct WithCookies<T> {
+    pub body: T,
+    /// Values of the Set-Cookie headers
+    pub cookies: Vec<String>,
+}
+
+/// A crud route, `{id}` in the path is filled in per request
+#[derive(Debug, Clone)]
+pub struct Route {
+    pub method: Method,
+    pub path: &'static str,
+}
+
+impl Route {
+    const fn get(path: &'static str) -> Self {
+        Route {
+            method: Method::GET,
+            path,
+        }
+    }
+
+    const fn post(path: &'static str) -> Self {
+        Route {
+            method: Method::POST,
+            path,
+        }
+    }
+
+    const fn put(path: &'static str) -> Self {
+        Route {
+            method: Method::PUT,
+            path,
+        }
+    }
+
+    const fn delete(path: &'static str) -> Self {
+        Route {
+            method: Method::DELETE,
+            path,
+        }
+    }
+}
+
+/// Calls crud as whoever its headers say, cloning is cheap and shares connections
+#[derive(Debug, Clone)]
+pub struct CrudClient {
+    http: reqwest::Client,
+    uri: String,
+    headers: HeaderMap,
+}
+
+impl CrudClient {
+    pub fn new(uri: impl Into<String>) -> Self {
+        Self::with_client(reqwest::Client::new(), uri)
+    }
+
+    /// For callers that configure their own client, such as with timeouts
+    pub fn with_client(http: reqwest::Client, uri: impl Into<String>) -> Self {
+        let uri: String = uri.into();
+        CrudClient {
+            http,
+            uri: uri.trim_end_matches('/').to_string(),
+            headers: HeaderMap::new(),
+        }
+    }
+
+    /// Calls as the user the session cookies belong to
+    pub fn with_cookie(&self, cookie: &str) -> Self {
+        self.with_header(COOKIE, cookie)
+    }
+
+    /// Calls as the user the api key belongs to
+    pub fn with_api_key(&self, key: &str) -> Self {
+        self.with_header(AUTHORIZATION, &format!("Bearer {}", key))
+    }
+
+    /// Calls with the token crud signed for an instance deployment
+    pub fn with_jwt(&self, jwt: &str) -> Self {
+        self.with_header(HeaderName::from_static("jwt"), jwt)
+    }
+
+    /// Calls as an already verified user, the way the gateway forwards requests
+    pub fn as_user(&self, user: &ReqUser) -> Self {
+        self.with_header(
+            HeaderName::from_static("user"),
+            &serde_json::to_string(user).unwrap(),
+        )
+    }
+
+    /// Attributes changes to the user in crud's audit log, without acting as them
+    pub fn with_actor(&self, user: &Option<ReqUser>) -> Self {
+        match user {
+            Some(user) => self.with_header(
+                HeaderName::from_static(ACTOR_HEADER),
+                &serde_json::to_string(user).unwrap(),
+            ),
+            None => self.clone(),
+        }
+    }
+
+    fn with_header(&self, name: HeaderName, value: &str) -> Self {
+        let mut client = self.clone();
+        match HeaderValue::from_str(value) {
+            Ok(value) => {
+                client.headers.insert(name, value);
+            }
+            Err(_) => log::error!("Dropped invalid {} header for crud", name),
+        }
+        client
+    }
+
+    fn request(&self, route: Route, id: Option<&str>) -> RequestBuilder {
+        let path = match id {
+            Some(id) => route.path.replace("{id}", id),
+            None => route.path.to_string(),
+        };
+        self.http
+            .request(route.method, self.uri.clone() + &path)
+            .headers(self.headers.clone())
+    }
+}
+
+/// Sends the request, turning error responses into the error crud responded with
+async fn send(req: RequestBuilder) -> Result<Response, ApiError> {
+    check(dispatch(req).await?).await
+}
+
+async fn dispatch(req: RequestBuilder) -> Result<Response, ApiError> {
+    req.send().await.map_err(|err| {
+        log::error!("[Crud] request failed: {:?}", err);
+        ApiError::new(502, "The service couldn't be reached.".into())
+            .with_code(ErrorCode::ServiceUnavailable)
+    })
+}
+
+async fn check(res: Response) -> Result<Response, ApiError> {
+    if res.status().is_success() {
+        Ok(res)
+    } else {
+        let status = res.status();
+        Err(error_from(status, &res.bytes().await?))
+    }
+}
+
+async fn json<T: DeserializeOwned>(req: RequestBuilder) -> Result<T, ApiError> {
+    Ok(send(req).await?.json().await?)
+}
+
+async fn empty(req: RequestBuilder) -> Result<(), ApiError> {
+    send(req).await.map(|_| ())
+}
+
+fn cookies(res: &Response) -> Vec<String> {
+    res.headers()
+        .get_all(SET_COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .map(String::from)
+        .collect()
+}
+
+/// Crud responds with an ApiError body, anything else is kept as the message
+fn error_from(status: StatusCode, body: &[u8]) -> ApiError {
+    match serde_json::from_slice::<ApiError>(body) {
+        Ok(err) => ApiError {
+            status_code: status.as_u16(),
+            ..err
+        },
+        Err(_) => ApiError::new(status.as_u16(), String::from_utf8_lossy(body).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,57 @@
+use errors::ApiError;
+use models::{types::NotificationKind, NotificationPreference, UpdateNotificationPreference};
+use serde::{Deserialize, Serialize};
+
+use crate::{empty, json, CrudClient, Route};
+
+const SEND: Route = Route::post("/notifications");
+const PREFERENCES: Route = Route::get("/accounts/{id}/notification-preferences");
+const UPDATE_PREFERENCES: Route = Route::put("/accounts/{id}/notification-preferences");
+
+#[cfg(test)]
+pub(crate) const ROUTES: &[Route] = &[SEND, PREFERENCES, UPDATE_PREFERENCES];
+
+/// Account problems only we can fix are sent to
+pub const OPERATOR: &str = "admin";
+
+/// Something an account should hear about, delivered the ways its preferences ask for
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct Notification {
+    /// Account told, OPERATOR for problems only we can fix
+    pub account_id: String,
+    pub kind: NotificationKind,
+    pub subject: String,
+    pub body: String,
+    /// Instance it's about, if any
+    #[serde(default)]
+    pub instance_id: Option<String>,
+}
+
+impl CrudClient {
+    /// Queues the notification, only internal requests and the admin account can send them
+    pub async fn send_notification(&self, notification: &Notification) -> Result<(), ApiError> {
+        empty(self.request(SEND, None).json(notification)).await
+    }
+
+    /// The account's preferences, the defaults if it never changed them
+    pub async fn find_notification_preferences(
+        &self,
+        account_id: &str,
+    ) -> Result<NotificationPreference, ApiError> {
+        json(self.request(PREFERENCES, Some(account_id))).await
+    }
+
+    pub async fn update_notification_preferences(
+        &self,
+        account_id: &str,
+        preferences: &UpdateNotificationPreference,
+    ) -> Result<NotificationPreference, ApiError> {
+        json(
+            self.request(UPDATE_PREFERENCES, Some(account_id))
+                .json(preferences),
+        )
+        .await
+    }
+}
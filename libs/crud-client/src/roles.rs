@@ -0,0 +1,39 @@
+use errors::ApiError;
+use models::{AccountRole, ListQuery, NewAccountRole, Page, UpdateAccountRole};
+
+use crate::{json, CrudClient, DeleteBody, Route};
+
+const FIND_ALL: Route = Route::get("/roles");
+const CREATE: Route = Route::post("/roles");
+const FIND: Route = Route::get("/roles/{id}");
+const UPDATE: Route = Route::put("/roles/{id}");
+const DELETE: Route = Route::delete("/roles/{id}");
+
+#[cfg(test)]
+pub(crate) const ROUTES: &[Route] = &[FIND_ALL, CREATE, FIND, UPDATE, DELETE];
+
+impl CrudClient {
+    pub async fn find_roles(&self, query: &ListQuery) -> Result<Page<AccountRole>, ApiError> {
+        json(self.request(FIND_ALL, None).query(query)).await
+    }
+
+    pub async fn create_role(&self, role: &NewAccountRole) -> Result<AccountRole, ApiError> {
+        json(self.request(CREATE, None).json(role)).await
+    }
+
+    pub async fn find_role(&self, id: &str) -> Result<AccountRole, ApiError> {
+        json(self.request(FIND, Some(id))).await
+    }
+
+    pub async fn update_role(
+        &self,
+        id: &str,
+        role: &UpdateAccountRole,
+    ) -> Result<AccountRole, ApiError> {
+        json(self.request(UPDATE, Some(id)).json(role)).await
+    }
+
+    pub async fn delete_role(&self, id: &str) -> Result<DeleteBody, ApiError> {
+        json(self.request(DELETE, Some(id))).await
+    }
+}
@@ -0,0 +1,90 @@
+use std::collections::BTreeSet;
+
+use errors::{ApiError, ErrorCode};
+use reqwest::{Method, StatusCode};
+
+use crate::{
+    accounts, api_keys, audit, auth, error_from, instances, invites, notifications, roles, users,
+};
+use crate::{CrudClient, Route};
+
+fn client_routes() -> BTreeSet<String> {
+    [
+        accounts::ROUTES,
+        api_keys::ROUTES,
+        audit::ROUTES,
+        auth::ROUTES,
+        instances::ROUTES,
+        invites::ROUTES,
+        notifications::ROUTES,
+        roles::ROUTES,
+        users::ROUTES,
+    ]
+    .concat()
+    .into_iter()
+    .map(|route| format!("{} {}", route.method, route.path))
+    .collect()
+}
+
+/// crud keeps its openapi.json current, so it is what crud actually routes
+#[test]
+fn routes_match_crud() {
+    let spec: serde_json::Value = serde_json::from_str(include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../../services/crud/openapi.json"
+    )))
+    .unwrap();
+    let crud_routes: BTreeSet<String> = spec["paths"]
+        .as_object()
+        .unwrap()
+        .iter()
+        .flat_map(|(path, item)| {
+            item.as_object()
+                .unwrap()
+                .keys()
+                .map(move |method| format!("{} {}", method.to_uppercase(), path))
+        })
+        .collect();
+    let client_routes = client_routes();
+
+    let missing: Vec<_> = crud_routes.difference(&client_routes).collect();
+    assert!(
+        missing.is_empty(),
+        "crud routes without a method: {:?}",
+        missing
+    );
+    let unknown: Vec<_> = client_routes.difference(&crud_routes).collect();
+    assert!(
+        unknown.is_empty(),
+        "routes crud doesn't have: {:?}",
+        unknown
+    );
+}
+
+#[test]
+fn fills_in_id() {
+    let req = CrudClient::new("http://127.0.0.1:8080/")
+        .with_jwt("token")
+        .request(Route::post("/instances/{id}/callback"), Some("abc"))
+        .build()
+        .unwrap();
+
+    assert_eq!(req.method(), Method::POST);
+    assert_eq!(
+        req.url().as_str(),
+        "http://127.0.0.1:8080/instances/abc/callback"
+    );
+    assert_eq!(req.headers()["jwt"], "token");
+}
+
+#[test]
+fn keeps_crud_errors() {
+    let body = ApiError::not_subbed().to_json();
+    let err = error_from(StatusCode::FORBIDDEN, body.as_bytes());
+    assert_eq!(err.status_code, 403);
+    assert_eq!(err.code, ErrorCode::NotSubscribed);
+
+    let err = error_from(StatusCode::BAD_GATEWAY, b"upstream down");
+    assert_eq!(err.status_code, 502);
+    assert_eq!(err.message, "upstream down");
+}
@@ -0,0 +1,132 @@
+use errors::ApiError;
+use models::{ListQuery, NewUser, Page, UpdateUser, User};
+use reqwest::{header::CONTENT_TYPE, StatusCode};
+use serde::{Deserialize, Serialize};
+
+use crate::{check, dispatch, empty, error_from, json, CrudClient, DeleteBody, Route};
+
+const FIND_ALL: Route = Route::get("/users");
+const CREATE: Route = Route::post("/users");
+const FIND: Route = Route::get("/users/{id}");
+const UPDATE: Route = Route::put("/users/{id}");
+const DELETE: Route = Route::delete("/users/{id}");
+const RESTORE: Route = Route::post("/users/{id}/restore");
+const TOGGLE_STATUS: Route = Route::put("/users/{id}/toggle-status");
+const TRANSFER_OWNER: Route = Route::put("/users/{id}/transfer-owner");
+const CHANGE_PASSWORD: Route = Route::put("/users/me/password");
+const IMPORT: Route = Route::post("/accounts/{id}/users/import");
+
+#[cfg(test)]
+pub(crate) const ROUTES: &[Route] = &[
+    FIND_ALL,
+    CREATE,
+    FIND,
+    UPDATE,
+    DELETE,
+    RESTORE,
+    TOGGLE_STATUS,
+    TRANSFER_OWNER,
+    CHANGE_PASSWORD,
+    IMPORT,
+];
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct PasswordChange {
+    pub current_password: String,
+    pub new_password: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+#[serde(rename_all = "camelCase")]
+pub struct ImportQuery {
+    /// Only check the rows, nothing is created
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct RowError {
+    /// Line in the csv, the header is line 1
+    pub row: u64,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub rows: usize,
+    pub imported: usize,
+    pub errors: Vec<RowError>,
+}
+
+impl CrudClient {
+    pub async fn find_users(&self, query: &ListQuery) -> Result<Page<User>, ApiError> {
+        json(self.request(FIND_ALL, None).query(query)).await
+    }
+
+    pub async fn create_user(&self, user: &NewUser) -> Result<User, ApiError> {
+        json(self.request(CREATE, None).json(user)).await
+    }
+
+    pub async fn find_user(&self, id: &str) -> Result<User, ApiError> {
+        json(self.request(FIND, Some(id))).await
+    }
+
+    pub async fn update_user(&self, id: &str, user: &UpdateUser) -> Result<User, ApiError> {
+        json(self.request(UPDATE, Some(id)).json(user)).await
+    }
+
+    pub async fn delete_user(&self, id: &str) -> Result<DeleteBody, ApiError> {
+        json(self.request(DELETE, Some(id))).await
+    }
+
+    pub async fn restore_user(&self, id: &str) -> Result<User, ApiError> {
+        json(self.request(RESTORE, Some(id))).await
+    }
+
+    pub async fn toggle_user_status(&self, id: &str) -> Result<User, ApiError> {
+        json(self.request(TOGGLE_STATUS, Some(id))).await
+    }
+
+    /// Makes the user the owner of their account
+    pub async fn transfer_owner(&self, id: &str) -> Result<(), ApiError> {
+        empty(self.request(TRANSFER_OWNER, Some(id))).await
+    }
+
+    /// Changes the password of the user the client calls as
+    pub async fn change_password(&self, change: &PasswordChange) -> Result<(), ApiError> {
+        empty(self.request(CHANGE_PASSWORD, None).json(change)).await
+    }
+
+    /// Imports users into the account from a csv, a report with row errors means nothing was
+    /// imported
+    pub async fn import_users(
+        &self,
+        account_id: &str,
+        query: &ImportQuery,
+        csv: String,
+    ) -> Result<ImportReport, ApiError> {
+        let req = self
+            .request(IMPORT, Some(account_id))
+            .query(query)
+            .header(CONTENT_TYPE, "text/csv")
+            .body(csv);
+
+        let res = dispatch(req).await?;
+        if res.status() == StatusCode::BAD_REQUEST {
+            // rows that failed come back as a report, anything else is an error
+            let body = res.bytes().await?;
+            return serde_json::from_slice(&body)
+                .map_err(|_| error_from(StatusCode::BAD_REQUEST, &body));
+        }
+        Ok(check(res).await?.json().await?)
+    }
+}
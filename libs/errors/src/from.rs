@@ -0,0 +1,139 @@
+//! Conversions from the errors of the frameworks and libraries each service uses
+
+#[allow(unused_imports)]
+use crate::{ApiError, ErrorCode};
+
+#[cfg(feature = "actix")]
+impl actix_web::ResponseError for ApiError {
+    fn status_code(&self) -> actix_web::http::StatusCode {
+        actix_web::http::StatusCode::from_u16(self.status_code)
+            .unwrap_or(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR)
+    }
+
+    fn error_response(&self) -> actix_web::HttpResponse {
+        actix_web::HttpResponse::build(actix_web::ResponseError::status_code(self)).json(self)
+    }
+}
+
+#[cfg(feature = "actix")]
+impl From<actix_web::error::BlockingError> for ApiError {
+    fn from(_err: actix_web::error::BlockingError) -> Self {
+        ApiError::server_err()
+    }
+}
+
+#[cfg(feature = "axum")]
+impl axum::response::IntoResponse for ApiError {
+    fn into_response(self) -> axum::response::Response {
+        let status = axum::http::StatusCode::from_u16(self.status_code)
+            .unwrap_or(axum::http::StatusCode::INTERNAL_SERVER_ERROR);
+
+        (status, axum::Json(self)).into_response()
+    }
+}
+
+#[cfg(feature = "diesel")]
+impl From<diesel::result::Error> for ApiError {
+    fn from(error: diesel::result::Error) -> ApiError {
+        use diesel::result::{DatabaseErrorKind, Error as DieselError};
+
+        log::error!("[Diesel] error: {:?}", error);
+        match error {
+            DieselError::DatabaseError(kind, _err) => match kind {
+                DatabaseErrorKind::UniqueViolation => ApiError::new(
+                    409,
+                    "One of the provided fields failed unique validation.".into(),
+                )
+                .with_code(ErrorCode::AlreadyExists),
+                DatabaseErrorKind::UnableToSendCommand => {
+                    ApiError::new(500, "Unable to send command to database.".into())
+                }
+                DatabaseErrorKind::ForeignKeyViolation => {
+                    ApiError::new(409, "Cannot add to an account that doesn't exist.".into())
+                }
+                _ => ApiError::new(500, "A database error occurred.".into()),
+            },
+            DieselError::NotFound => ApiError::new(404, "Record not found".into()),
+            _ => ApiError::new(500, "A database error occurred.".into()),
+        }
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl From<reqwest::Error> for ApiError {
+    fn from(err: reqwest::Error) -> Self {
+        log::error!("[Reqwest] error: {:?}", err);
+        ApiError::server_err()
+    }
+}
+
+#[cfg(feature = "bcrypt")]
+impl From<bcrypt::BcryptError> for ApiError {
+    fn from(err: bcrypt::BcryptError) -> Self {
+        log::error!("[Bcrypt] error: {:?}", err);
+        ApiError::new(500, "Error hashing password.".into())
+    }
+}
+
+#[cfg(feature = "csv")]
+impl From<csv::Error> for ApiError {
+    fn from(err: csv::Error) -> Self {
+        log::error!("[Csv] error: {:?}", err);
+        ApiError::server_err()
+    }
+}
+
+#[cfg(feature = "zip")]
+impl From<zip::result::ZipError> for ApiError {
+    fn from(err: zip::result::ZipError) -> Self {
+        log::error!("[Zip] error: {:?}", err);
+        ApiError::server_err()
+    }
+}
+
+#[cfg(feature = "hyper")]
+impl From<hyper::Error> for ApiError {
+    fn from(err: hyper::Error) -> Self {
+        log::error!("[hyper] An error ocurred: {}", err.message());
+        ApiError::new(500, "An error ocurred with internal communication.".into())
+            .with_code(ErrorCode::ServiceUnavailable)
+    }
+}
+
+#[cfg(feature = "stripe")]
+impl From<stripe::StripeError> for ApiError {
+    fn from(err: stripe::StripeError) -> Self {
+        use stripe::{ErrorType, StripeError};
+
+        log::error!("[Stripe] An error ocurred: {:?}", err);
+        match err {
+            StripeError::ClientError(message) => ApiError::new(400, message),
+            StripeError::Stripe(request_err) => {
+                let message: String = match request_err.error_type {
+                    ErrorType::Card => request_err
+                        .message
+                        .unwrap_or("A card error ocurred.".into()),
+                    ErrorType::InvalidRequest => "Bad details provided to stripe.".into(),
+                    _ => "A Stripe error ocurred.".into(),
+                };
+                let err = ApiError::new(request_err.http_status, message);
+
+                match request_err.error_type {
+                    ErrorType::Card => err.with_code(ErrorCode::PaymentFailed),
+                    _ => err,
+                }
+            }
+            StripeError::Timeout => ApiError::new(500, "An internal server error ocurred.".into())
+                .with_code(ErrorCode::ServiceUnavailable),
+            _ => ApiError::new(500, "An internal server error ocurred.".into()),
+        }
+    }
+}
+
+#[cfg(feature = "stripe")]
+impl From<stripe::ParseIdError> for ApiError {
+    fn from(_: stripe::ParseIdError) -> Self {
+        log::error!("[Stripe] Invalid id received.");
+        ApiError::new(500, "Invalid id.".into())
+    }
+}
@@ -0,0 +1,238 @@
+mod from;
+#[cfg(feature = "openapi")]
+pub mod openapi;
+
+use serde::{Deserialize, Serialize};
+use validator::{ValidationErrors, ValidationErrorsKind};
+
+/// Why a request failed, stable so clients can match on it instead of the message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    BadRequest,
+    /// Fields failed validation, details says which validator failed on which field
+    ValidationFailed,
+    /// Link or token is invalid or expired
+    InvalidToken,
+    Unauthorized,
+    InvalidCredentials,
+    Forbidden,
+    NotSubscribed,
+    UserDeactivated,
+    NotFound,
+    Conflict,
+    /// A field that has to be unique is already used
+    AlreadyExists,
+    Locked,
+    TooManyRequests,
+    PaymentFailed,
+    Internal,
+    /// A service or provider couldn't be reached
+    ServiceUnavailable,
+}
+
+impl ErrorCode {
+    /// Code for errors that don't have a more specific one
+    pub fn from_status(status_code: u16) -> Self {
+        match status_code {
+            401 => ErrorCode::Unauthorized,
+            402 => ErrorCode::PaymentFailed,
+            403 => ErrorCode::Forbidden,
+            404 => ErrorCode::NotFound,
+            409 => ErrorCode::Conflict,
+            423 => ErrorCode::Locked,
+            429 => ErrorCode::TooManyRequests,
+            502..=504 => ErrorCode::ServiceUnavailable,
+            400..=499 => ErrorCode::BadRequest,
+            _ => ErrorCode::Internal,
+        }
+    }
+}
+
+/// One validator that failed on one field
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct FieldError {
+    /// Path to the field, nested fields and list items are joined with dots
+    pub field: String,
+    /// Name of the validator, like "length", "regex" or a custom validator's code
+    pub code: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// Error every service responds with, serialized as the response body
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ApiError {
+    #[serde(skip, default = "internal_status")]
+    pub status_code: u16,
+    pub code: ErrorCode,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub details: Vec<FieldError>,
+}
+
+fn internal_status() -> u16 {
+    500
+}
+
+impl ApiError {
+    pub fn new(status_code: u16, message: String) -> ApiError {
+        ApiError {
+            status_code,
+            code: ErrorCode::from_status(status_code),
+            message,
+            details: vec![],
+        }
+    }
+
+    pub fn with_code(self, code: ErrorCode) -> ApiError {
+        ApiError { code, ..self }
+    }
+
+    pub fn server_err() -> ApiError {
+        ApiError::new(500, "Internal server error.".into())
+    }
+
+    pub fn forbidden() -> ApiError {
+        ApiError::new(403, "You do not have access to this resource".into())
+    }
+
+    pub fn unauthorized() -> ApiError {
+        ApiError::new(401, "You are not authorized.".into())
+    }
+
+    pub fn not_subbed() -> ApiError {
+        ApiError::new(403, "You cannot do this while not subscribed.".into())
+            .with_code(ErrorCode::NotSubscribed)
+    }
+
+    /// Response body, for services that build responses by hand
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(self.message.as_str())
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+fn field_errors(path: &str, errors: &ValidationErrors, found: &mut Vec<FieldError>) {
+    for (field, kind) in errors.errors() {
+        let path = if path.is_empty() {
+            field.to_string()
+        } else {
+            format!("{}.{}", path, field)
+        };
+
+        match kind {
+            ValidationErrorsKind::Field(failed) => {
+                found.extend(failed.iter().map(|failed| FieldError {
+                    field: path.clone(),
+                    code: failed.code.to_string(),
+                    message: failed.message.as_ref().map(|message| message.to_string()),
+                }))
+            }
+            ValidationErrorsKind::Struct(nested) => field_errors(&path, nested, found),
+            ValidationErrorsKind::List(items) => {
+                for (index, nested) in items {
+                    field_errors(&format!("{}.{}", path, index), nested, found);
+                }
+            }
+        }
+    }
+}
+
+impl From<ValidationErrors> for ApiError {
+    fn from(err: ValidationErrors) -> Self {
+        let mut details = vec![];
+        field_errors("", &err, &mut details);
+        details.sort_by(|a, b| a.field.cmp(&b.field));
+
+        let mut fields: Vec<&str> = details.iter().map(|failed| failed.field.as_str()).collect();
+        fields.dedup();
+        let message = format!("Validation failed for fields: {}", fields.join(", "));
+        log::error!("Validation failed: {}", message);
+
+        ApiError {
+            details,
+            ..ApiError::new(400, message).with_code(ErrorCode::ValidationFailed)
+        }
+    }
+}
+
+impl From<serde_json::Error> for ApiError {
+    fn from(err: serde_json::Error) -> Self {
+        log::error!("[Serde] error: {:?}", err);
+        match err.classify() {
+            // the request body didn't match
+            serde_json::error::Category::Data => ApiError::new(400, err.to_string()),
+            _ => ApiError::server_err(),
+        }
+    }
+}
+
+impl From<std::io::Error> for ApiError {
+    fn from(err: std::io::Error) -> Self {
+        log::error!("[Io] error: {:?}", err);
+        ApiError::server_err()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use validator::Validate;
+
+    #[derive(Validate)]
+    struct Signup {
+        #[validate(length(min = 1), email)]
+        email: String,
+        #[validate(range(min = 18))]
+        age: u32,
+    }
+
+    #[test]
+    fn validation_details() {
+        let err = ApiError::from(
+            Signup {
+                email: "".into(),
+                age: 3,
+            }
+            .validate()
+            .unwrap_err(),
+        );
+
+        assert_eq!(err.status_code, 400);
+        assert_eq!(err.code, ErrorCode::ValidationFailed);
+        assert_eq!(err.message, "Validation failed for fields: age, email");
+        let failed: Vec<(&str, &str)> = err
+            .details
+            .iter()
+            .map(|failed| (failed.field.as_str(), failed.code.as_str()))
+            .collect();
+        assert_eq!(failed.len(), 3);
+        assert!(failed.contains(&("age", "range")));
+        assert!(failed.contains(&("email", "length")));
+        assert!(failed.contains(&("email", "email")));
+    }
+
+    #[test]
+    fn body() {
+        let body: serde_json::Value =
+            serde_json::from_str(&ApiError::not_subbed().to_json()).unwrap();
+
+        assert_eq!(body["code"], "not_subscribed");
+        assert_eq!(body["message"], "You cannot do this while not subscribed.");
+        assert!(body.get("details").is_none());
+        assert!(body.get("statusCode").is_none());
+    }
+}
@@ -0,0 +1,47 @@
+//! Documents the error body every operation can respond with
+
+use utoipa::openapi::{response::ResponseBuilder, ContentBuilder, OpenApi, Ref, RefOr, Response};
+use utoipa::{Modify, ToSchema};
+
+use crate::{ApiError, ErrorCode, FieldError};
+
+/// Adds `ApiError` to the components and as the default response of operations without one
+pub struct ErrorResponses;
+
+impl Modify for ErrorResponses {
+    fn modify(&self, openapi: &mut OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        for (name, schema) in [
+            ApiError::schema(),
+            ErrorCode::schema(),
+            FieldError::schema(),
+        ] {
+            components.schemas.insert(name.into(), schema);
+        }
+        components.responses.insert(
+            "Error".into(),
+            RefOr::T(
+                ResponseBuilder::new()
+                    .description("Request failed, match on the code instead of the message")
+                    .content(
+                        "application/json",
+                        ContentBuilder::new()
+                            .schema(Ref::from_schema_name("ApiError"))
+                            .build(),
+                    )
+                    .build(),
+            ),
+        );
+
+        let error: RefOr<Response> = Ref::from_response_name("Error").into();
+        for item in openapi.paths.paths.values_mut() {
+            for operation in item.operations.values_mut() {
+                operation
+                    .responses
+                    .responses
+                    .entry("default".into())
+                    .or_insert_with(|| error.clone());
+            }
+        }
+    }
+}
@@ -1,7 +1,7 @@
 macro_rules! account_models {
     () => {
         model! {
-            String, NaiveDateTime, "accounts", NewAccount, UpdateAccount, "server gen",
+            String, NaiveDateTime, "accounts", NewAccount, UpdateAccount, "server gen", "soft delete",
             Account {
                 #[validate(length(min = 1))]
                 address1: String,
@@ -37,6 +37,7 @@ pub mod schema {
             id -> Text,
             created_at -> Timestamp,
             updated_at -> Timestamp,
+            deleted_at -> Nullable<Timestamp>,
             address1 -> Text,
             address2 -> Nullable<Text>,
             email -> Text,
@@ -0,0 +1,53 @@
+macro_rules! account_closure_models {
+    ($parent:ident) => {
+        child_model! {
+            String, NaiveDateTime, "account_closures", NewAccountClosure, UpdateAccountClosure, "server gen", $parent,
+            AccountClosure {
+                account_id: String,
+                /// User that asked for the account to be closed, none for internal requests
+                requested_by: Option<String>,
+                /// Next step to run, steps before it are finished and aren't run again
+                step: ClosureStep,
+                attempts: i32,
+                /// Why the last attempt stopped, cleared once a step finishes
+                last_error: Option<String>,
+                completed_at: Option<NaiveDateTime>,
+            }
+        }
+    };
+}
+
+#[cfg(feature = "diesel")]
+pub mod schema {
+    use diesel::table;
+
+    table! {
+        use diesel::sql_types::*;
+        use crate::types::closure_step_sql::ClosureStep;
+
+        account_closures {
+            id -> Text,
+            created_at -> Timestamp,
+            updated_at -> Timestamp,
+            account_id -> Text,
+            requested_by -> Nullable<Text>,
+            step -> ClosureStep,
+            attempts -> Int4,
+            last_error -> Nullable<Text>,
+            completed_at -> Nullable<Timestamp>,
+        }
+    }
+}
+
+pub mod model {
+    #[cfg(feature = "diesel")]
+    use super::schema::account_closures;
+    use crate::types::*;
+    #[cfg(feature = "diesel")]
+    use crate::Account;
+    use chrono::NaiveDateTime;
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    account_closure_models!(Account);
+}
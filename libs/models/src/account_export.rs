@@ -0,0 +1,53 @@
+macro_rules! account_export_models {
+    ($parent:ident) => {
+        child_model! {
+            String, NaiveDateTime, "account_exports", NewAccountExport, UpdateAccountExport, "server gen", $parent,
+            AccountExport {
+                account_id: String,
+                /// User that asked for the export, none for internal requests
+                requested_by: Option<String>,
+                /// Zip of the account's data, set once the export is ready and only sent as a download
+                #[serde(skip)]
+                archive: Option<Vec<u8>>,
+                /// Why building the archive failed, a new export is started the next time it's asked for
+                error: Option<String>,
+                completed_at: Option<NaiveDateTime>,
+                /// Archive is dropped after this and a new export has to be built
+                expires_at: NaiveDateTime,
+            }
+        }
+    };
+}
+
+#[cfg(feature = "diesel")]
+pub mod schema {
+    use diesel::table;
+
+    table! {
+        use diesel::sql_types::*;
+
+        account_exports {
+            id -> Text,
+            created_at -> Timestamp,
+            updated_at -> Timestamp,
+            account_id -> Text,
+            requested_by -> Nullable<Text>,
+            archive -> Nullable<Bytea>,
+            error -> Nullable<Text>,
+            completed_at -> Nullable<Timestamp>,
+            expires_at -> Timestamp,
+        }
+    }
+}
+
+pub mod model {
+    #[cfg(feature = "diesel")]
+    use super::schema::account_exports;
+    #[cfg(feature = "diesel")]
+    use crate::Account;
+    use chrono::NaiveDateTime;
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    account_export_models!(Account);
+}
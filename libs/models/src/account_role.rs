@@ -0,0 +1,46 @@
+macro_rules! account_role_models {
+    () => {
+        model! {
+            String, NaiveDateTime, "roles", NewAccountRole, UpdateAccountRole, "server gen",
+            #[cfg_attr(feature = "diesel", table_name = "roles")]
+            AccountRole {
+                /// None for the built in roles, which every account can use
+                #[serde(default)]
+                account_id: Option<String>,
+                #[validate(length(min = 1))]
+                name: String,
+                capabilities: Vec<Capability>,
+            }
+        }
+    };
+}
+
+#[cfg(feature = "diesel")]
+pub mod schema {
+    use diesel::table;
+
+    table! {
+        use diesel::sql_types::*;
+        use crate::types::capability_sql::Capability;
+
+        roles {
+            id -> Text,
+            created_at -> Timestamp,
+            updated_at -> Timestamp,
+            account_id -> Nullable<Text>,
+            name -> Text,
+            capabilities -> Array<Capability>,
+        }
+    }
+}
+
+pub mod model {
+    #[cfg(feature = "diesel")]
+    use super::schema::roles;
+    use crate::types::*;
+    use chrono::NaiveDateTime;
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    account_role_models!();
+}
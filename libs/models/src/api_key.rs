@@ -0,0 +1,63 @@
+macro_rules! api_key_models {
+    ($parent:ident) => {
+        child_model! {
+            String, NaiveDateTime, "api_keys", NewApiKey, UpdateApiKey, "server gen", $parent,
+            ApiKey {
+                #[serde(default)]
+                account_id: String,
+                #[validate(length(min = 1))]
+                name: String,
+                /// Highest role the key can act as
+                role: Role,
+                /// Hash of the key's secret, the key itself is only shown once
+                #[serde(skip)]
+                key_hash: String,
+                /// User that created the key
+                #[serde(default)]
+                created_by: String,
+                expires_at: Option<NaiveDateTime>,
+                #[serde(default)]
+                last_used_at: Option<NaiveDateTime>,
+                #[serde(default)]
+                revoked_at: Option<NaiveDateTime>,
+            }
+        }
+    };
+}
+
+#[cfg(feature = "diesel")]
+pub mod schema {
+    use diesel::table;
+
+    table! {
+        use diesel::sql_types::*;
+        use crate::types::role_sql::Role;
+
+        api_keys {
+            id -> Text,
+            created_at -> Timestamp,
+            updated_at -> Timestamp,
+            account_id -> Text,
+            name -> Text,
+            role -> Role,
+            key_hash -> Text,
+            created_by -> Text,
+            expires_at -> Nullable<Timestamp>,
+            last_used_at -> Nullable<Timestamp>,
+            revoked_at -> Nullable<Timestamp>,
+        }
+    }
+}
+
+pub mod model {
+    #[cfg(feature = "diesel")]
+    use super::schema::api_keys;
+    use crate::types::*;
+    #[cfg(feature = "diesel")]
+    use crate::Account;
+    use chrono::NaiveDateTime;
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    api_key_models!(Account);
+}
@@ -0,0 +1,60 @@
+macro_rules! audit_event_models {
+    ($parent:ident) => {
+        child_model! {
+            String, NaiveDateTime, "audit_events", NewAuditEvent, UpdateAuditEvent, "server gen", $parent,
+            AuditEvent {
+                account_id: String,
+                /// User or api key that made the change, none for internal requests
+                actor_id: Option<String>,
+                actor_role: Option<Role>,
+                /// What was done, e.g. "create", "update", "transfer_owner"
+                action: String,
+                target_type: String,
+                target_id: String,
+                /// Changed fields before the action, only what changed is kept
+                before: Option<serde_json::Value>,
+                /// Changed fields after the action
+                after: Option<serde_json::Value>,
+                ip: Option<String>,
+            }
+        }
+    };
+}
+
+#[cfg(feature = "diesel")]
+pub mod schema {
+    use diesel::table;
+
+    table! {
+        use diesel::sql_types::*;
+        use crate::types::role_sql::Role;
+
+        audit_events {
+            id -> Text,
+            created_at -> Timestamp,
+            updated_at -> Timestamp,
+            account_id -> Text,
+            actor_id -> Nullable<Text>,
+            actor_role -> Nullable<Role>,
+            action -> Text,
+            target_type -> Text,
+            target_id -> Text,
+            before -> Nullable<Jsonb>,
+            after -> Nullable<Jsonb>,
+            ip -> Nullable<Text>,
+        }
+    }
+}
+
+pub mod model {
+    #[cfg(feature = "diesel")]
+    use super::schema::audit_events;
+    use crate::types::*;
+    #[cfg(feature = "diesel")]
+    use crate::Account;
+    use chrono::NaiveDateTime;
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    audit_event_models!(Account);
+}
@@ -1,7 +1,7 @@
 macro_rules! instance_models {
     ($parent:ident) => {
         child_model! {
-            String, NaiveDateTime, "instances", NewInstance, UpdateInstance, "sever gen", $parent,
+            String, NaiveDateTime, "instances", NewInstance, UpdateInstance, "sever gen", "soft delete", $parent,
             Instance {
                 account_id: String,
                 #[validate(length(min = 1))]
@@ -51,6 +51,7 @@ pub mod schema {
             id -> Text,
             created_at -> Timestamp,
             updated_at -> Timestamp,
+            deleted_at -> Nullable<Timestamp>,
             account_id -> Text,
             business_name -> Text,
             short_name -> Text,
@@ -0,0 +1,50 @@
+macro_rules! instance_health_check_models {
+    ($parent:ident) => {
+        child_model! {
+            String, NaiveDateTime, "instance_health_checks", NewInstanceHealthCheck, UpdateInstanceHealthCheck, "server gen", $parent,
+            InstanceHealthCheck {
+                instance_id: String,
+                account_id: String,
+                /// Answered /health with the redirect a running instance gives
+                healthy: bool,
+                /// How long the instance took to answer, none if it didn't
+                latency_ms: Option<i32>,
+                /// Http status it answered with
+                status_code: Option<i32>,
+                /// Why the request failed, none if the instance answered
+                error: Option<String>,
+            }
+        }
+    };
+}
+
+#[cfg(feature = "diesel")]
+pub mod schema {
+    use diesel::table;
+
+    table! {
+        instance_health_checks {
+            id -> Text,
+            created_at -> Timestamp,
+            updated_at -> Timestamp,
+            instance_id -> Text,
+            account_id -> Text,
+            healthy -> Bool,
+            latency_ms -> Nullable<Int4>,
+            status_code -> Nullable<Int4>,
+            error -> Nullable<Text>,
+        }
+    }
+}
+
+pub mod model {
+    #[cfg(feature = "diesel")]
+    use super::schema::instance_health_checks;
+    #[cfg(feature = "diesel")]
+    use crate::Instance;
+    use chrono::NaiveDateTime;
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    instance_health_check_models!(Instance);
+}
@@ -0,0 +1,50 @@
+macro_rules! instance_status_change_models {
+    ($parent:ident) => {
+        child_model! {
+            String, NaiveDateTime, "instance_status_history", NewInstanceStatusChange, UpdateInstanceStatusChange, "server gen", $parent,
+            #[cfg_attr(feature = "diesel", table_name = "instance_status_history")]
+            InstanceStatusChange {
+                instance_id: String,
+                account_id: String,
+                /// None when the instance was created with its first status
+                from_status: Option<InstanceStatus>,
+                to_status: InstanceStatus,
+                event: InstanceEvent,
+            }
+        }
+    };
+}
+
+#[cfg(feature = "diesel")]
+pub mod schema {
+    use diesel::table;
+
+    table! {
+        use diesel::sql_types::*;
+        use crate::types::instance_status_sql::{InstanceEvent, InstanceStatus};
+
+        instance_status_history {
+            id -> Text,
+            created_at -> Timestamp,
+            updated_at -> Timestamp,
+            instance_id -> Text,
+            account_id -> Text,
+            from_status -> Nullable<InstanceStatus>,
+            to_status -> InstanceStatus,
+            event -> InstanceEvent,
+        }
+    }
+}
+
+pub mod model {
+    #[cfg(feature = "diesel")]
+    use super::schema::instance_status_history;
+    use crate::types::*;
+    #[cfg(feature = "diesel")]
+    use crate::Instance;
+    use chrono::NaiveDateTime;
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    instance_status_change_models!(Instance);
+}
@@ -0,0 +1,71 @@
+macro_rules! invite_models {
+    ($parent:ident) => {
+        child_model! {
+            String, NaiveDateTime, "invites", NewInvite, UpdateInvite, "server gen", $parent,
+            Invite {
+                account_id: String,
+                /// Where the invite link is sent
+                #[validate(regex = "crate::EMAIL_RE")]
+                email: String,
+                /// Role and perms the user gets when they accept
+                role_id: String,
+                instances: Vec<String>,
+                create_perms: Vec<Resource>,
+                update_perms: Vec<Resource>,
+                delete_perms: Vec<Resource>,
+                /// User that sent the invite
+                invited_by: String,
+                /// Hash of the signed invite token, the token itself is only ever emailed
+                #[serde(skip)]
+                token_hash: String,
+                expires_at: NaiveDateTime,
+                accepted_at: Option<NaiveDateTime>,
+                revoked_at: Option<NaiveDateTime>,
+                /// User created by accepting the invite
+                user_id: Option<String>,
+            }
+        }
+    };
+}
+
+#[cfg(feature = "diesel")]
+pub mod schema {
+    use diesel::table;
+
+    table! {
+        use diesel::sql_types::*;
+        use crate::types::resource_sql::Resource;
+
+        invites {
+            id -> Text,
+            created_at -> Timestamp,
+            updated_at -> Timestamp,
+            account_id -> Text,
+            email -> Text,
+            role_id -> Text,
+            instances -> Array<Text>,
+            create_perms -> Array<Resource>,
+            update_perms -> Array<Resource>,
+            delete_perms -> Array<Resource>,
+            invited_by -> Text,
+            token_hash -> Text,
+            expires_at -> Timestamp,
+            accepted_at -> Nullable<Timestamp>,
+            revoked_at -> Nullable<Timestamp>,
+            user_id -> Nullable<Text>,
+        }
+    }
+}
+
+pub mod model {
+    #[cfg(feature = "diesel")]
+    use super::schema::invites;
+    use crate::types::*;
+    #[cfg(feature = "diesel")]
+    use crate::Account;
+    use chrono::NaiveDateTime;
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    invite_models!(Account);
+}
@@ -0,0 +1,55 @@
+macro_rules! job_models {
+    () => {
+        model! {
+            String, NaiveDateTime, "jobs", NewJob, UpdateJob, "server gen",
+            Job {
+                /// What to run, e.g. "ensure_deployment"
+                kind: String,
+                /// Arguments for the kind of job
+                payload: serde_json::Value,
+                /// Not run before this, pushed back after a failed attempt
+                run_at: NaiveDateTime,
+                attempts: i32,
+                /// Left unfinished with its last error once it has failed this many times
+                max_attempts: i32,
+                /// Set while a runner has claimed the job, another runner can take it after
+                locked_until: Option<NaiveDateTime>,
+                last_error: Option<String>,
+                completed_at: Option<NaiveDateTime>,
+            }
+        }
+    };
+}
+
+#[cfg(feature = "diesel")]
+pub mod schema {
+    use diesel::table;
+
+    table! {
+        use diesel::sql_types::*;
+
+        jobs {
+            id -> Text,
+            created_at -> Timestamp,
+            updated_at -> Timestamp,
+            kind -> Text,
+            payload -> Jsonb,
+            run_at -> Timestamp,
+            attempts -> Int4,
+            max_attempts -> Int4,
+            locked_until -> Nullable<Timestamp>,
+            last_error -> Nullable<Text>,
+            completed_at -> Nullable<Timestamp>,
+        }
+    }
+}
+
+pub mod model {
+    #[cfg(feature = "diesel")]
+    use super::schema::jobs;
+    use chrono::NaiveDateTime;
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    job_models!();
+}
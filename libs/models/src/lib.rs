@@ -20,7 +20,63 @@ mod instance;
 pub use instance::model::*;
 #[cfg(feature = "diesel")]
 pub use instance::schema::*;
+mod session;
+pub use session::model::*;
+#[cfg(feature = "diesel")]
+pub use session::schema::*;
+mod password_reset;
+pub use password_reset::model::*;
+#[cfg(feature = "diesel")]
+pub use password_reset::schema::*;
+mod mfa;
+pub use mfa::model::*;
+#[cfg(feature = "diesel")]
+pub use mfa::schema::*;
+mod api_key;
+pub use api_key::model::*;
+#[cfg(feature = "diesel")]
+pub use api_key::schema::*;
+mod account_role;
+pub use account_role::model::*;
+#[cfg(feature = "diesel")]
+pub use account_role::schema::*;
+mod invite;
+pub use invite::model::*;
+#[cfg(feature = "diesel")]
+pub use invite::schema::*;
+mod account_closure;
+pub use account_closure::model::*;
+#[cfg(feature = "diesel")]
+pub use account_closure::schema::*;
+mod account_export;
+pub use account_export::model::*;
+#[cfg(feature = "diesel")]
+pub use account_export::schema::*;
+mod audit_event;
+pub use audit_event::model::*;
+#[cfg(feature = "diesel")]
+pub use audit_event::schema::*;
+mod instance_health_check;
+pub use instance_health_check::model::*;
+#[cfg(feature = "diesel")]
+pub use instance_health_check::schema::*;
+mod instance_status_change;
+pub use instance_status_change::model::*;
+#[cfg(feature = "diesel")]
+pub use instance_status_change::schema::*;
+mod notification_preference;
+pub use notification_preference::model::*;
+#[cfg(feature = "diesel")]
+pub use notification_preference::schema::*;
+mod job;
+pub use job::model::*;
+#[cfg(feature = "diesel")]
+pub use job::schema::*;
 pub use validator::{Validate, ValidationError, ValidationErrors};
+mod list;
+pub use list::*;
+mod password;
+pub use password::*;
 pub mod types;
 
 // validation regular expressions
@@ -37,10 +93,36 @@ pub trait Model<Id, New, Up, Err> {
     where
         Self: Sized;
 
+    /// Finds one page of records matching query, only within account if one is given
+    fn find_page(account: Option<String>, query: ListQuery) -> Result<Page<Self>, Err>
+    where
+        Self: Sized;
+
     fn find_by_id(target: Id) -> Result<Self, Err>
     where
         Self: Sized;
 
+    /// Finds all records belonging to account
+    fn find_all_in(account: String) -> Result<Vec<Self>, Err>
+    where
+        Self: Sized;
+
+    /// Finds record by id, errors with not found if it doesn't belong to account
+    fn find_in(account: String, target: Id) -> Result<Self, Err>
+    where
+        Self: Sized;
+
+    /// Finds record within account if one is given, otherwise finds it anywhere
+    fn find_scoped(account: Option<String>, target: Id) -> Result<Self, Err>
+    where
+        Self: Sized,
+    {
+        match account {
+            Some(account) => Self::find_in(account, target),
+            None => Self::find_by_id(target),
+        }
+    }
+
     fn insert(new: New) -> Result<Self, Err>
     where
         Self: Sized;
@@ -52,6 +134,21 @@ pub trait Model<Id, New, Up, Err> {
     fn delete(target: Id) -> Result<usize, Err>;
 }
 
+/// Models which are hidden when deleted, `Model` queries skip them until they're restored
+pub trait SoftDelete<Id, Err> {
+    /// Finds deleted record within account if one is given, otherwise finds it anywhere
+    fn find_deleted(account: Option<String>, target: Id) -> Result<Self, Err>
+    where
+        Self: Sized;
+
+    fn restore(target: Id) -> Result<Self, Err>
+    where
+        Self: Sized;
+
+    /// Hard deletes records which were deleted before cutoff
+    fn purge(cutoff: chrono::NaiveDateTime) -> Result<usize, Err>;
+}
+
 const STATES: [&str; 50] = [
     "AL", "AK", "AZ", "AR", "CA", "CO", "CT", "DE", "FL", "GA", "HI", "ID", "IL", "IN", "IA", "KS",
     "KY", "LA", "ME", "MD", "MA", "MI", "MN", "MS", "MO", "MT", "NE", "NV", "NH", "NJ", "NM", "NY",
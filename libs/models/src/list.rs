@@ -0,0 +1,85 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::types::{InstanceStatus, Role};
+#[cfg(feature = "openapi")]
+use crate::{Account, AccountRole, ApiKey, AuditEvent, Instance, Invite, User};
+
+/// Page size used when a list request doesn't set one
+pub const DEFAULT_LIMIT: i64 = 50;
+/// Largest page size a list request can ask for
+pub const MAX_LIMIT: i64 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+/// Query params accepted by list routes, filters which don't exist on a model are ignored
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+#[serde(rename_all = "camelCase")]
+pub struct ListQuery {
+    pub limit: Option<i64>,
+    /// Offset to start at, use the `nextCursor` of the previous page
+    pub cursor: Option<i64>,
+    /// camelCase name of the field to sort by, defaults to createdAt
+    pub sort: Option<String>,
+    pub order: Option<SortOrder>,
+    pub created_after: Option<NaiveDateTime>,
+    pub created_before: Option<NaiveDateTime>,
+    pub status: Option<InstanceStatus>,
+    pub role: Option<Role>,
+    pub active: Option<bool>,
+}
+
+impl ListQuery {
+    pub fn limit(&self) -> i64 {
+        self.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+    }
+
+    pub fn offset(&self) -> i64 {
+        self.cursor.unwrap_or(0).max(0)
+    }
+
+    pub fn descending(&self) -> bool {
+        self.order == Some(SortOrder::Desc)
+    }
+}
+
+/// Envelope returned by list routes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "openapi", aliases(
+    AccountPage = Page<Account>,
+    UserPage = Page<User>,
+    InstancePage = Page<Instance>,
+    ApiKeyPage = Page<ApiKey>,
+    AccountRolePage = Page<AccountRole>,
+    InvitePage = Page<Invite>,
+    AuditEventPage = Page<AuditEvent>,
+))]
+#[serde(rename_all = "camelCase")]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Number of items matching the filters across all pages
+    pub total: i64,
+    /// Cursor for the next page, none if this is the last page
+    pub next_cursor: Option<i64>,
+}
+
+impl<T> Page<T> {
+    pub fn new(items: Vec<T>, total: i64, query: &ListQuery) -> Self {
+        let end = query.offset() + items.len() as i64;
+
+        Self {
+            next_cursor: if end < total { Some(end) } else { None },
+            items,
+            total,
+        }
+    }
+}
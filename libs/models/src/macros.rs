@@ -5,6 +5,60 @@
 /// Make sure serde::Serialize and serde::Deserialize are in scope
 #[macro_export]
 macro_rules! model {
+	// For models which are hidden instead of deleted, deleted_at is set until they're restored or purged
+	(
+		$id_type:ty, $t_stamp_type:ty, $table_name:tt, $new_name:ident, $up_name:ident, $server_gen_id:tt, "soft delete",
+		$(#[$meta:meta])*
+		$struct_name:ident {
+			$(
+				$(#[$field_meta:meta])*
+				$field_name:ident : $field_type:ty
+			),*$(,)+
+		}
+	) => {
+		#[derive(Validate, Debug, Clone, Serialize, Deserialize, PartialEq)]
+		#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+		#[cfg_attr(feature = "diesel", derive(Insertable, Queryable, Identifiable))]
+		#[serde(rename_all = "camelCase")]
+		$(#[$meta])*
+		pub struct $struct_name {
+			pub id: $id_type,
+			pub created_at: $t_stamp_type,
+        	pub updated_at: $t_stamp_type,
+			#[serde(default)]
+			pub deleted_at: Option<$t_stamp_type>,
+			$(
+				$(#[$field_meta])*
+				pub $field_name: $field_type,
+			)*
+		}
+
+		#[derive(Validate, Debug, Clone, Serialize, Deserialize)]
+		#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+		#[cfg_attr(feature = "diesel", derive(Insertable))]
+		#[cfg_attr(feature = "diesel", table_name=$table_name)]
+		#[serde(rename_all = "camelCase")]
+		pub struct $new_name {
+			#[serde(skip_deserializing)]
+			pub id: $id_type,
+			$(
+				$(#[$field_meta])*
+				pub $field_name: $field_type,
+			)*
+		}
+
+		#[derive(Validate, Debug, Default, Clone, Serialize, Deserialize)]
+		#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+		#[cfg_attr(feature = "diesel", derive(AsChangeset))]
+		#[cfg_attr(feature = "diesel", table_name=$table_name)]
+		#[serde(rename_all = "camelCase")]
+		pub struct $up_name {
+			$(
+				$(#[$field_meta])*
+				pub $field_name: Option<$field_type>,
+			)*
+		}
+	};
 	(
 		$id_type:ty, $t_stamp_type:ty, $table_name:tt, $new_name:ident, $up_name:ident,
 		$(#[$meta:meta])*
@@ -16,6 +70,7 @@ macro_rules! model {
 		}
 	) => {
 		#[derive(Validate, Debug, Clone, Serialize, Deserialize, PartialEq)]
+		#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 		#[cfg_attr(feature = "diesel", derive(Insertable, Queryable, Identifiable))]
 		#[serde(rename_all = "camelCase")]
 		$(#[$meta])*
@@ -30,6 +85,7 @@ macro_rules! model {
 		}
 
 		#[derive(Validate, Debug, Clone, Serialize, Deserialize, Insertable)]
+		#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 		#[cfg_attr(feature = "diesel", derive(Insertable))]
 		#[serde(rename_all = "camelCase")]
 		#[cfg_attr(feature = "diesel", table_name=$table_name)]
@@ -41,6 +97,7 @@ macro_rules! model {
 		}
 
 		#[derive(Validate, Debug, Default, Clone, Serialize, Deserialize)]
+		#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 		#[cfg_attr(feature = "diesel", derive(AsChangeset))]
 		#[cfg_attr(feature = "diesel", table_name=$table_name)]
 		#[serde(rename_all = "camelCase")]
@@ -63,6 +120,7 @@ macro_rules! model {
 		}
 	) => {
 		#[derive(Validate, Debug, Clone, Serialize, Deserialize, PartialEq)]
+		#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 		#[cfg_attr(feature = "diesel", derive(Insertable, Queryable, Identifiable))]
 		#[serde(rename_all = "camelCase")]
 		$(#[$meta])*
@@ -77,6 +135,7 @@ macro_rules! model {
 		}
 
 		#[derive(Validate, Debug, Clone, Serialize, Deserialize)]
+		#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 		#[cfg_attr(feature = "diesel", derive(Insertable))]
 		#[cfg_attr(feature = "diesel", table_name=$table_name)]
 		#[serde(rename_all = "camelCase")]
@@ -90,6 +149,7 @@ macro_rules! model {
 		}
 
 		#[derive(Validate, Debug, Default, Clone, Serialize, Deserialize)]
+		#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 		#[cfg_attr(feature = "diesel", derive(AsChangeset))]
 		#[cfg_attr(feature = "diesel", table_name=$table_name)]
 		#[serde(rename_all = "camelCase")]
@@ -104,6 +164,61 @@ macro_rules! model {
 
 #[macro_export]
 macro_rules! child_model {
+	// For models which are hidden instead of deleted, deleted_at is set until they're restored or purged
+	(
+		$id_type:ty, $t_stamp_type:ty, $table_name:tt, $new_name:ident, $up_name:ident, $server_gen_id:tt, "soft delete", $parent:ident,
+		$(#[$meta:meta])*
+		$struct_name:ident {
+			$(
+				$(#[$field_meta:meta])*
+				$field_name:ident : $field_type:ty
+			),*$(,)+
+		}
+	) => {
+		#[derive(Validate, Debug, Clone, Serialize, Deserialize, PartialEq)]
+		#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+		#[cfg_attr(feature = "diesel", derive(Identifiable, Associations, Queryable, Insertable))]
+		#[cfg_attr(feature = "diesel", belongs_to($parent))]
+		#[serde(rename_all = "camelCase")]
+		$(#[$meta])*
+		pub struct $struct_name {
+			pub id: $id_type,
+			pub created_at: $t_stamp_type,
+        	pub updated_at: $t_stamp_type,
+			#[serde(default)]
+			pub deleted_at: Option<$t_stamp_type>,
+			$(
+				$(#[$field_meta])*
+				pub $field_name: $field_type,
+			)*
+		}
+
+		#[derive(Validate, Debug, Clone, Serialize, Deserialize)]
+		#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+		#[cfg_attr(feature = "diesel", derive(Identifiable, Insertable))]
+		#[cfg_attr(feature = "diesel", table_name=$table_name)]
+		#[serde(rename_all = "camelCase")]
+		pub struct $new_name {
+			#[serde(skip_deserializing)]
+			pub id: $id_type,
+			$(
+				$(#[$field_meta])*
+				pub $field_name: $field_type,
+			)*
+		}
+
+		#[derive(Validate, Debug, Default, Clone, Serialize, Deserialize)]
+		#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+		#[cfg_attr(feature = "diesel", derive(AsChangeset))]
+		#[cfg_attr(feature = "diesel", table_name=$table_name)]
+		#[serde(rename_all = "camelCase")]
+		pub struct $up_name {
+			$(
+				$(#[$field_meta])*
+				pub $field_name: Option<$field_type>,
+			)*
+		}
+	};
 	(
 		$id_type:ty, $t_stamp_type:ty, $table_name:tt, $new_name:ident, $up_name:ident, $parent:ident,
 		$(#[$meta:meta])*
@@ -115,6 +230,7 @@ macro_rules! child_model {
 		}
 	) => {
 		#[derive(Validate, Debug, Clone, Serialize, Deserialize, PartialEq)]
+		#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 		#[cfg_attr(feature = "diesel", derive(Identifiable, Associations, Queryable, Insertable))]
 		#[cfg_attr(feature = "diesel", belongs_to($parent))]
 		#[cfg_attr(feature = "diesel", table_name=$table_name)]
@@ -131,6 +247,7 @@ macro_rules! child_model {
 		}
 
 		#[derive(Validate, Debug, Clone, Serialize, Deserialize)]
+		#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 		#[cfg_attr(feature = "diesel", derive(Insertable))]
 		#[serde(rename_all = "camelCase")]
 		#[cfg_attr(feature = "diesel", table_name=$table_name)]
@@ -142,6 +259,7 @@ macro_rules! child_model {
 		}
 
 		#[derive(Validate, Debug, Default, Clone, Serialize, Deserialize)]
+		#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 		#[cfg_attr(feature = "diesel", derive(AsChangeset))]
 		#[cfg_attr(feature = "diesel", table_name=$table_name)]
 		#[serde(rename_all = "camelCase")]
@@ -164,6 +282,7 @@ macro_rules! child_model {
 		}
 	) => {
 		#[derive(Validate, Debug, Clone, Serialize, Deserialize, PartialEq)]
+		#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 		#[cfg_attr(feature = "diesel", derive(Identifiable, Associations, Queryable, Insertable))]
 		#[cfg_attr(feature = "diesel", belongs_to($parent))]
 		#[serde(rename_all = "camelCase")]
@@ -179,6 +298,7 @@ macro_rules! child_model {
 		}
 
 		#[derive(Validate, Debug, Clone, Serialize, Deserialize)]
+		#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 		#[cfg_attr(feature = "diesel", derive(Identifiable, Insertable))]
 		#[cfg_attr(feature = "diesel", table_name=$table_name)]
 		#[serde(rename_all = "camelCase")]
@@ -192,6 +312,7 @@ macro_rules! child_model {
 		}
 
 		#[derive(Validate, Debug, Default, Clone, Serialize, Deserialize)]
+		#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 		#[cfg_attr(feature = "diesel", derive(AsChangeset))]
 		#[cfg_attr(feature = "diesel", table_name=$table_name)]
 		#[serde(rename_all = "camelCase")]
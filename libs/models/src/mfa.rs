@@ -0,0 +1,52 @@
+macro_rules! mfa_models {
+    ($parent:ident) => {
+        child_model! {
+            String, NaiveDateTime, "user_mfa", NewMfa, UpdateMfa, "server gen", $parent,
+            #[cfg_attr(feature = "diesel", table_name = "user_mfa")]
+            Mfa {
+                user_id: String,
+                /// Base32 TOTP secret, only shown once while enrolling
+                #[serde(skip)]
+                secret: String,
+                /// False until the user proves their authenticator works
+                enabled: bool,
+                /// Hashes of the unused recovery codes
+                #[serde(skip)]
+                recovery_codes: Vec<String>,
+                /// Last time step a code was accepted for, codes can't be replayed
+                #[serde(skip)]
+                last_step: Option<i64>,
+            }
+        }
+    };
+}
+
+#[cfg(feature = "diesel")]
+pub mod schema {
+    use diesel::table;
+
+    table! {
+        user_mfa {
+            id -> Text,
+            created_at -> Timestamp,
+            updated_at -> Timestamp,
+            user_id -> Text,
+            secret -> Text,
+            enabled -> Bool,
+            recovery_codes -> Array<Text>,
+            last_step -> Nullable<Int8>,
+        }
+    }
+}
+
+pub mod model {
+    #[cfg(feature = "diesel")]
+    use super::schema::user_mfa;
+    #[cfg(feature = "diesel")]
+    use crate::User;
+    use chrono::NaiveDateTime;
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    mfa_models!(User);
+}
@@ -0,0 +1,50 @@
+macro_rules! notification_preference_models {
+    ($parent:ident) => {
+        child_model! {
+            String, NaiveDateTime, "notification_preferences", NewNotificationPreference, UpdateNotificationPreference, "server gen", $parent,
+            NotificationPreference {
+                account_id: String,
+                /// Sends notifications to the account's owners by email
+                email: bool,
+                /// Posts every notification as json here
+                #[validate(url)]
+                webhook_url: Option<String>,
+                /// Kinds of notifications the account doesn't want
+                muted: Vec<NotificationKind>,
+            }
+        }
+    };
+}
+
+#[cfg(feature = "diesel")]
+pub mod schema {
+    use diesel::table;
+
+    table! {
+        use diesel::sql_types::*;
+        use crate::types::notification_kind_sql::NotificationKind;
+
+        notification_preferences {
+            id -> Text,
+            created_at -> Timestamp,
+            updated_at -> Timestamp,
+            account_id -> Text,
+            email -> Bool,
+            webhook_url -> Nullable<Text>,
+            muted -> Array<NotificationKind>,
+        }
+    }
+}
+
+pub mod model {
+    #[cfg(feature = "diesel")]
+    use super::schema::notification_preferences;
+    use crate::types::*;
+    #[cfg(feature = "diesel")]
+    use crate::Account;
+    use chrono::NaiveDateTime;
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    notification_preference_models!(Account);
+}
@@ -0,0 +1,74 @@
+use std::borrow::Cow;
+
+use validator::ValidationError;
+
+/// Rules passwords must follow, read from the environment so deployments can tighten them
+#[derive(Debug, Clone)]
+pub struct PasswordPolicy {
+    /// PASSWORD_MIN_LENGTH, defaults to 8
+    pub min_length: usize,
+    /// PASSWORD_REQUIRE_LOWERCASE, defaults to true
+    pub require_lowercase: bool,
+    /// PASSWORD_REQUIRE_UPPERCASE, defaults to true
+    pub require_uppercase: bool,
+    /// PASSWORD_REQUIRE_DIGIT, defaults to true
+    pub require_digit: bool,
+    /// PASSWORD_REQUIRE_SYMBOL, defaults to false
+    pub require_symbol: bool,
+}
+
+fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+impl PasswordPolicy {
+    pub fn from_env() -> Self {
+        Self {
+            min_length: env_or("PASSWORD_MIN_LENGTH", 8),
+            require_lowercase: env_or("PASSWORD_REQUIRE_LOWERCASE", true),
+            require_uppercase: env_or("PASSWORD_REQUIRE_UPPERCASE", true),
+            require_digit: env_or("PASSWORD_REQUIRE_DIGIT", true),
+            require_symbol: env_or("PASSWORD_REQUIRE_SYMBOL", false),
+        }
+    }
+
+    /// Checks password against every rule, erroring with the first one it breaks
+    pub fn check(&self, password: &str) -> Result<(), ValidationError> {
+        let failed = |code: &'static str, message: String| {
+            let mut err = ValidationError::new(code);
+            err.message = Some(Cow::Owned(message));
+            Err(err)
+        };
+
+        if password.chars().count() < self.min_length {
+            return failed(
+                "password_length",
+                format!("Password must be at least {} characters.", self.min_length),
+            );
+        }
+        if self.require_lowercase && !password.chars().any(char::is_lowercase) {
+            return failed("password_lowercase", "Password needs a lowercase letter.".into());
+        }
+        if self.require_uppercase && !password.chars().any(char::is_uppercase) {
+            return failed("password_uppercase", "Password needs an uppercase letter.".into());
+        }
+        if self.require_digit && !password.chars().any(|c| c.is_ascii_digit()) {
+            return failed("password_digit", "Password needs a number.".into());
+        }
+        if self.require_symbol && password.chars().all(char::is_alphanumeric) {
+            return failed("password_symbol", "Password needs a symbol.".into());
+        }
+        Ok(())
+    }
+}
+
+lazy_static! {
+    pub static ref PASSWORD_POLICY: PasswordPolicy = PasswordPolicy::from_env();
+}
+
+pub fn validate_password(password: &str) -> Result<(), ValidationError> {
+    PASSWORD_POLICY.check(password)
+}
@@ -0,0 +1,44 @@
+macro_rules! password_reset_models {
+    ($parent:ident) => {
+        child_model! {
+            String, NaiveDateTime, "password_resets", NewPasswordReset, UpdatePasswordReset, "server gen", $parent,
+            PasswordReset {
+                user_id: String,
+                /// Hash of the signed reset token, the token itself is only ever emailed
+                #[serde(skip)]
+                token_hash: String,
+                expires_at: NaiveDateTime,
+                used_at: Option<NaiveDateTime>,
+            }
+        }
+    };
+}
+
+#[cfg(feature = "diesel")]
+pub mod schema {
+    use diesel::table;
+
+    table! {
+        password_resets {
+            id -> Text,
+            created_at -> Timestamp,
+            updated_at -> Timestamp,
+            user_id -> Text,
+            token_hash -> Text,
+            expires_at -> Timestamp,
+            used_at -> Nullable<Timestamp>,
+        }
+    }
+}
+
+pub mod model {
+    #[cfg(feature = "diesel")]
+    use super::schema::password_resets;
+    #[cfg(feature = "diesel")]
+    use crate::User;
+    use chrono::NaiveDateTime;
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    password_reset_models!(User);
+}
@@ -0,0 +1,46 @@
+macro_rules! session_models {
+    ($parent:ident) => {
+        child_model! {
+            String, NaiveDateTime, "sessions", NewSession, UpdateSession, "server gen", $parent,
+            Session {
+                user_id: String,
+                account_id: String,
+                /// Hash of the current refresh token's secret
+                #[serde(skip)]
+                token_hash: String,
+                expires_at: NaiveDateTime,
+                revoked_at: Option<NaiveDateTime>,
+            }
+        }
+    };
+}
+
+#[cfg(feature = "diesel")]
+pub mod schema {
+    use diesel::table;
+
+    table! {
+        sessions {
+            id -> Text,
+            created_at -> Timestamp,
+            updated_at -> Timestamp,
+            user_id -> Text,
+            account_id -> Text,
+            token_hash -> Text,
+            expires_at -> Timestamp,
+            revoked_at -> Nullable<Timestamp>,
+        }
+    }
+}
+
+pub mod model {
+    #[cfg(feature = "diesel")]
+    use super::schema::sessions;
+    #[cfg(feature = "diesel")]
+    use crate::User;
+    use chrono::NaiveDateTime;
+    use serde::{Deserialize, Serialize};
+    use validator::Validate;
+
+    session_models!(User);
+}
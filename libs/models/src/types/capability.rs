@@ -0,0 +1,94 @@
+#[cfg(feature = "diesel")]
+use diesel::deserialize::{self, FromSql};
+#[cfg(feature = "diesel")]
+use diesel::pg::Pg;
+#[cfg(feature = "diesel")]
+use diesel::serialize::{self, Output, ToSql};
+#[cfg(feature = "diesel")]
+use diesel::sql_types::Text;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "diesel")]
+use std::io::Write;
+
+#[cfg(feature = "diesel")]
+pub mod sql_type {
+    #[derive(SqlType, Debug, Clone, Copy, Default)]
+    #[postgres(type_name = "Capability")]
+    pub struct Capability;
+}
+
+/// Something a role lets its users do
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "diesel", derive(FromSqlRow, AsExpression))]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "diesel", sql_type = "sql_type::Capability")]
+#[serde(rename_all = "camelCase")]
+pub enum Capability {
+    /// Create and update users with fewer capabilities
+    ManageUsers,
+    ManageInstances,
+    ManageApiKeys,
+    ManageRoles,
+    ViewAudit,
+    /// Create, update and delete every resource regardless of perms
+    AllResources,
+    ManageBilling,
+    TransferOwnership,
+    /// Bring back deleted accounts, users and instances before they're purged
+    RestoreDeleted,
+    /// Download an archive of all of the account's data
+    ExportData,
+}
+
+impl Capability {
+    pub const ALL: [Capability; 10] = [
+        Capability::ManageUsers,
+        Capability::ManageInstances,
+        Capability::ManageApiKeys,
+        Capability::ManageRoles,
+        Capability::ViewAudit,
+        Capability::AllResources,
+        Capability::ManageBilling,
+        Capability::TransferOwnership,
+        Capability::RestoreDeleted,
+        Capability::ExportData,
+    ];
+}
+
+#[cfg(feature = "diesel")]
+impl ToSql<sql_type::Capability, Pg> for Capability {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        let t = match *self {
+            Capability::ManageUsers => "manage_users",
+            Capability::ManageInstances => "manage_instances",
+            Capability::ManageApiKeys => "manage_api_keys",
+            Capability::ManageRoles => "manage_roles",
+            Capability::ViewAudit => "view_audit",
+            Capability::AllResources => "all_resources",
+            Capability::ManageBilling => "manage_billing",
+            Capability::TransferOwnership => "transfer_ownership",
+            Capability::RestoreDeleted => "restore_deleted",
+            Capability::ExportData => "export_data",
+        };
+        <&str as ToSql<Text, Pg>>::to_sql(&t, out)
+    }
+}
+
+#[cfg(feature = "diesel")]
+impl FromSql<sql_type::Capability, Pg> for Capability {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        match bytes.expect("Empty capability") {
+            b"manage_users" => Ok(Capability::ManageUsers),
+            b"manage_instances" => Ok(Capability::ManageInstances),
+            b"manage_api_keys" => Ok(Capability::ManageApiKeys),
+            b"manage_roles" => Ok(Capability::ManageRoles),
+            b"view_audit" => Ok(Capability::ViewAudit),
+            b"all_resources" => Ok(Capability::AllResources),
+            b"manage_billing" => Ok(Capability::ManageBilling),
+            b"transfer_ownership" => Ok(Capability::TransferOwnership),
+            b"restore_deleted" => Ok(Capability::RestoreDeleted),
+            b"export_data" => Ok(Capability::ExportData),
+            _ => Err("Unrecognized enum variant".into()),
+        }
+    }
+}
@@ -0,0 +1,75 @@
+#[cfg(feature = "diesel")]
+use diesel::deserialize::{self, FromSql};
+#[cfg(feature = "diesel")]
+use diesel::pg::Pg;
+#[cfg(feature = "diesel")]
+use diesel::serialize::{self, Output, ToSql};
+#[cfg(feature = "diesel")]
+use diesel::sql_types::Text;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "diesel")]
+use std::io::Write;
+
+#[cfg(feature = "diesel")]
+pub mod sql_type {
+    #[derive(SqlType, QueryId, Debug, Clone, Copy, Default)]
+    #[postgres(type_name = "ClosureStep")]
+    pub struct ClosureStep;
+}
+
+/// Next step an account closure has to finish, steps run in the order listed
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "diesel", derive(FromSqlRow, AsExpression))]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "diesel", sql_type = "sql_type::ClosureStep")]
+#[serde(rename_all = "camelCase")]
+pub enum ClosureStep {
+    /// Cancel the Stripe subscription through the payments service
+    CancelSubscription,
+    /// Tear down the environment and dns of every running instance
+    DeactivateInstances,
+    DeleteUsers,
+    DeleteAccount,
+    /// Everything is torn down
+    Done,
+}
+
+impl ClosureStep {
+    /// Step that runs after this one
+    pub fn next(&self) -> ClosureStep {
+        match self {
+            ClosureStep::CancelSubscription => ClosureStep::DeactivateInstances,
+            ClosureStep::DeactivateInstances => ClosureStep::DeleteUsers,
+            ClosureStep::DeleteUsers => ClosureStep::DeleteAccount,
+            ClosureStep::DeleteAccount | ClosureStep::Done => ClosureStep::Done,
+        }
+    }
+}
+
+#[cfg(feature = "diesel")]
+impl ToSql<sql_type::ClosureStep, Pg> for ClosureStep {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        let t = match *self {
+            ClosureStep::CancelSubscription => "cancel_subscription",
+            ClosureStep::DeactivateInstances => "deactivate_instances",
+            ClosureStep::DeleteUsers => "delete_users",
+            ClosureStep::DeleteAccount => "delete_account",
+            ClosureStep::Done => "done",
+        };
+        <&str as ToSql<Text, Pg>>::to_sql(&t, out)
+    }
+}
+
+#[cfg(feature = "diesel")]
+impl FromSql<sql_type::ClosureStep, Pg> for ClosureStep {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        match bytes.expect("Empty closure step") {
+            b"cancel_subscription" => Ok(ClosureStep::CancelSubscription),
+            b"deactivate_instances" => Ok(ClosureStep::DeactivateInstances),
+            b"delete_users" => Ok(ClosureStep::DeleteUsers),
+            b"delete_account" => Ok(ClosureStep::DeleteAccount),
+            b"done" => Ok(ClosureStep::Done),
+            _ => Err("Unrecognized enum variant".into()),
+        }
+    }
+}
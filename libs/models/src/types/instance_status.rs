@@ -7,6 +7,7 @@ use diesel::serialize::{self, Output, ToSql};
 #[cfg(feature = "diesel")]
 use diesel::sql_types::Text;
 use serde::{Deserialize, Serialize};
+use std::fmt;
 #[cfg(feature = "diesel")]
 use std::io::Write;
 
@@ -15,10 +16,15 @@ pub mod sql_type {
     #[derive(SqlType, Debug, Clone, Copy, Default)]
     #[postgres(type_name = "InstanceStatus")]
     pub struct InstanceStatus;
+
+    #[derive(SqlType, QueryId, Debug, Clone, Copy, Default)]
+    #[postgres(type_name = "InstanceEvent")]
+    pub struct InstanceEvent;
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[cfg_attr(feature = "diesel", derive(FromSqlRow, AsExpression))]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[cfg_attr(feature = "diesel", sql_type = "sql_type::InstanceStatus")]
 #[serde(rename_all = "camelCase")]
 pub enum InstanceStatus {
@@ -92,3 +98,145 @@ impl From<&str> for InstanceStatus {
         }
     }
 }
+
+/// What happens to an instance, each moves it to a new status from the statuses it's allowed in
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "diesel", derive(FromSqlRow, AsExpression))]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "diesel", sql_type = "sql_type::InstanceEvent")]
+#[serde(rename_all = "camelCase")]
+pub enum InstanceEvent {
+    /// Created, or deployed again after it was torn down or failed
+    Deploy,
+    /// Deploy lambda set up the domain & load balancer
+    Configure,
+    /// Deployment failed or timed out
+    Fail,
+    /// Health check got the redirect a running instance answers with
+    Healthy,
+    /// Health check got a bad response or none at all
+    Unhealthy,
+    /// Environment and domain were torn down
+    Deactivate,
+}
+
+/// Every legal transition, the status an event leads to from each status it can happen in
+const TRANSITIONS: &[(InstanceEvent, &[InstanceStatus], InstanceStatus)] = &[
+    (
+        InstanceEvent::Deploy,
+        &[InstanceStatus::Inactive, InstanceStatus::Failed],
+        InstanceStatus::Deploying,
+    ),
+    (
+        InstanceEvent::Configure,
+        &[InstanceStatus::Deploying],
+        InstanceStatus::Configured,
+    ),
+    (
+        InstanceEvent::Fail,
+        &[InstanceStatus::Deploying, InstanceStatus::Configured],
+        InstanceStatus::Failed,
+    ),
+    (
+        InstanceEvent::Healthy,
+        &[
+            InstanceStatus::Configured,
+            InstanceStatus::Ok,
+            InstanceStatus::Unhealthy,
+        ],
+        InstanceStatus::Ok,
+    ),
+    (
+        InstanceEvent::Unhealthy,
+        &[
+            InstanceStatus::Configured,
+            InstanceStatus::Ok,
+            InstanceStatus::Unhealthy,
+        ],
+        InstanceStatus::Unhealthy,
+    ),
+    (
+        InstanceEvent::Deactivate,
+        &[InstanceStatus::Ok, InstanceStatus::Unhealthy],
+        InstanceStatus::Inactive,
+    ),
+];
+
+/// Event can't happen to an instance with this status
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidTransition {
+    pub from: InstanceStatus,
+    pub event: InstanceEvent,
+}
+
+impl fmt::Display for InvalidTransition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let allowed: Vec<String> = TRANSITIONS
+            .iter()
+            .filter(|(event, _, _)| *event == self.event)
+            .flat_map(|(_, from, _)| from.iter())
+            .map(|status| format!("'{}'", Into::<String>::into(status.clone())))
+            .collect();
+
+        write!(
+            f,
+            "Can't {} an instance with status '{}', it has to be {}.",
+            Into::<String>::into(self.event),
+            Into::<String>::into(self.from.clone()),
+            allowed.join(" or ")
+        )
+    }
+}
+
+impl std::error::Error for InvalidTransition {}
+
+/// Status an instance moves to when event happens to it
+pub fn try_transition(
+    from: &InstanceStatus,
+    event: InstanceEvent,
+) -> Result<InstanceStatus, InvalidTransition> {
+    TRANSITIONS
+        .iter()
+        .find(|(allowed, statuses, _)| *allowed == event && statuses.contains(from))
+        .map(|(_, _, to)| to.clone())
+        .ok_or_else(|| InvalidTransition {
+            from: from.clone(),
+            event,
+        })
+}
+
+#[cfg(feature = "diesel")]
+impl ToSql<sql_type::InstanceEvent, Pg> for InstanceEvent {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        let t: String = (*self).into();
+        <&str as ToSql<Text, Pg>>::to_sql(&t.as_str(), out)
+    }
+}
+
+#[cfg(feature = "diesel")]
+impl FromSql<sql_type::InstanceEvent, Pg> for InstanceEvent {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        match bytes.expect("Empty instance event") {
+            b"deploy" => Ok(InstanceEvent::Deploy),
+            b"configure" => Ok(InstanceEvent::Configure),
+            b"fail" => Ok(InstanceEvent::Fail),
+            b"healthy" => Ok(InstanceEvent::Healthy),
+            b"unhealthy" => Ok(InstanceEvent::Unhealthy),
+            b"deactivate" => Ok(InstanceEvent::Deactivate),
+            _ => Err("Unrecognized enum variant".into()),
+        }
+    }
+}
+
+impl From<InstanceEvent> for String {
+    fn from(event: InstanceEvent) -> Self {
+        match event {
+            InstanceEvent::Deploy => "deploy".into(),
+            InstanceEvent::Configure => "configure".into(),
+            InstanceEvent::Fail => "fail".into(),
+            InstanceEvent::Healthy => "healthy".into(),
+            InstanceEvent::Unhealthy => "unhealthy".into(),
+            InstanceEvent::Deactivate => "deactivate".into(),
+        }
+    }
+}
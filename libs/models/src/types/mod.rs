@@ -1,6 +1,17 @@
+mod capability;
+mod closure_step;
 mod resource;
 mod role;
 mod instance_status;
+mod notification_kind;
+
+#[cfg(feature = "diesel")]
+pub use capability::sql_type as capability_sql;
+pub use capability::Capability;
+
+#[cfg(feature = "diesel")]
+pub use closure_step::sql_type as closure_step_sql;
+pub use closure_step::ClosureStep;
 
 #[cfg(feature = "diesel")]
 pub use resource::sql_type as resource_sql;
@@ -12,4 +23,8 @@ pub use role::Role;
 
 #[cfg(feature = "diesel")]
 pub use instance_status::sql_type as instance_status_sql;
-pub use instance_status::InstanceStatus;
+pub use instance_status::{try_transition, InstanceEvent, InstanceStatus, InvalidTransition};
+
+#[cfg(feature = "diesel")]
+pub use notification_kind::sql_type as notification_kind_sql;
+pub use notification_kind::NotificationKind;
@@ -0,0 +1,65 @@
+#[cfg(feature = "diesel")]
+use diesel::deserialize::{self, FromSql};
+#[cfg(feature = "diesel")]
+use diesel::pg::Pg;
+#[cfg(feature = "diesel")]
+use diesel::serialize::{self, Output, ToSql};
+#[cfg(feature = "diesel")]
+use diesel::sql_types::Text;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "diesel")]
+use std::io::Write;
+
+#[cfg(feature = "diesel")]
+pub mod sql_type {
+    #[derive(SqlType, Debug, Clone, Copy, Default)]
+    #[postgres(type_name = "NotificationKind")]
+    pub struct NotificationKind;
+}
+
+/// What a notification is about, accounts can mute each one
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "diesel", derive(FromSqlRow, AsExpression))]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "diesel", sql_type = "sql_type::NotificationKind")]
+#[serde(rename_all = "camelCase")]
+pub enum NotificationKind {
+    /// Deploying an instance failed or timed out
+    DeploymentFailed,
+    /// An instance's health checks have been failing
+    InstanceUnhealthy,
+    /// Usage couldn't be sent to stripe, so billing is behind
+    UsageSyncFailed,
+    /// A subscription payment didn't go through
+    PaymentPastDue,
+    /// A subscription ended and the account couldn't be updated to match
+    SubscriptionSyncFailed,
+}
+
+#[cfg(feature = "diesel")]
+impl ToSql<sql_type::NotificationKind, Pg> for NotificationKind {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        let t = match *self {
+            NotificationKind::DeploymentFailed => "deployment_failed",
+            NotificationKind::InstanceUnhealthy => "instance_unhealthy",
+            NotificationKind::UsageSyncFailed => "usage_sync_failed",
+            NotificationKind::PaymentPastDue => "payment_past_due",
+            NotificationKind::SubscriptionSyncFailed => "subscription_sync_failed",
+        };
+        <&str as ToSql<Text, Pg>>::to_sql(&t, out)
+    }
+}
+
+#[cfg(feature = "diesel")]
+impl FromSql<sql_type::NotificationKind, Pg> for NotificationKind {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        match bytes.expect("Empty notification kind") {
+            b"deployment_failed" => Ok(NotificationKind::DeploymentFailed),
+            b"instance_unhealthy" => Ok(NotificationKind::InstanceUnhealthy),
+            b"usage_sync_failed" => Ok(NotificationKind::UsageSyncFailed),
+            b"payment_past_due" => Ok(NotificationKind::PaymentPastDue),
+            b"subscription_sync_failed" => Ok(NotificationKind::SubscriptionSyncFailed),
+            _ => Err("Unrecognized enum variant".into()),
+        }
+    }
+}
@@ -19,6 +19,7 @@ pub mod sql_type {
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[cfg_attr(feature = "diesel", derive(FromSqlRow, AsExpression))]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[cfg_attr(feature = "diesel", sql_type = "sql_type::Resource")]
 #[serde(rename_all = "camelCase")]
 pub enum Resource {
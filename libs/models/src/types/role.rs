@@ -7,6 +7,8 @@ use diesel::serialize::{self, Output, ToSql};
 #[cfg(feature = "diesel")]
 use diesel::sql_types::Text;
 use serde::{Deserialize, Serialize};
+
+use super::Capability;
 #[cfg(feature = "diesel")]
 use std::io::Write;
 
@@ -19,6 +21,7 @@ pub mod sql_type {
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[cfg_attr(feature = "diesel", derive(FromSqlRow, AsExpression))]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[cfg_attr(feature = "diesel", sql_type = "sql_type::Role")]
 #[serde(rename_all = "camelCase")]
 pub enum Role {
@@ -28,6 +31,37 @@ pub enum Role {
     User,
 }
 
+impl Role {
+    /// Capabilities of the built in role, also the id of its row in the roles table
+    pub fn capabilities(&self) -> Vec<Capability> {
+        use Capability::*;
+        match self {
+            Role::Owner => Capability::ALL.to_vec(),
+            Role::Admin => vec![
+                ManageUsers,
+                ManageInstances,
+                ManageApiKeys,
+                ManageRoles,
+                ViewAudit,
+                AllResources,
+            ],
+            Role::Moderator => vec![ManageUsers],
+            Role::User => vec![],
+        }
+    }
+
+    /// Built in role with the given role id, none for custom roles
+    pub fn built_in(role_id: &str) -> Option<Role> {
+        match role_id {
+            "owner" => Some(Role::Owner),
+            "admin" => Some(Role::Admin),
+            "moderator" => Some(Role::Moderator),
+            "user" => Some(Role::User),
+            _ => None,
+        }
+    }
+}
+
 #[cfg(feature = "diesel")]
 impl ToSql<sql_type::Role, Pg> for Role {
     fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
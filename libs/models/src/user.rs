@@ -3,7 +3,7 @@
 macro_rules! user_models {
     ($parent:ident) => {
         child_model! {
-            String, NaiveDateTime, "users", NewUser, UpdateUser, "server gen", $parent,
+            String, NaiveDateTime, "users", NewUser, UpdateUser, "server gen", "soft delete", $parent,
             User {
                 #[serde(default)]
                 account_id: String,
@@ -13,15 +13,32 @@ macro_rules! user_models {
                 first_name: String,
                 #[validate(length(min = 1))]
                 last_name: String,
-                #[serde(skip_serializing_if = "skip_serialize_pass")]
+                /// Plain text until a route hashes it, so validate before hashing
+                // left out of responses, so clients reading a user get it empty
+                #[serde(default, skip_serializing_if = "skip_serialize_pass")]
+                #[validate(custom = "crate::validate_password")]
                 password: String,
                 active: bool,
                 instances: Vec<String>,
                 create_perms: Vec<Resource>,
                 update_perms: Vec<Resource>,
                 delete_perms: Vec<Resource>,
+                /// Legacy role, kept in sync with role_id for the built in roles
                 role: Role,
                 notes: Option<String>,
+                /// Consecutive failed logins, reset on success or lockout
+                #[serde(default)]
+                failed_logins: i32,
+                /// User can't log in until after this time
+                #[serde(default)]
+                locked_until: Option<NaiveDateTime>,
+                /// Where password reset links are sent
+                #[serde(default)]
+                #[validate(regex = "crate::EMAIL_RE")]
+                email: Option<String>,
+                /// Role the user's capabilities come from, built in or defined by the account
+                #[serde(default)]
+                role_id: String,
             }
         }
     };
@@ -40,6 +57,7 @@ pub mod schema {
             id -> Text,
             created_at -> Timestamp,
             updated_at -> Timestamp,
+            deleted_at -> Nullable<Timestamp>,
             account_id -> Text,
             username -> Text,
             first_name -> Text,
@@ -52,6 +70,10 @@ pub mod schema {
             delete_perms -> Array<Resource>,
             role -> Role,
             notes -> Nullable<Text>,
+            failed_logins -> Int4,
+            locked_until -> Nullable<Timestamp>,
+            email -> Nullable<Text>,
+            role_id -> Text,
         }
     }
 }
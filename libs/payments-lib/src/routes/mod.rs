@@ -3,6 +3,7 @@ pub mod create_usage_record {
     use serde::{Deserialize, Serialize};
 
     #[derive(Debug, Deserialize, Serialize)]
+    #[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
     pub struct CreateUsageRecordParams {
         pub sub_id: String,
         /// Should be either "instances" or "users"
@@ -17,13 +18,80 @@ pub mod create_usage_record {
     pub const ROUTE: &str = "/create-usage-record";
 }
 
+/// Accepts POST requests
+pub mod cancel_subscription {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Deserialize, Serialize)]
+    #[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+    pub struct CancelSubscriptionParams {
+        pub sub_id: String,
+    }
+
+    pub type CancelSubscriptionResponse = ();
+
+    /// Accepts POST requests
+    pub const ROUTE: &str = "/cancel-subscription";
+}
+
+/// Accepts GET requests
+pub mod invoices {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Deserialize, Serialize)]
+    #[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+    #[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+    #[serde(rename_all = "camelCase")]
+    pub struct InvoicesQuery {
+        pub customer_id: String,
+    }
+
+    /// One thing an invoice billed for, quantity is the usage for metered prices
+    #[derive(Debug, Deserialize, Serialize)]
+    #[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+    #[serde(rename_all = "camelCase")]
+    pub struct InvoiceLine {
+        pub description: Option<String>,
+        pub quantity: Option<u64>,
+        /// In the smallest unit of currency
+        pub amount: i64,
+        /// Unix timestamps
+        pub period_start: Option<i64>,
+        pub period_end: Option<i64>,
+    }
+
+    #[derive(Debug, Deserialize, Serialize)]
+    #[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+    #[serde(rename_all = "camelCase")]
+    pub struct Invoice {
+        pub id: String,
+        pub number: Option<String>,
+        pub status: Option<String>,
+        pub currency: Option<String>,
+        pub amount_due: Option<i64>,
+        pub amount_paid: Option<i64>,
+        pub total: Option<i64>,
+        /// Unix timestamp
+        pub created: Option<i64>,
+        pub hosted_invoice_url: Option<String>,
+        pub lines: Vec<InvoiceLine>,
+    }
+
+    /// Every invoice for the customer, newest first
+    pub type InvoicesResponse = Vec<Invoice>;
+
+    pub const ROUTE: &str = "/invoices";
+}
+
 pub mod subscription {
+    use models::Account;
     use serde::Deserialize;
 
     #[derive(Debug, Deserialize)]
+    #[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
     #[serde(rename_all = "camelCase")]
     pub struct CreateSubscriptionParams {
-        pub account: models::Account,
+        pub account: Account,
         pub payment_method_id: String,
     }
     pub type CreateSubscriptionResponse = ();
@@ -53,6 +121,8 @@ pub mod sub_status {
     use serde::Deserialize;
 
     #[derive(Debug, Deserialize)]
+    #[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+    #[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
     #[serde(rename_all = "camelCase")]
     pub struct IsSubbedQuery {
         pub sub_id: String,
@@ -0,0 +1,21 @@
+-- Your SQL goes here
+CREATE TABLE public.sessions (
+	id				TEXT		NOT NULL PRIMARY KEY,
+	created_at		TIMESTAMP	NOT NULL DEFAULT NOW(),
+	updated_at		TIMESTAMP	NOT NULL DEFAULT NOW(),
+	user_id			TEXT		NOT NULL,
+	account_id		TEXT		NOT NULL,
+	token_hash		TEXT		NOT NULL,
+	expires_at		TIMESTAMP	NOT NULL,
+	revoked_at		TIMESTAMP
+);
+
+SELECT diesel_manage_updated_at ('sessions');
+
+CREATE INDEX sessions_user_id ON public.sessions (user_id);
+
+ALTER TABLE public.sessions
+	ADD CONSTRAINT fk_user_session
+	FOREIGN KEY(user_id)
+	REFERENCES public.users (id)
+	ON DELETE CASCADE;
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE public.users
+	DROP COLUMN failed_logins,
+	DROP COLUMN locked_until;
@@ -0,0 +1,4 @@
+-- Your SQL goes here
+ALTER TABLE public.users
+	ADD COLUMN failed_logins	INTEGER		NOT NULL DEFAULT 0,
+	ADD COLUMN locked_until		TIMESTAMP;
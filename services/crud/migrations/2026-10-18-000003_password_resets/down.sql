@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE public.password_resets;
+
+ALTER TABLE public.users
+	DROP COLUMN email;
@@ -0,0 +1,23 @@
+-- Your SQL goes here
+ALTER TABLE public.users
+	ADD COLUMN email		TEXT;
+
+CREATE TABLE public.password_resets (
+	id				TEXT		NOT NULL PRIMARY KEY,
+	created_at		TIMESTAMP	NOT NULL DEFAULT NOW(),
+	updated_at		TIMESTAMP	NOT NULL DEFAULT NOW(),
+	user_id			TEXT		NOT NULL,
+	token_hash		TEXT		NOT NULL,
+	expires_at		TIMESTAMP	NOT NULL,
+	used_at			TIMESTAMP
+);
+
+SELECT diesel_manage_updated_at ('password_resets');
+
+CREATE INDEX password_resets_user_id ON public.password_resets (user_id);
+
+ALTER TABLE public.password_resets
+	ADD CONSTRAINT fk_user_password_reset
+	FOREIGN KEY(user_id)
+	REFERENCES public.users (id)
+	ON DELETE CASCADE;
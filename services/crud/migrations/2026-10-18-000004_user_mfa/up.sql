@@ -0,0 +1,19 @@
+-- Your SQL goes here
+CREATE TABLE public.user_mfa (
+	id				TEXT		NOT NULL PRIMARY KEY,
+	created_at		TIMESTAMP	NOT NULL DEFAULT NOW(),
+	updated_at		TIMESTAMP	NOT NULL DEFAULT NOW(),
+	user_id			TEXT		NOT NULL UNIQUE,
+	secret			TEXT		NOT NULL,
+	enabled			BOOLEAN		NOT NULL DEFAULT FALSE,
+	recovery_codes	TEXT[]		NOT NULL DEFAULT '{}',
+	last_step		BIGINT
+);
+
+SELECT diesel_manage_updated_at ('user_mfa');
+
+ALTER TABLE public.user_mfa
+	ADD CONSTRAINT fk_user_mfa
+	FOREIGN KEY(user_id)
+	REFERENCES public.users (id)
+	ON DELETE CASCADE;
@@ -0,0 +1,24 @@
+-- Your SQL goes here
+CREATE TABLE public.api_keys (
+	id				TEXT		NOT NULL PRIMARY KEY,
+	created_at		TIMESTAMP	NOT NULL DEFAULT NOW(),
+	updated_at		TIMESTAMP	NOT NULL DEFAULT NOW(),
+	account_id		TEXT		NOT NULL,
+	name			TEXT		NOT NULL,
+	role			Role		NOT NULL DEFAULT 'user'::Role,
+	key_hash		TEXT		NOT NULL,
+	created_by		TEXT		NOT NULL,
+	expires_at		TIMESTAMP,
+	last_used_at	TIMESTAMP,
+	revoked_at		TIMESTAMP
+);
+
+SELECT diesel_manage_updated_at ('api_keys');
+
+CREATE INDEX api_keys_account_id ON public.api_keys (account_id);
+
+ALTER TABLE public.api_keys
+	ADD CONSTRAINT fk_account_api_key
+	FOREIGN KEY(account_id)
+	REFERENCES public.accounts (id)
+	ON DELETE CASCADE;
@@ -0,0 +1,20 @@
+-- Your SQL goes here
+CREATE TABLE public.audit_events (
+	id				TEXT		NOT NULL PRIMARY KEY,
+	created_at		TIMESTAMP	NOT NULL DEFAULT NOW(),
+	updated_at		TIMESTAMP	NOT NULL DEFAULT NOW(),
+	account_id		TEXT		NOT NULL,
+	actor_id		TEXT,
+	actor_role		Role,
+	action			TEXT		NOT NULL,
+	target_type		TEXT		NOT NULL,
+	target_id		TEXT		NOT NULL,
+	before			JSONB,
+	after			JSONB,
+	ip				TEXT
+);
+
+SELECT diesel_manage_updated_at ('audit_events');
+
+-- no foreign key, the log should outlive what it describes
+CREATE INDEX audit_events_account_id_created_at ON public.audit_events (account_id, created_at);
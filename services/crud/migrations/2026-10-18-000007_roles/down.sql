@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE public.users DROP COLUMN role_id;
+DROP TABLE public.roles;
+DROP TYPE Capability;
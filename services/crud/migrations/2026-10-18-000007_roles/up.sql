@@ -0,0 +1,46 @@
+-- Your SQL goes here
+CREATE TYPE Capability AS ENUM (
+	'manage_users',
+	'manage_instances',
+	'manage_api_keys',
+	'manage_roles',
+	'view_audit',
+	'all_resources',
+	'manage_billing',
+	'transfer_ownership'
+);
+
+CREATE TABLE public.roles (
+	id				TEXT			NOT NULL PRIMARY KEY,
+	created_at		TIMESTAMP		NOT NULL DEFAULT NOW(),
+	updated_at		TIMESTAMP		NOT NULL DEFAULT NOW(),
+	account_id		TEXT,
+	name			TEXT			NOT NULL,
+	capabilities	Capability[]	NOT NULL DEFAULT '{}'
+);
+
+SELECT diesel_manage_updated_at ('roles');
+
+CREATE INDEX roles_account_id ON public.roles (account_id);
+
+ALTER TABLE public.roles
+	ADD CONSTRAINT fk_account_role
+	FOREIGN KEY(account_id)
+	REFERENCES public.accounts (id)
+	ON DELETE CASCADE;
+
+-- built in roles, their ids match the old role enum so existing users map onto them
+INSERT INTO public.roles (id, name, capabilities) VALUES
+	('owner', 'Owner', '{manage_users,manage_instances,manage_api_keys,manage_roles,view_audit,all_resources,manage_billing,transfer_ownership}'),
+	('admin', 'Admin', '{manage_users,manage_instances,manage_api_keys,manage_roles,view_audit,all_resources}'),
+	('moderator', 'Moderator', '{manage_users}'),
+	('user', 'User', '{}');
+
+ALTER TABLE public.users ADD COLUMN role_id TEXT NOT NULL DEFAULT 'user';
+
+UPDATE public.users SET role_id = role::TEXT;
+
+ALTER TABLE public.users
+	ADD CONSTRAINT fk_role_user
+	FOREIGN KEY(role_id)
+	REFERENCES public.roles (id);
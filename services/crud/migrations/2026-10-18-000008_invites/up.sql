@@ -0,0 +1,35 @@
+-- Your SQL goes here
+CREATE TABLE public.invites (
+	id				TEXT		NOT NULL PRIMARY KEY,
+	created_at		TIMESTAMP	NOT NULL DEFAULT NOW(),
+	updated_at		TIMESTAMP	NOT NULL DEFAULT NOW(),
+	account_id		TEXT		NOT NULL,
+	email			TEXT		NOT NULL,
+	role_id			TEXT		NOT NULL,
+	instances		TEXT[]		NOT NULL DEFAULT '{}',
+	create_perms	Resource[]	NOT NULL DEFAULT '{}',
+	update_perms	Resource[]	NOT NULL DEFAULT '{}',
+	delete_perms	Resource[]	NOT NULL DEFAULT '{}',
+	invited_by		TEXT		NOT NULL,
+	token_hash		TEXT		NOT NULL,
+	expires_at		TIMESTAMP	NOT NULL,
+	accepted_at		TIMESTAMP,
+	revoked_at		TIMESTAMP,
+	user_id			TEXT
+);
+
+SELECT diesel_manage_updated_at ('invites');
+
+CREATE INDEX invites_account_id ON public.invites (account_id);
+
+ALTER TABLE public.invites
+	ADD CONSTRAINT fk_account_invite
+	FOREIGN KEY(account_id)
+	REFERENCES public.accounts (id)
+	ON DELETE CASCADE;
+
+ALTER TABLE public.invites
+	ADD CONSTRAINT fk_role_invite
+	FOREIGN KEY(role_id)
+	REFERENCES public.roles (id)
+	ON DELETE CASCADE;
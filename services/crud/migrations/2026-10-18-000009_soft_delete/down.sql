@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+-- postgres can't drop an enum value, 'restore_deleted' stays on Capability
+ALTER TABLE public.instances DROP COLUMN deleted_at;
+ALTER TABLE public.users DROP COLUMN deleted_at;
+ALTER TABLE public.accounts DROP COLUMN deleted_at;
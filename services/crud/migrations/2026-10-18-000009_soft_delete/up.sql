@@ -0,0 +1,12 @@
+-- Your SQL goes here
+ALTER TABLE public.accounts ADD COLUMN deleted_at TIMESTAMP;
+ALTER TABLE public.users ADD COLUMN deleted_at TIMESTAMP;
+ALTER TABLE public.instances ADD COLUMN deleted_at TIMESTAMP;
+
+-- only deleted rows are looked up by it, for restoring and purging
+CREATE INDEX accounts_deleted_at ON public.accounts (deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX users_deleted_at ON public.users (deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX instances_deleted_at ON public.instances (deleted_at) WHERE deleted_at IS NOT NULL;
+
+-- new values can't be used in the transaction that adds them, owners get it in the next migration
+ALTER TYPE Capability ADD VALUE 'restore_deleted';
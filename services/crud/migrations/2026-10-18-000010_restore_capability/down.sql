@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+UPDATE public.roles
+	SET capabilities = array_remove(capabilities, 'restore_deleted')
+	WHERE id = 'owner';
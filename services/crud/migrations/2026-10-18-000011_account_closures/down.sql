@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE public.account_closures;
+DROP TYPE ClosureStep;
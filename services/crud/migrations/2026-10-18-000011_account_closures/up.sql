@@ -0,0 +1,29 @@
+-- Your SQL goes here
+CREATE TYPE ClosureStep AS ENUM (
+	'cancel_subscription',
+	'deactivate_instances',
+	'delete_users',
+	'delete_account',
+	'done'
+);
+
+CREATE TABLE public.account_closures (
+	id				TEXT			NOT NULL PRIMARY KEY,
+	created_at		TIMESTAMP		NOT NULL DEFAULT NOW(),
+	updated_at		TIMESTAMP		NOT NULL DEFAULT NOW(),
+	account_id		TEXT			NOT NULL UNIQUE,
+	requested_by	TEXT,
+	step			ClosureStep		NOT NULL DEFAULT 'cancel_subscription',
+	attempts		INTEGER			NOT NULL DEFAULT 0,
+	last_error		TEXT,
+	completed_at	TIMESTAMP
+);
+
+SELECT diesel_manage_updated_at ('account_closures');
+
+-- goes with the account when it's purged
+ALTER TABLE public.account_closures
+	ADD CONSTRAINT fk_account_closure
+	FOREIGN KEY(account_id)
+	REFERENCES public.accounts (id)
+	ON DELETE CASCADE;
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+-- postgres can't drop an enum value, 'export_data' stays on Capability
+DROP TABLE public.account_exports;
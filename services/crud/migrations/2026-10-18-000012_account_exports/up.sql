@@ -0,0 +1,24 @@
+-- Your SQL goes here
+CREATE TABLE public.account_exports (
+	id				TEXT			NOT NULL PRIMARY KEY,
+	created_at		TIMESTAMP		NOT NULL DEFAULT NOW(),
+	updated_at		TIMESTAMP		NOT NULL DEFAULT NOW(),
+	account_id		TEXT			NOT NULL,
+	requested_by	TEXT,
+	archive			BYTEA,
+	error			TEXT,
+	completed_at	TIMESTAMP,
+	expires_at		TIMESTAMP		NOT NULL
+);
+
+SELECT diesel_manage_updated_at ('account_exports');
+
+CREATE INDEX account_exports_account_id ON public.account_exports (account_id, created_at);
+
+ALTER TABLE public.account_exports
+	ADD CONSTRAINT fk_account_export
+	FOREIGN KEY(account_id)
+	REFERENCES public.accounts (id)
+	ON DELETE CASCADE;
+
+ALTER TYPE Capability ADD VALUE 'export_data';
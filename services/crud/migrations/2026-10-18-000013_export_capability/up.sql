@@ -0,0 +1,4 @@
+-- Your SQL goes here
+UPDATE public.roles
+	SET capabilities = array_append(capabilities, 'export_data')
+	WHERE id = 'owner';
@@ -0,0 +1,19 @@
+-- Your SQL goes here
+CREATE TABLE public.jobs (
+	id				TEXT			NOT NULL PRIMARY KEY,
+	created_at		TIMESTAMP		NOT NULL DEFAULT NOW(),
+	updated_at		TIMESTAMP		NOT NULL DEFAULT NOW(),
+	kind			TEXT			NOT NULL,
+	payload			JSONB			NOT NULL,
+	run_at			TIMESTAMP		NOT NULL DEFAULT NOW(),
+	attempts		INTEGER			NOT NULL DEFAULT 0,
+	max_attempts	INTEGER			NOT NULL DEFAULT 5,
+	locked_until	TIMESTAMP,
+	last_error		TEXT,
+	completed_at	TIMESTAMP
+);
+
+SELECT diesel_manage_updated_at ('jobs');
+
+-- what the runner looks through for due jobs
+CREATE INDEX jobs_due ON public.jobs (run_at) WHERE completed_at IS NULL;
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE public.instance_status_history;
+DROP TYPE InstanceEvent;
@@ -0,0 +1,31 @@
+-- Your SQL goes here
+CREATE TYPE InstanceEvent AS ENUM (
+	'deploy',
+	'configure',
+	'fail',
+	'healthy',
+	'unhealthy',
+	'deactivate'
+);
+
+CREATE TABLE public.instance_status_history (
+	id				TEXT			NOT NULL PRIMARY KEY,
+	created_at		TIMESTAMP		NOT NULL DEFAULT NOW(),
+	updated_at		TIMESTAMP		NOT NULL DEFAULT NOW(),
+	instance_id		TEXT			NOT NULL,
+	account_id		TEXT			NOT NULL,
+	from_status		InstanceStatus,
+	to_status		InstanceStatus	NOT NULL,
+	event			InstanceEvent	NOT NULL
+);
+
+SELECT diesel_manage_updated_at ('instance_status_history');
+
+CREATE INDEX instance_status_history_instance_id ON public.instance_status_history (instance_id, created_at);
+
+-- goes with the instance when it's purged
+ALTER TABLE public.instance_status_history
+	ADD CONSTRAINT fk_instance_status_change
+	FOREIGN KEY(instance_id)
+	REFERENCES public.instances (id)
+	ON DELETE CASCADE;
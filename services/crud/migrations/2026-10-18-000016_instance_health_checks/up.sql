@@ -0,0 +1,23 @@
+-- Your SQL goes here
+CREATE TABLE public.instance_health_checks (
+	id				TEXT			NOT NULL PRIMARY KEY,
+	created_at		TIMESTAMP		NOT NULL DEFAULT NOW(),
+	updated_at		TIMESTAMP		NOT NULL DEFAULT NOW(),
+	instance_id		TEXT			NOT NULL,
+	account_id		TEXT			NOT NULL,
+	healthy			BOOLEAN			NOT NULL,
+	latency_ms		INTEGER,
+	status_code		INTEGER,
+	error			TEXT
+);
+
+SELECT diesel_manage_updated_at ('instance_health_checks');
+
+CREATE INDEX instance_health_checks_instance_id ON public.instance_health_checks (instance_id, created_at);
+
+-- goes with the instance when it's purged
+ALTER TABLE public.instance_health_checks
+	ADD CONSTRAINT fk_instance_health_check
+	FOREIGN KEY(instance_id)
+	REFERENCES public.instances (id)
+	ON DELETE CASCADE;
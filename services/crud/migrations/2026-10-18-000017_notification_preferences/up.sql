@@ -0,0 +1,27 @@
+-- Your SQL goes here
+CREATE TYPE NotificationKind AS ENUM (
+	'deployment_failed',
+	'instance_unhealthy',
+	'usage_sync_failed',
+	'payment_past_due',
+	'subscription_sync_failed'
+);
+
+CREATE TABLE public.notification_preferences (
+	id				TEXT				NOT NULL PRIMARY KEY,
+	created_at		TIMESTAMP			NOT NULL DEFAULT NOW(),
+	updated_at		TIMESTAMP			NOT NULL DEFAULT NOW(),
+	account_id		TEXT				NOT NULL UNIQUE,
+	email			BOOLEAN				NOT NULL DEFAULT TRUE,
+	webhook_url		TEXT,
+	muted			NotificationKind[]	NOT NULL DEFAULT '{}'
+);
+
+SELECT diesel_manage_updated_at ('notification_preferences');
+
+-- goes with the account when it's purged
+ALTER TABLE public.notification_preferences
+	ADD CONSTRAINT fk_notification_preference
+	FOREIGN KEY(account_id)
+	REFERENCES public.accounts (id)
+	ON DELETE CASCADE;
@@ -0,0 +1,219 @@
+use actix_web::web;
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use models::account_closures::dsl::*;
+use models::types::{try_transition, ClosureStep, InstanceEvent, InstanceStatus};
+use models::{Account, AccountClosure, Instance, Model, NewAccountClosure, UpdateInstance};
+use payments_lib::routes::cancel_subscription::{self, CancelSubscriptionParams};
+use reqwest::Client;
+
+use crate::audit::{utils::audited, Actor, Change};
+use crate::{
+    api_error::ApiError,
+    db,
+    instances::{self, aws},
+    jobs::{self, Task},
+    AppData, ID_SIZE, PAYMENTS_URI,
+};
+
+/// Finds account's unfinished closure, starting a new one if there isn't one
+pub fn find_or_start(
+    conn: &PgConnection,
+    account: &str,
+    requester: Option<String>,
+) -> Result<AccountClosure, ApiError> {
+    // a finished closure means the account was restored since, so it's closed again from the start
+    diesel::delete(account_closures.filter(account_id.eq(account)))
+        .filter(step.eq(ClosureStep::Done))
+        .execute(conn)?;
+
+    diesel::insert_into(account_closures)
+        .values(NewAccountClosure {
+            id: nanoid!(ID_SIZE),
+            account_id: account.into(),
+            requested_by: requester,
+            step: ClosureStep::CancelSubscription,
+            attempts: 0,
+            last_error: None,
+            completed_at: None,
+        })
+        .on_conflict(account_id)
+        .do_nothing()
+        .execute(conn)?;
+
+    Ok(account_closures
+        .filter(account_id.eq(account))
+        .get_result::<AccountClosure>(conn)?)
+}
+
+/// Runs closure from the step it stopped at
+///
+/// Every finished step is saved, so running it again after a failure doesn't repeat them
+pub async fn run(
+    closure: AccountClosure,
+    account: Account,
+    actor: Actor,
+    app_data: web::Data<AppData>,
+) -> Result<AccountClosure, ApiError> {
+    let mut closure = web::block(move || {
+        let conn = db::connection()?;
+        Ok::<_, ApiError>(
+            diesel::update(account_closures.find(&closure.id))
+                .set(attempts.eq(attempts + 1))
+                .get_result::<AccountClosure>(&conn)?,
+        )
+    })
+    .await??;
+
+    while closure.step != ClosureStep::Done {
+        let result = match closure.step {
+            ClosureStep::CancelSubscription => cancel(&account).await,
+            ClosureStep::DeactivateInstances => {
+                deactivate_instances(&account, &actor, &app_data).await
+            }
+            // database only steps run in the same transaction as saving them
+            _ => Ok(()),
+        };
+
+        let (running, step_actor) = (closure.clone(), actor.clone());
+        closure = match result {
+            Ok(()) => web::block(move || finish_step(running, &step_actor)).await??,
+            Err(err) => {
+                let message = err.message.clone();
+                web::block(move || {
+                    let conn = db::connection()?;
+                    diesel::update(account_closures.find(&running.id))
+                        .set(last_error.eq(message))
+                        .execute(&conn)?;
+                    Ok::<_, ApiError>(())
+                })
+                .await??;
+
+                error!(
+                    "Closing account {} failed at {:?}.",
+                    account.id, closure.step
+                );
+                return Err(err);
+            }
+        };
+    }
+
+    Ok(closure)
+}
+
+/// Saves step as finished, along with whatever database changes the step makes
+fn finish_step(closure: AccountClosure, actor: &Actor) -> Result<AccountClosure, ApiError> {
+    audited(actor, &closure.account_id, |conn| {
+        // deleted along with the closure's created_at so they can be restored together
+        match closure.step {
+            ClosureStep::DeleteUsers => {
+                super::utils::delete_users(conn, &closure.account_id, closure.created_at)?;
+            }
+            ClosureStep::DeleteAccount => {
+                super::utils::soft_delete(conn, &closure.account_id, closure.created_at)?;
+            }
+            _ => {}
+        }
+
+        let next = closure.step.next();
+        let finished = match next {
+            ClosureStep::Done => Some(Utc::now().naive_utc()),
+            _ => None,
+        };
+        let updated = diesel::update(account_closures.find(&closure.id))
+            .set((
+                step.eq(next),
+                last_error.eq(None::<String>),
+                completed_at.eq(finished),
+            ))
+            .get_result::<AccountClosure>(conn)?;
+        let change = Change::new("close", "account_closure", &closure.id)
+            .before(&closure)
+            .after(&updated);
+
+        Ok((updated, change))
+    })
+}
+
+/// Cancels account's subscription through the payments service
+async fn cancel(account: &Account) -> Result<(), ApiError> {
+    let sub = match &account.sub_id {
+        Some(sub) => sub.clone(),
+        None => return Ok(()),
+    };
+
+    let res = Client::new()
+        .post(PAYMENTS_URI.to_string() + cancel_subscription::ROUTE)
+        .json(&CancelSubscriptionParams { sub_id: sub })
+        .send()
+        .await?;
+    if res.error_for_status().is_err() {
+        return Err(ApiError::new(
+            500,
+            "Failed to cancel subscription with Stripe.".into(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Tears down every running instance in account, the same way deactivating one does
+async fn deactivate_instances(
+    account: &Account,
+    actor: &Actor,
+    app_data: &AppData,
+) -> Result<(), ApiError> {
+    let target = account.id.clone();
+    let account_instances = web::block(move || Instance::find_all_in(target)).await??;
+
+    // they'd come back up once the deploy finishes
+    if account_instances.iter().any(|instance| {
+        instance.status == InstanceStatus::Deploying
+            || instance.status == InstanceStatus::Configured
+    }) {
+        return Err(ApiError::new(
+            409,
+            "Cannot close an account while instances are deploying. Try again once they finish."
+                .into(),
+        ));
+    }
+
+    let running = account_instances
+        .into_iter()
+        .filter(|instance| try_transition(&instance.status, InstanceEvent::Deactivate).is_ok());
+    for instance in running {
+        let mut dns = None;
+        if let (Some(env_id), Some(url)) = (&instance.env_id, &instance.url) {
+            let env = aws::delete_instance(&app_data.aws, env_id).await?;
+            dns = Some(Task::DeleteDns {
+                url: url.clone(),
+                target: env.cname.unwrap_or_default(),
+            });
+        }
+
+        // saved one at a time so a retry only tears down what's left, a terminated
+        // environment can't be terminated again so its dns is left to a job
+        let actor = actor.clone();
+        web::block(move || {
+            audited(&actor, &instance.account_id, |conn| {
+                let updated = instances::utils::transition(
+                    conn,
+                    &instance,
+                    InstanceEvent::Deactivate,
+                    UpdateInstance::default(),
+                )?;
+                if let Some(task) = dns {
+                    jobs::schedule(conn, task, Duration::zero())?;
+                }
+                let change = Change::new("deactivate", "instance", &instance.id)
+                    .before(&instance)
+                    .after(&updated);
+
+                Ok(((), change))
+            })
+        })
+        .await??;
+    }
+
+    Ok(())
+}
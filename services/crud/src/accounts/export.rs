@@ -0,0 +1,203 @@
+use std::io::{Cursor, Write};
+
+use actix_web::web;
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::prelude::*;
+use models::account_exports::dsl::*;
+use models::{Account, AccountExport, Instance, Model, NewAccountExport, User};
+use payments_lib::routes::invoices::{self, InvoicesQuery, InvoicesResponse};
+use reqwest::Client;
+use serde::Serialize;
+use zip::{write::FileOptions, ZipWriter};
+
+use crate::{
+    api_error::ApiError,
+    db,
+    jobs::{self, Task},
+    ID_SIZE, PAYMENTS_URI,
+};
+
+lazy_static! {
+    /// Days a finished archive can be downloaded, set with EXPORT_EXPIRY_DAYS
+    static ref EXPIRY_DAYS: i64 = std::env::var("EXPORT_EXPIRY_DAYS")
+        .ok()
+        .and_then(|days| days.parse::<i64>().ok())
+        .unwrap_or(7);
+}
+
+/// Account's newest export that hasn't failed or expired
+pub fn find_current(conn: &PgConnection, account: &str) -> Result<Option<AccountExport>, ApiError> {
+    Ok(account_exports
+        .filter(account_id.eq(account))
+        .filter(error.is_null())
+        .filter(expires_at.gt(Utc::now().naive_utc()))
+        .order(created_at.desc())
+        .first::<AccountExport>(conn)
+        .optional()?)
+}
+
+/// Saves a pending export and the job that builds its archive, in conn's transaction if it has one
+pub fn start(
+    conn: &PgConnection,
+    account: &str,
+    requester: Option<String>,
+) -> Result<AccountExport, ApiError> {
+    // only the newest export is ever downloaded, so older archives aren't kept around
+    diesel::delete(account_exports.filter(account_id.eq(account))).execute(conn)?;
+
+    let started = diesel::insert_into(account_exports)
+        .values(NewAccountExport {
+            id: nanoid!(ID_SIZE),
+            account_id: account.into(),
+            requested_by: requester,
+            archive: None,
+            error: None,
+            completed_at: None,
+            expires_at: Utc::now().naive_utc() + Duration::days(*EXPIRY_DAYS),
+        })
+        .get_result::<AccountExport>(conn)?;
+    let task = Task::BuildExport {
+        export_id: started.id.clone(),
+    };
+    jobs::schedule(conn, task, Duration::zero())?;
+
+    Ok(started)
+}
+
+/// Builds export's archive, saving it or why it failed, nothing to do once a newer export replaced it
+pub async fn run(target: String) -> Result<(), ApiError> {
+    let found = web::block(move || {
+        let conn = db::connection()?;
+        let export = match account_exports
+            .find(&target)
+            .first::<AccountExport>(&conn)
+            .optional()?
+        {
+            Some(export) => export,
+            None => return Ok(None),
+        };
+        let account = models::accounts::table
+            .find(&export.account_id)
+            .first::<Account>(&conn)?;
+
+        Ok::<_, ApiError>(Some((export, account)))
+    })
+    .await??;
+    let (export, account) = match found {
+        Some(found) => found,
+        None => return Ok(()),
+    };
+
+    // the error is kept for the account to see, asking again starts a new export
+    let built = build(&account).await;
+    web::block(move || {
+        let conn = db::connection()?;
+        let export = account_exports.find(&export.id);
+        match built {
+            Ok(zipped) => diesel::update(export)
+                .set((
+                    archive.eq(Some(zipped)),
+                    completed_at.eq(Some(Utc::now().naive_utc())),
+                ))
+                .execute(&conn)?,
+            Err(err) => diesel::update(export)
+                .set(error.eq(Some(err.0.message)))
+                .execute(&conn)?,
+        };
+        Ok(())
+    })
+    .await?
+}
+
+/// One row of usage.csv, from a line of an invoice
+#[derive(Serialize)]
+struct UsageRow<'a> {
+    invoice: &'a str,
+    period_start: Option<NaiveDateTime>,
+    period_end: Option<NaiveDateTime>,
+    description: Option<&'a str>,
+    quantity: Option<u64>,
+    amount: i64,
+    currency: Option<&'a str>,
+}
+
+/// Zips up everything stored for account, users are left out once deleted and never have passwords
+async fn build(account: &Account) -> Result<Vec<u8>, ApiError> {
+    let target = account.id.clone();
+    let account_users = web::block(move || User::find_all_in(target)).await??;
+    let target = account.id.clone();
+    let account_instances = web::block(move || Instance::find_all_in(target)).await??;
+    let account_invoices = match &account.stripe_id {
+        Some(customer_id) => fetch_invoices(customer_id).await?,
+        None => vec![],
+    };
+
+    let mut users_json = serde_json::to_value(&account_users)?;
+    if let Some(exported) = users_json.as_array_mut() {
+        for user in exported.iter_mut().filter_map(|user| user.as_object_mut()) {
+            user.remove("password");
+        }
+    }
+
+    let mut usage = csv::Writer::from_writer(vec![]);
+    for invoice in &account_invoices {
+        for line in &invoice.lines {
+            usage.serialize(UsageRow {
+                invoice: &invoice.id,
+                period_start: line
+                    .period_start
+                    .and_then(|at| NaiveDateTime::from_timestamp_opt(at, 0)),
+                period_end: line
+                    .period_end
+                    .and_then(|at| NaiveDateTime::from_timestamp_opt(at, 0)),
+                description: line.description.as_deref(),
+                quantity: line.quantity,
+                amount: line.amount,
+                currency: invoice.currency.as_deref(),
+            })?;
+        }
+    }
+    let usage = usage
+        .into_inner()
+        .map_err(|err| ApiError::from(std::io::Error::from(err.error().kind())))?;
+
+    let mut zip = ZipWriter::new(Cursor::new(vec![]));
+    let files = [
+        ("account.json", serde_json::to_vec_pretty(account)?),
+        ("users.json", serde_json::to_vec_pretty(&users_json)?),
+        (
+            "instances.json",
+            serde_json::to_vec_pretty(&account_instances)?,
+        ),
+        (
+            "invoices.json",
+            serde_json::to_vec_pretty(&account_invoices)?,
+        ),
+        ("usage.csv", usage),
+    ];
+    for (name, contents) in files {
+        zip.start_file(name, FileOptions::default())?;
+        zip.write_all(&contents)?;
+    }
+
+    Ok(zip.finish()?.into_inner())
+}
+
+/// Every invoice for customer through the payments service
+async fn fetch_invoices(customer_id: &str) -> Result<InvoicesResponse, ApiError> {
+    let res = Client::new()
+        .get(PAYMENTS_URI.to_string() + invoices::ROUTE)
+        .query(&InvoicesQuery {
+            customer_id: customer_id.into(),
+        })
+        .send()
+        .await?;
+
+    match res.error_for_status() {
+        Ok(res) => Ok(res.json::<InvoicesResponse>().await?),
+        Err(_) => Err(ApiError::new(
+            500,
+            "Failed to get invoices from Stripe.".into(),
+        )),
+    }
+}
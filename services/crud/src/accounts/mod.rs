@@ -1,3 +1,5 @@
+pub mod closure;
+pub mod export;
 pub mod model;
 pub mod routes;
 pub mod utils;
@@ -1,20 +1,89 @@
+use chrono::{NaiveDateTime, Utc};
+use diesel::pg::Pg;
 use diesel::prelude::*;
-use models::{Account, Model, NewAccount, UpdateAccount};
-use models::accounts::dsl::*;
+use models::{Account, ListQuery, Model, NewAccount, Page, SoftDelete, UpdateAccount};
+use models::accounts::{self, dsl::*};
 
-use crate::{api_error::ApiError, db, ID_SIZE};
+use crate::{
+    api_error::ApiError,
+    db,
+    list::{invalid_sort, sort_by},
+    ID_SIZE,
+};
+
+fn filtered(account: &Option<String>, query: &ListQuery) -> accounts::BoxedQuery<'static, Pg> {
+    let mut filtered = accounts.filter(deleted_at.is_null()).into_boxed();
+
+    if let Some(account) = account {
+        filtered = filtered.filter(id.eq(account.clone()));
+    }
+    if let Some(after) = query.created_after {
+        filtered = filtered.filter(created_at.ge(after));
+    }
+    if let Some(before) = query.created_before {
+        filtered = filtered.filter(created_at.lt(before));
+    }
+
+    filtered
+}
 
 impl Model<String, NewAccount, UpdateAccount, ApiError> for Account {
     fn find_all() -> Result<Vec<Self>, ApiError> {
         let conn = db::connection()?;
-        let result = accounts.load::<Self>(&conn)?;
+        let result = accounts.filter(deleted_at.is_null()).load::<Self>(&conn)?;
 
         Ok(result)
     }
 
+    fn find_page(account: Option<String>, query: ListQuery) -> Result<Page<Self>, ApiError> {
+        let conn = db::connection()?;
+        let total = filtered(&account, &query).count().get_result::<i64>(&conn)?;
+
+        let page = filtered(&account, &query);
+        let page = match query.sort.as_deref().unwrap_or("createdAt") {
+            "createdAt" => sort_by!(page, created_at, query),
+            "updatedAt" => sort_by!(page, updated_at, query),
+            "businessName" => sort_by!(page, business_name, query),
+            "shortName" => sort_by!(page, short_name, query),
+            "email" => sort_by!(page, email, query),
+            field => return Err(invalid_sort(field)),
+        };
+        let result = page
+            .then_order_by(id.asc())
+            .limit(query.limit())
+            .offset(query.offset())
+            .load::<Self>(&conn)?;
+
+        Ok(Page::new(result, total, &query))
+    }
+
     fn find_by_id(target: String) -> Result<Self, ApiError> {
         let conn = db::connection()?;
-        let result = accounts.filter(id.eq(target)).get_result::<Self>(&conn)?;
+        let result = accounts
+            .filter(id.eq(target))
+            .filter(deleted_at.is_null())
+            .get_result::<Self>(&conn)?;
+
+        Ok(result)
+    }
+
+    fn find_all_in(account: String) -> Result<Vec<Self>, ApiError> {
+        let conn = db::connection()?;
+        let result = accounts
+            .filter(id.eq(account))
+            .filter(deleted_at.is_null())
+            .load::<Self>(&conn)?;
+
+        Ok(result)
+    }
+
+    fn find_in(account: String, target: String) -> Result<Self, ApiError> {
+        let conn = db::connection()?;
+        let result = accounts
+            .filter(id.eq(target))
+            .filter(id.eq(account))
+            .filter(deleted_at.is_null())
+            .get_result::<Self>(&conn)?;
 
         Ok(result)
     }
@@ -36,15 +105,46 @@ impl Model<String, NewAccount, UpdateAccount, ApiError> for Account {
         let conn = db::connection()?;
         let result = diesel::update(accounts)
             .filter(id.eq(target))
+            .filter(deleted_at.is_null())
             .set(new_vals)
             .get_result(&conn)?;
 
         Ok(result)
     }
 
+    /// Hides account along with its users and instances, restoring it brings them back too
     fn delete(target: String) -> Result<usize, ApiError> {
         let conn = db::connection()?;
-        let result = diesel::delete(accounts.filter(id.eq(target))).execute(&conn)?;
+        let result = super::utils::soft_delete(&conn, &target, Utc::now().naive_utc())?;
+
+        Ok(result)
+    }
+}
+
+impl SoftDelete<String, ApiError> for Account {
+    fn find_deleted(account: Option<String>, target: String) -> Result<Self, ApiError> {
+        let conn = db::connection()?;
+        let mut query = accounts
+            .filter(id.eq(target))
+            .filter(deleted_at.is_not_null())
+            .into_boxed();
+        if let Some(account) = account {
+            query = query.filter(id.eq(account));
+        }
+
+        Ok(query.get_result::<Self>(&conn)?)
+    }
+
+    fn restore(target: String) -> Result<Self, ApiError> {
+        let conn = db::connection()?;
+        let result = super::utils::restore(&conn, &target)?;
+
+        Ok(result)
+    }
+
+    fn purge(cutoff: NaiveDateTime) -> Result<usize, ApiError> {
+        let conn = db::connection()?;
+        let result = diesel::delete(accounts.filter(deleted_at.lt(cutoff))).execute(&conn)?;
 
         Ok(result)
     }
@@ -1,89 +1,79 @@
 use actix_web::{delete, get, post, put, web, HttpResponse};
-use auth::{belongs_to_account, ReqUser};
+use auth::{account_scope, belongs_to_account, require_cap, ReqUser};
 use diesel::prelude::*;
-use models::{Account, Instance, Model, NewAccount, UpdateAccount, User, Validate};
+use models::types::Capability;
+use models::{
+    Account, AccountClosure, Instance, ListQuery, Model, NewAccount, SoftDelete, UpdateAccount, User,
+    Validate,
+};
 use payments_lib::routes::customer;
 use reqwest::Client;
 
-use crate::{api_error::ApiError, db, json::DeleteBody, PAYMENTS_URI};
+use crate::audit::{utils::audited, Actor, Change};
+use crate::{api_error::ApiError, db, json::DeleteBody, AppData, ID_SIZE, PAYMENTS_URI};
 
+#[utoipa::path(tag = "accounts", params(ListQuery), responses((status = 200, body = AccountPage)))]
 #[get("/accounts")]
-async fn find_all(req_user: Option<ReqUser>) -> Result<HttpResponse, ApiError> {
-    let accounts = web::block(Account::find_all).await??;
-
-    let accounts = if let Some(req_user) = req_user {
-        // if filter func returns true item will be allowed into the iterator
-        // so if the account ids match or if the account is admin of site they will see the account
-        accounts
-            .into_iter()
-            .filter(|x| x.id == req_user.account_id || req_user.account_id == "admin")
-            .collect()
-    } else {
-        accounts
-    };
+async fn find_all(
+    query: web::Query<ListQuery>,
+    req_user: Option<ReqUser>,
+) -> Result<HttpResponse, ApiError> {
+    // users outside of the admin account will only see their own account
+    let account = account_scope(&req_user);
+    let accounts = web::block(move || Account::find_page(account, query.into_inner())).await??;
 
     Ok(HttpResponse::Ok().json(accounts))
 }
 
+#[utoipa::path(tag = "accounts", responses((status = 200, body = Account)))]
 #[get("/accounts/{id}")]
 async fn find(id: web::Path<String>, req_user: Option<ReqUser>) -> Result<HttpResponse, ApiError> {
-    let account = web::block(move || Account::find_by_id(id.into_inner())).await??;
-
-    if !belongs_to_account(&req_user, &account.id) {
-        return Err(ApiError::forbidden());
-    }
+    let account = account_scope(&req_user);
+    let account = web::block(move || Account::find_scoped(account, id.into_inner())).await??;
 
     Ok(HttpResponse::Ok().json(account))
 }
 
 // TODO tests
+#[utoipa::path(tag = "accounts", params(ListQuery), responses((status = 200, body = UserPage)))]
 #[get("/accounts/{id}/users")]
 async fn find_users(
     target: web::Path<String>,
+    query: web::Query<ListQuery>,
     req_user: Option<ReqUser>,
 ) -> Result<HttpResponse, ApiError> {
     let target = target.into_inner();
     let for_find_to_be_found = target.clone();
-    let to_be_found = web::block(move || Account::find_by_id(for_find_to_be_found)).await??;
-    if !belongs_to_account(&req_user, &to_be_found.id) {
-        return Err(ApiError::forbidden());
-    }
+    let account = account_scope(&req_user);
+    // make sure account exists and is visible to user
+    web::block(move || Account::find_scoped(account, for_find_to_be_found)).await??;
 
-    use models::users::dsl::*;
-    let account = web::block::<_, Result<Vec<User>, ApiError>>(move || {
-        Ok(models::users::table
-            .filter(account_id.eq(target))
-            .get_results(&db::connection()?)?)
-    })
-    .await??;
+    let users = web::block(move || User::find_page(Some(target), query.into_inner())).await??;
 
-    Ok(HttpResponse::Ok().json(account))
+    Ok(HttpResponse::Ok().json(users))
 }
 
 // TODO tests
+#[utoipa::path(tag = "accounts", params(ListQuery), responses((status = 200, body = InstancePage)))]
 #[get("/accounts/{id}/instances")]
 async fn find_instances(
     target: web::Path<String>,
+    query: web::Query<ListQuery>,
     req_user: Option<ReqUser>,
 ) -> Result<HttpResponse, ApiError> {
     let target = target.into_inner();
     let for_find_to_be_found = target.clone();
-    let to_be_found = web::block(move || Account::find_by_id(for_find_to_be_found)).await??;
-    if !belongs_to_account(&req_user, &to_be_found.id) {
-        return Err(ApiError::forbidden());
-    }
+    let account = account_scope(&req_user);
+    // make sure account exists and is visible to user
+    web::block(move || Account::find_scoped(account, for_find_to_be_found)).await??;
 
-    use models::instances::dsl::*;
-    let account = web::block::<_, Result<Vec<Instance>, ApiError>>(move || {
-        Ok(models::instances::table
-            .filter(account_id.eq(target))
-            .get_results(&db::connection()?)?)
-    })
-    .await??;
+    let instances =
+        web::block(move || Instance::find_page(Some(target), query.into_inner())).await??;
 
-    Ok(HttpResponse::Ok().json(account))
+    Ok(HttpResponse::Ok().json(instances))
 }
 
+#[utoipa::path(tag = "accounts", responses((status = 200, body = Usage)))]
 #[get("/accounts/{id}/usage")]
 async fn usage(
     target: web::Path<String>,
@@ -99,6 +89,7 @@ async fn usage(
     Ok(HttpResponse::Ok().json(usage))
 }
 
+#[utoipa::path(tag = "accounts", responses((status = 200, body = Account)))]
 #[get("/accounts/by-sub/{id}")]
 async fn find_by_sub(
     target: web::Path<String>,
@@ -120,6 +111,7 @@ async fn find_by_sub(
     Ok(HttpResponse::Ok().json(account))
 }
 
+#[utoipa::path(tag = "accounts", responses((status = 200, body = Account)))]
 #[get("/accounts/by-customer/{id}")]
 async fn find_by_customer(
     target: web::Path<String>,
@@ -141,35 +133,52 @@ async fn find_by_customer(
     Ok(HttpResponse::Ok().json(account))
 }
 
+#[utoipa::path(tag = "accounts", responses((status = 200, body = Account)))]
 #[post("/accounts")]
 async fn create(
     account: web::Json<NewAccount>,
     req_user: Option<ReqUser>,
+    actor: Actor,
 ) -> Result<HttpResponse, ApiError> {
     if !belongs_to_account(&req_user, &account.id) || req_user.is_some() {
         return Err(ApiError::forbidden());
     }
 
-    let account = account.into_inner();
+    let account = NewAccount {
+        id: nanoid!(ID_SIZE),
+        ..account.into_inner()
+    };
     account.validate()?;
 
-    let account = web::block(move || Account::insert(account)).await??;
+    let account = web::block(move || {
+        let account_id = account.id.clone();
+        audited(&actor, &account_id, |conn| {
+            let account = diesel::insert_into(models::accounts::table)
+                .values(&account)
+                .get_result::<Account>(conn)?;
+            let change = Change::new("create", "account", &account.id).after(&account);
+
+            Ok((account, change))
+        })
+    })
+    .await??;
 
     Ok(HttpResponse::Ok().json(account))
 }
 
+#[utoipa::path(tag = "accounts", responses((status = 200, body = Account)))]
 #[put("/accounts/{id}")]
 async fn update(
     id: web::Path<String>,
     account: web::Json<UpdateAccount>,
     req_user: Option<ReqUser>,
+    actor: Actor,
 ) -> Result<HttpResponse, ApiError> {
     let id = id.into_inner();
     let for_find_to_be_updated = id.clone();
-    let to_be_updated = web::block(move || Account::find_by_id(for_find_to_be_updated)).await??;
-    if !belongs_to_account(&req_user, &to_be_updated.id) {
-        return Err(ApiError::forbidden());
-    }
+    let scope = account_scope(&req_user);
+    let to_be_updated =
+        web::block(move || Account::find_scoped(scope, for_find_to_be_updated)).await??;
 
     let update_set: UpdateAccount = if req_user == None {
         account.into_inner()
@@ -184,7 +193,7 @@ async fn update(
 
     update_set.validate()?;
 
-    if let Some(stripe_id) = to_be_updated.stripe_id {
+    if let Some(stripe_id) = &to_be_updated.stripe_id {
         let res = Client::new()
             .put(PAYMENTS_URI.to_string() + &customer::id_route(stripe_id.as_str()))
             .json(&update_set)
@@ -198,27 +207,162 @@ async fn update(
         }
     };
 
-    let account = web::block(move || Account::update(id, update_set)).await??;
+    let account = web::block(move || {
+        audited(&actor, &id, |conn| {
+            let account = diesel::update(models::accounts::table.find(&id))
+                .set(update_set)
+                .get_result::<Account>(conn)?;
+            let change = Change::new("update", "account", &id)
+                .before(&to_be_updated)
+                .after(&account);
+
+            Ok((account, change))
+        })
+    })
+    .await??;
 
     Ok(HttpResponse::Ok().json(account))
 }
 
+/// Closes account, cancelling its subscription and tearing down its instances before deleting it
+///
+/// Finished steps are saved, calling this again after a failure picks up where it stopped
+#[utoipa::path(tag = "accounts", responses((status = 200, body = DeleteBody)))]
 #[delete("/accounts/{id}")]
 async fn delete(
     id: web::Path<String>,
     req_user: Option<ReqUser>,
+    actor: Actor,
+    app_data: web::Data<AppData>,
 ) -> Result<HttpResponse, ApiError> {
     let find_id = id.clone();
-    if !belongs_to_account(
-        &req_user,
-        &web::block(move || Account::find_by_id(find_id)).await??.id,
-    ) {
+    let account = account_scope(&req_user);
+    // make sure account exists and is visible to user
+    let deleted = web::block(move || Account::find_scoped(account, find_id)).await??;
+    if !require_cap(&req_user, Capability::ManageBilling) {
+        return Err(ApiError::forbidden());
+    }
+
+    let (closing, requester) = (deleted.id.clone(), req_user.map(|user| user.id));
+    let closure = web::block(move || {
+        let conn = db::connection()?;
+        super::closure::find_or_start(&conn, &closing, requester)
+    })
+    .await??;
+    super::closure::run(closure, deleted, actor, app_data).await?;
+
+    Ok(HttpResponse::Ok().json(DeleteBody::new(1)))
+}
+
+/// Progress of account's closure, still visible once the account is deleted
+#[utoipa::path(tag = "accounts", responses((status = 200, body = AccountClosure)))]
+#[get("/accounts/{id}/closure")]
+async fn find_closure(
+    id: web::Path<String>,
+    req_user: Option<ReqUser>,
+) -> Result<HttpResponse, ApiError> {
+    if !belongs_to_account(&req_user, &id) {
+        return Err(ApiError::forbidden());
+    }
+
+    let closing = id.into_inner();
+    let closure = web::block(move || {
+        use models::account_closures::dsl::*;
+        let conn = db::connection()?;
+        Ok::<_, ApiError>(
+            account_closures
+                .filter(account_id.eq(closing))
+                .get_result::<AccountClosure>(&conn)?,
+        )
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(closure))
+}
+
+/// Downloads a zip of everything stored for account once it's ready
+///
+/// Starts building one in the background when there isn't one, responding with it as accepted until it's done
+#[utoipa::path(
+    tag = "accounts",
+    responses(
+        (
+            status = 200,
+            description = "Zip of the account's data",
+            body = Vec<u8>,
+            content_type = "application/zip",
+        ),
+        (status = 202, description = "Export is still being built", body = AccountExport),
+    ),
+)]
+#[get("/accounts/{id}/export")]
+async fn export(
+    id: web::Path<String>,
+    req_user: Option<ReqUser>,
+    actor: Actor,
+) -> Result<HttpResponse, ApiError> {
+    let account = account_scope(&req_user);
+    let exporting = web::block(move || Account::find_scoped(account, id.into_inner())).await??;
+    if !require_cap(&req_user, Capability::ExportData) {
+        return Err(ApiError::forbidden());
+    }
+
+    let (target, requester) = (exporting.id.clone(), req_user.map(|user| user.id));
+    let current = web::block(move || {
+        let conn = db::connection()?;
+        if let Some(current) = super::export::find_current(&conn, &target)? {
+            return Ok(current);
+        }
+        drop(conn);
+
+        audited(&actor, &target, |conn| {
+            let started = super::export::start(conn, &target, requester)?;
+            let change = Change::new("export", "account", &target).after(&started);
+
+            Ok((started, change))
+        })
+    })
+    .await??;
+
+    match current.archive {
+        Some(zipped) => Ok(HttpResponse::Ok()
+            .content_type("application/zip")
+            .insert_header((
+                "Content-Disposition",
+                format!("attachment; filename=\"{}-export.zip\"", current.account_id),
+            ))
+            .body(zipped)),
+        None => Ok(HttpResponse::Accepted().json(current)),
+    }
+}
+
+/// Brings back a deleted account with the users and instances deleted along with it
+#[utoipa::path(tag = "accounts", responses((status = 200, body = Account)))]
+#[post("/accounts/{id}/restore")]
+async fn restore(
+    id: web::Path<String>,
+    req_user: Option<ReqUser>,
+    actor: Actor,
+) -> Result<HttpResponse, ApiError> {
+    let account = account_scope(&req_user);
+    let deleted = web::block(move || Account::find_deleted(account, id.into_inner())).await??;
+    if !require_cap(&req_user, Capability::RestoreDeleted) {
         return Err(ApiError::forbidden());
     }
 
-    let affected = web::block(move || Account::delete(id.into_inner())).await??;
+    let account = web::block(move || {
+        audited(&actor, &deleted.id, |conn| {
+            let account = super::utils::restore(conn, &deleted.id)?;
+            let change = Change::new("restore", "account", &deleted.id)
+                .before(&deleted)
+                .after(&account);
+
+            Ok((account, change))
+        })
+    })
+    .await??;
 
-    Ok(HttpResponse::Ok().json(DeleteBody::new(affected.try_into().unwrap())))
+    Ok(HttpResponse::Ok().json(account))
 }
 
 pub fn init_routes(config: &mut web::ServiceConfig) {
@@ -230,6 +374,9 @@ pub fn init_routes(config: &mut web::ServiceConfig) {
     config.service(create);
     config.service(update);
     config.service(delete);
+    config.service(find_closure);
+    config.service(export);
+    config.service(restore);
     config.service(find_by_sub);
     config.service(find_by_customer);
 }
@@ -1,6 +1,9 @@
-use models::{Account, NewAccount, accounts::dsl::*, UpdateAccount};
+use models::{Account, AccountClosure, NewAccount, accounts::dsl::*, Page, UpdateAccount};
+use models::types::{ClosureStep, InstanceStatus};
 use crate::{db, json::DeleteBody, tests::{self, mock_payments}, ID_SIZE};
 use actix_web::test;
+use cloud::{beanstalk::NewEnvironment, dns::AliasRecord, Beanstalk, Dns, FakeAws};
+use std::sync::Arc;
 use diesel::prelude::*;
 
 fn compare(got: &Account, exp: &NewAccount) {
@@ -83,10 +86,13 @@ async fn get() {
     // Check for both inserted records with the get all route
     let req = test::TestRequest::get().uri("/accounts").to_request();
 
-    let resp: Vec<Account> = test::call_and_read_body_json(&app, req).await;
+    let resp: Page<Account> = test::call_and_read_body_json(&app, req).await;
     let conn = db::connection().unwrap();
 
-    assert!(resp.len() >= 2);
+    assert!(resp.items.len() >= 2);
+    // the shared database can hold more accounts than fit on one page
+    assert!(resp.total >= resp.items.len() as i64);
+    assert_eq!(resp.next_cursor.is_some(), resp.total > resp.items.len() as i64);
 
     remove(result1.id, &conn);
     remove(result2.id, &conn);
@@ -157,15 +163,27 @@ async fn put() {
 
 #[actix_web::test]
 async fn delete() {
+    actix_web::rt::spawn(mock_payments());
     let (default1, default2) = defaults("accounts-delete".into());
 
     let app = tests::init(super::routes::init_routes).await;
     let conn = db::connection().unwrap();
 
     let result1: Account = diesel::insert_into(accounts)
-        .values(&default1)
+        .values(&NewAccount {
+            sub_id: Some("sub_closing".into()),
+            ..default1
+        })
         .get_result::<Account>(&conn)
         .expect("couldn't insert");
+    let (member, _) = crate::users::tests::defaults("accounts-delete");
+    let member: models::User = diesel::insert_into(models::users::table)
+        .values(&models::NewUser {
+            account_id: result1.id.clone(),
+            ..member
+        })
+        .get_result(&conn)
+        .expect("couldn't insert user");
 
     // update record 1, to be record 2's values
     let req = test::TestRequest::delete()
@@ -180,11 +198,275 @@ async fn delete() {
 
     assert_eq!(resp.affected, 1);
 
-    let result1: Option<Account> = accounts
-        .filter(id.eq(result1.id))
+    // account and its users are hidden until restored or purged
+    let deleted: Account = accounts
+        .filter(id.eq(&result1.id))
+        .get_result(&conn)
+        .expect("deleted account was removed");
+    let deleted_member: models::User = models::users::table
+        .find(&member.id)
         .get_result(&conn)
-        .optional()
-        .expect("failed to convert to to option");
+        .expect("deleted user was removed");
+    assert!(deleted.deleted_at.is_some());
+    assert_eq!(deleted_member.deleted_at, deleted.deleted_at);
+    drop(conn);
 
-    assert!(result1 == None);
+    let req = test::TestRequest::get()
+        .uri(&format!("/accounts/{}", result1.id))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), actix_http::StatusCode::NOT_FOUND);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/accounts/{}/closure", result1.id))
+        .to_request();
+    let closure: AccountClosure = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(closure.step, ClosureStep::Done);
+    assert_eq!(closure.attempts, 1);
+    assert!(closure.completed_at.is_some());
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/accounts/{}/restore", result1.id))
+        .to_request();
+    let restored: Account = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(restored.deleted_at, None);
+
+    let conn = db::connection().unwrap();
+    let restored_member: models::User = models::users::table
+        .find(&member.id)
+        .get_result(&conn)
+        .expect("restored user is missing");
+    assert_eq!(restored_member.deleted_at, None);
+
+    crate::users::tests::remove(member.id, &conn);
+    remove(result1.id, &conn);
+}
+
+#[actix_web::test]
+async fn close_resumes() {
+    let (default1, _default2) = defaults("accounts-close-resumes".into());
+
+    let app = tests::init(super::routes::init_routes).await;
+    let conn = db::connection().unwrap();
+
+    let result1: Account = diesel::insert_into(accounts)
+        .values(&default1)
+        .get_result::<Account>(&conn)
+        .expect("couldn't insert");
+    let (instance, _) = crate::instances::tests::defaults("accounts-close-resumes".into());
+    let instance: models::Instance = diesel::insert_into(models::instances::table)
+        .values(&models::NewInstance {
+            account_id: result1.id.clone(),
+            status: InstanceStatus::Deploying,
+            ..instance
+        })
+        .get_result(&conn)
+        .expect("couldn't insert instance");
+    drop(conn);
+
+    let close = || {
+        test::TestRequest::delete()
+            .uri(&format!("/accounts/{}", result1.id))
+            .to_request()
+    };
+    let find_closure = || {
+        test::TestRequest::get()
+            .uri(&format!("/accounts/{}/closure", result1.id))
+            .to_request()
+    };
+
+    // stops before tearing down instances while one is deploying
+    let resp = test::call_service(&app, close()).await;
+    assert_eq!(resp.status(), actix_http::StatusCode::CONFLICT);
+    let closure: AccountClosure = test::call_and_read_body_json(&app, find_closure()).await;
+    assert_eq!(closure.step, ClosureStep::DeactivateInstances);
+    assert!(closure.last_error.is_some());
+
+    let conn = db::connection().unwrap();
+    diesel::update(models::instances::table.find(&instance.id))
+        .set(models::instances::status.eq(InstanceStatus::Failed))
+        .execute(&conn)
+        .expect("couldn't update instance");
+    drop(conn);
+
+    // picks up where it stopped
+    let resp: DeleteBody = test::call_and_read_body_json(&app, close()).await;
+    assert_eq!(resp.affected, 1);
+    let closure: AccountClosure = test::call_and_read_body_json(&app, find_closure()).await;
+    assert_eq!(closure.step, ClosureStep::Done);
+    assert_eq!(closure.attempts, 2);
+    assert_eq!(closure.last_error, None);
+
+    let conn = db::connection().unwrap();
+    diesel::delete(models::instances::table.find(&instance.id))
+        .execute(&conn)
+        .expect("couldn't delete test instance");
+    remove(result1.id, &conn);
+}
+
+#[actix_web::test]
+async fn close_tears_down_instances() {
+    actix_web::rt::spawn(mock_payments());
+    let (default1, _default2) = defaults("accounts-close-tears-down".into());
+
+    // its own fake, the shared one failing would fail other tests
+    let aws = Arc::new(FakeAws::default());
+    let app_data = crate::AppData {
+        aws: cloud::Aws::fake(aws.clone()),
+        ..tests::app_data()
+    };
+    let app = tests::init_with(app_data.clone(), super::routes::init_routes).await;
+
+    let env = aws
+        .create_environment(NewEnvironment {
+            application_name: "pudo".into(),
+            environment_name: "accounts-close-tears-down".into(),
+            version_label: "v1".into(),
+            solution_stack: "docker".into(),
+            options: vec![],
+        })
+        .await
+        .unwrap();
+    let domain = "accounts-close-tears-down.milkyweb.app".to_string();
+    aws.create_alias(&AliasRecord {
+        hosted_zone_id: "Z0898550109O7ZB98C1FF".into(),
+        name: domain.clone(),
+        target_dns_name: env.cname.clone().unwrap(),
+        target_zone_id: "Z117KPS5GTRQ2G".into(),
+    })
+    .await
+    .unwrap();
+
+    let conn = db::connection().unwrap();
+    let result1: Account = diesel::insert_into(accounts)
+        .values(&NewAccount {
+            sub_id: Some("sub_close".into()),
+            ..default1
+        })
+        .get_result::<Account>(&conn)
+        .expect("couldn't insert");
+    let (instance, _) = crate::instances::tests::defaults("accounts-close-tears-down".into());
+    let instance: models::Instance = diesel::insert_into(models::instances::table)
+        .values(&models::NewInstance {
+            account_id: result1.id.clone(),
+            status: InstanceStatus::Ok,
+            env_id: Some(env.id.clone()),
+            url: Some(domain.clone()),
+            ..instance
+        })
+        .get_result(&conn)
+        .expect("couldn't insert instance");
+    drop(conn);
+
+    // the environment is terminated even though its dns can't be deleted yet
+    aws.fail_on("delete_alias");
+    let req = test::TestRequest::delete()
+        .uri(&format!("/accounts/{}", result1.id))
+        .to_request();
+    let resp: DeleteBody = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resp.affected, 1);
+    let req = test::TestRequest::get()
+        .uri(&format!("/accounts/{}/closure", result1.id))
+        .to_request();
+    let closure: AccountClosure = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(closure.step, ClosureStep::Done);
+    assert_eq!(closure.attempts, 1);
+
+    assert!(aws.environments()[0].terminated);
+    assert!(aws.records().iter().any(|record| record.name == domain));
+    let conn = db::connection().unwrap();
+    let deactivated: models::Instance = models::instances::table
+        .find(&instance.id)
+        .get_result(&conn)
+        .unwrap();
+    assert_eq!(deactivated.status, InstanceStatus::Inactive);
+    drop(conn);
+
+    // the dns is retried on its own, without terminating the environment again
+    crate::jobs::run_due(&app_data).await.unwrap();
+    assert!(aws.records().iter().any(|record| record.name == domain));
+    aws.recover("delete_alias");
+    let conn = db::connection().unwrap();
+    let job = models::jobs::table
+        .filter(models::jobs::kind.eq("delete_dns"))
+        .filter(models::jobs::payload.eq(serde_json::json!({
+            "url": domain,
+            "target": env.cname.clone().unwrap(),
+        })));
+    diesel::update(job)
+        .set(models::jobs::run_at.eq(chrono::Utc::now().naive_utc()))
+        .execute(&conn)
+        .expect("couldn't update job");
+    drop(conn);
+    crate::jobs::run_due(&app_data).await.unwrap();
+    assert!(!aws.records().iter().any(|record| record.name == domain));
+    assert_eq!(aws.environments().len(), 1);
+
+    let conn = db::connection().unwrap();
+    diesel::delete(models::instances::table.find(&instance.id))
+        .execute(&conn)
+        .expect("couldn't delete test instance");
+    remove(result1.id, &conn);
+}
+
+#[actix_web::test]
+async fn export() {
+    actix_web::rt::spawn(mock_payments());
+    let (default1, _default2) = defaults("accounts-export".into());
+
+    let app = tests::init(super::routes::init_routes).await;
+    let conn = db::connection().unwrap();
+
+    let result1: Account = diesel::insert_into(accounts)
+        .values(&default1)
+        .get_result::<Account>(&conn)
+        .expect("couldn't insert");
+    let (member, _) = crate::users::tests::defaults("accounts-export");
+    let member: models::User = diesel::insert_into(models::users::table)
+        .values(&models::NewUser {
+            account_id: result1.id.clone(),
+            ..member
+        })
+        .get_result(&conn)
+        .expect("couldn't insert user");
+    drop(conn);
+
+    let export = || {
+        test::TestRequest::get()
+            .uri(&format!("/accounts/{}/export", result1.id))
+            .to_request()
+    };
+
+    // built by a job, asking again gives back the same export until it's ready
+    let resp = test::call_service(&app, export()).await;
+    assert_eq!(resp.status(), actix_http::StatusCode::ACCEPTED);
+    let resp = test::call_service(&app, export()).await;
+    assert_eq!(resp.status(), actix_http::StatusCode::ACCEPTED);
+
+    crate::jobs::run_due(&tests::app_data()).await.unwrap();
+    let resp = test::call_service(&app, export()).await;
+    assert_eq!(resp.status(), actix_http::StatusCode::OK);
+    let zipped = test::read_body(resp).await;
+
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(zipped.to_vec())).unwrap();
+    let mut read = |name: &str| {
+        let mut contents = String::new();
+        std::io::Read::read_to_string(&mut archive.by_name(name).unwrap(), &mut contents).unwrap();
+        contents
+    };
+
+    let exported: Vec<serde_json::Value> = serde_json::from_str(&read("users.json")).unwrap();
+    assert_eq!(exported.len(), 1);
+    assert_eq!(exported[0]["id"], member.id);
+    assert!(exported[0].get("password").is_none());
+    assert!(read("account.json").contains(&result1.id));
+    assert!(read("instances.json").starts_with('['));
+    assert!(read("invoices.json").contains("in_test"));
+    let usage = read("usage.csv");
+    assert!(usage.starts_with("invoice,period_start"));
+    assert!(usage.contains("in_test"));
+
+    let conn = db::connection().unwrap();
+    crate::users::tests::remove(member.id, &conn);
+    remove(result1.id, &conn);
 }
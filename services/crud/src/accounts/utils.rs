@@ -1,15 +1,13 @@
 use actix_web::web;
+use chrono::{NaiveDateTime, Utc};
 use diesel::prelude::*;
 use models::types::InstanceStatus;
-use serde::{Deserialize, Serialize};
+use models::Account;
 
 use crate::{api_error::ApiError, db};
 
-#[derive(Debug, Deserialize, Serialize)]
-pub struct Usage {
-    pub instances: i64,
-    pub users: i64,
-}
+pub use crud_client::accounts::Usage;
+
 pub async fn usage(target: String) -> Result<Usage, ApiError> {
 	let users_acct_id = target.clone();
     let num_users = web::block::<_, Result<i64, ApiError>>(move || {
@@ -17,6 +15,7 @@ pub async fn usage(target: String) -> Result<Usage, ApiError> {
         Ok(users
             .count()
             .filter(account_id.eq(users_acct_id))
+            .filter(deleted_at.is_null())
             .get_result::<i64>(&db::connection()?)?)
     })
     .await??;
@@ -27,6 +26,7 @@ pub async fn usage(target: String) -> Result<Usage, ApiError> {
         Ok(instances
             .count()
             .filter(account_id.eq(instances_acct_id))
+            .filter(deleted_at.is_null())
             .filter(status.eq_any(vec![
                 InstanceStatus::Ok,
                 InstanceStatus::Unhealthy,
@@ -41,3 +41,65 @@ pub async fn usage(target: String) -> Result<Usage, ApiError> {
         users: num_users,
     })
 }
+
+/// Hides account with its users and instances, ending every session in it
+///
+/// They all share deleted_at `at` so restoring brings back only what was deleted with the account
+pub fn soft_delete(conn: &PgConnection, target: &str, at: NaiveDateTime) -> Result<usize, ApiError> {
+    use models::accounts::dsl::*;
+
+    let affected = diesel::update(accounts.filter(id.eq(target)))
+        .filter(deleted_at.is_null())
+        .set(deleted_at.eq(at))
+        .execute(conn)?;
+    if affected == 0 {
+        return Ok(0);
+    }
+
+    delete_users(conn, target, at)?;
+    diesel::update(models::instances::table.filter(models::instances::account_id.eq(target)))
+        .filter(models::instances::deleted_at.is_null())
+        .set(models::instances::deleted_at.eq(at))
+        .execute(conn)?;
+
+    Ok(affected)
+}
+
+/// Hides every user in account and ends their sessions, users already deleted keep their deleted_at
+pub fn delete_users(conn: &PgConnection, target: &str, at: NaiveDateTime) -> Result<usize, ApiError> {
+    let affected =
+        diesel::update(models::users::table.filter(models::users::account_id.eq(target)))
+            .filter(models::users::deleted_at.is_null())
+            .set(models::users::deleted_at.eq(at))
+            .execute(conn)?;
+    diesel::update(models::sessions::table.filter(models::sessions::account_id.eq(target)))
+        .filter(models::sessions::revoked_at.is_null())
+        .set(models::sessions::revoked_at.eq(Utc::now().naive_utc()))
+        .execute(conn)?;
+
+    Ok(affected)
+}
+
+/// Brings back a deleted account with the users and instances that were deleted along with it
+pub fn restore(conn: &PgConnection, target: &str) -> Result<Account, ApiError> {
+    use models::accounts::dsl::*;
+    let deleted = accounts
+        .filter(id.eq(target))
+        .select(deleted_at)
+        .first::<Option<NaiveDateTime>>(conn)?;
+
+    if let Some(deleted) = deleted {
+        diesel::update(models::users::table.filter(models::users::account_id.eq(target)))
+            .filter(models::users::deleted_at.eq(deleted))
+            .set(models::users::deleted_at.eq(None::<NaiveDateTime>))
+            .execute(conn)?;
+        diesel::update(models::instances::table.filter(models::instances::account_id.eq(target)))
+            .filter(models::instances::deleted_at.eq(deleted))
+            .set(models::instances::deleted_at.eq(None::<NaiveDateTime>))
+            .execute(conn)?;
+    }
+
+    Ok(diesel::update(accounts.filter(id.eq(target)))
+        .set(deleted_at.eq(None::<NaiveDateTime>))
+        .get_result::<Account>(conn)?)
+}
@@ -1,115 +1,100 @@
-use actix_web::error::BlockingError;
-use actix_web::http::StatusCode;
-use actix_web::{HttpResponse, ResponseError};
-use bcrypt::BcryptError;
-use diesel::result::{DatabaseErrorKind, Error as DieselError};
-use models::ValidationErrors;
-use serde::Deserialize;
-use serde_json::json;
+//! Errors are shared with the other services so every response has the same shape,
+//! crud wraps them so the models' traits can be implemented with them
 use std::fmt;
-use std::str::FromStr;
+use std::ops::{Deref, DerefMut};
 
-#[derive(Debug, Deserialize)]
-pub struct ApiError {
-    pub status_code: u16,
-    pub message: String,
-}
+use actix_web::{http::StatusCode, HttpResponse, ResponseError};
+pub use errors::ErrorCode;
+use serde::Serialize;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct ApiError(pub errors::ApiError);
 
 impl ApiError {
     pub fn new(status_code: u16, message: String) -> ApiError {
-        ApiError {
-            status_code,
-            message,
-        }
+        ApiError(errors::ApiError::new(status_code, message))
+    }
+
+    pub fn with_code(self, code: ErrorCode) -> ApiError {
+        ApiError(self.0.with_code(code))
     }
 
     pub fn server_err() -> ApiError {
-        ApiError::new(500, "Internal server error.".into())
+        ApiError(errors::ApiError::server_err())
     }
 
     pub fn forbidden() -> ApiError {
-        ApiError::new(403, "You do not have access to this resource".into())
+        ApiError(errors::ApiError::forbidden())
     }
 
-    pub fn not_subbed() -> ApiError {
-        ApiError::new(403, "You cannot do this while not subscribed.".into())
+    pub fn unauthorized() -> ApiError {
+        ApiError(errors::ApiError::unauthorized())
     }
-}
 
-impl fmt::Display for ApiError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.write_str(self.message.as_str())
+    pub fn not_subbed() -> ApiError {
+        ApiError(errors::ApiError::not_subbed())
     }
 }
 
-impl From<DieselError> for ApiError {
-    fn from(error: DieselError) -> ApiError {
-        error!("[Diesel] error: {:?}", error);
-        match error {
-            DieselError::DatabaseError(kind, _err) => match kind {
-                DatabaseErrorKind::UniqueViolation => ApiError::new(
-                    409,
-                    "One of the provided fields failed unique validation.".into(),
-                ),
-                DatabaseErrorKind::UnableToSendCommand => {
-                    ApiError::new(500, "Unable to send command to database.".into())
-                }
-                DatabaseErrorKind::ForeignKeyViolation => {
-                    ApiError::new(409, "Cannot add to an account that doesn't exist.".into())
-                }
-                _ => ApiError::new(500, "A database error occurred.".into()),
-            },
-            DieselError::NotFound => ApiError::new(404, "Record not found".into()),
-            _ => ApiError::new(500, "A database error occurred.".into()),
-        }
+impl Deref for ApiError {
+    type Target = errors::ApiError;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
     }
 }
 
-impl From<ValidationErrors> for ApiError {
-    fn from(err: ValidationErrors) -> Self {
-        let mut message = String::from_str("Validation failed for fields: ").unwrap();
-        message.push_str(&err.into_errors().into_iter().fold(
-            String::new(),
-            |mut err_fields, (curr_field, _)| {
-                err_fields.reserve(curr_field.len() + 1);
-                err_fields.push_str(curr_field);
-                err_fields.push_str(",");
-                err_fields
-            },
-        ));
-        error!("Validation failed: {}", message);
-
-        ApiError::new(400, message)
+impl DerefMut for ApiError {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
     }
 }
 
-impl From<reqwest::Error> for ApiError {
-    fn from(err: reqwest::Error) -> Self {
-        error!("[Reqwest] error: {:?}", err);
-        ApiError::server_err()
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
     }
 }
 
-impl From<BcryptError> for ApiError {
-    fn from(err: BcryptError) -> Self {
-        error!("[Bcrypt] error: {:?}", err);
-        ApiError::new(500, "Error hashing password.".into())
-    }
+/// Converts through the shared error, which knows how to report each of these
+macro_rules! from_shared {
+    ($($err:ty),* $(,)?) => {
+        $(
+            impl From<$err> for ApiError {
+                fn from(err: $err) -> Self {
+                    ApiError(err.into())
+                }
+            }
+        )*
+    };
 }
 
-impl From<BlockingError> for ApiError {
-    fn from(_err: BlockingError) -> Self {
-        ApiError::server_err()
+from_shared!(
+    errors::ApiError,
+    diesel::result::Error,
+    models::ValidationErrors,
+    reqwest::Error,
+    serde_json::Error,
+    csv::Error,
+    zip::result::ZipError,
+    std::io::Error,
+    bcrypt::BcryptError,
+    actix_web::error::BlockingError,
+);
+
+impl From<models::types::InvalidTransition> for ApiError {
+    fn from(err: models::types::InvalidTransition) -> Self {
+        ApiError::new(400, err.to_string())
     }
 }
 
 impl ResponseError for ApiError {
-    fn error_response(&self) -> HttpResponse {
-        let status_code = match StatusCode::from_u16(self.status_code) {
-            Ok(status_code) => status_code,
-            Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
-        };
+    fn status_code(&self) -> StatusCode {
+        self.0.status_code()
+    }
 
-        HttpResponse::build(status_code).json(json!({ "message": self.message.clone() }))
+    fn error_response(&self) -> HttpResponse {
+        self.0.error_response()
     }
 }
@@ -0,0 +1,9 @@
+pub mod model;
+pub mod routes;
+pub mod utils;
+
+pub use crud_client::api_keys::CreatedApiKey;
+
+#[cfg(test)]
+mod tests;
+
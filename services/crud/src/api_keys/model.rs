@@ -0,0 +1,123 @@
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use models::api_keys::{self, dsl::*};
+use models::{ApiKey, ListQuery, Model, NewApiKey, Page, UpdateApiKey};
+
+use crate::{
+    api_error::ApiError,
+    db,
+    list::{invalid_sort, sort_by},
+    ID_SIZE,
+};
+
+fn filtered(account: &Option<String>, query: &ListQuery) -> api_keys::BoxedQuery<'static, Pg> {
+    let mut filtered = api_keys.into_boxed();
+
+    if let Some(account) = account {
+        filtered = filtered.filter(account_id.eq(account.clone()));
+    }
+    if let Some(after) = query.created_after {
+        filtered = filtered.filter(created_at.ge(after));
+    }
+    if let Some(before) = query.created_before {
+        filtered = filtered.filter(created_at.lt(before));
+    }
+    if let Some(target_role) = query.role.clone() {
+        filtered = filtered.filter(role.eq(target_role));
+    }
+    if let Some(active) = query.active {
+        filtered = match active {
+            true => filtered.filter(revoked_at.is_null()),
+            false => filtered.filter(revoked_at.is_not_null()),
+        };
+    }
+
+    filtered
+}
+
+impl Model<String, NewApiKey, UpdateApiKey, ApiError> for ApiKey {
+    fn find_all() -> Result<Vec<Self>, ApiError> {
+        let conn = db::connection()?;
+        let result = api_keys.load::<Self>(&conn)?;
+
+        Ok(result)
+    }
+
+    fn find_page(account: Option<String>, query: ListQuery) -> Result<Page<Self>, ApiError> {
+        let conn = db::connection()?;
+        let total = filtered(&account, &query).count().get_result::<i64>(&conn)?;
+
+        let page = filtered(&account, &query);
+        let page = match query.sort.as_deref().unwrap_or("createdAt") {
+            "createdAt" => sort_by!(page, created_at, query),
+            "updatedAt" => sort_by!(page, updated_at, query),
+            "name" => sort_by!(page, name, query),
+            "role" => sort_by!(page, role, query),
+            "expiresAt" => sort_by!(page, expires_at, query),
+            "lastUsedAt" => sort_by!(page, last_used_at, query),
+            field => return Err(invalid_sort(field)),
+        };
+        let result = page
+            .then_order_by(id.asc())
+            .limit(query.limit())
+            .offset(query.offset())
+            .load::<Self>(&conn)?;
+
+        Ok(Page::new(result, total, &query))
+    }
+
+    fn find_by_id(target: String) -> Result<Self, ApiError> {
+        let conn = db::connection()?;
+        let result = api_keys.filter(id.eq(target)).get_result::<Self>(&conn)?;
+
+        Ok(result)
+    }
+
+    fn find_all_in(account: String) -> Result<Vec<Self>, ApiError> {
+        let conn = db::connection()?;
+        let result = api_keys.filter(account_id.eq(account)).load::<Self>(&conn)?;
+
+        Ok(result)
+    }
+
+    fn find_in(account: String, target: String) -> Result<Self, ApiError> {
+        let conn = db::connection()?;
+        let result = api_keys
+            .filter(id.eq(target))
+            .filter(account_id.eq(account))
+            .get_result::<Self>(&conn)?;
+
+        Ok(result)
+    }
+
+    /// Keys made here have no usable secret, use utils::create to get one
+    fn insert(new: NewApiKey) -> Result<Self, ApiError> {
+        let conn = db::connection()?;
+        let with_id = NewApiKey {
+            id: nanoid!(ID_SIZE),
+            ..new
+        };
+        let result = diesel::insert_into(api_keys)
+            .values(&with_id)
+            .get_result::<Self>(&conn)?;
+
+        Ok(result)
+    }
+
+    fn update(target: String, new_vals: UpdateApiKey) -> Result<Self, ApiError> {
+        let conn = db::connection()?;
+        let result = diesel::update(api_keys)
+            .filter(id.eq(target))
+            .set(new_vals)
+            .get_result(&conn)?;
+
+        Ok(result)
+    }
+
+    fn delete(target: String) -> Result<usize, ApiError> {
+        let conn = db::connection()?;
+        let result = diesel::delete(api_keys.filter(id.eq(target))).execute(&conn)?;
+
+        Ok(result)
+    }
+}
@@ -0,0 +1,104 @@
+use actix_web::{get, post, put, web, HttpResponse};
+use auth::{account_scope, covers, require_cap, ReqUser};
+use chrono::Utc;
+use models::types::Capability;
+use models::{ApiKey, ListQuery, Model, NewApiKey, Validate};
+
+use super::{utils, CreatedApiKey};
+use crate::api_error::ApiError;
+use crate::audit::{utils::audited, Actor, Change};
+
+#[utoipa::path(tag = "api-keys", params(ListQuery), responses((status = 200, body = ApiKeyPage)))]
+#[get("/api-keys")]
+async fn find_all(
+    query: web::Query<ListQuery>,
+    req_user: Option<ReqUser>,
+) -> Result<HttpResponse, ApiError> {
+    if !require_cap(&req_user, Capability::ManageApiKeys) {
+        return Err(ApiError::forbidden());
+    }
+
+    let account = account_scope(&req_user);
+    let keys = web::block(move || ApiKey::find_page(account, query.into_inner())).await??;
+
+    Ok(HttpResponse::Ok().json(keys))
+}
+
+#[utoipa::path(tag = "api-keys", responses((status = 200, body = CreatedApiKey)))]
+#[post("/api-keys")]
+async fn create(
+    new_key: web::Json<NewApiKey>,
+    req_user: Option<ReqUser>,
+    actor: Actor,
+) -> Result<HttpResponse, ApiError> {
+    // keys are made by and for a user's account
+    let req_user = req_user.ok_or_else(ApiError::forbidden)?;
+    let new_key = new_key.into_inner();
+
+    // key can't be given more capabilities than the user making it
+    let maker = Some(req_user.clone());
+    if !require_cap(&maker, Capability::ManageApiKeys)
+        || !covers(&maker, &new_key.role.capabilities())
+    {
+        return Err(ApiError::forbidden());
+    }
+    if let Some(expiry) = new_key.expires_at {
+        if expiry <= Utc::now().naive_utc() {
+            return Err(ApiError::new(400, "Expiry must be in the future.".into()));
+        }
+    }
+
+    let new_key = NewApiKey {
+        account_id: req_user.account_id,
+        created_by: req_user.id,
+        ..new_key
+    };
+    new_key.validate()?;
+
+    let (api_key, key) = web::block(move || {
+        let account_id = new_key.account_id.clone();
+        audited(&actor, &account_id, |conn| {
+            let (api_key, key) = utils::create(conn, new_key)?;
+            let change = Change::new("create", "api_key", &api_key.id).after(&api_key);
+
+            Ok(((api_key, key), change))
+        })
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(CreatedApiKey { api_key, key }))
+}
+
+#[utoipa::path(tag = "api-keys", responses((status = 200, body = ApiKey)))]
+#[put("/api-keys/{id}/revoke")]
+async fn revoke(
+    id: web::Path<String>,
+    req_user: Option<ReqUser>,
+    actor: Actor,
+) -> Result<HttpResponse, ApiError> {
+    let account = account_scope(&req_user);
+    let api_key = web::block(move || ApiKey::find_scoped(account, id.into_inner())).await??;
+    if !require_cap(&req_user, Capability::ManageApiKeys) {
+        return Err(ApiError::forbidden());
+    }
+
+    let api_key = web::block(move || {
+        audited(&actor, &api_key.account_id, |conn| {
+            let revoked = utils::revoke(conn, &api_key.id)?;
+            let change = Change::new("revoke", "api_key", &api_key.id)
+                .before(&api_key)
+                .after(&revoked);
+
+            Ok((revoked, change))
+        })
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(api_key))
+}
+
+pub fn init_routes(config: &mut web::ServiceConfig) {
+    config.service(find_all);
+    config.service(create);
+    config.service(revoke);
+}
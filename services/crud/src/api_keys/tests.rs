@@ -0,0 +1,99 @@
+use actix_http::StatusCode;
+use actix_web::test;
+use auth::ReqUser;
+use models::{types::Role, ApiKey, Page};
+
+use super::CreatedApiKey;
+use crate::{db, tests};
+
+fn admin(test_name: &str) -> String {
+    serde_json::to_string(&ReqUser {
+        id: test_name.into(),
+        account_id: "test".into(),
+        role: Role::Admin,
+        create_perms: vec![],
+        update_perms: vec![],
+        delete_perms: vec![],
+        capabilities: None,
+    })
+    .unwrap()
+}
+
+pub fn remove(target: String, conn: &db::PoolConn) {
+    use diesel::prelude::*;
+    use models::api_keys::dsl::*;
+    diesel::delete(api_keys.filter(id.eq(target)))
+        .execute(conn)
+        .expect("couldn't delete test api key from table");
+}
+
+#[actix_web::test]
+async fn create_and_revoke() {
+    let app = tests::init(|config| {
+        super::routes::init_routes(config);
+        crate::auth::routes::init_routes(config);
+    })
+    .await;
+
+    // can't give a key a higher role than your own
+    let req = test::TestRequest::post()
+        .uri("/api-keys")
+        .insert_header(("user", admin("api-keys-create")))
+        .set_json(serde_json::json!({ "name": "ci", "role": "owner" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    let req = test::TestRequest::post()
+        .uri("/api-keys")
+        .insert_header(("user", admin("api-keys-create")))
+        .set_json(serde_json::json!({ "name": "ci", "role": "moderator" }))
+        .to_request();
+    let created: CreatedApiKey = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(created.api_key.account_id, "test");
+    assert_eq!(created.api_key.created_by, "api-keys-create");
+
+    let req = test::TestRequest::get()
+        .uri("/api-keys?active=true")
+        .insert_header(("user", admin("api-keys-create")))
+        .to_request();
+    let page: Page<ApiKey> = test::call_and_read_body_json(&app, req).await;
+    assert!(page.items.iter().any(|key| key.id == created.api_key.id));
+
+    // key acts as a moderator in the account
+    let verify = |key: &str| {
+        test::TestRequest::get()
+            .uri("/verify-key")
+            .insert_header(("Authorization", format!("Bearer {}", key)))
+            .to_request()
+    };
+    let req_user: ReqUser = test::call_and_read_body_json(&app, verify(&created.key)).await;
+    assert_eq!(
+        req_user,
+        ReqUser {
+            id: created.api_key.id.clone(),
+            account_id: "test".into(),
+            role: Role::Moderator,
+            create_perms: vec![],
+            update_perms: vec![],
+            delete_perms: vec![],
+            capabilities: None,
+        }
+    );
+
+    let resp = test::call_service(&app, verify(&format!("{}.wrong", created.api_key.id))).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    let req = test::TestRequest::put()
+        .uri(&format!("/api-keys/{}/revoke", created.api_key.id))
+        .insert_header(("user", admin("api-keys-create")))
+        .to_request();
+    let revoked: ApiKey = test::call_and_read_body_json(&app, req).await;
+    assert!(revoked.revoked_at.is_some());
+
+    let resp = test::call_service(&app, verify(&created.key)).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    let conn = db::connection().unwrap();
+    remove(created.api_key.id, &conn);
+}
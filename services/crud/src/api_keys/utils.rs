@@ -0,0 +1,69 @@
+use chrono::Utc;
+use diesel::prelude::*;
+use models::api_keys::dsl::*;
+use models::{ApiKey, NewApiKey};
+use sha2::{Digest, Sha256};
+
+use crate::{api_error::ApiError, db, ID_SIZE};
+
+const SECRET_SIZE: usize = 32;
+
+fn hash_secret(secret: &str) -> String {
+    format!("{:x}", Sha256::digest(secret.as_bytes()))
+}
+
+/// Creates key, returns it with the key to hand out
+///
+/// Keys are the key id and a random secret, only a hash of the secret is stored
+pub fn create(conn: &PgConnection, new: NewApiKey) -> Result<(ApiKey, String), ApiError> {
+    let secret = nanoid!(SECRET_SIZE);
+    let api_key = diesel::insert_into(api_keys)
+        .values(NewApiKey {
+            id: nanoid!(ID_SIZE),
+            key_hash: hash_secret(&secret),
+            last_used_at: None,
+            revoked_at: None,
+            ..new
+        })
+        .get_result::<ApiKey>(conn)?;
+
+    let key = format!("{}.{}", api_key.id, secret);
+    Ok((api_key, key))
+}
+
+/// Finds the key if it is valid, hasn't expired and hasn't been revoked, marking it as used
+pub fn resolve(key: &str) -> Result<Option<ApiKey>, ApiError> {
+    let (key_id, secret) = match key.split_once('.') {
+        Some(parts) => parts,
+        None => return Ok(None),
+    };
+    let now = Utc::now().naive_utc();
+
+    let conn = db::connection()?;
+    let api_key = diesel::update(api_keys.filter(id.eq(key_id)))
+        .filter(key_hash.eq(hash_secret(secret)))
+        .filter(revoked_at.is_null())
+        .filter(expires_at.is_null().or(expires_at.gt(now)))
+        .set(last_used_at.eq(now))
+        .get_result::<ApiKey>(&conn)
+        .optional()?;
+
+    // keys stop working while their account is deleted
+    match api_key {
+        Some(api_key) => {
+            let account_deleted = models::accounts::table
+                .find(&api_key.account_id)
+                .select(models::accounts::deleted_at)
+                .first::<Option<chrono::NaiveDateTime>>(&conn)?;
+
+            Ok(Some(api_key).filter(|_| account_deleted.is_none()))
+        }
+        None => Ok(None),
+    }
+}
+
+pub fn revoke(conn: &PgConnection, target: &str) -> Result<ApiKey, ApiError> {
+    Ok(diesel::update(api_keys.filter(id.eq(target)))
+        .set(revoked_at.eq(Utc::now().naive_utc()))
+        .get_result::<ApiKey>(conn)?)
+}
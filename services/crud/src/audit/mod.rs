@@ -0,0 +1,115 @@
+pub mod model;
+pub mod routes;
+pub mod utils;
+
+use std::future::{ready, Ready};
+
+use actix_web::{dev::Payload, FromRequest, HttpMessage, HttpRequest};
+use auth::ReqUser;
+use serde::Serialize;
+use serde_json::Value;
+
+#[cfg(test)]
+mod tests;
+
+/// Who is making a change and from where
+#[derive(Debug, Clone, Default)]
+pub struct Actor {
+    pub user: Option<ReqUser>,
+    pub ip: Option<String>,
+}
+
+impl FromRequest for Actor {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let user = req
+            .extensions()
+            .get::<Option<ReqUser>>()
+            .cloned()
+            .flatten()
+            // only trusted when the request came from internally
+            .or_else(|| {
+                req.headers()
+                    .get(auth::ACTOR_HEADER)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| auth::extract(v.into()))
+            });
+        let ip = crate::auth::client_ip(req);
+
+        ready(Ok(Actor { user, ip }))
+    }
+}
+
+/// Fields which are never written to the audit log
+const REDACTED: [&str; 1] = ["password"];
+/// Fields which change on every write, so aren't worth logging
+const IGNORED: [&str; 1] = ["updatedAt"];
+
+/// A change to be recorded, build with `Change::new(...).before(&old).after(&new)`
+#[derive(Debug, Clone)]
+pub struct Change {
+    pub action: String,
+    pub target_type: String,
+    pub target_id: String,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+impl Change {
+    pub fn new(action: &str, target_type: &str, target_id: &str) -> Self {
+        Change {
+            action: action.into(),
+            target_type: target_type.into(),
+            target_id: target_id.into(),
+            before: None,
+            after: None,
+        }
+    }
+
+    pub fn before(self, value: &impl Serialize) -> Self {
+        Change {
+            before: serde_json::to_value(value).ok(),
+            ..self
+        }
+    }
+
+    pub fn after(self, value: &impl Serialize) -> Self {
+        Change {
+            after: serde_json::to_value(value).ok(),
+            ..self
+        }
+    }
+
+    /// Strips redacted fields and, when there is a before and after, fields which didn't change
+    pub fn diff(self) -> (Option<Value>, Option<Value>) {
+        match (self.before.map(redact), self.after.map(redact)) {
+            (Some(Value::Object(mut before)), Some(Value::Object(mut after))) => {
+                let unchanged: Vec<String> = before
+                    .iter()
+                    .filter(|(key, value)| after.get(*key) == Some(*value))
+                    .map(|(key, _)| key.clone())
+                    .collect();
+                for key in unchanged {
+                    before.remove(&key);
+                    after.remove(&key);
+                }
+                (Some(Value::Object(before)), Some(Value::Object(after)))
+            }
+            diff => diff,
+        }
+    }
+}
+
+fn redact(value: Value) -> Value {
+    match value {
+        Value::Object(mut fields) => {
+            for key in REDACTED.iter().chain(IGNORED.iter()) {
+                fields.remove(*key);
+            }
+            Value::Object(fields)
+        }
+        value => value,
+    }
+}
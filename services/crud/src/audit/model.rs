@@ -0,0 +1,112 @@
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use models::audit_events::{self, dsl::*};
+use models::{AuditEvent, ListQuery, Model, NewAuditEvent, Page, UpdateAuditEvent};
+
+use crate::{
+    api_error::ApiError,
+    db,
+    list::{invalid_sort, sort_by},
+    ID_SIZE,
+};
+
+fn filtered(account: &Option<String>, query: &ListQuery) -> audit_events::BoxedQuery<'static, Pg> {
+    let mut filtered = audit_events.into_boxed();
+
+    if let Some(account) = account {
+        filtered = filtered.filter(account_id.eq(account.clone()));
+    }
+    if let Some(start) = query.created_after {
+        filtered = filtered.filter(created_at.ge(start));
+    }
+    if let Some(end) = query.created_before {
+        filtered = filtered.filter(created_at.lt(end));
+    }
+    if let Some(target_role) = query.role.clone() {
+        filtered = filtered.filter(actor_role.eq(target_role));
+    }
+
+    filtered
+}
+
+impl Model<String, NewAuditEvent, UpdateAuditEvent, ApiError> for AuditEvent {
+    fn find_all() -> Result<Vec<Self>, ApiError> {
+        let conn = db::connection()?;
+        let result = audit_events.load::<Self>(&conn)?;
+
+        Ok(result)
+    }
+
+    fn find_page(account: Option<String>, query: ListQuery) -> Result<Page<Self>, ApiError> {
+        let conn = db::connection()?;
+        let total = filtered(&account, &query)
+            .count()
+            .get_result::<i64>(&conn)?;
+
+        let page = filtered(&account, &query);
+        let page = match query.sort.as_deref().unwrap_or("createdAt") {
+            "createdAt" => sort_by!(page, created_at, query),
+            "action" => sort_by!(page, action, query),
+            "targetType" => sort_by!(page, target_type, query),
+            field => return Err(invalid_sort(field)),
+        };
+        let result = page
+            .then_order_by(id.asc())
+            .limit(query.limit())
+            .offset(query.offset())
+            .load::<Self>(&conn)?;
+
+        Ok(Page::new(result, total, &query))
+    }
+
+    fn find_by_id(target: String) -> Result<Self, ApiError> {
+        let conn = db::connection()?;
+        let result = audit_events
+            .filter(id.eq(target))
+            .get_result::<Self>(&conn)?;
+
+        Ok(result)
+    }
+
+    fn find_all_in(account: String) -> Result<Vec<Self>, ApiError> {
+        let conn = db::connection()?;
+        let result = audit_events
+            .filter(account_id.eq(account))
+            .load::<Self>(&conn)?;
+
+        Ok(result)
+    }
+
+    fn find_in(account: String, target: String) -> Result<Self, ApiError> {
+        let conn = db::connection()?;
+        let result = audit_events
+            .filter(id.eq(target))
+            .filter(account_id.eq(account))
+            .get_result::<Self>(&conn)?;
+
+        Ok(result)
+    }
+
+    fn insert(new: NewAuditEvent) -> Result<Self, ApiError> {
+        let conn = db::connection()?;
+        let with_id = NewAuditEvent {
+            id: nanoid!(ID_SIZE),
+            ..new
+        };
+        let result = diesel::insert_into(audit_events)
+            .values(&with_id)
+            .get_result::<Self>(&conn)?;
+
+        Ok(result)
+    }
+
+    /// The log is append only
+    fn update(_target: String, _new_vals: UpdateAuditEvent) -> Result<Self, ApiError> {
+        Err(ApiError::new(400, "Audit events can't be changed.".into()))
+    }
+
+    /// The log is append only
+    fn delete(_target: String) -> Result<usize, ApiError> {
+        Err(ApiError::new(400, "Audit events can't be deleted.".into()))
+    }
+}
@@ -0,0 +1,51 @@
+use actix_web::{get, post, web, HttpResponse};
+use auth::{account_scope, require_cap, ReqUser};
+use models::types::Capability;
+use models::{Account, AuditEvent, ListQuery, Model, NewAuditEvent};
+
+use crate::api_error::ApiError;
+
+#[utoipa::path(tag = "audit", params(ListQuery), responses((status = 200, body = AuditEventPage)))]
+#[get("/accounts/{id}/audit")]
+async fn find_by_account(
+    target: web::Path<String>,
+    query: web::Query<ListQuery>,
+    req_user: Option<ReqUser>,
+) -> Result<HttpResponse, ApiError> {
+    if !require_cap(&req_user, Capability::ViewAudit) {
+        return Err(ApiError::forbidden());
+    }
+
+    let target = target.into_inner();
+    let for_find_to_be_found = target.clone();
+    let account = account_scope(&req_user);
+    // make sure account exists and is visible to user
+    web::block(move || Account::find_scoped(account, for_find_to_be_found)).await??;
+
+    let events =
+        web::block(move || AuditEvent::find_page(Some(target), query.into_inner())).await??;
+
+    Ok(HttpResponse::Ok().json(events))
+}
+
+/// Lets other services record changes they make, such as payments
+#[utoipa::path(tag = "audit", responses((status = 200, body = AuditEvent)))]
+#[post("/audit-events")]
+async fn create(
+    event: web::Json<NewAuditEvent>,
+    req_user: Option<ReqUser>,
+) -> Result<HttpResponse, ApiError> {
+    // only internal requests can write to the log directly
+    if req_user.is_some() {
+        return Err(ApiError::forbidden());
+    }
+
+    let event = web::block(move || AuditEvent::insert(event.into_inner())).await??;
+
+    Ok(HttpResponse::Ok().json(event))
+}
+
+pub fn init_routes(config: &mut web::ServiceConfig) {
+    config.service(find_by_account);
+    config.service(create);
+}
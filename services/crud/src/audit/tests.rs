@@ -0,0 +1,99 @@
+use actix_http::StatusCode;
+use actix_web::test;
+use auth::ReqUser;
+use diesel::prelude::*;
+use models::{types::Role, AuditEvent, Page, User};
+
+use crate::{db, tests, users};
+
+fn req_user(test_name: &str, role: Role) -> String {
+    serde_json::to_string(&ReqUser {
+        id: test_name.into(),
+        account_id: "test".into(),
+        role,
+        create_perms: vec![],
+        update_perms: vec![],
+        delete_perms: vec![],
+        capabilities: None,
+    })
+    .unwrap()
+}
+
+#[actix_web::test]
+async fn records_changes() {
+    let (default1, _) = users::tests::defaults("audit-records");
+    let app = tests::init(|config| {
+        super::routes::init_routes(config);
+        users::routes::init_routes(config);
+    })
+    .await;
+    let conn = db::connection().unwrap();
+    let user = diesel::insert_into(models::users::table)
+        .values(&default1)
+        .get_result::<User>(&conn)
+        .expect("couldn't insert");
+    drop(conn);
+
+    let req = test::TestRequest::put()
+        .uri(&format!("/users/{}", user.id))
+        .insert_header(("user", req_user("audit-records", Role::Owner)))
+        // through the gateway
+        .peer_addr("127.0.0.1:4000".parse().unwrap())
+        .insert_header(("X-Forwarded-For", "10.0.0.1"))
+        .set_json(serde_json::json!({ "notes": "promoted", "password": "Another passw0rd" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    // only admins and owners can read the log
+    let req = test::TestRequest::get()
+        .uri("/accounts/test/audit")
+        .insert_header(("user", req_user("audit-records", Role::Moderator)))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    let req = test::TestRequest::get()
+        .uri("/accounts/test/audit?sort=createdAt&order=desc&limit=100")
+        .insert_header(("user", req_user("audit-records", Role::Admin)))
+        .to_request();
+    let page: Page<AuditEvent> = test::call_and_read_body_json(&app, req).await;
+    let event = page
+        .items
+        .iter()
+        .find(|event| event.target_id == user.id)
+        .expect("update wasn't recorded");
+    assert_eq!(event.action, "update");
+    assert_eq!(event.target_type, "user");
+    assert_eq!(event.actor_id.as_deref(), Some("audit-records"));
+    assert_eq!(event.actor_role, Some(Role::Owner));
+    assert_eq!(event.ip.as_deref(), Some("10.0.0.1"));
+    // only what changed is kept, and never the password
+    assert_eq!(event.before, Some(serde_json::json!({ "notes": null })));
+    assert_eq!(
+        event.after,
+        Some(serde_json::json!({ "notes": "promoted" }))
+    );
+
+    // users can't write to the log directly
+    let req = test::TestRequest::post()
+        .uri("/audit-events")
+        .insert_header(("user", req_user("audit-records", Role::Owner)))
+        .set_json(serde_json::json!({
+            "accountId": "test",
+            "action": "update",
+            "targetType": "user",
+            "targetId": user.id,
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    let conn = db::connection().unwrap();
+    users::tests::remove(user.id.clone(), &conn);
+    diesel::delete(
+        models::audit_events::table.filter(models::audit_events::target_id.eq(&user.id)),
+    )
+    .execute(&conn)
+    .expect("couldn't delete test audit events");
+}
@@ -0,0 +1,47 @@
+use diesel::prelude::*;
+use models::audit_events::dsl::*;
+use models::{AuditEvent, NewAuditEvent};
+
+use super::{Actor, Change};
+use crate::{api_error::ApiError, ID_SIZE};
+
+/// Writes change to the audit log, call inside the same transaction as the change
+pub fn record(
+    conn: &PgConnection,
+    actor: &Actor,
+    account: &str,
+    change: Change,
+) -> Result<AuditEvent, ApiError> {
+    let (old, new) = change.clone().diff();
+
+    Ok(diesel::insert_into(audit_events)
+        .values(NewAuditEvent {
+            id: nanoid!(ID_SIZE),
+            account_id: account.into(),
+            actor_id: actor.user.as_ref().map(|user| user.id.clone()),
+            actor_role: actor.user.as_ref().map(|user| user.role.clone()),
+            action: change.action,
+            target_type: change.target_type,
+            target_id: change.target_id,
+            before: old,
+            after: new,
+            ip: actor.ip.clone(),
+        })
+        .get_result::<AuditEvent>(conn)?)
+}
+
+/// Runs change in a transaction, recording the change it returns alongside it
+///
+/// Nothing is written if either the change or the record fails
+pub fn audited<T, F>(actor: &Actor, account: &str, change: F) -> Result<T, ApiError>
+where
+    F: FnOnce(&PgConnection) -> Result<(T, Change), ApiError>,
+{
+    let conn = crate::db::connection()?;
+    conn.transaction(|| {
+        let (result, change) = change(&conn)?;
+        record(&conn, actor, account, change)?;
+
+        Ok(result)
+    })
+}
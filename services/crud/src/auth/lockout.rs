@@ -0,0 +1,53 @@
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use diesel::PgConnection;
+use models::users::dsl::*;
+use models::User;
+
+use crate::api_error::ApiError;
+
+/// Consecutive failed logins before a user is locked
+pub const MAX_FAILED_LOGINS: i32 = 5;
+/// Minutes a user stays locked for
+const LOCKOUT_MINUTES: i64 = 15;
+
+pub fn is_locked(user: &User) -> bool {
+    user.locked_until
+        .map(|until| until > Utc::now().naive_utc())
+        .unwrap_or(false)
+}
+
+/// Counts a failed login for user, locking them once they hit the max. Returns whether they are now locked
+pub fn record_failure(conn: &PgConnection, user: &User) -> Result<bool, ApiError> {
+    let failures = diesel::update(users.filter(id.eq(&user.id)))
+        .set(failed_logins.eq(failed_logins + 1))
+        .returning(failed_logins)
+        .get_result::<i32>(conn)?;
+
+    if failures < MAX_FAILED_LOGINS {
+        return Ok(false);
+    }
+
+    info!("Locking user {} after {} failed logins.", user.id, failures);
+    diesel::update(users.filter(id.eq(&user.id)))
+        .set((
+            failed_logins.eq(0),
+            locked_until.eq(Utc::now().naive_utc() + Duration::minutes(LOCKOUT_MINUTES)),
+        ))
+        .execute(conn)?;
+
+    Ok(true)
+}
+
+/// Clears failed logins and any lockout after a successful login
+pub fn reset(conn: &PgConnection, user: &User) -> Result<(), ApiError> {
+    if user.failed_logins == 0 && user.locked_until.is_none() {
+        return Ok(());
+    }
+
+    diesel::update(users.filter(id.eq(&user.id)))
+        .set((failed_logins.eq(0), locked_until.eq(None::<chrono::NaiveDateTime>)))
+        .execute(conn)?;
+
+    Ok(())
+}
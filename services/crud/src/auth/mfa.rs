@@ -0,0 +1,184 @@
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel::PgConnection;
+use hmac::{Hmac, Mac};
+use models::user_mfa::dsl::*;
+use models::{Mfa, NewMfa};
+use rand::RngCore;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+use crate::{api_error::ApiError, ID_SIZE};
+
+const ISSUER: &str = "MilkyWeb";
+/// Seconds each code is good for
+const STEP: i64 = 30;
+const DIGITS: u32 = 6;
+/// Steps either side of now that are still accepted, for clock drift
+const DRIFT_STEPS: i64 = 1;
+const SECRET_BYTES: usize = 20;
+const RECOVERY_CODE_COUNT: usize = 10;
+const RECOVERY_CODE_ALPHABET: [char; 32] = [
+    'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'j', 'k', 'm', 'n', 'p', 'q', 'r', 's', 't', 'u',
+    'v', 'w', 'x', 'y', 'z', '2', '3', '4', '5', '6', '7', '8', '9', '0',
+];
+
+const BASE32: base32::Alphabet = base32::Alphabet::RFC4648 { padding: false };
+
+fn hash_code(code: &str) -> String {
+    format!("{:x}", Sha256::digest(code.trim().to_lowercase().as_bytes()))
+}
+
+pub fn new_secret() -> String {
+    let mut bytes = [0u8; SECRET_BYTES];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    base32::encode(BASE32, &bytes)
+}
+
+/// otpauth uri authenticator apps read the secret from, usually shown as a qr code
+pub fn provisioning_uri(totp_secret: &str, username: &str) -> String {
+    let encode = |value: &str| {
+        value
+            .bytes()
+            .map(|byte| match byte {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                    (byte as char).to_string()
+                }
+                _ => format!("%{:02X}", byte),
+            })
+            .collect::<String>()
+    };
+
+    format!(
+        "otpauth://totp/{issuer}:{label}?secret={secret}&issuer={issuer}&algorithm=SHA1&digits={digits}&period={period}",
+        issuer = encode(ISSUER),
+        label = encode(username),
+        secret = totp_secret,
+        digits = DIGITS,
+        period = STEP,
+    )
+}
+
+fn current_step() -> i64 {
+    Utc::now().timestamp() / STEP
+}
+
+/// Code for time step, as in RFC 6238
+pub fn code_at(totp_secret: &str, step: i64) -> Option<String> {
+    let key = base32::decode(BASE32, totp_secret)?;
+    let mut mac = Hmac::<Sha1>::new_from_slice(&key).ok()?;
+    mac.update(&step.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+
+    let offset = (hash[hash.len() - 1] & 0xf) as usize;
+    let binary = u32::from_be_bytes([
+        hash[offset] & 0x7f,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]);
+
+    Some(format!(
+        "{:0width$}",
+        binary % 10u32.pow(DIGITS),
+        width = DIGITS as usize
+    ))
+}
+
+#[cfg(test)]
+pub fn current_code(totp_secret: &str) -> Option<String> {
+    code_at(totp_secret, current_step())
+}
+
+/// Finds the step code is valid for, if it is newer than the last one used
+fn matching_step(mfa: &Mfa, code: &str) -> Option<i64> {
+    let now = current_step();
+    (now - DRIFT_STEPS..=now + DRIFT_STEPS)
+        .filter(|step| mfa.last_step.map(|last| *step > last).unwrap_or(true))
+        .find(|step| code_at(&mfa.secret, *step).as_deref() == Some(code.trim()))
+}
+
+pub fn find(conn: &PgConnection, user: &str) -> Result<Option<Mfa>, ApiError> {
+    Ok(user_mfa
+        .filter(user_id.eq(user))
+        .first::<Mfa>(conn)
+        .optional()?)
+}
+
+/// Whether user has to give a code to log in
+pub fn is_enabled(conn: &PgConnection, user: &str) -> Result<bool, ApiError> {
+    Ok(find(conn, user)?.map(|mfa| mfa.enabled).unwrap_or(false))
+}
+
+/// Starts enrolling user with a new secret, replacing any enrollment they didn't finish
+///
+/// Callers must make sure user doesn't have mfa enabled already
+pub fn enroll(conn: &PgConnection, user: String) -> Result<Mfa, ApiError> {
+    let new_mfa = NewMfa {
+        id: nanoid!(ID_SIZE),
+        user_id: user,
+        secret: new_secret(),
+        enabled: false,
+        recovery_codes: vec![],
+        last_step: None,
+    };
+
+    Ok(diesel::insert_into(user_mfa)
+        .values(&new_mfa)
+        .on_conflict(user_id)
+        .do_update()
+        .set((
+            secret.eq(&new_mfa.secret),
+            recovery_codes.eq(Vec::<String>::new()),
+            last_step.eq(None::<i64>),
+        ))
+        .get_result::<Mfa>(conn)?)
+}
+
+/// Checks a totp code, or failing that a recovery code which is used up
+pub fn check(conn: &PgConnection, mfa: &Mfa, code: &str) -> Result<bool, ApiError> {
+    if let Some(step) = matching_step(mfa, code) {
+        diesel::update(user_mfa.filter(id.eq(&mfa.id)))
+            .set(last_step.eq(step))
+            .execute(conn)?;
+        return Ok(true);
+    }
+
+    let hashed = hash_code(code);
+    if !mfa.enabled || !mfa.recovery_codes.contains(&hashed) {
+        return Ok(false);
+    }
+
+    let remaining: Vec<String> = mfa
+        .recovery_codes
+        .iter()
+        .filter(|recovery_code| **recovery_code != hashed)
+        .cloned()
+        .collect();
+    diesel::update(user_mfa.filter(id.eq(&mfa.id)))
+        .set(recovery_codes.eq(remaining))
+        .execute(conn)?;
+    info!("Recovery code used for user {}.", mfa.user_id);
+
+    Ok(true)
+}
+
+/// Turns on mfa once the user has shown their authenticator works, returns their recovery codes
+pub fn enable(conn: &PgConnection, mfa: &Mfa) -> Result<Vec<String>, ApiError> {
+    let codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+        .map(|_| nanoid!(10, &RECOVERY_CODE_ALPHABET))
+        .collect();
+
+    diesel::update(user_mfa.filter(id.eq(&mfa.id)))
+        .set((
+            enabled.eq(true),
+            recovery_codes.eq(codes.iter().map(|code| hash_code(code)).collect::<Vec<_>>()),
+        ))
+        .execute(conn)?;
+
+    Ok(codes)
+}
+
+pub fn disable(conn: &PgConnection, user: &str) -> Result<usize, ApiError> {
+    Ok(diesel::delete(user_mfa.filter(user_id.eq(user))).execute(conn)?)
+}
@@ -1,38 +1,46 @@
+pub mod lockout;
+pub mod mfa;
 pub mod middleware;
+pub mod password_reset;
 pub mod routes;
+pub mod sessions;
+pub mod throttle;
 
+use std::net::IpAddr;
+
+use actix_web::HttpRequest;
 use chrono::prelude::*;
 use hmac::{Hmac, Mac};
 use jwt::{SignWithKey, VerifyWithKey};
 use serde::{Deserialize, Serialize};
 use sha2::Sha256;
 
-#[derive(Debug, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct Login {
-    account_id: String,
-    username: String,
-    password: String,
-}
+pub use crud_client::auth::{
+    Login, MfaCode, MfaEnrollment, MfaRequired, PasswordResetConfirm, PasswordResetRequest,
+    RecoveryCodes, VerifiedUser,
+};
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Claim {
     pub id: String,
     pub account_id: String,
+    /// Session the token was issued for, token is invalid once it is revoked
+    pub sid: String,
     pub exp: i64,
     pub iat: i64,
 }
 
 /// Milliseconds to token expiry
-const TOKEN_EXPIRY: i64 = 43_200_000;
+const TOKEN_EXPIRY: i64 = 900_000;
 
 impl Claim {
-    pub fn new(id: String, account_id: String) -> Self {
+    pub fn new(id: String, account_id: String, sid: String) -> Self {
         let now = Utc::now().timestamp_millis();
-        // expires in 12 hours
+        // expires in 15 minutes, refresh token is used to get a new one
         Self {
             id,
             account_id,
+            sid,
             exp: now + TOKEN_EXPIRY,
             iat: now,
         }
@@ -54,10 +62,24 @@ lazy_static! {
         std::env::var("INSTANCE_KEY_SECRET").unwrap().as_bytes()
     )
     .unwrap();
+    static ref PASSWORD_RESET_SECRET: Hmac<Sha256> = Hmac::new_from_slice(
+        std::env::var("PASSWORD_RESET_SECRET").unwrap().as_bytes()
+    )
+    .unwrap();
+    static ref INVITE_SECRET: Hmac<Sha256> = Hmac::new_from_slice(
+        std::env::var("INVITE_SECRET").unwrap().as_bytes()
+    )
+    .unwrap();
+    /// Milliseconds an invite link is good for, set in hours with INVITE_EXPIRY_HOURS
+    pub static ref INVITE_EXPIRY: i64 = std::env::var("INVITE_EXPIRY_HOURS")
+        .ok()
+        .and_then(|hours| hours.parse::<i64>().ok())
+        .unwrap_or(72)
+        * 3_600_000;
 }
 
-fn sign(id: String, account_id: String) -> Result<String, jwt::Error> {
-    let claim = Claim::new(id, account_id);
+pub(crate) fn sign(id: String, account_id: String, sid: String) -> Result<String, jwt::Error> {
+    let claim = Claim::new(id, account_id, sid);
     claim.sign_with_key(&*JWT_SECRET)
 }
 
@@ -74,6 +96,80 @@ fn verify(token: &String) -> Result<Claim, jwt::Error> {
     }
 }
 
+/// Milliseconds a user has to give their mfa code after their password
+const MFA_PENDING_EXPIRY: i64 = 300_000;
+
+/// Proves the password was right for a user who still has to give an mfa code, not an access token
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MfaPendingClaim {
+    pub id: String,
+    pub account_id: String,
+    pub mfa_pending: bool,
+    pub exp: i64,
+    pub iat: i64,
+}
+
+impl MfaPendingClaim {
+    pub fn new(id: String, account_id: String) -> Self {
+        let now = Utc::now().timestamp_millis();
+        Self {
+            id,
+            account_id,
+            mfa_pending: true,
+            exp: now + MFA_PENDING_EXPIRY,
+            iat: now,
+        }
+    }
+}
+
+fn sign_mfa_pending(id: String, account_id: String) -> Result<String, jwt::Error> {
+    let claim = MfaPendingClaim::new(id, account_id);
+    claim.sign_with_key(&*JWT_SECRET)
+}
+
+fn verify_mfa_pending(token: &str) -> Result<MfaPendingClaim, jwt::Error> {
+    let result: Result<MfaPendingClaim, jwt::Error> = token.verify_with_key(&*JWT_SECRET);
+    match result {
+        Ok(claim) => {
+            if Utc::now().timestamp_millis() > claim.exp || !claim.mfa_pending {
+                return Err(jwt::Error::Format);
+            }
+            Ok(claim)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Session the request's access token was issued for
+pub fn session_id(req: &HttpRequest) -> Option<String> {
+    req.cookie("at")
+        .and_then(|token| verify(&token.value().into()).ok())
+        .map(|claim| claim.sid)
+}
+
+/// Address the request came from, or the one the gateway forwarded it for when it came through it
+///
+/// Only the last X-Forwarded-For entry is the gateway's, the ones before it are whatever the client sent
+pub fn client_ip(req: &HttpRequest) -> Option<String> {
+    let peer = req.peer_addr()?.ip();
+    let from_gateway = match peer {
+        IpAddr::V4(ip) => ip.is_loopback() || ip.is_private(),
+        IpAddr::V6(ip) => ip.is_loopback(),
+    };
+
+    let forwarded = req
+        .headers()
+        .get("X-Forwarded-For")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.rsplit(',').next())
+        .and_then(|hop| hop.trim().parse::<IpAddr>().ok());
+
+    match forwarded {
+        Some(ip) if from_gateway => Some(ip.to_string()),
+        _ => Some(peer.to_string()),
+    }
+}
+
 const INSTANCE_DEPLOY_TOKEN_EXPIRY: i64 = 900_000;
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -126,5 +222,74 @@ pub fn verify_instance_key(token: &String) -> Result<InstanceKeyClaim, jwt::Erro
     result
 }
 
+/// Milliseconds to password reset token expiry
+const PASSWORD_RESET_TOKEN_EXPIRY: i64 = 3_600_000;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PasswordResetClaim {
+    /// Password reset the token was issued for, token is single use through it
+    pub rid: String,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+impl PasswordResetClaim {
+    pub fn new(rid: String) -> Self {
+        let now = Utc::now().timestamp_millis();
+        Self { rid, iat: now, exp: now + PASSWORD_RESET_TOKEN_EXPIRY }
+    }
+}
+
+pub fn sign_password_reset(rid: String) -> Result<String, jwt::Error> {
+    let claim = PasswordResetClaim::new(rid);
+    claim.sign_with_key(&*PASSWORD_RESET_SECRET)
+}
+
+pub fn verify_password_reset(token: &str) -> Result<PasswordResetClaim, jwt::Error> {
+    let result: Result<PasswordResetClaim, jwt::Error> = token.verify_with_key(&*PASSWORD_RESET_SECRET);
+    match result {
+        Ok(claim) => {
+            if Utc::now().timestamp_millis() > claim.exp {
+                return Err(jwt::Error::Format);
+            }
+            Ok(claim)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct InviteClaim {
+    /// Invite the token was issued for, token is single use through it
+    pub iid: String,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+impl InviteClaim {
+    pub fn new(iid: String) -> Self {
+        let now = Utc::now().timestamp_millis();
+        Self { iid, iat: now, exp: now + *INVITE_EXPIRY }
+    }
+}
+
+pub fn sign_invite(iid: String) -> Result<String, jwt::Error> {
+    let claim = InviteClaim::new(iid);
+    claim.sign_with_key(&*INVITE_SECRET)
+}
+
+pub fn verify_invite(token: &str) -> Result<InviteClaim, jwt::Error> {
+    let result: Result<InviteClaim, jwt::Error> = token.verify_with_key(&*INVITE_SECRET);
+    match result {
+        Ok(claim) => {
+            if Utc::now().timestamp_millis() > claim.exp {
+                return Err(jwt::Error::Format);
+            }
+            Ok(claim)
+        }
+        Err(err) => Err(err),
+    }
+}
+
 #[cfg(test)]
 mod tests;
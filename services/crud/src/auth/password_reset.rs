@@ -0,0 +1,97 @@
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use diesel::PgConnection;
+use models::password_resets::dsl::*;
+use models::{NewPasswordReset, PasswordReset, User};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    api_error::{ApiError, ErrorCode},
+    db,
+    mail::{Email, Mailer},
+    DASHBOARD_URL, ID_SIZE,
+};
+
+fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+fn invalid_token() -> ApiError {
+    ApiError::new(400, "Invalid or expired password reset link.".into())
+        .with_code(ErrorCode::InvalidToken)
+}
+
+/// Starts a password reset for user, returns the signed token to send them
+///
+/// Only a hash of the token is stored, any earlier unused resets for user stop working
+pub fn create(conn: &PgConnection, user: String) -> Result<String, ApiError> {
+    conn.transaction(|| {
+        let now = Utc::now().naive_utc();
+        diesel::update(password_resets.filter(user_id.eq(&user)))
+            .filter(used_at.is_null())
+            .set(used_at.eq(now))
+            .execute(conn)?;
+
+        let reset_id = nanoid!(ID_SIZE);
+        let token = super::sign_password_reset(reset_id.clone()).map_err(|err| {
+            error!("Failed to sign password reset token: {:?}", err);
+            ApiError::server_err()
+        })?;
+
+        diesel::insert_into(password_resets)
+            .values(NewPasswordReset {
+                id: reset_id,
+                user_id: user,
+                token_hash: hash_token(&token),
+                expires_at: now + Duration::milliseconds(super::PASSWORD_RESET_TOKEN_EXPIRY),
+                used_at: None,
+            })
+            .execute(conn)?;
+
+        Ok(token)
+    })
+}
+
+/// Marks the reset token was issued for as used, token can't be used again after this
+pub fn consume(conn: &PgConnection, token: &str) -> Result<PasswordReset, ApiError> {
+    let claim = super::verify_password_reset(token).map_err(|_| invalid_token())?;
+
+    diesel::update(password_resets.filter(id.eq(claim.rid)))
+        .filter(token_hash.eq(hash_token(token)))
+        .filter(used_at.is_null())
+        .filter(expires_at.gt(Utc::now().naive_utc()))
+        .set(used_at.eq(Utc::now().naive_utc()))
+        .get_result::<PasswordReset>(conn)
+        .optional()?
+        .ok_or_else(invalid_token)
+}
+
+/// Emails user a reset link, users that don't exist, are deactivated or have no email get nothing
+pub fn send(mailer: &dyn Mailer, account: String, name: String) -> Result<(), ApiError> {
+    use models::users;
+    let conn = db::connection()?;
+    let user = users::table
+        .filter(users::username.eq(name))
+        .filter(users::account_id.eq(account))
+        .filter(users::deleted_at.is_null())
+        .first::<User>(&conn)
+        .optional()?;
+
+    let (user, address) = match user {
+        Some(user) if user.active => match user.email.clone() {
+            Some(address) => (user, address),
+            None => return Ok(()),
+        },
+        _ => return Ok(()),
+    };
+
+    let token = create(&conn, user.id)?;
+    mailer.send(Email {
+        to: address,
+        subject: "Reset your password".into(),
+        body: format!(
+            "Hi {},\n\nUse this link to reset your password, it expires in an hour:\n{}/reset-password?token={}\n\nIf you didn't ask for this you can ignore this email.",
+            user.first_name, *DASHBOARD_URL, token
+        ),
+    })
+}
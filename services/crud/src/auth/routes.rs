@@ -4,82 +4,292 @@ use actix_web::{
     web::{block, Json, ServiceConfig},
     HttpRequest, HttpResponse,
 };
-use models::User;
+use auth::ReqUser;
+use models::{User, ValidationErrors};
 use diesel::prelude::*;
 
-use crate::{api_error::ApiError, auth::Login, db, json::ErrorBody};
+use super::{
+    lockout, mfa, password_reset,
+    sessions::{self, REFRESH_TOKEN_EXPIRY},
+    throttle, MfaCode, MfaEnrollment, MfaRequired, PasswordResetConfirm, PasswordResetRequest,
+    RecoveryCodes, VerifiedUser,
+};
+use crate::{
+    api_error::{ApiError, ErrorCode},
+    api_keys,
+    auth::Login,
+    db,
+    jobs::{self, Task},
+    roles,
+};
+
+const DEACTIVATED: &str = "This user has been deactivated.";
+const MFA_ENABLED: &str = "Mfa is already enabled.";
+const LOCKED: &str = "This user is locked after too many failed logins. Try again later.";
+
+fn incorrect_login() -> ApiError {
+    ApiError::new(401, "Incorrect username or password.".into())
+        .with_code(ErrorCode::InvalidCredentials)
+}
+
+fn locked() -> ApiError {
+    ApiError::new(423, LOCKED.into())
+}
+
+fn deactivated(status_code: u16) -> ApiError {
+    ApiError::new(status_code, DEACTIVATED.into()).with_code(ErrorCode::UserDeactivated)
+}
+
+fn access_cookie(token: String) -> Cookie<'static> {
+    Cookie::build("at", token)
+        .path("/")
+        .http_only(true)
+        .secure(true)
+        .max_age(Duration::milliseconds(super::TOKEN_EXPIRY))
+        .finish()
+}
+
+fn refresh_cookie(token: String) -> Cookie<'static> {
+    Cookie::build("rt", token)
+        .path("/")
+        .http_only(true)
+        .secure(true)
+        .max_age(Duration::milliseconds(REFRESH_TOKEN_EXPIRY))
+        .finish()
+}
+
+fn mfa_cookie(token: String) -> Cookie<'static> {
+    Cookie::build("mfa", token)
+        .path("/")
+        .http_only(true)
+        .secure(true)
+        .max_age(Duration::milliseconds(super::MFA_PENDING_EXPIRY))
+        .finish()
+}
 
+fn removal_cookie(name: &'static str) -> Cookie<'static> {
+    let mut cookie = Cookie::build(name, "").path("/").finish();
+    cookie.make_removal();
+    cookie
+}
+
+#[utoipa::path(
+    tag = "auth",
+    responses(
+        (status = 200, description = "Logged in, sets the auth cookies", body = User),
+        (status = 202, description = "User has mfa, finish with /login/mfa", body = MfaRequired),
+    ),
+)]
 #[post("/login")]
-async fn login(login: Json<Login>) -> Result<HttpResponse, ApiError> {
+async fn login(login: Json<Login>, req: HttpRequest) -> Result<HttpResponse, ApiError> {
     use models::users::dsl::*;
-    let conn = db::connection()?;
     let login = login.into_inner();
+
+    // back off clients that keep guessing wrong
+    let ip_key = super::client_ip(&req).map(|ip| throttle::ip_key(&ip));
+    let username_key = throttle::username_key(&login.account_id, &login.username);
+    let wait = ip_key
+        .as_ref()
+        .and_then(|key| throttle::wait_time(key, throttle::IP_FREE_ATTEMPTS))
+        .or_else(|| throttle::wait_time(&username_key, throttle::USERNAME_FREE_ATTEMPTS));
+    if let Some(wait) = wait {
+        let seconds = (wait.as_secs() + 1).to_string();
+        return Ok(HttpResponse::TooManyRequests()
+            .insert_header(("Retry-After", seconds.clone()))
+            .json(ApiError::new(
+                429,
+                format!("Too many login attempts. Try again in {} seconds.", seconds),
+            )));
+    }
+    let throttle_failure = || {
+        if let Some(ip_key) = &ip_key {
+            throttle::record_failure(ip_key);
+        }
+        throttle::record_failure(&username_key);
+    };
+
+    let conn = db::connection()?;
+    let login_username = login.username.clone();
+    let login_account_id = login.account_id.clone();
     let user = block(move || {
         users
-            .filter(username.eq(login.username))
-            .filter(account_id.eq(login.account_id))
+            .filter(username.eq(login_username))
+            .filter(account_id.eq(login_account_id))
+            .filter(deleted_at.is_null())
             .first::<User>(&conn)
             .optional()
     })
     .await??;
 
     // check if found a matching user
-    if let Some(user) = user {
-        // check password hashes
-        let pass = user.password.clone();
-        match block(move || bcrypt::verify(login.password, &pass)).await? {
-            Ok(result) => {
-                if result {
-                    // passwords match
-                    let token = super::sign(user.id.clone(), user.account_id.clone());
-                    match token {
-                        Ok(token) => Ok(HttpResponse::Ok()
-                            .cookie(
-                                Cookie::build("at", token)
-                                    .path("/")
-                                    .http_only(true)
-                                    .secure(true)
-                                    .max_age(Duration::milliseconds(super::TOKEN_EXPIRY))
-                                    .finish(),
-                            )
-                            .json(user)),
-                        _ => {
-                            Ok(HttpResponse::InternalServerError()
-                                .json(ErrorBody::server_err(None)))
-                        }
-                    }
-                } else {
-                    // password do not match
-                    Ok(HttpResponse::Unauthorized()
-                        .json(ErrorBody::new("Incorrect username or password.")))
-                }
-            }
-            // error comparing passwords
-            _ => Ok(HttpResponse::InternalServerError().json(ErrorBody::server_err(None))),
+    let user = match user {
+        Some(user) => user,
+        None => {
+            throttle_failure();
+            return Err(incorrect_login());
         }
-    } else {
-        // handle found no user
-        Ok(HttpResponse::Unauthorized().json(ErrorBody::new("Incorrect username or password.")))
+    };
+
+    if lockout::is_locked(&user) {
+        return Err(locked());
     }
+
+    // check password hashes
+    let pass = user.password.clone();
+    let matches = match block(move || bcrypt::verify(login.password, &pass)).await? {
+        Ok(matches) => matches,
+        // error comparing passwords
+        _ => return Err(ApiError::server_err()),
+    };
+
+    if !matches {
+        // password do not match
+        throttle_failure();
+        let conn = db::connection()?;
+        let failed_user = user.clone();
+        let locked = block(move || lockout::record_failure(&conn, &failed_user)).await??;
+
+        return Err(if locked { self::locked() } else { incorrect_login() });
+    }
+
+    if !user.active {
+        // deactivated users can't start new sessions
+        return Err(deactivated(403));
+    }
+
+    throttle::clear(&username_key);
+
+    // users with mfa need to give a code before they get a session
+    let conn = db::connection()?;
+    let mfa_user = user.id.clone();
+    if block(move || mfa::is_enabled(&conn, &mfa_user)).await?? {
+        return match super::sign_mfa_pending(user.id, user.account_id) {
+            Ok(token) => Ok(HttpResponse::Accepted()
+                .cookie(mfa_cookie(token))
+                .json(MfaRequired { mfa_required: true })),
+            _ => Err(ApiError::server_err()),
+        };
+    }
+
+    start_session(user).await
+}
+
+/// Finishes logging in user, clearing failed logins and handing out auth cookies
+async fn start_session(user: User) -> Result<HttpResponse, ApiError> {
+    let conn = db::connection()?;
+    let session_user = user.clone();
+    let (session, refresh_token) = block(move || {
+        lockout::reset(&conn, &session_user)?;
+        sessions::create(&conn, session_user.id, session_user.account_id)
+    })
+    .await??;
+
+    let user = User {
+        failed_logins: 0,
+        locked_until: None,
+        ..user
+    };
+    let token = super::sign(user.id.clone(), user.account_id.clone(), session.id);
+    match token {
+        Ok(token) => Ok(HttpResponse::Ok()
+            .cookie(access_cookie(token))
+            .cookie(refresh_cookie(refresh_token))
+            .json(user)),
+        _ => Err(ApiError::server_err()),
+    }
+}
+
+/// Second step of logging in for users with mfa, takes a code from their authenticator or a recovery code
+#[utoipa::path(
+    tag = "auth",
+    responses((status = 200, description = "Logged in, sets the auth cookies", body = User)),
+)]
+#[post("/login/mfa")]
+async fn login_mfa(body: Json<MfaCode>, req: HttpRequest) -> Result<HttpResponse, ApiError> {
+    use models::users::dsl::*;
+    let claim = match req
+        .cookie("mfa")
+        .and_then(|token| super::verify_mfa_pending(token.value()).ok())
+    {
+        Some(claim) => claim,
+        None => {
+            return Err(ApiError::new(401, "Log in with your password first.".into()))
+        }
+    };
+
+    let mfa_key = throttle::mfa_key(&claim.id);
+    if let Some(wait) = throttle::wait_time(&mfa_key, throttle::MFA_FREE_ATTEMPTS) {
+        let seconds = (wait.as_secs() + 1).to_string();
+        return Ok(HttpResponse::TooManyRequests()
+            .insert_header(("Retry-After", seconds.clone()))
+            .json(ApiError::new(
+                429,
+                format!("Too many attempts. Try again in {} seconds.", seconds),
+            )));
+    }
+
+    let conn = db::connection()?;
+    let (user, passed) = block(move || {
+        let user = users
+            .filter(id.eq(claim.id))
+            .filter(account_id.eq(claim.account_id))
+            .filter(deleted_at.is_null())
+            .first::<User>(&conn)?;
+        let passed = match mfa::find(&conn, &user.id)? {
+            Some(user_mfa) if user_mfa.enabled => mfa::check(&conn, &user_mfa, &body.code)?,
+            _ => false,
+        };
+
+        Ok::<_, ApiError>((user, passed))
+    })
+    .await??;
+
+    if !passed {
+        throttle::record_failure(&mfa_key);
+        return Err(ApiError::new(401, "Incorrect code.".into()).with_code(ErrorCode::InvalidCredentials));
+    }
+    if lockout::is_locked(&user) {
+        return Err(locked());
+    }
+    if !user.active {
+        return Err(deactivated(403));
+    }
+
+    throttle::clear(&mfa_key);
+    let mut res = start_session(user).await?;
+    res.add_cookie(&removal_cookie("mfa"))
+        .map_err(|_| ApiError::server_err())?;
+    Ok(res)
 }
 
 /// Returns whether the request is authenticated (has valid jwt)
+#[utoipa::path(tag = "auth", responses((status = 200)))]
 #[get("/authenticate")]
 async fn authenticate(req: HttpRequest) -> Result<HttpResponse, ApiError> {
     match req.cookie("at") {
         // cookie exists try to verify
         Some(token) => match super::verify(&token.value().into()) {
-            // valid token, return ok
-            Ok(_) => Ok(HttpResponse::Ok().finish()),
+            // valid token, make sure its session hasn't ended
+            Ok(claim) => {
+                let conn = db::connection()?;
+                let session = block(move || sessions::find_active(&conn, &claim.sid)).await??;
+
+                if session.is_some() {
+                    Ok(HttpResponse::Ok().finish())
+                } else {
+                    Err(ApiError::unauthorized())
+                }
+            }
             // invalid token, fail
-            _ => Ok(HttpResponse::Unauthorized().json(ErrorBody::unauthorized(None))),
+            _ => Err(ApiError::unauthorized()),
         },
         // cookie doesn't exist, automatic fail
-        _ => Ok(HttpResponse::Unauthorized().json(ErrorBody::unauthorized(None))),
+        _ => Err(ApiError::unauthorized()),
     }
 }
 
 /// Returns full user from jwt
+#[utoipa::path(tag = "auth", responses((status = 200, body = VerifiedUser)))]
 #[get("/verify")]
 async fn verify(req: HttpRequest) -> Result<HttpResponse, ApiError> {
     use models::users::dsl::*;
@@ -88,13 +298,34 @@ async fn verify(req: HttpRequest) -> Result<HttpResponse, ApiError> {
         Some(token) => match super::verify(&token.value().into()) {
             // valid token, get user from db
             Ok(claim) => {
+                // token is only good while its session is
                 let conn = db::connection()?;
-                let user = block(move || {
-                    users
+                let sid = claim.sid.clone();
+                let session = block(move || sessions::find_active(&conn, &sid)).await??;
+                if session.is_none() {
+                    return Err(ApiError::new(401, "Session has ended.".into()));
+                }
+
+                let conn = db::connection()?;
+                let user = block(move || -> Result<_, ApiError> {
+                    let user = users
                         .filter(id.eq(claim.id))
                         .filter(account_id.eq(claim.account_id))
+                        .filter(deleted_at.is_null())
                         .get_result::<User>(&conn)
-                        .optional()
+                        .optional()?;
+                    // capabilities are looked up every time so role changes apply right away
+                    match user {
+                        Some(user) => {
+                            let capabilities = roles::utils::capabilities_of(
+                                &conn,
+                                &user.account_id,
+                                &user.role_id,
+                            )?;
+                            Ok(Some(VerifiedUser { user, capabilities }))
+                        }
+                        None => Ok(None),
+                    }
                 })
                 .await?;
 
@@ -102,26 +333,278 @@ async fn verify(req: HttpRequest) -> Result<HttpResponse, ApiError> {
                 if let Ok(user) = user {
                     // check if found a matching user
                     if let Some(user) = user {
+                        if !user.user.active {
+                            // any tokens they still have are useless now
+                            return Err(deactivated(401));
+                        }
+
                         Ok(HttpResponse::Ok().json(user))
                     } else {
                         // handle found no user
-                        Ok(HttpResponse::Unauthorized()
-                            .json(ErrorBody::new("Incorrect username or password.")))
+                        Err(incorrect_login())
                     }
                 } else {
                     // handle db error
-                    Ok(HttpResponse::InternalServerError().json(ErrorBody::new("Database error.")))
+                    Err(ApiError::new(500, "Database error.".into()))
                 }
             }
             // invalid token, fail
-            _ => Ok(HttpResponse::Unauthorized().json(ErrorBody::new("Invalid authentication"))),
+            _ => Err(ApiError::new(401, "Invalid authentication".into())),
         },
         // doesn't exists, automatic fail
-        _ => Ok(HttpResponse::Unauthorized().json(ErrorBody::new("No authentication."))),
+        _ => Err(ApiError::new(401, "No authentication.".into())),
+    }
+}
+
+/// Resolves an api key from the Authorization header into the user it acts as
+#[utoipa::path(tag = "auth", responses((status = 200, body = ReqUser)))]
+#[get("/verify-key")]
+async fn verify_key(req: HttpRequest) -> Result<HttpResponse, ApiError> {
+    let key = req
+        .headers()
+        .get("Authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(|key| key.trim().to_string());
+    let key = match key {
+        Some(key) => key,
+        None => return Err(ApiError::new(401, "No api key.".into())),
+    };
+
+    match block(move || api_keys::utils::resolve(&key)).await?? {
+        // keys only get resource perms and capabilities through their built in role
+        Some(api_key) => Ok(HttpResponse::Ok().json(ReqUser {
+            id: api_key.id,
+            account_id: api_key.account_id,
+            role: api_key.role,
+            create_perms: vec![],
+            update_perms: vec![],
+            delete_perms: vec![],
+            capabilities: None,
+        })),
+        None => Err(ApiError::new(401, "Invalid api key.".into())),
+    }
+}
+
+/// Trades refresh token for a new access token, the refresh token is rotated as well
+#[utoipa::path(tag = "auth", responses((status = 200, description = "Sets new auth cookies")))]
+#[post("/refresh")]
+async fn refresh(req: HttpRequest) -> Result<HttpResponse, ApiError> {
+    let refresh_token = match req.cookie("rt") {
+        Some(refresh_token) => refresh_token.value().to_string(),
+        None => return Err(ApiError::new(401, "No session.".into())),
+    };
+
+    let conn = db::connection()?;
+    let (session, refresh_token) =
+        block(move || sessions::rotate(&conn, &refresh_token)).await??;
+
+    let conn = db::connection()?;
+    let session_id = session.id.clone();
+    let session_user = session.user_id.clone();
+    let user_active = block(move || {
+        use models::users::dsl::*;
+        // a deleted user is as good as deactivated
+        let user_active = users
+            .filter(id.eq(session_user))
+            .filter(deleted_at.is_null())
+            .select(active)
+            .first::<bool>(&conn)
+            .optional()?
+            .unwrap_or(false);
+
+        if !user_active {
+            sessions::revoke(&conn, &session_id)?;
+        }
+
+        Ok::<_, ApiError>(user_active)
+    })
+    .await??;
+
+    if !user_active {
+        return Err(deactivated(403));
+    }
+
+    match super::sign(session.user_id, session.account_id, session.id) {
+        Ok(token) => Ok(HttpResponse::Ok()
+            .cookie(access_cookie(token))
+            .cookie(refresh_cookie(refresh_token))
+            .finish()),
+        _ => Err(ApiError::server_err()),
+    }
+}
+
+/// Ends the current session and clears auth cookies
+#[utoipa::path(tag = "auth", responses((status = 200, description = "Clears the auth cookies")))]
+#[post("/logout")]
+async fn logout(req: HttpRequest) -> Result<HttpResponse, ApiError> {
+    let claim = req
+        .cookie("at")
+        .and_then(|token| super::verify(&token.value().into()).ok());
+    let refresh_token = req.cookie("rt").map(|token| token.value().to_string());
+
+    // access token may have expired already, so fall back to the refresh token
+    if claim.is_some() || refresh_token.is_some() {
+        let conn = db::connection()?;
+        block(move || match (claim, refresh_token) {
+            (Some(claim), _) => sessions::revoke(&conn, &claim.sid),
+            (None, Some(refresh_token)) => sessions::revoke_by_token(&conn, &refresh_token),
+            (None, None) => Ok(0),
+        })
+        .await??;
+    }
+
+    Ok(HttpResponse::Ok()
+        .cookie(removal_cookie("at"))
+        .cookie(removal_cookie("rt"))
+        .finish())
+}
+
+/// Emails user a link to reset their password, responds the same whether or not the user exists
+#[utoipa::path(tag = "auth", responses((status = 200)))]
+#[post("/password-reset/request")]
+async fn request_password_reset(body: Json<PasswordResetRequest>) -> Result<HttpResponse, ApiError> {
+    let body = body.into_inner();
+
+    // every request counts, so an address can't be flooded with emails
+    let reset_key = throttle::reset_key(&body.account_id, &body.username);
+    if throttle::wait_time(&reset_key, throttle::RESET_FREE_ATTEMPTS).is_some() {
+        return Ok(HttpResponse::Ok().finish());
+    }
+    throttle::record_failure(&reset_key);
+
+    // the user is looked up by the job, so the response is the same whether they exist or not
+    block(move || {
+        let conn = db::connection()?;
+        let task = Task::EmailPasswordReset {
+            account_id: body.account_id,
+            username: body.username,
+        };
+        jobs::schedule(&conn, task, chrono::Duration::zero())
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Sets a new password with a reset token, ending every session the user had
+#[utoipa::path(tag = "auth", responses((status = 200)))]
+#[post("/password-reset/confirm")]
+async fn confirm_password_reset(body: Json<PasswordResetConfirm>) -> Result<HttpResponse, ApiError> {
+    use models::users::dsl::*;
+    let body = body.into_inner();
+
+    if let Err(err) = models::validate_password(&body.password) {
+        let mut errors = ValidationErrors::new();
+        errors.add("password", err);
+        return Err(errors.into());
     }
+    let new_password = body.password;
+    let hashed = block(move || bcrypt::hash(new_password, bcrypt::DEFAULT_COST)).await??;
+
+    let conn = db::connection()?;
+    block(move || {
+        conn.transaction::<_, ApiError, _>(|| {
+            let reset = password_reset::consume(&conn, &body.token)?;
+
+            // proving they own the email is enough to lift a lockout too
+            diesel::update(users.filter(id.eq(&reset.user_id)))
+                .set((
+                    password.eq(hashed),
+                    failed_logins.eq(0),
+                    locked_until.eq(None::<chrono::NaiveDateTime>),
+                ))
+                .execute(&conn)?;
+            sessions::revoke_all(&conn, &reset.user_id)?;
+
+            Ok(())
+        })
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Starts setting up mfa for the requesting user, they still need to verify a code to turn it on
+#[utoipa::path(tag = "auth", responses((status = 200, body = MfaEnrollment)))]
+#[post("/mfa/enroll")]
+async fn enroll_mfa(req_user: Option<ReqUser>) -> Result<HttpResponse, ApiError> {
+    use models::users::dsl::*;
+    let req_user = req_user.ok_or_else(ApiError::forbidden)?;
+
+    let conn = db::connection()?;
+    let enrollment = block(move || {
+        if mfa::is_enabled(&conn, &req_user.id)? {
+            return Err(ApiError::new(409, MFA_ENABLED.into()));
+        }
+
+        let user = users
+            .filter(id.eq(&req_user.id))
+            .filter(account_id.eq(&req_user.account_id))
+            .first::<User>(&conn)?;
+        let user_mfa = mfa::enroll(&conn, user.id)?;
+
+        Ok(MfaEnrollment {
+            provisioning_uri: mfa::provisioning_uri(&user_mfa.secret, &user.username),
+            secret: user_mfa.secret,
+        })
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(enrollment))
+}
+
+/// Turns on mfa once the user gives a code from their authenticator, responds with recovery codes
+#[utoipa::path(tag = "auth", responses((status = 200, body = RecoveryCodes)))]
+#[post("/mfa/verify")]
+async fn verify_mfa(body: Json<MfaCode>, req_user: Option<ReqUser>) -> Result<HttpResponse, ApiError> {
+    let req_user = req_user.ok_or_else(ApiError::forbidden)?;
+
+    let conn = db::connection()?;
+    let codes = block(move || {
+        let user_mfa = match mfa::find(&conn, &req_user.id)? {
+            Some(user_mfa) if user_mfa.enabled => {
+                return Err(ApiError::new(409, MFA_ENABLED.into()))
+            }
+            Some(user_mfa) => user_mfa,
+            None => return Err(ApiError::new(404, "Start enrolling in mfa first.".into())),
+        };
+
+        if !mfa::check(&conn, &user_mfa, &body.code)? {
+            return Err(ApiError::new(400, "Incorrect code.".into()));
+        }
+        mfa::enable(&conn, &user_mfa)
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(RecoveryCodes { recovery_codes: codes }))
+}
+
+/// Turns off mfa, needs a current code or a recovery code
+#[utoipa::path(tag = "auth", responses((status = 200)))]
+#[post("/mfa/disable")]
+async fn disable_mfa(body: Json<MfaCode>, req_user: Option<ReqUser>) -> Result<HttpResponse, ApiError> {
+    let req_user = req_user.ok_or_else(ApiError::forbidden)?;
+
+    let conn = db::connection()?;
+    block(move || {
+        let user_mfa = match mfa::find(&conn, &req_user.id)? {
+            Some(user_mfa) if user_mfa.enabled => user_mfa,
+            _ => return Err(ApiError::new(404, "Mfa is not enabled.".into())),
+        };
+
+        if !mfa::check(&conn, &user_mfa, &body.code)? {
+            return Err(ApiError::new(400, "Incorrect code.".into()));
+        }
+        mfa::disable(&conn, &req_user.id)
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().finish())
 }
 
 // used to make sure reqs to instance deploy are authorized
+#[utoipa::path(tag = "auth", responses((status = 200)))]
 #[get("/verify-deploy")]
 async fn verify_deploy(req: HttpRequest) -> Result<HttpResponse, ApiError> {
     match req.headers().get("jwt") {
@@ -144,7 +627,16 @@ async fn verify_deploy(req: HttpRequest) -> Result<HttpResponse, ApiError> {
 
 pub fn init_routes(config: &mut ServiceConfig) {
     config.service(login);
+    config.service(login_mfa);
     config.service(verify);
     config.service(authenticate);
+    config.service(verify_key);
+    config.service(refresh);
+    config.service(logout);
+    config.service(request_password_reset);
+    config.service(confirm_password_reset);
+    config.service(enroll_mfa);
+    config.service(verify_mfa);
+    config.service(disable_mfa);
     config.service(verify_deploy);
 }
@@ -0,0 +1,113 @@
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::prelude::*;
+use diesel::PgConnection;
+use models::sessions::dsl::*;
+use models::{NewSession, Session};
+use sha2::{Digest, Sha256};
+
+use crate::{api_error::ApiError, ID_SIZE};
+
+/// Milliseconds to refresh token expiry
+pub const REFRESH_TOKEN_EXPIRY: i64 = 2_592_000_000;
+
+const SECRET_SIZE: usize = 32;
+
+fn hash_secret(secret: &str) -> String {
+    format!("{:x}", Sha256::digest(secret.as_bytes()))
+}
+
+fn new_expiry() -> NaiveDateTime {
+    Utc::now().naive_utc() + Duration::milliseconds(REFRESH_TOKEN_EXPIRY)
+}
+
+fn invalid_session() -> ApiError {
+    ApiError::new(401, "Invalid session.".into())
+}
+
+/// Starts a session for user, returns it with its refresh token
+///
+/// Refresh tokens are the session id and a random secret, only a hash of the secret is stored
+pub fn create(
+    conn: &PgConnection,
+    user: String,
+    account: String,
+) -> Result<(Session, String), ApiError> {
+    let secret = nanoid!(SECRET_SIZE);
+    let session = diesel::insert_into(sessions)
+        .values(NewSession {
+            id: nanoid!(ID_SIZE),
+            user_id: user,
+            account_id: account,
+            token_hash: hash_secret(&secret),
+            expires_at: new_expiry(),
+            revoked_at: None,
+        })
+        .get_result::<Session>(conn)?;
+
+    let refresh_token = format!("{}.{}", session.id, secret);
+    Ok((session, refresh_token))
+}
+
+/// Trades a refresh token for a new one, using an already rotated token revokes the session
+pub fn rotate(conn: &PgConnection, refresh_token: &str) -> Result<(Session, String), ApiError> {
+    let (session_id, secret) = refresh_token.split_once('.').ok_or_else(invalid_session)?;
+    let session = find_active(conn, session_id)?.ok_or_else(invalid_session)?;
+
+    if session.token_hash != hash_secret(secret) {
+        // old token was reused, it may have been stolen so end the session
+        info!("Refresh token reused for session {}, revoking.", session.id);
+        revoke(conn, &session.id)?;
+        return Err(invalid_session());
+    }
+
+    let secret = nanoid!(SECRET_SIZE);
+    let session = diesel::update(sessions.filter(id.eq(session.id)))
+        .set((token_hash.eq(hash_secret(&secret)), expires_at.eq(new_expiry())))
+        .get_result::<Session>(conn)?;
+
+    let refresh_token = format!("{}.{}", session.id, secret);
+    Ok((session, refresh_token))
+}
+
+/// Finds session if it hasn't expired or been revoked
+pub fn find_active(conn: &PgConnection, target: &str) -> Result<Option<Session>, ApiError> {
+    Ok(sessions
+        .filter(id.eq(target))
+        .filter(revoked_at.is_null())
+        .filter(expires_at.gt(Utc::now().naive_utc()))
+        .first::<Session>(conn)
+        .optional()?)
+}
+
+/// Revokes the session refresh token belongs to, if the token is still current
+pub fn revoke_by_token(conn: &PgConnection, refresh_token: &str) -> Result<usize, ApiError> {
+    let (session_id, secret) = refresh_token.split_once('.').ok_or_else(invalid_session)?;
+
+    Ok(diesel::update(sessions.filter(id.eq(session_id)))
+        .filter(token_hash.eq(hash_secret(secret)))
+        .set(revoked_at.eq(Utc::now().naive_utc()))
+        .execute(conn)?)
+}
+
+pub fn revoke(conn: &PgConnection, target: &str) -> Result<usize, ApiError> {
+    Ok(diesel::update(sessions.filter(id.eq(target)))
+        .set(revoked_at.eq(Utc::now().naive_utc()))
+        .execute(conn)?)
+}
+
+/// Revokes every session user has, logging them out everywhere
+pub fn revoke_all(conn: &PgConnection, user: &str) -> Result<usize, ApiError> {
+    Ok(diesel::update(sessions.filter(user_id.eq(user)))
+        .filter(revoked_at.is_null())
+        .set(revoked_at.eq(Utc::now().naive_utc()))
+        .execute(conn)?)
+}
+
+/// Revokes every session user has except keep, logging them out everywhere else
+pub fn revoke_others(conn: &PgConnection, user: &str, keep: &str) -> Result<usize, ApiError> {
+    Ok(diesel::update(sessions.filter(user_id.eq(user)))
+        .filter(id.ne(keep))
+        .filter(revoked_at.is_null())
+        .set(revoked_at.eq(Utc::now().naive_utc()))
+        .execute(conn)?)
+}
@@ -3,7 +3,7 @@ use crate::{
     db, tests,
     users
 };
-use models::{User, NewUser};
+use models::{types::{Resource, Role}, User, NewUser};
 use actix_http::StatusCode;
 use actix_web::{cookie::Cookie, test};
 use bcrypt::{hash, DEFAULT_COST};
@@ -50,14 +50,24 @@ async fn authenticate() {
     let (default1, _default2) = users::tests::defaults("auth authenticate");
 
     let app = tests::init(routes::init_routes).await;
+    let conn = db::connection().unwrap();
+
+    let result1: User = diesel::insert_into(models::users::table)
+        .values(&default1)
+        .get_result::<User>(&conn)
+        .expect("couldn't insert");
+    let (session, _) = sessions::create(&conn, result1.id.clone(), result1.account_id.clone())
+        .expect("couldn't create session");
 
     // send req with valid cookie expect success response
+    drop(conn);
     let req = test::TestRequest::get()
         .uri("/authenticate")
         .cookie(
             Cookie::build(
                 "at",
-                sign("1".into(), default1.account_id.clone()).expect("Failed to sign jwt."),
+                sign(result1.id.clone(), result1.account_id.clone(), session.id)
+                    .expect("Failed to sign jwt."),
             )
             .path("/")
             .finish(),
@@ -77,6 +87,10 @@ async fn authenticate() {
     let resp = test::call_service(&app, req).await;
 
     assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    let conn = db::connection().unwrap();
+
+    users::tests::remove(result1.id, &conn);
 }
 
 #[actix_web::test]
@@ -94,6 +108,8 @@ async fn verify() {
         })
         .get_result::<User>(&conn)
         .expect("couldn't insert");
+    let (session, _) = sessions::create(&conn, result1.id.clone(), result1.account_id.clone())
+        .expect("couldn't create session");
 
     // try to login with creds
     drop(conn);
@@ -102,18 +118,498 @@ async fn verify() {
         .cookie(
             Cookie::build(
                 "at",
-                sign(result1.id.clone(), result1.account_id.clone()).expect("Failed to sign jwt."),
+                sign(result1.id.clone(), result1.account_id.clone(), session.id)
+                    .expect("Failed to sign jwt."),
             )
             .path("/")
             .finish(),
         )
         .to_request();
 
-    let resp: User = test::call_and_read_body_json(&app, req).await;
+    let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let verified: VerifiedUser = serde_json::from_value(resp.clone()).unwrap();
 
-    users::tests::compare(&resp, &default1);
+    users::tests::compare(&verified.user, &default1);
+    assert_eq!(verified.capabilities, Role::Admin.capabilities());
+
+    // the gateway reads the same body as a ReqUser, perms and capabilities included
+    let req_user: auth::ReqUser =
+        serde_json::from_value(resp).expect("Failed to read as ReqUser");
+    assert_eq!(req_user.capabilities, Some(Role::Admin.capabilities()));
+    let no_capabilities = Some(vec![]);
+    assert!(auth::require_perm(
+        &Some(auth::ReqUser { capabilities: no_capabilities.clone(), ..req_user.clone() }),
+        auth::Action::Create,
+        Resource::Load
+    ));
+    assert!(!auth::require_perm(
+        &Some(auth::ReqUser { capabilities: no_capabilities, ..req_user }),
+        auth::Action::Create,
+        Resource::Carrier
+    ));
 
     let conn = db::connection().unwrap();
 
     users::tests::remove(result1.id, &conn);
 }
+
+#[actix_web::test]
+async fn refresh_and_logout() {
+    let (default1, _default2) = users::tests::defaults("auth refresh");
+
+    let app = tests::init(routes::init_routes).await;
+    let conn = db::connection().unwrap();
+
+    let result1: User = diesel::insert_into(models::users::table)
+        .values(NewUser {
+            password: hash(default1.password.clone(), DEFAULT_COST)
+                .expect("Failed to hash password."),
+            ..default1.clone()
+        })
+        .get_result::<User>(&conn)
+        .expect("couldn't insert");
+
+    drop(conn);
+    let req = test::TestRequest::post()
+        .uri("/login")
+        .set_json(Login {
+            account_id: result1.account_id.clone(),
+            username: result1.username.clone(),
+            password: default1.password,
+        })
+        .to_request();
+
+    let resp = test::call_service(&app, req).await;
+    let first_refresh = resp
+        .response()
+        .cookies()
+        .find(|cookie| cookie.name() == "rt")
+        .expect("No refresh token set.")
+        .into_owned();
+
+    // trade refresh token for new tokens
+    let req = test::TestRequest::post()
+        .uri("/refresh")
+        .cookie(first_refresh.clone())
+        .to_request();
+
+    let resp = test::call_service(&app, req).await;
+
+    assert_eq!(resp.status(), StatusCode::OK);
+    let access = resp
+        .response()
+        .cookies()
+        .find(|cookie| cookie.name() == "at")
+        .expect("No access token set.")
+        .into_owned();
+
+    // access token works until logging out
+    let req = test::TestRequest::get()
+        .uri("/authenticate")
+        .cookie(access.clone())
+        .to_request();
+
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    let req = test::TestRequest::post()
+        .uri("/logout")
+        .cookie(access.clone())
+        .to_request();
+
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    let req = test::TestRequest::get()
+        .uri("/authenticate")
+        .cookie(access)
+        .to_request();
+
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::UNAUTHORIZED
+    );
+
+    // rotated refresh token can't be used again
+    let req = test::TestRequest::post()
+        .uri("/refresh")
+        .cookie(first_refresh)
+        .to_request();
+
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::UNAUTHORIZED
+    );
+
+    let conn = db::connection().unwrap();
+
+    users::tests::remove(result1.id, &conn);
+}
+
+#[actix_web::test]
+async fn deactivated() {
+    // second default user is inactive
+    let (default1, default2) = users::tests::defaults("auth deactivated");
+
+    let app = tests::init(routes::init_routes).await;
+    let conn = db::connection().unwrap();
+
+    let result2: User = diesel::insert_into(models::users::table)
+        .values(NewUser {
+            password: hash(default2.password.clone(), DEFAULT_COST)
+                .expect("Failed to hash password."),
+            ..default2.clone()
+        })
+        .get_result::<User>(&conn)
+        .expect("couldn't insert");
+    let (session, _) = sessions::create(&conn, result2.id.clone(), result2.account_id.clone())
+        .expect("couldn't create session");
+
+    // correct creds still can't log in
+    drop(conn);
+    let req = test::TestRequest::post()
+        .uri("/login")
+        .set_json(Login {
+            account_id: result2.account_id.clone(),
+            username: result2.username.clone(),
+            password: default2.password,
+        })
+        .to_request();
+
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::FORBIDDEN);
+
+    // token from before they were deactivated doesn't verify
+    let req = test::TestRequest::get()
+        .uri("/verify")
+        .cookie(
+            Cookie::build(
+                "at",
+                sign(result2.id.clone(), result2.account_id.clone(), session.id)
+                    .expect("Failed to sign jwt."),
+            )
+            .path("/")
+            .finish(),
+        )
+        .to_request();
+
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::UNAUTHORIZED
+    );
+
+    // a deleted user's session can't be refreshed either
+    let conn = db::connection().unwrap();
+    let result1: User = diesel::insert_into(models::users::table)
+        .values(default1)
+        .get_result::<User>(&conn)
+        .expect("couldn't insert");
+    diesel::update(models::users::table.find(&result1.id))
+        .set(models::users::deleted_at.eq(Some(chrono::Utc::now().naive_utc())))
+        .execute(&conn)
+        .expect("couldn't delete user");
+    let (_, refresh_token) = sessions::create(&conn, result1.id.clone(), result1.account_id.clone())
+        .expect("couldn't create session");
+    drop(conn);
+
+    let req = test::TestRequest::post()
+        .uri("/refresh")
+        .cookie(Cookie::build("rt", refresh_token.clone()).path("/").finish())
+        .to_request();
+
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::FORBIDDEN);
+
+    let conn = db::connection().unwrap();
+    let session_id = refresh_token.split_once('.').unwrap().0;
+    assert!(sessions::find_active(&conn, session_id).unwrap().is_none());
+
+    users::tests::remove(result1.id, &conn);
+    users::tests::remove(result2.id, &conn);
+}
+
+#[actix_web::test]
+async fn lockout() {
+    let (default1, _default2) = users::tests::defaults("auth lockout");
+
+    let app = tests::init(routes::init_routes).await;
+    let conn = db::connection().unwrap();
+
+    let result1: User = diesel::insert_into(models::users::table)
+        .values(NewUser {
+            password: hash(default1.password.clone(), DEFAULT_COST)
+                .expect("Failed to hash password."),
+            ..default1.clone()
+        })
+        .get_result::<User>(&conn)
+        .expect("couldn't insert");
+
+    drop(conn);
+    let login = |password: &str| {
+        test::TestRequest::post()
+            .uri("/login")
+            .set_json(Login {
+                account_id: result1.account_id.clone(),
+                username: result1.username.clone(),
+                password: password.into(),
+            })
+            .to_request()
+    };
+
+    // wrong password until the last allowed failure locks the user
+    for _ in 1..lockout::MAX_FAILED_LOGINS {
+        let resp = test::call_service(&app, login("wrong")).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+    let resp = test::call_service(&app, login("wrong")).await;
+    assert_eq!(resp.status(), StatusCode::LOCKED);
+
+    // correct password doesn't help while locked
+    let resp = test::call_service(&app, login(&default1.password)).await;
+    assert_eq!(resp.status(), StatusCode::LOCKED);
+
+    let conn = db::connection().unwrap();
+    let locked: User = models::users::table
+        .find(&result1.id)
+        .get_result(&conn)
+        .expect("Failed to get user");
+
+    assert!(locked.locked_until.is_some());
+    assert_eq!(locked.failed_logins, 0);
+
+    users::tests::remove(result1.id, &conn);
+}
+
+#[actix_web::test]
+async fn password_reset() {
+    let (default1, _default2) = users::tests::defaults("auth password reset");
+    let address = "auth-password-reset@testys.test";
+
+    let app = tests::init(routes::init_routes).await;
+    let conn = db::connection().unwrap();
+
+    let result1: User = diesel::insert_into(models::users::table)
+        .values(NewUser {
+            email: Some(address.into()),
+            ..default1.clone()
+        })
+        .get_result::<User>(&conn)
+        .expect("couldn't insert");
+    let (session, _) = sessions::create(&conn, result1.id.clone(), result1.account_id.clone())
+        .expect("couldn't create session");
+
+    // unknown users get the same response, but no email
+    drop(conn);
+    let req = test::TestRequest::post()
+        .uri("/password-reset/request")
+        .set_json(PasswordResetRequest {
+            account_id: result1.account_id.clone(),
+            username: "nobody".into(),
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let req = test::TestRequest::post()
+        .uri("/password-reset/request")
+        .set_json(PasswordResetRequest {
+            account_id: result1.account_id.clone(),
+            username: result1.username.clone(),
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    // sent by the job runner, not while responding
+    assert!(crate::mail::outbox::take(address).is_empty());
+    crate::jobs::run_due(&tests::app_data()).await.unwrap();
+    let sent = crate::mail::outbox::take(address);
+    assert_eq!(sent.len(), 1);
+    let token = sent[0]
+        .body
+        .split("token=")
+        .nth(1)
+        .and_then(|rest| rest.split_whitespace().next())
+        .expect("No token in email")
+        .to_string();
+
+    let confirm = |token: &str| {
+        test::TestRequest::post()
+            .uri("/password-reset/confirm")
+            .set_json(PasswordResetConfirm {
+                token: token.into(),
+                password: "Brand new passw0rd".into(),
+            })
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, confirm(&token)).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    // tokens are single use
+    let resp = test::call_service(&app, confirm(&token)).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    // new password works and old sessions are gone
+    let req = test::TestRequest::post()
+        .uri("/login")
+        .set_json(Login {
+            account_id: result1.account_id.clone(),
+            username: result1.username.clone(),
+            password: "Brand new passw0rd".into(),
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let conn = db::connection().unwrap();
+    let old_session = sessions::find_active(&conn, &session.id).expect("Failed to get session");
+    assert!(old_session.is_none());
+
+    users::tests::remove(result1.id, &conn);
+}
+
+#[actix_web::test]
+async fn mfa_login() {
+    let (default1, _default2) = users::tests::defaults("auth mfa login");
+
+    let app = tests::init(routes::init_routes).await;
+    let conn = db::connection().unwrap();
+
+    let result1: User = diesel::insert_into(models::users::table)
+        .values(NewUser {
+            password: hash(default1.password.clone(), DEFAULT_COST)
+                .expect("Failed to hash password."),
+            ..default1.clone()
+        })
+        .get_result::<User>(&conn)
+        .expect("couldn't insert");
+
+    drop(conn);
+    let req_user = serde_json::to_string(&auth::ReqUser {
+        id: result1.id.clone(),
+        account_id: result1.account_id.clone(),
+        role: Role::Admin,
+        create_perms: vec![],
+        update_perms: vec![],
+        delete_perms: vec![],
+        capabilities: None,
+    })
+    .unwrap();
+    let with_code = |uri: &str, code: &str| {
+        test::TestRequest::post()
+            .uri(uri)
+            .insert_header(("user", req_user.clone()))
+            .set_json(serde_json::json!({ "code": code }))
+    };
+
+    let req = test::TestRequest::post()
+        .uri("/mfa/enroll")
+        .insert_header(("user", req_user.clone()))
+        .to_request();
+    let enrollment: MfaEnrollment = test::call_and_read_body_json(&app, req).await;
+    assert!(enrollment.provisioning_uri.starts_with("otpauth://totp/"));
+
+    // not on until a code is verified
+    let resp = test::call_service(&app, with_code("/mfa/verify", "000000").to_request()).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let code = mfa::current_code(&enrollment.secret).unwrap();
+    let codes: RecoveryCodes =
+        test::call_and_read_body_json(&app, with_code("/mfa/verify", &code).to_request()).await;
+    assert_eq!(codes.recovery_codes.len(), 10);
+
+    // password alone only gets a pending cookie now
+    let req = test::TestRequest::post()
+        .uri("/login")
+        .set_json(Login {
+            account_id: result1.account_id.clone(),
+            username: result1.username.clone(),
+            password: default1.password.clone(),
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::ACCEPTED);
+    assert!(resp.response().cookies().all(|cookie| cookie.name() != "at"));
+    let pending = resp
+        .response()
+        .cookies()
+        .find(|cookie| cookie.name() == "mfa")
+        .expect("No mfa cookie")
+        .into_owned();
+
+    // pending cookie isn't an access token
+    let req = test::TestRequest::get()
+        .uri("/authenticate")
+        .cookie(Cookie::new("at", pending.value().to_string()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    // the code used to verify can't be replayed
+    let req = with_code("/login/mfa", &code).cookie(pending.clone()).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    // recovery codes work once
+    let recovery_code = codes.recovery_codes[0].clone();
+    let req = with_code("/login/mfa", &recovery_code).cookie(pending.clone()).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(resp.response().cookies().any(|cookie| cookie.name() == "at"));
+
+    let req = with_code("/login/mfa", &recovery_code).cookie(pending.clone()).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    let req = with_code("/mfa/disable", &codes.recovery_codes[1]).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let conn = db::connection().unwrap();
+    assert!(!mfa::is_enabled(&conn, &result1.id).unwrap());
+
+    users::tests::remove(result1.id, &conn);
+}
+
+#[actix_web::test]
+async fn throttle_backs_off() {
+    use std::time::Duration;
+
+    let key = throttle::username_key("test", "throttle backoff");
+    let base = Duration::from_secs(10);
+
+    // free attempts don't wait
+    for _ in 0..throttle::USERNAME_FREE_ATTEMPTS {
+        assert_eq!(throttle::wait_time_from(&key, throttle::USERNAME_FREE_ATTEMPTS, base), None);
+        throttle::record_failure(&key);
+    }
+
+    // then doubles with every failure
+    let wait = throttle::wait_time_from(&key, throttle::USERNAME_FREE_ATTEMPTS, base).unwrap();
+    assert!(wait > Duration::from_secs(9) && wait <= base);
+    throttle::record_failure(&key);
+    let wait = throttle::wait_time_from(&key, throttle::USERNAME_FREE_ATTEMPTS, base).unwrap();
+    assert!(wait > Duration::from_secs(19) && wait <= Duration::from_secs(20));
+
+    // up to five minutes
+    for _ in 0..10 {
+        throttle::record_failure(&key);
+    }
+    let wait = throttle::wait_time_from(&key, throttle::USERNAME_FREE_ATTEMPTS, base).unwrap();
+    assert!(wait > Duration::from_secs(299) && wait <= Duration::from_secs(300));
+
+    throttle::clear(&key);
+    assert_eq!(throttle::wait_time_from(&key, throttle::USERNAME_FREE_ATTEMPTS, base), None);
+}
+
+#[actix_web::test]
+async fn trusts_only_the_gateways_hop() {
+    let forwarded = |peer: &str| {
+        test::TestRequest::default()
+            .peer_addr(peer.parse().unwrap())
+            .insert_header(("X-Forwarded-For", "6.6.6.6, 203.0.113.7"))
+            .to_http_request()
+    };
+
+    // the gateway appends who it forwarded for, anything before it came from the client
+    assert_eq!(client_ip(&forwarded("127.0.0.1:4000")).as_deref(), Some("203.0.113.7"));
+    // clients calling crud directly can't say who they are
+    assert_eq!(client_ip(&forwarded("198.51.100.2:4000")).as_deref(), Some("198.51.100.2"));
+}
@@ -0,0 +1,92 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Failed logins allowed from one ip before backing off, many users can share an ip
+pub const IP_FREE_ATTEMPTS: u32 = 10;
+/// Failed logins allowed for one username before backing off
+pub const USERNAME_FREE_ATTEMPTS: u32 = 3;
+/// Wrong mfa codes allowed for one user before backing off
+pub const MFA_FREE_ATTEMPTS: u32 = 3;
+/// Password reset emails sent for one username before backing off
+pub const RESET_FREE_ATTEMPTS: u32 = 3;
+
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+/// Failures older than this are forgotten
+const FORGET_AFTER: Duration = Duration::from_secs(3600);
+
+struct Failures {
+    count: u32,
+    last: Instant,
+}
+
+lazy_static! {
+    /// Wait after the first failure past the free attempts, doubles with every failure after,
+    /// set in milliseconds with THROTTLE_BACKOFF_MS
+    static ref BACKOFF_BASE: Duration = Duration::from_millis(
+        std::env::var("THROTTLE_BACKOFF_MS")
+            .ok()
+            .and_then(|ms| ms.parse::<u64>().ok())
+            .unwrap_or(1000)
+    );
+    static ref FAILURES: Mutex<HashMap<String, Failures>> = Mutex::new(HashMap::new());
+}
+
+pub fn ip_key(ip: &str) -> String {
+    format!("ip:{}", ip)
+}
+
+pub fn username_key(account_id: &str, username: &str) -> String {
+    format!("username:{}:{}", account_id, username)
+}
+
+pub fn mfa_key(user_id: &str) -> String {
+    format!("mfa:{}", user_id)
+}
+
+pub fn reset_key(account_id: &str, username: &str) -> String {
+    format!("reset:{}:{}", account_id, username)
+}
+
+fn backoff(count: u32, free_attempts: u32, base: Duration) -> Duration {
+    if count < free_attempts {
+        return Duration::ZERO;
+    }
+
+    let exponent = (count - free_attempts).min(31);
+    base.saturating_mul(2u32.pow(exponent))
+        .min(MAX_BACKOFF)
+}
+
+/// Time left before key may try to log in again, none if it can try now
+pub fn wait_time(key: &str, free_attempts: u32) -> Option<Duration> {
+    wait_time_from(key, free_attempts, *BACKOFF_BASE)
+}
+
+/// Like wait_time, backing off from base instead of THROTTLE_BACKOFF_MS
+pub fn wait_time_from(key: &str, free_attempts: u32, base: Duration) -> Option<Duration> {
+    let failures = FAILURES.lock().unwrap();
+    let failure = failures.get(key)?;
+
+    (failure.last + backoff(failure.count, free_attempts, base))
+        .checked_duration_since(Instant::now())
+        .filter(|wait| !wait.is_zero())
+}
+
+pub fn record_failure(key: &str) {
+    let mut failures = FAILURES.lock().unwrap();
+    failures.retain(|_, failure| failure.last.elapsed() < FORGET_AFTER);
+
+    let failure = failures.entry(key.to_string()).or_insert(Failures {
+        count: 0,
+        last: Instant::now(),
+    });
+    failure.count += 1;
+    failure.last = Instant::now();
+}
+
+pub fn clear(key: &str) {
+    FAILURES.lock().unwrap().remove(key);
+}
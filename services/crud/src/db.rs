@@ -84,6 +84,10 @@ pub fn init() {
                 delete_perms: vec![Resource::Load, Resource::Carrier, Resource::Shipper],
                 role: Role::Owner,
                 notes: None,
+                failed_logins: 0,
+                locked_until: None,
+                email: None,
+                role_id: Role::Owner.into(),
             })
             .on_conflict_do_nothing()
             .execute(&conn)
@@ -127,6 +131,10 @@ pub fn init() {
                 delete_perms: vec![Resource::Load, Resource::Carrier, Resource::Shipper],
                 role: Role::Owner,
                 notes: None,
+                failed_logins: 0,
+                locked_until: None,
+                email: None,
+                role_id: Role::Owner.into(),
             })
             .on_conflict_do_nothing()
             .execute(&conn)
@@ -1,70 +1,32 @@
-use aws_sdk_elasticbeanstalk::output::TerminateEnvironmentOutput;
-use aws_sdk_route53::model::{ChangeBatch, Change, ChangeAction, ResourceRecordSet, RrType, AliasTarget};
+use cloud::{beanstalk::Environment, dns::AliasRecord, Aws};
 
 use crate::api_error::ApiError;
 
-pub async fn delete_instance(
-    eb_client: &aws_sdk_elasticbeanstalk::Client,
-    env_id: &str,
-) -> Result<TerminateEnvironmentOutput, ApiError> {
-    let res = eb_client
-        .terminate_environment()
-        .environment_id(env_id)
-        .force_terminate(true)
-        .send()
-        .await;
-
-    if let Err(err) = res {
-        error!("{:?}", err);
-        return Err(ApiError::new(
-            500,
-            "An error ocurred while terminating the instance.".into(),
-        ));
-    }
-
-    Ok(res.unwrap())
+pub async fn delete_instance(aws: &Aws, env_id: &str) -> Result<Environment, ApiError> {
+    aws.beanstalk
+        .terminate_environment(env_id)
+        .await
+        .map_err(|_| {
+            ApiError::new(
+                500,
+                "An error ocurred while terminating the instance.".into(),
+            )
+        })
 }
 
-pub async fn delete_dns(
-    r53_client: &aws_sdk_route53::Client,
-    url: &str,
-    env: &TerminateEnvironmentOutput,
-) -> Result<(), ApiError> {
-    let res = r53_client
-        .change_resource_record_sets()
-        .hosted_zone_id("Z0898550109O7ZB98C1FF")
-        .change_batch(
-            ChangeBatch::builder()
-                .changes(
-                    Change::builder()
-                        .action(ChangeAction::Delete)
-                        .resource_record_set(
-                            ResourceRecordSet::builder()
-                                .name(url)
-                                .r#type(RrType::A)
-                                .alias_target(
-                                    AliasTarget::builder()
-                                        .dns_name(env.cname().unwrap())
-                                        .evaluate_target_health(false)
-                                        .hosted_zone_id("Z117KPS5GTRQ2G")
-                                        .build(),
-                                )
-                                .build(),
-                        )
-                        .build(),
-                )
-                .build(),
-        )
-        .send()
-        .await;
+/// Removes the alias from url to target, the cname of the instance's environment
+pub async fn delete_dns(aws: &Aws, url: &str, target: &str) -> Result<(), ApiError> {
+    let record = AliasRecord {
+        hosted_zone_id: "Z0898550109O7ZB98C1FF".into(),
+        name: url.into(),
+        target_dns_name: target.into(),
+        target_zone_id: "Z117KPS5GTRQ2G".into(),
+    };
 
-    if let Err(err) = res {
-        error!("{:?}", err);
-        return Err(ApiError::new(
-                        500,
-                        "An error ocurred while deleting the dns. Your instance has terminated and is no longer available. Please try again.".into(),
-                    ));
-    }
-
-	Ok(())
+    aws.dns.delete_alias(&record).await.map_err(|_| {
+        ApiError::new(
+            500,
+            "An error ocurred while deleting the dns. Your instance has terminated and is no longer available. Please try again.".into(),
+        )
+    })
 }
@@ -0,0 +1,289 @@
+//! Probing instances' /health, when it's asked for and on an interval for every deployed instance
+use std::time::{Duration as StdDuration, Instant};
+
+use actix_web::web;
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::prelude::*;
+use futures_util::{stream, StreamExt};
+use models::instance_health_checks::dsl::*;
+use models::{
+    types::{try_transition, InstanceEvent, InstanceStatus},
+    Instance, InstanceHealthCheck, NewInstanceHealthCheck, UpdateInstance,
+};
+use reqwest::{redirect::Policy, Client};
+
+use crate::{api_error::ApiError, db, notifications, ID_SIZE};
+
+pub use crud_client::instances::Uptime;
+
+lazy_static! {
+    /// Seconds between probing every deployed instance, set with HEALTH_INTERVAL_SECONDS
+    static ref INTERVAL_SECONDS: u64 = std::env::var("HEALTH_INTERVAL_SECONDS")
+        .ok()
+        .and_then(|seconds| seconds.parse::<u64>().ok())
+        .unwrap_or(60);
+    /// Instances probed at the same time, set with HEALTH_CONCURRENCY
+    static ref CONCURRENCY: usize = std::env::var("HEALTH_CONCURRENCY")
+        .ok()
+        .and_then(|count| count.parse::<usize>().ok())
+        .unwrap_or(10);
+    /// Checks in a row that have to agree before the monitor changes a status, set with HEALTH_THRESHOLD
+    static ref THRESHOLD: i64 = std::env::var("HEALTH_THRESHOLD")
+        .ok()
+        .and_then(|count| count.parse::<i64>().ok())
+        .unwrap_or(3);
+}
+
+/// Checks returned with an instance's uptime
+const RECENT_CHECKS: i64 = 20;
+/// Days checks are kept, a day past the month uptime looks back
+const RETENTION_DAYS: i64 = 31;
+
+/// What one request to an instance's /health found
+#[derive(Debug, Clone, PartialEq)]
+pub struct Probe {
+    pub healthy: bool,
+    pub latency_ms: Option<i32>,
+    pub status_code: Option<i32>,
+    pub error: Option<String>,
+    /// Couldn't connect at all
+    pub unreachable: bool,
+}
+
+impl Probe {
+    pub fn event(&self) -> InstanceEvent {
+        if self.healthy {
+            InstanceEvent::Healthy
+        } else {
+            InstanceEvent::Unhealthy
+        }
+    }
+
+    /// A configured instance that can't be reached yet is still coming up, it isn't unhealthy
+    pub fn pending(&self, instance: &Instance) -> bool {
+        instance.status == InstanceStatus::Configured && self.unreachable
+    }
+}
+
+pub fn client() -> Client {
+    Client::builder()
+        .redirect(Policy::none())
+        .connect_timeout(StdDuration::from_secs(5))
+        .timeout(StdDuration::from_secs(10))
+        .build()
+        .unwrap()
+}
+
+pub async fn probe(client: &Client, url: &str) -> Probe {
+    let started = Instant::now();
+    let res = client.get(format!("https://{url}/health")).send().await;
+    let elapsed = i32::try_from(started.elapsed().as_millis()).unwrap_or(i32::MAX);
+
+    match res {
+        // redirect is desireable response for health check
+        Ok(res) => Probe {
+            healthy: res.status().is_redirection(),
+            latency_ms: Some(elapsed),
+            status_code: Some(res.status().as_u16().into()),
+            error: None,
+            unreachable: false,
+        },
+        Err(err) => Probe {
+            healthy: false,
+            latency_ms: None,
+            status_code: None,
+            error: Some(err.to_string()),
+            unreachable: err.is_connect(),
+        },
+    }
+}
+
+pub fn record(
+    conn: &PgConnection,
+    instance: &Instance,
+    probe: &Probe,
+) -> Result<InstanceHealthCheck, ApiError> {
+    Ok(diesel::insert_into(instance_health_checks)
+        .values(NewInstanceHealthCheck {
+            id: nanoid!(ID_SIZE),
+            instance_id: instance.id.clone(),
+            account_id: instance.account_id.clone(),
+            healthy: probe.healthy,
+            latency_ms: probe.latency_ms,
+            status_code: probe.status_code,
+            error: probe.error.clone(),
+        })
+        .get_result::<InstanceHealthCheck>(conn)?)
+}
+
+/// Changes instance's status once its last few checks agree, so one dropped request doesn't flip it
+pub fn settle(conn: &PgConnection, instance: &Instance) -> Result<Option<Instance>, ApiError> {
+    let recent = instance_health_checks
+        .select(healthy)
+        .filter(instance_id.eq(&instance.id))
+        .order(created_at.desc())
+        .limit(*THRESHOLD)
+        .load::<bool>(conn)?;
+
+    let agreed = match recent.first() {
+        Some(latest) if recent.len() as i64 >= *THRESHOLD => {
+            recent.iter().all(|passed| passed == latest)
+        }
+        _ => false,
+    };
+    if !agreed {
+        return Ok(None);
+    }
+
+    let event = if recent[0] {
+        InstanceEvent::Healthy
+    } else {
+        InstanceEvent::Unhealthy
+    };
+    if try_transition(&instance.status, event)? == instance.status {
+        return Ok(None);
+    }
+
+    Ok(Some(change_status(conn, instance, event)?))
+}
+
+/// Moves instance to the status a check found, in conn's transaction, telling its account once it turns unhealthy
+pub fn change_status(
+    conn: &PgConnection,
+    instance: &Instance,
+    event: InstanceEvent,
+) -> Result<Instance, ApiError> {
+    let changed = super::utils::transition(conn, instance, event, UpdateInstance::default())?;
+    if changed.status == InstanceStatus::Unhealthy && instance.status != InstanceStatus::Unhealthy {
+        notifications::queue(conn, notifications::unhealthy(&changed))?;
+    }
+
+    Ok(changed)
+}
+
+/// Probes instance, saving what it found and changing its status once enough checks agree
+async fn monitor(client: Client, instance: Instance) -> Result<(), ApiError> {
+    let url = match &instance.url {
+        Some(url) => url.clone(),
+        None => return Ok(()),
+    };
+
+    let probe = probe(&client, &url).await;
+    if probe.pending(&instance) {
+        return Ok(());
+    }
+
+    web::block(move || {
+        let conn = db::connection()?;
+        conn.transaction(|| {
+            record(&conn, &instance, &probe)?;
+            if let Some(changed) = settle(&conn, &instance)? {
+                info!(
+                    "Instance {} is now {}.",
+                    changed.id,
+                    Into::<String>::into(changed.status)
+                );
+            }
+            Ok(())
+        })
+    })
+    .await?
+}
+
+/// Instances in a status a health check can happen in
+fn deployed() -> Result<Vec<Instance>, ApiError> {
+    use models::instances::dsl::*;
+    let conn = db::connection()?;
+
+    Ok(instances
+        .filter(deleted_at.is_null())
+        .filter(status.eq_any(vec![
+            InstanceStatus::Ok,
+            InstanceStatus::Unhealthy,
+            InstanceStatus::Configured,
+        ]))
+        .load::<Instance>(&conn)?)
+}
+
+/// Probes every deployed instance once, a few at a time, returns how many were probed
+pub async fn check_all() -> Result<usize, ApiError> {
+    let deployed = web::block(deployed).await??;
+    let probed = deployed.len();
+    let client = client();
+
+    stream::iter(deployed)
+        .map(|instance| {
+            let (client, target) = (client.clone(), instance.id.clone());
+            async move {
+                if monitor(client, instance).await.is_err() {
+                    error!("Failed to check the health of instance {}.", target);
+                }
+            }
+        })
+        .buffer_unordered(*CONCURRENCY)
+        .collect::<Vec<()>>()
+        .await;
+
+    Ok(probed)
+}
+
+/// Probes every deployed instance on an interval while the server runs
+pub fn spawn() {
+    actix_web::rt::spawn(async {
+        let every = StdDuration::from_secs(*INTERVAL_SECONDS);
+        let mut interval = actix_web::rt::time::interval(every);
+
+        loop {
+            interval.tick().await;
+
+            if check_all().await.is_err() {
+                error!("Failed to check the health of instances.");
+            }
+        }
+    });
+}
+
+/// Percent of instance's checks since `since` that passed
+fn percent_healthy(
+    conn: &PgConnection,
+    instance: &Instance,
+    since: NaiveDateTime,
+) -> Result<Option<f64>, ApiError> {
+    let checks = instance_health_checks
+        .filter(instance_id.eq(&instance.id))
+        .filter(created_at.ge(since));
+    let total = checks.count().get_result::<i64>(conn)?;
+    let passed = checks
+        .filter(healthy.eq(true))
+        .count()
+        .get_result::<i64>(conn)?;
+
+    Ok(match total {
+        0 => None,
+        _ => Some(passed as f64 * 100.0 / total as f64),
+    })
+}
+
+pub fn uptime(instance: &Instance) -> Result<Uptime, ApiError> {
+    let conn = db::connection()?;
+    let now = Utc::now().naive_utc();
+
+    Ok(Uptime {
+        last_day: percent_healthy(&conn, instance, now - Duration::days(1))?,
+        last_week: percent_healthy(&conn, instance, now - Duration::weeks(1))?,
+        last_month: percent_healthy(&conn, instance, now - Duration::days(30))?,
+        recent: instance_health_checks
+            .filter(instance_id.eq(&instance.id))
+            .order(created_at.desc())
+            .limit(RECENT_CHECKS)
+            .load::<InstanceHealthCheck>(&conn)?,
+    })
+}
+
+/// Drops checks older than the retention period, returns how many were removed
+pub fn purge() -> Result<usize, ApiError> {
+    let conn = db::connection()?;
+    let cutoff = Utc::now().naive_utc() - Duration::days(RETENTION_DAYS);
+
+    Ok(diesel::delete(instance_health_checks.filter(created_at.lt(cutoff))).execute(&conn)?)
+}
@@ -2,6 +2,7 @@ pub mod model;
 pub mod routes;
 
 #[cfg(test)]
-mod tests;
-mod utils;
-mod aws;
+pub mod tests;
+pub mod utils;
+pub mod aws;
+pub mod health;
@@ -1,20 +1,92 @@
+use chrono::{NaiveDateTime, Utc};
+use diesel::pg::Pg;
 use diesel::prelude::*;
-use models::{Instance, Model, NewInstance, UpdateInstance};
-use models::instances::dsl::*;
+use models::{Instance, ListQuery, Model, NewInstance, Page, SoftDelete, UpdateInstance};
+use models::instances::{self, dsl::*};
 
-use crate::{api_error::ApiError, db, ID_SIZE};
+use crate::{
+    api_error::ApiError,
+    db,
+    list::{invalid_sort, sort_by},
+    ID_SIZE,
+};
+
+fn filtered(account: &Option<String>, query: &ListQuery) -> instances::BoxedQuery<'static, Pg> {
+    let mut filtered = instances.filter(deleted_at.is_null()).into_boxed();
+
+    if let Some(account) = account {
+        filtered = filtered.filter(account_id.eq(account.clone()));
+    }
+    if let Some(after) = query.created_after {
+        filtered = filtered.filter(created_at.ge(after));
+    }
+    if let Some(before) = query.created_before {
+        filtered = filtered.filter(created_at.lt(before));
+    }
+    if let Some(target_status) = query.status.clone() {
+        filtered = filtered.filter(status.eq(target_status));
+    }
+
+    filtered
+}
 
 impl Model<String, NewInstance, UpdateInstance, ApiError> for Instance {
     fn find_all() -> Result<Vec<Self>, ApiError> {
         let conn = db::connection()?;
-        let result = instances.load::<Self>(&conn)?;
+        let result = instances.filter(deleted_at.is_null()).load::<Self>(&conn)?;
 
         Ok(result)
     }
 
+    fn find_page(account: Option<String>, query: ListQuery) -> Result<Page<Self>, ApiError> {
+        let conn = db::connection()?;
+        let total = filtered(&account, &query).count().get_result::<i64>(&conn)?;
+
+        let page = filtered(&account, &query);
+        let page = match query.sort.as_deref().unwrap_or("createdAt") {
+            "createdAt" => sort_by!(page, created_at, query),
+            "updatedAt" => sort_by!(page, updated_at, query),
+            "name" => sort_by!(page, name, query),
+            "businessName" => sort_by!(page, business_name, query),
+            "status" => sort_by!(page, status, query),
+            field => return Err(invalid_sort(field)),
+        };
+        let result = page
+            .then_order_by(id.asc())
+            .limit(query.limit())
+            .offset(query.offset())
+            .load::<Self>(&conn)?;
+
+        Ok(Page::new(result, total, &query))
+    }
+
     fn find_by_id(target: String) -> Result<Self, ApiError> {
         let conn = db::connection()?;
-        let result = instances.filter(id.eq(target)).get_result::<Self>(&conn)?;
+        let result = instances
+            .filter(id.eq(target))
+            .filter(deleted_at.is_null())
+            .get_result::<Self>(&conn)?;
+
+        Ok(result)
+    }
+
+    fn find_all_in(account: String) -> Result<Vec<Self>, ApiError> {
+        let conn = db::connection()?;
+        let result = instances
+            .filter(account_id.eq(account))
+            .filter(deleted_at.is_null())
+            .load::<Self>(&conn)?;
+
+        Ok(result)
+    }
+
+    fn find_in(account: String, target: String) -> Result<Self, ApiError> {
+        let conn = db::connection()?;
+        let result = instances
+            .filter(id.eq(target))
+            .filter(account_id.eq(account))
+            .filter(deleted_at.is_null())
+            .get_result::<Self>(&conn)?;
 
         Ok(result)
     }
@@ -36,6 +108,7 @@ impl Model<String, NewInstance, UpdateInstance, ApiError> for Instance {
         let conn = db::connection()?;
         let result = diesel::update(instances)
             .filter(id.eq(target))
+            .filter(deleted_at.is_null())
             .set(new_vals)
             .get_result(&conn)?;
 
@@ -44,7 +117,41 @@ impl Model<String, NewInstance, UpdateInstance, ApiError> for Instance {
 
     fn delete(target: String) -> Result<usize, ApiError> {
         let conn = db::connection()?;
-        let result = diesel::delete(instances.filter(id.eq(target))).execute(&conn)?;
+        let result = diesel::update(instances.filter(id.eq(target)))
+            .filter(deleted_at.is_null())
+            .set(deleted_at.eq(Utc::now().naive_utc()))
+            .execute(&conn)?;
+
+        Ok(result)
+    }
+}
+
+impl SoftDelete<String, ApiError> for Instance {
+    fn find_deleted(account: Option<String>, target: String) -> Result<Self, ApiError> {
+        let conn = db::connection()?;
+        let mut query = instances
+            .filter(id.eq(target))
+            .filter(deleted_at.is_not_null())
+            .into_boxed();
+        if let Some(account) = account {
+            query = query.filter(account_id.eq(account));
+        }
+
+        Ok(query.get_result::<Self>(&conn)?)
+    }
+
+    fn restore(target: String) -> Result<Self, ApiError> {
+        let conn = db::connection()?;
+        let result = diesel::update(instances.filter(id.eq(target)))
+            .set(deleted_at.eq(None::<NaiveDateTime>))
+            .get_result(&conn)?;
+
+        Ok(result)
+    }
+
+    fn purge(cutoff: NaiveDateTime) -> Result<usize, ApiError> {
+        let conn = db::connection()?;
+        let result = diesel::delete(instances.filter(deleted_at.lt(cutoff))).execute(&conn)?;
 
         Ok(result)
     }
@@ -1,55 +1,57 @@
-use std::time::Duration;
-
 use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse};
-use auth::{belongs_to_account, require_role, ReqUser};
+use auth::{account_scope, belongs_to_account, require_cap, ReqUser};
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
 use models::{
-    types::{InstanceStatus, Role},
-    Account, Instance, Model, NewInstance, UpdateInstance, Validate,
+    types::{try_transition, Capability, InstanceEvent, InstanceStatus},
+    Account, Instance, InstanceStatusChange, ListQuery, Model, NewInstance, SoftDelete,
+    UpdateInstance, Validate,
 };
-use reqwest::{redirect::Policy, Client};
-use serde::Deserialize;
 
+pub use crud_client::instances::CallbackParams;
+
+use crate::audit::{utils::audited, Actor, Change};
 use crate::{
-    accounts, api_error::ApiError, auth::verify_instance_deploy, json::DeleteBody, update_usage,
-    AppData,
+    accounts, api_error::ApiError, auth::verify_instance_deploy, db, json::DeleteBody,
+    notifications, update_usage, AppData, ID_SIZE,
 };
 
+#[utoipa::path(
+    tag = "instances",
+    params(ListQuery),
+    responses((status = 200, body = InstancePage)),
+)]
 #[get("/instances")]
-async fn find_all(req_user: Option<ReqUser>) -> Result<HttpResponse, ApiError> {
-    let instances = web::block(Instance::find_all).await??;
-
-    let instances = if let Some(req_user) = req_user {
-        // if filter func returns true item will be allowed into the iterator
-        // so if the account ids match or if the instance is admin of site they will see the instance
-        instances
-            .into_iter()
-            .filter(|x| x.account_id == req_user.account_id || req_user.account_id == "admin")
-            .collect()
-    } else {
-        instances
-    };
+async fn find_all(
+    query: web::Query<ListQuery>,
+    req_user: Option<ReqUser>,
+) -> Result<HttpResponse, ApiError> {
+    // users outside of the admin account will only see instances in their account
+    let account = account_scope(&req_user);
+    let instances = web::block(move || Instance::find_page(account, query.into_inner())).await??;
 
     Ok(HttpResponse::Ok().json(instances))
 }
 
+#[utoipa::path(tag = "instances", responses((status = 200, body = Instance)))]
 #[get("/instances/{id}")]
 async fn find(id: web::Path<String>, req_user: Option<ReqUser>) -> Result<HttpResponse, ApiError> {
-    let instance = web::block(move || Instance::find_by_id(id.into_inner())).await??;
-
-    if !belongs_to_account(&req_user, &instance.account_id) {
-        return Err(ApiError::forbidden());
-    }
+    let account = account_scope(&req_user);
+    let instance = web::block(move || Instance::find_scoped(account, id.into_inner())).await??;
 
     Ok(HttpResponse::Ok().json(instance))
 }
 
+#[utoipa::path(tag = "instances", responses((status = 200, body = Instance)))]
 #[post("/instances")]
 async fn create(
     instance: web::Json<NewInstance>,
     req_user: Option<ReqUser>,
     app_data: web::Data<AppData>,
+    actor: Actor,
 ) -> Result<HttpResponse, ApiError> {
-    if !belongs_to_account(&req_user, &instance.account_id) || !require_role(&req_user, Role::Admin)
+    if !belongs_to_account(&req_user, &instance.account_id)
+        || !require_cap(&req_user, Capability::ManageInstances)
     {
         return Err(ApiError::forbidden());
     }
@@ -64,29 +66,35 @@ async fn create(
     }
 
     let created = NewInstance {
+        id: nanoid!(ID_SIZE),
         status: InstanceStatus::Deploying,
         ..instance.into_inner()
     };
 
     created.validate()?;
 
-    let instance = web::block(move || Instance::insert(created)).await??;
+    let instance = web::block(move || {
+        let account_id = created.account_id.clone();
+        audited(&actor, &account_id, |conn| {
+            let instance = diesel::insert_into(models::instances::table)
+                .values(&created)
+                .get_result::<Instance>(conn)?;
+            super::utils::record(conn, &instance, None, InstanceEvent::Deploy)?;
+            super::utils::ensure_deployment(conn, &instance.id)?;
+            let change = Change::new("create", "instance", &instance.id).after(&instance);
+
+            Ok((instance, change))
+        })
+    })
+    .await??;
 
     // just start deployment with aws, lambda will call back later with url and env_id
-    let deploy_result = super::utils::deploy(&instance, &app_data.sns_client).await;
+    let deploy_result = super::utils::deploy(&instance, &app_data.aws).await;
 
     if let Err(_) = deploy_result {
         // initial deployment failed
-        let update_result = web::block(move || {
-            Instance::update(
-                instance.id,
-                UpdateInstance {
-                    status: Some(InstanceStatus::Failed),
-                    ..Default::default()
-                },
-            )
-        })
-        .await?;
+        let update_result =
+            super::utils::apply(instance, InstanceEvent::Fail, UpdateInstance::default()).await;
 
         match update_result {
                     Ok(_) => Err(ApiError::new(
@@ -96,23 +104,24 @@ async fn create(
                     Err(_) => Err(ApiError::new(500, "Initial deployment failed, current instance status is 'Failed', but couldn't be updated.".into()))
                 }
     } else {
-        super::utils::ensure_deployment(instance.id.clone());
         Ok(HttpResponse::Ok().json(instance))
     }
 }
 
+#[utoipa::path(tag = "instances", responses((status = 200, body = Instance)))]
 #[put("/instances/{id}")]
 async fn update(
     id: web::Path<String>,
     instance: web::Json<UpdateInstance>,
     req_user: Option<ReqUser>,
+    actor: Actor,
 ) -> Result<HttpResponse, ApiError> {
     let id = id.into_inner();
     let for_find_to_be_updated = id.clone();
-    let to_be_updated = web::block(move || Instance::find_by_id(for_find_to_be_updated)).await??;
-    if !belongs_to_account(&req_user, &to_be_updated.account_id)
-        || !require_role(&req_user, Role::Admin)
-    {
+    let account = account_scope(&req_user);
+    let to_be_updated =
+        web::block(move || Instance::find_scoped(account, for_find_to_be_updated)).await??;
+    if !require_cap(&req_user, Capability::ManageInstances) {
         return Err(ApiError::forbidden());
     }
 
@@ -122,26 +131,42 @@ async fn update(
         name: None,
         url: None,
         env_id: None,
+        // only changes through transitions
+        status: None,
         ..instance.into_inner()
     };
 
     update_set.validate()?;
 
-    let instance = web::block(move || Instance::update(id, update_set)).await??;
+    let instance = web::block(move || {
+        audited(&actor, &to_be_updated.account_id, |conn| {
+            let instance = diesel::update(models::instances::table.find(&id))
+                .set(update_set)
+                .get_result::<Instance>(conn)?;
+            let change = Change::new("update", "instance", &id)
+                .before(&to_be_updated)
+                .after(&instance);
+
+            Ok((instance, change))
+        })
+    })
+    .await??;
 
     Ok(HttpResponse::Ok().json(instance))
 }
 
+#[utoipa::path(tag = "instances", responses((status = 200, body = DeleteBody)))]
 #[delete("/instances/{id}")]
 async fn delete(
     id: web::Path<String>,
     req_user: Option<ReqUser>,
     app_data: web::Data<AppData>,
+    actor: Actor,
 ) -> Result<HttpResponse, ApiError> {
     let find_id = id.clone();
-    let instance = web::block(move || Instance::find_by_id(find_id)).await??;
-    if !belongs_to_account(&req_user, &instance.account_id) || !require_role(&req_user, Role::Admin)
-    {
+    let account = account_scope(&req_user);
+    let instance = web::block(move || Instance::find_scoped(account, find_id)).await??;
+    if !require_cap(&req_user, Capability::ManageInstances) {
         return Err(ApiError::forbidden());
     }
 
@@ -150,89 +175,145 @@ async fn delete(
     let num_instances = accounts::utils::usage(owner.id.clone()).await?.instances;
     println!("{num_instances}");
 
-    if instance.status == InstanceStatus::Deploying || instance.status == InstanceStatus::Configured
-    {
-        // cannot delete while these are happening
-        return Err(ApiError::new(
-            400,
-            "Cannot delete an instance that has status 'Deploying' or 'Configured'.".into(),
-        ));
-    }
+    // running instances are torn down first, ones that never finished deploying have nothing to tear down
+    let running = match try_transition(&instance.status, InstanceEvent::Deactivate) {
+        Ok(_) => true,
+        Err(_) if matches!(instance.status, InstanceStatus::Inactive | InstanceStatus::Failed) => {
+            false
+        }
+        Err(err) => return Err(err.into()),
+    };
 
-    if let Some(env_id) = instance.env_id {
-        if let Some(url) = instance.url {
-            if instance.status == InstanceStatus::Ok || instance.status == InstanceStatus::Unhealthy
-            {
-                // only these two statuses require aws termination
-                let env = super::aws::delete_instance(&app_data.eb_client, &env_id).await?;
+    if running {
+        if let (Some(env_id), Some(url)) = (&instance.env_id, &instance.url) {
+            let env = super::aws::delete_instance(&app_data.aws, env_id).await?;
 
-                super::aws::delete_dns(&app_data.r53_client, &url, &env).await?;
-            }
+            super::aws::delete_dns(&app_data.aws, url, env.cname.as_deref().unwrap_or_default())
+                .await?;
         }
     }
 
-    let affected = web::block(move || Instance::delete(id.into_inner())).await??;
+    let affected = web::block(move || {
+        audited(&actor, &instance.account_id, |conn| {
+            use models::instances::dsl::*;
+            // hidden until purged, it was torn down above so it comes back inactive if restored
+            if running {
+                super::utils::transition(
+                    conn,
+                    &instance,
+                    InstanceEvent::Deactivate,
+                    UpdateInstance::default(),
+                )?;
+            }
+            let affected = diesel::update(instances.find(&instance.id))
+                .set(deleted_at.eq(Utc::now().naive_utc()))
+                .execute(conn)?;
+            let change = Change::new("delete", "instance", &instance.id).before(&instance);
+
+            Ok((affected, change))
+        })
+    })
+    .await??;
 
     // subtract 1 because it was successfully deleted
+    let owner_id = owner.id.clone();
     let res = web::block(move || update_usage(&owner, "instances".into(), num_instances - 1)).await??;
 
     if let Err(_) = res.error_for_status() {
-        // TODO notify me
         error!("Failed to update instance usage with Stripe. Your instance is still deleted.");
+        notifications::send(notifications::usage_sync_failed(&owner_id, num_instances - 1)).await;
         return Ok(HttpResponse::InternalServerError().finish());
     };
 
     Ok(HttpResponse::Ok().json(DeleteBody::new(affected as i32)))
 }
 
+/// Brings back a deleted instance, it stays inactive until it's deployed again
+#[utoipa::path(tag = "instances", responses((status = 200, body = Instance)))]
+#[post("/instances/{id}/restore")]
+async fn restore(
+    id: web::Path<String>,
+    req_user: Option<ReqUser>,
+    actor: Actor,
+) -> Result<HttpResponse, ApiError> {
+    let account = account_scope(&req_user);
+    let instance = web::block(move || Instance::find_deleted(account, id.into_inner())).await??;
+    if !require_cap(&req_user, Capability::RestoreDeleted) {
+        return Err(ApiError::forbidden());
+    }
+
+    let restored = web::block(move || {
+        audited(&actor, &instance.account_id, |conn| {
+            use models::instances::dsl::*;
+            let restored = diesel::update(instances.find(&instance.id))
+                .set(deleted_at.eq(None::<NaiveDateTime>))
+                .get_result::<Instance>(conn)?;
+            let change = Change::new("restore", "instance", &instance.id)
+                .before(&instance)
+                .after(&restored);
+
+            Ok((restored, change))
+        })
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(restored))
+}
+
+#[utoipa::path(tag = "instances", responses((status = 200)))]
 #[put("/instances/{id}/deactivate")]
 async fn deactivate(
     id: web::Path<String>,
     req_user: Option<ReqUser>,
     app_data: web::Data<AppData>,
+    actor: Actor,
 ) -> Result<HttpResponse, ApiError> {
     let find_id = id.clone();
-    let instance = web::block(move || Instance::find_by_id(find_id)).await??;
+    let account = account_scope(&req_user);
+    let instance = web::block(move || Instance::find_scoped(account, find_id)).await??;
     let owner_id = instance.account_id.clone();
     let owner = web::block(|| Account::find_by_id(owner_id)).await??;
-    if !belongs_to_account(&req_user, &instance.account_id) || !require_role(&req_user, Role::Admin)
-    {
+    if !require_cap(&req_user, Capability::ManageInstances) {
         return Err(ApiError::forbidden());
     }
 
-    if instance.status != InstanceStatus::Ok && instance.status != InstanceStatus::Unhealthy {
-        // cannot deactivate while these are happening
-        return Err(ApiError::new(
-            400,
-            "Cannot deactivate an instance that has status 'Deploying' or 'Configured'.".into(),
-        ));
-    }
+    // checked before tearing anything down
+    try_transition(&instance.status, InstanceEvent::Deactivate)?;
 
-    let usage = accounts::utils::usage(instance.account_id).await?;
-    if let Some(env_id) = instance.env_id {
-        if let Some(url) = instance.url {
+    let usage = accounts::utils::usage(instance.account_id.clone()).await?;
+    if let Some(env_id) = &instance.env_id {
+        if let Some(url) = &instance.url {
             // must ba one of these two to be deactivated
-            let env = super::aws::delete_instance(&app_data.eb_client, &env_id).await?;
-            super::aws::delete_dns(&app_data.r53_client, &url, &env).await?;
-
-            web::block(|| {
-                Instance::update(
-                    id.into_inner(),
-                    UpdateInstance {
-                        status: Some(InstanceStatus::Inactive),
-                        ..Default::default()
-                    },
-                )
+            let env = super::aws::delete_instance(&app_data.aws, env_id).await?;
+            super::aws::delete_dns(&app_data.aws, url, env.cname.as_deref().unwrap_or_default())
+                .await?;
+
+            web::block(move || {
+                audited(&actor, &instance.account_id, |conn| {
+                    let updated = super::utils::transition(
+                        conn,
+                        &instance,
+                        InstanceEvent::Deactivate,
+                        UpdateInstance::default(),
+                    )?;
+                    let change = Change::new("deactivate", "instance", &instance.id)
+                        .before(&instance)
+                        .after(&updated);
+
+                    Ok((updated, change))
+                })
             })
             .await??;
 
             let num_instances = usage.instances - 1;
+            let owner_id = owner.id.clone();
             let res = web::block(move || update_usage(&owner, "instances".into(), num_instances))
                 .await??;
 
             if let Err(_) = res.error_for_status() {
-                // TODO notify me
                 error!("Failed to update instance usage with Stripe. Your instance will still be deactivated.");
+                notifications::send(notifications::usage_sync_failed(&owner_id, num_instances))
+                    .await;
                 return Ok(HttpResponse::InternalServerError().finish());
             };
 
@@ -251,55 +332,48 @@ async fn deactivate(
     }
 }
 
+#[utoipa::path(tag = "instances", responses((status = 200, body = Instance)))]
 #[put("/instances/{id}/deploy")]
 async fn deploy(
     id: web::Path<String>,
     req_user: Option<ReqUser>,
     app_data: web::Data<AppData>,
+    actor: Actor,
 ) -> Result<HttpResponse, ApiError> {
     let find_id = id.clone();
-    let instance = web::block(move || Instance::find_by_id(find_id)).await??;
-    if !belongs_to_account(&req_user, &instance.account_id) || !require_role(&req_user, Role::Admin)
-    {
+    let account = account_scope(&req_user);
+    let instance = web::block(move || Instance::find_scoped(account, find_id)).await??;
+    if !require_cap(&req_user, Capability::ManageInstances) {
         return Err(ApiError::forbidden());
     }
 
-    if instance.status != InstanceStatus::Inactive && instance.status != InstanceStatus::Failed {
-        // cannot deploy while these are happening
-        return Err(ApiError::new(
-            400,
-            "Can only deploy an instance that has status 'Inactive' or 'Failed'.".into(),
-        ));
-    }
-
-    let update_id = instance.id.clone();
-    web::block(|| {
-        Instance::update(
-            update_id,
-            UpdateInstance {
-                status: Some(InstanceStatus::Deploying),
-                ..Default::default()
-            },
-        )
+    let deploying = instance.clone();
+    let instance = web::block(move || {
+        audited(&actor, &deploying.account_id, |conn| {
+            let updated = super::utils::transition(
+                conn,
+                &deploying,
+                InstanceEvent::Deploy,
+                UpdateInstance::default(),
+            )?;
+            super::utils::ensure_deployment(conn, &deploying.id)?;
+            let change = Change::new("deploy", "instance", &deploying.id)
+                .before(&deploying)
+                .after(&updated);
+
+            Ok((updated, change))
+        })
     })
     .await??;
 
     // just start deployment with aws, lambda will call back later with url and env_id
-    let deploy_result = super::utils::deploy(&instance, &app_data.sns_client).await;
+    let deploy_result = super::utils::deploy(&instance, &app_data.aws).await;
 
     if let Err(_) = deploy_result {
         info!("failed to send req to deploy instance");
         // initial deployment failed
-        let update_result = web::block(move || {
-            Instance::update(
-                instance.id,
-                UpdateInstance {
-                    status: Some(InstanceStatus::Failed),
-                    ..Default::default()
-                },
-            )
-        })
-        .await?;
+        let update_result =
+            super::utils::apply(instance, InstanceEvent::Fail, UpdateInstance::default()).await;
 
         match update_result {
                     Ok(_) => Err(ApiError::new(
@@ -309,19 +383,11 @@ async fn deploy(
                     Err(_) => Err(ApiError::new(500, "Initial deployment failed, current instance status is 'Failed', but couldn't be updated.".into()))
                 }
     } else {
-        super::utils::ensure_deployment(instance.id.clone());
         Ok(HttpResponse::Ok().json(instance))
     }
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct CallbackParams {
-    env_id: String,
-    url: String,
-    account_id: String,
-}
-
+#[utoipa::path(tag = "instances", responses((status = 200)))]
 #[post("/instances/{id}/callback")]
 async fn callback(
     target: web::Path<String>,
@@ -345,32 +411,33 @@ async fn callback(
     let owner = web::block(move || Account::find_by_id(owner_id)).await??;
 
     let params = params.into_inner();
-    web::block(move || {
-        Instance::update(
-            target.into_inner(),
-            UpdateInstance {
-                env_id: Some(Some(params.env_id)),
-                url: Some(Some(params.url)),
-                status: Some(InstanceStatus::Configured),
-                ..Default::default()
-            },
-        )
-    })
-    .await??;
+    let instance = web::block(move || Instance::find_by_id(target.into_inner())).await??;
+    super::utils::apply(
+        instance,
+        InstanceEvent::Configure,
+        UpdateInstance {
+            env_id: Some(Some(params.env_id)),
+            url: Some(Some(params.url)),
+            ..Default::default()
+        },
+    )
+    .await?;
 
     let num_instances = accounts::utils::usage(owner.id.clone()).await?.instances;
 
+    let owner_id = owner.id.clone();
     let res = web::block(move || update_usage(&owner, "instances".into(), num_instances)).await??;
 
     if let Err(_) = res.error_for_status() {
-        // TODO notify me
         error!("Failed to update instance usage with Stripe. Your instance will still be usable.");
+        notifications::send(notifications::usage_sync_failed(&owner_id, num_instances)).await;
         return Ok(HttpResponse::InternalServerError().finish());
     };
 
     Ok(HttpResponse::Ok().finish())
 }
 
+#[utoipa::path(tag = "instances", responses((status = 200)))]
 #[post("/instances/{id}/fail-callback")]
 async fn fail_callback(
     target: web::Path<String>,
@@ -389,137 +456,109 @@ async fn fail_callback(
         return Err(ApiError::forbidden());
     }
 
-    web::block(move || {
-        Instance::update(
-            target.into_inner(),
-            UpdateInstance {
-                status: Some(InstanceStatus::Failed),
-                ..Default::default()
-            },
-        )
-    })
-    .await??;
+    let instance = web::block(move || Instance::find_by_id(target.into_inner())).await??;
+    let failed =
+        super::utils::apply(instance, InstanceEvent::Fail, UpdateInstance::default()).await?;
+    notifications::send(notifications::deployment_failed(&failed, "check its logs for why")).await;
 
     Ok(HttpResponse::Ok().finish())
 }
 
 // Checks instance health
+#[utoipa::path(tag = "instances", responses((status = 200, description = "Instance is reachable")))]
 #[get("/instances/{id}/health")]
 async fn health(
     id: web::Path<String>,
     req_user: Option<ReqUser>,
 ) -> Result<HttpResponse, ApiError> {
-    let id = id.into_inner();
-    let for_find_to_be_updated = id.clone();
-    let instance = web::block(move || Instance::find_by_id(for_find_to_be_updated)).await??;
-    if !belongs_to_account(&req_user, &instance.account_id) {
-        return Err(ApiError::forbidden());
-    }
+    let account = account_scope(&req_user);
+    let instance = web::block(move || Instance::find_scoped(account, id.into_inner())).await??;
 
-    if instance.status != InstanceStatus::Ok
-        && instance.status != InstanceStatus::Unhealthy
-        && instance.status != InstanceStatus::Configured
-    {
-        // must be one of the above to be valid for refreshing status
-        return Err(ApiError::new(
-            400,
-            "Instance must be 'Ok', 'Unhealthy', or 'Configured' to have it's status checked."
-                .into(),
-        ));
+    // must be deployed to be valid for refreshing status
+    try_transition(&instance.status, InstanceEvent::Healthy)?;
+
+    let url = match &instance.url {
+        Some(url) => url.clone(),
+        None => return Err(ApiError::new(400, "Instance does not have a url.".into())),
+    };
+    let probe = super::health::probe(&super::health::client(), &url).await;
+    if probe.pending(&instance) {
+        return Ok(HttpResponse::Ok().finish());
     }
 
-    if let Some(url) = instance.url {
-        let client = Client::builder()
-            .redirect(Policy::none())
-            .connect_timeout(Duration::from_secs(5))
-            .timeout(Duration::from_secs(10))
-            .build()
-            .unwrap();
-        let res = client.get(format!("https://{url}/health")).send().await;
-
-        match res {
-            Ok(res) => {
-                if res.status().is_redirection() {
-                    // redirect is desireable response for health check
-                    web::block(move || {
-                        Instance::update(
-                            id,
-                            UpdateInstance {
-                                status: Some(InstanceStatus::Ok),
-                                ..Default::default()
-                            },
-                        )
-                    })
-                    .await??;
-                    Ok(HttpResponse::Ok().finish())
-                } else {
-                    web::block(move || {
-                        Instance::update(
-                            id,
-                            UpdateInstance {
-                                status: Some(InstanceStatus::Unhealthy),
-                                ..Default::default()
-                            },
-                        )
-                    })
-                    .await??;
-                    Err(ApiError::new(
-                        500,
-                        "Instance returned a bad response, setting to unhealthy.".into(),
-                    ))
-                }
-            }
-            Err(err) => {
-                match instance.status {
-                    InstanceStatus::Configured => {
-                        // if its configured and couldn't connect its okay, otherwise there is an error
-                        if err.is_connect() {
-                            Ok(HttpResponse::Ok().finish())
-                        } else {
-                            web::block(move || {
-                                Instance::update(
-                                    id,
-                                    UpdateInstance {
-                                        status: Some(InstanceStatus::Unhealthy),
-                                        ..Default::default()
-                                    },
-                                )
-                            })
-                            .await??;
-                            Ok(HttpResponse::Ok().finish())
-                        }
-                    }
-                    _ => {
-                        // any other status must be updated to unhealthy
-                        web::block(move || {
-                            Instance::update(
-                                id,
-                                UpdateInstance {
-                                    status: Some(InstanceStatus::Unhealthy),
-                                    ..Default::default()
-                                },
-                            )
-                        })
-                        .await??;
-                        Ok(HttpResponse::Ok().finish())
-                    }
-                }
-            }
-        }
+    // asked for directly, so the status changes right away instead of waiting for more checks
+    let checked = probe.clone();
+    web::block(move || {
+        let conn = db::connection()?;
+        conn.transaction(|| {
+            super::health::record(&conn, &instance, &checked)?;
+            super::health::change_status(&conn, &instance, checked.event())
+        })
+    })
+    .await??;
+
+    if probe.healthy || probe.status_code.is_none() {
+        Ok(HttpResponse::Ok().finish())
     } else {
-        Err(ApiError::new(400, "Instance does not have a url.".into()))
+        Err(ApiError::new(
+            500,
+            "Instance returned a bad response, setting to unhealthy.".into(),
+        ))
     }
 }
 
+/// Percent of health checks the instance passed over the last day, week and month
+#[utoipa::path(tag = "instances", responses((status = 200, body = Uptime)))]
+#[get("/instances/{id}/uptime")]
+async fn uptime(
+    id: web::Path<String>,
+    req_user: Option<ReqUser>,
+) -> Result<HttpResponse, ApiError> {
+    let account = account_scope(&req_user);
+    let uptime = web::block(move || {
+        let instance = Instance::find_scoped(account, id.into_inner())?;
+        super::health::uptime(&instance)
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(uptime))
+}
+
+/// Every status the instance has moved to, newest first
+#[utoipa::path(tag = "instances", responses((status = 200, body = [InstanceStatusChange])))]
+#[get("/instances/{id}/history")]
+async fn history(
+    id: web::Path<String>,
+    req_user: Option<ReqUser>,
+) -> Result<HttpResponse, ApiError> {
+    let account = account_scope(&req_user);
+    let changes = web::block(move || {
+        let instance = Instance::find_scoped(account, id.into_inner())?;
+        let conn = db::connection()?;
+
+        Ok::<_, ApiError>(
+            InstanceStatusChange::belonging_to(&instance)
+                .order(models::instance_status_history::created_at.desc())
+                .load::<InstanceStatusChange>(&conn)?,
+        )
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(changes))
+}
+
 pub fn init_routes(config: &mut web::ServiceConfig) {
     config.service(find_all);
     config.service(find);
     config.service(create);
     config.service(update);
     config.service(delete);
+    config.service(restore);
     config.service(deactivate);
     config.service(deploy);
     config.service(callback);
     config.service(fail_callback);
     config.service(health);
+    config.service(history);
+    config.service(uptime);
 }
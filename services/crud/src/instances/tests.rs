@@ -1,5 +1,7 @@
-use models::{NewInstance, Instance, instances::dsl::*, UpdateInstance, types::InstanceStatus};
-use crate::{db, json::DeleteBody, tests::{self, mock_instance_deploy}, ID_SIZE};
+use models::{NewInstance, Instance, InstanceStatusChange, instances::dsl::*, Page, UpdateInstance, types::{InstanceEvent, InstanceStatus}};
+use crate::{auth, db, json::DeleteBody, tests::{self, mock_instance_deploy, mock_payments}, ID_SIZE};
+use super::{health::{self, Probe, Uptime}, routes::CallbackParams};
+use cloud::{beanstalk::NewEnvironment, dns::AliasRecord, Beanstalk, Dns};
 use actix_web::test;
 use diesel::prelude::*;
 
@@ -17,7 +19,7 @@ fn compare(got: &Instance, exp: &NewInstance) {
     assert_eq!(got.state, exp.state);
 }
 
-fn defaults(test_name: String) -> (NewInstance, NewInstance) {
+pub fn defaults(test_name: String) -> (NewInstance, NewInstance) {
     (
         NewInstance {
             id: nanoid!(ID_SIZE),
@@ -98,10 +100,20 @@ async fn get() {
     // Check for both inserted records with the get all route
     let req = test::TestRequest::get().uri("/instances").to_request();
 
-    let resp: Vec<Instance> = test::call_and_read_body_json(&app, req).await;
+    let resp: Page<Instance> = test::call_and_read_body_json(&app, req).await;
+
+    assert!(resp.items.len() >= 2);
+
+    // only the deploying instance should match the status filter
+    let req = test::TestRequest::get()
+        .uri("/instances?status=deploying")
+        .to_request();
+
+    let resp: Page<Instance> = test::call_and_read_body_json(&app, req).await;
     let conn = db::connection().unwrap();
 
-    assert!(resp.len() >= 2);
+    assert!(resp.items.iter().any(|x| x.id == result2.id));
+    assert!(resp.items.iter().all(|x| x.status == InstanceStatus::Deploying));
 
     remove(result1.id, &conn);
     remove(result2.id, &conn);
@@ -132,6 +144,12 @@ async fn post() {
     // make sure inserted is same as what we gave the route
     compare(&got, &default1);
 
+    // deploy lambda was told about it
+    assert!(tests::AWS.published().iter().any(|message| {
+        message.to == super::utils::DEPLOY_TOPIC
+            && message.body.contains(&format!("\"instanceId\":\"{}\"", resp.id))
+    }));
+
     // reset db by removing test record
     remove(resp.id, &conn);
 }
@@ -195,11 +213,212 @@ async fn delete() {
 
     assert_eq!(resp.affected, 1);
 
-    let result1: Option<Instance> = instances
-        .filter(id.eq(result1.id))
+    // hidden until restored or purged
+    let deleted: Instance = instances
+        .filter(id.eq(&result1.id))
         .get_result(&conn)
-        .optional()
-        .expect("failed to convert to to option");
+        .expect("deleted instance was removed");
+    assert!(deleted.deleted_at.is_some());
+    drop(conn);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/instances/{}", result1.id))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), actix_http::StatusCode::NOT_FOUND);
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/instances/{}/restore", result1.id))
+        .to_request();
+    let restored: Instance = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(restored.deleted_at, None);
+
+    let conn = db::connection().unwrap();
+    remove(result1.id, &conn);
+}
+
+#[actix_web::test]
+async fn delete_running() {
+    actix_web::rt::spawn(mock_payments());
+    let (default1, _default2) = defaults("delete-running".into());
+
+    // deployed the way the lambdas would
+    let env = tests::AWS
+        .create_environment(NewEnvironment {
+            application_name: "pudo".into(),
+            environment_name: default1.name.clone(),
+            version_label: "v1".into(),
+            solution_stack: "docker".into(),
+            options: vec![],
+        })
+        .await
+        .unwrap();
+    let domain = format!("{}.milkyweb.app", default1.name);
+    tests::AWS
+        .create_alias(&AliasRecord {
+            hosted_zone_id: "Z0898550109O7ZB98C1FF".into(),
+            name: domain.clone(),
+            target_dns_name: env.cname.clone().unwrap(),
+            target_zone_id: "Z117KPS5GTRQ2G".into(),
+        })
+        .await
+        .unwrap();
+
+    let app = tests::init(super::routes::init_routes).await;
+    let conn = db::connection().unwrap();
+    let running: Instance = diesel::insert_into(instances)
+        .values(&NewInstance {
+            status: InstanceStatus::Ok,
+            env_id: Some(env.id.clone()),
+            url: Some(domain.clone()),
+            ..default1
+        })
+        .get_result::<Instance>(&conn)
+        .expect("couldn't insert");
+    drop(conn);
+
+    let req = test::TestRequest::delete()
+        .uri(&format!("/instances/{}", running.id))
+        .to_request();
+    let resp: DeleteBody = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(resp.affected, 1);
+
+    // torn down
+    let fake_env = tests::AWS
+        .environments()
+        .into_iter()
+        .find(|fake| fake.environment.id == env.id)
+        .unwrap();
+    assert!(fake_env.terminated);
+    assert!(!tests::AWS.records().iter().any(|record| record.name == domain));
+
+    let conn = db::connection().unwrap();
+    let deleted: Instance = instances.find(&running.id).get_result(&conn).unwrap();
+    assert_eq!(deleted.status, InstanceStatus::Inactive);
+    remove(running.id, &conn);
+}
+
+#[actix_web::test]
+async fn callbacks_follow_transitions() {
+    actix_web::rt::spawn(mock_payments());
+    let (_default1, default2) = defaults("transitions".into());
+
+    let app = tests::init(super::routes::init_routes).await;
+    let conn = db::connection().unwrap();
+    let deploying: Instance = diesel::insert_into(instances)
+        .values(&default2)
+        .get_result::<Instance>(&conn)
+        .expect("couldn't insert");
+    drop(conn);
+
+    let jwt = auth::sign_instance_deploy().unwrap();
+    let callback = || {
+        test::TestRequest::post()
+            .uri(&format!("/instances/{}/callback", deploying.id))
+            .insert_header(("jwt", jwt.clone()))
+            .set_json(&CallbackParams {
+                env_id: "e-transitions".into(),
+                url: "transitions.milkyweb.app".into(),
+                account_id: deploying.account_id.clone(),
+            })
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, callback()).await;
+    assert_eq!(resp.status(), actix_http::StatusCode::OK);
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/instances/{}/fail-callback", deploying.id))
+        .insert_header(("jwt", jwt.clone()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), actix_http::StatusCode::OK);
+
+    // a late callback can't bring a failed instance back
+    let resp = test::call_service(&app, callback()).await;
+    assert_eq!(resp.status(), actix_http::StatusCode::BAD_REQUEST);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/instances/{}/history", deploying.id))
+        .to_request();
+    let history: Vec<InstanceStatusChange> = test::call_and_read_body_json(&app, req).await;
+    let moves: Vec<_> = history
+        .iter()
+        .map(|change| (change.from_status.clone(), change.to_status.clone(), change.event))
+        .collect();
+    // tests share one transaction so both were saved at the same time, order isn't checked
+    assert_eq!(moves.len(), 2);
+    assert!(moves.contains(&(Some(InstanceStatus::Deploying), InstanceStatus::Configured, InstanceEvent::Configure)));
+    assert!(moves.contains(&(Some(InstanceStatus::Configured), InstanceStatus::Failed, InstanceEvent::Fail)));
+
+    let conn = db::connection().unwrap();
+    let failed: Instance = instances.find(&deploying.id).get_result(&conn).unwrap();
+    assert_eq!(failed.status, InstanceStatus::Failed);
+    remove(deploying.id, &conn);
+}
 
-    assert!(result1 == None);
+#[actix_web::test]
+async fn health_checks_debounce() {
+    let (default1, _default2) = defaults("debounce".into());
+
+    let app = tests::init(super::routes::init_routes).await;
+    let conn = db::connection().unwrap();
+    let running: Instance = diesel::insert_into(instances)
+        .values(&NewInstance {
+            status: InstanceStatus::Ok,
+            url: Some("debounce.milkyweb.app".into()),
+            ..default1
+        })
+        .get_result::<Instance>(&conn)
+        .expect("couldn't insert");
+
+    let failed = Probe {
+        healthy: false,
+        latency_ms: Some(120),
+        status_code: Some(502),
+        error: None,
+        unreachable: false,
+    };
+
+    // one bad answer isn't enough to call it unhealthy
+    for _ in 0..2 {
+        health::record(&conn, &running, &failed).unwrap();
+        assert_eq!(health::settle(&conn, &running).unwrap(), None);
+    }
+    health::record(&conn, &running, &failed).unwrap();
+    let changed = health::settle(&conn, &running).unwrap().unwrap();
+    assert_eq!(changed.status, InstanceStatus::Unhealthy);
+    drop(conn);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/instances/{}/uptime", running.id))
+        .to_request();
+    let uptime: Uptime = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(uptime.last_day, Some(0.0));
+    assert_eq!(uptime.last_month, Some(0.0));
+    assert_eq!(uptime.recent.len(), 3);
+    assert_eq!(uptime.recent[0].latency_ms, Some(120));
+
+    // kept for the month uptime looks back, whatever deleted rows are kept for
+    let backdate = |check: &models::InstanceHealthCheck, days: i64| {
+        let conn = db::connection().unwrap();
+        let at = chrono::Utc::now().naive_utc() - chrono::Duration::days(days);
+        diesel::update(models::instance_health_checks::table.find(&check.id))
+            .set(models::instance_health_checks::created_at.eq(at))
+            .execute(&conn)
+            .unwrap();
+    };
+    backdate(&uptime.recent[0], 30);
+    backdate(&uptime.recent[1], 32);
+    health::purge().unwrap();
+    let conn = db::connection().unwrap();
+    let kept = models::instance_health_checks::table
+        .select(models::instance_health_checks::id)
+        .filter(models::instance_health_checks::instance_id.eq(&running.id))
+        .load::<String>(&conn)
+        .unwrap();
+    assert!(kept.contains(&uptime.recent[0].id));
+    assert!(!kept.contains(&uptime.recent[1].id));
+
+    remove(running.id, &conn);
 }
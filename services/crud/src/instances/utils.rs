@@ -1,17 +1,27 @@
-use models::{types::InstanceStatus, Instance, Model, UpdateInstance};
+use actix_web::web;
+use chrono::Duration;
+use cloud::Aws;
+use diesel::prelude::*;
+use models::{
+    types::{try_transition, InstanceEvent, InstanceStatus},
+    Instance, InstanceStatusChange, Job, Model, NewInstanceStatusChange, UpdateInstance,
+};
 
-use crate::{api_error::ApiError, auth};
+use crate::{
+    api_error::ApiError,
+    auth, db,
+    jobs::{self, Task},
+    notifications, ID_SIZE,
+};
 
-pub async fn deploy(instance: &Instance, sns_client: &aws_sdk_sns::Client) -> Result<(), ApiError> {
-    // dont test this, its sus
-    if cfg!(test) {
-        return Ok(());
-    }
+/// Topic the deploy lambda listens on
+pub const DEPLOY_TOPIC: &str = "arn:aws:sns:us-east-1:262246349843:InstanceDeploy";
 
-    let result = sns_client
-        .publish()
-        .topic_arn("arn:aws:sns:us-east-1:262246349843:InstanceDeploy")
-        .message(
+pub async fn deploy(instance: &Instance, aws: &Aws) -> Result<(), ApiError> {
+    let result = aws
+        .publisher
+        .publish(
+            DEPLOY_TOPIC,
             serde_json::to_string(&serde_json::json!({
                 "instanceId": instance.id,
                 "accountId": instance.account_id,
@@ -21,7 +31,6 @@ pub async fn deploy(instance: &Instance, sns_client: &aws_sdk_sns::Client) -> Re
             }))
             .unwrap(),
         )
-        .send()
         .await;
 
     if let Err(_) = result {
@@ -34,33 +43,110 @@ pub async fn deploy(instance: &Instance, sns_client: &aws_sdk_sns::Client) -> Re
     Ok(())
 }
 
-/// Queues a task that runs after 15mins to ensure that deployment completed successfully
-/// If the instance status
-pub fn ensure_deployment(id: String) {
-    actix_web::rt::spawn((|| async {
-        actix_web::rt::time::sleep(core::time::Duration::from_secs(15 * 60)).await;
-        let instance = Instance::find_by_id(id.clone());
-
-        if let Ok(instance) = instance {
-            if instance.status == InstanceStatus::Deploying {
-                // timeout while deploying
-                // TODO notify user
-                info!("Instance {} timed out while deploying.", instance.id);
-
-                match Instance::update(
-                    id,
-                    UpdateInstance {
-                        status: Some(InstanceStatus::Failed),
-                        ..Default::default()
-                    },
-                ) {
-                    Err(_) => {
-                        error!("Error updating failed deployment.")
-                    }
-                    _ => {}
-                };
-            }
-        } else {
-        }
-    })());
+/// Saves a job that fails the instance if it's still deploying in 15mins
+pub fn ensure_deployment(conn: &PgConnection, instance_id: &str) -> Result<Job, ApiError> {
+    jobs::schedule(
+        conn,
+        Task::EnsureDeployment {
+            instance_id: instance_id.into(),
+        },
+        Duration::minutes(15),
+    )
+}
+
+/// Saves instance moving to its current status in its history, from is none when it was just created
+pub fn record(
+    conn: &PgConnection,
+    instance: &Instance,
+    from: Option<InstanceStatus>,
+    event: InstanceEvent,
+) -> Result<InstanceStatusChange, ApiError> {
+    Ok(diesel::insert_into(models::instance_status_history::table)
+        .values(NewInstanceStatusChange {
+            id: nanoid!(ID_SIZE),
+            instance_id: instance.id.clone(),
+            account_id: instance.account_id.clone(),
+            from_status: from,
+            to_status: instance.status.clone(),
+            event,
+        })
+        .get_result::<InstanceStatusChange>(conn)?)
+}
+
+/// Moves instance to the status event leads to and saves changes with it
+///
+/// Only goes through if the instance still has the status it was read with, so two
+/// transitions racing each other can't both happen
+pub fn transition(
+    conn: &PgConnection,
+    instance: &Instance,
+    event: InstanceEvent,
+    changes: UpdateInstance,
+) -> Result<Instance, ApiError> {
+    use models::instances::dsl::*;
+    let next = try_transition(&instance.status, event)?;
+
+    let updated = diesel::update(instances.find(&instance.id).filter(status.eq(&instance.status)))
+        .set(UpdateInstance {
+            status: Some(next),
+            ..changes
+        })
+        .get_result::<Instance>(conn)
+        .optional()?
+        .ok_or_else(|| {
+            ApiError::new(
+                409,
+                "Instance status changed while updating it. Please try again.".into(),
+            )
+        })?;
+
+    // health checks keep confirming the same status, only actual changes are kept
+    if updated.status != instance.status {
+        record(conn, &updated, Some(instance.status.clone()), event)?;
+    }
+
+    Ok(updated)
+}
+
+/// Transition in its own transaction, for changes that aren't audited like callbacks and health checks
+pub async fn apply(
+    instance: Instance,
+    event: InstanceEvent,
+    changes: UpdateInstance,
+) -> Result<Instance, ApiError> {
+    web::block(move || {
+        let conn = db::connection()?;
+        conn.transaction(|| transition(&conn, &instance, event, changes))
+    })
+    .await?
+}
+
+/// Marks the instance failed if deployment still hasn't finished, it timed out
+pub fn check_deployment(id: String) -> Result<(), ApiError> {
+    let instance = match Instance::find_by_id(id) {
+        Ok(instance) => instance,
+        // deleted since, nothing to fail
+        Err(err) if err.0.status_code == 404 => return Ok(()),
+        Err(err) => return Err(err),
+    };
+
+    if instance.status == InstanceStatus::Deploying {
+        info!("Instance {} timed out while deploying.", instance.id);
+
+        let conn = db::connection()?;
+        conn.transaction(|| {
+            transition(
+                &conn,
+                &instance,
+                InstanceEvent::Fail,
+                UpdateInstance::default(),
+            )?;
+            notifications::queue(
+                &conn,
+                notifications::deployment_failed(&instance, "it timed out"),
+            )
+        })?;
+    }
+
+    Ok(())
 }
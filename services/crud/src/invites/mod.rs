@@ -0,0 +1,8 @@
+pub mod model;
+pub mod routes;
+pub mod utils;
+
+pub use crud_client::invites::{InviteAcceptance, InviteRequest};
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,131 @@
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use models::invites::{self, dsl::*};
+use models::{Invite, ListQuery, Model, NewInvite, Page, UpdateInvite};
+
+use crate::{
+    api_error::ApiError,
+    db,
+    list::{invalid_sort, sort_by},
+    ID_SIZE,
+};
+
+fn filtered(account: &Option<String>, query: &ListQuery) -> invites::BoxedQuery<'static, Pg> {
+    let mut filtered = invites.into_boxed();
+
+    if let Some(account) = account {
+        filtered = filtered.filter(account_id.eq(account.clone()));
+    }
+    if let Some(after) = query.created_after {
+        filtered = filtered.filter(created_at.ge(after));
+    }
+    if let Some(before) = query.created_before {
+        filtered = filtered.filter(created_at.lt(before));
+    }
+    // active invites can still be accepted
+    if let Some(active) = query.active {
+        let now = chrono::Utc::now().naive_utc();
+        filtered = match active {
+            true => filtered
+                .filter(accepted_at.is_null())
+                .filter(revoked_at.is_null())
+                .filter(expires_at.gt(now)),
+            false => filtered.filter(
+                accepted_at
+                    .is_not_null()
+                    .or(revoked_at.is_not_null())
+                    .or(expires_at.le(now)),
+            ),
+        };
+    }
+
+    filtered
+}
+
+impl Model<String, NewInvite, UpdateInvite, ApiError> for Invite {
+    fn find_all() -> Result<Vec<Self>, ApiError> {
+        let conn = db::connection()?;
+        let result = invites.load::<Self>(&conn)?;
+
+        Ok(result)
+    }
+
+    fn find_page(account: Option<String>, query: ListQuery) -> Result<Page<Self>, ApiError> {
+        let conn = db::connection()?;
+        let total = filtered(&account, &query)
+            .count()
+            .get_result::<i64>(&conn)?;
+
+        let page = filtered(&account, &query);
+        let page = match query.sort.as_deref().unwrap_or("createdAt") {
+            "createdAt" => sort_by!(page, created_at, query),
+            "updatedAt" => sort_by!(page, updated_at, query),
+            "email" => sort_by!(page, email, query),
+            "expiresAt" => sort_by!(page, expires_at, query),
+            "acceptedAt" => sort_by!(page, accepted_at, query),
+            field => return Err(invalid_sort(field)),
+        };
+        let result = page
+            .then_order_by(id.asc())
+            .limit(query.limit())
+            .offset(query.offset())
+            .load::<Self>(&conn)?;
+
+        Ok(Page::new(result, total, &query))
+    }
+
+    fn find_by_id(target: String) -> Result<Self, ApiError> {
+        let conn = db::connection()?;
+        let result = invites.filter(id.eq(target)).get_result::<Self>(&conn)?;
+
+        Ok(result)
+    }
+
+    fn find_all_in(account: String) -> Result<Vec<Self>, ApiError> {
+        let conn = db::connection()?;
+        let result = invites.filter(account_id.eq(account)).load::<Self>(&conn)?;
+
+        Ok(result)
+    }
+
+    fn find_in(account: String, target: String) -> Result<Self, ApiError> {
+        let conn = db::connection()?;
+        let result = invites
+            .filter(id.eq(target))
+            .filter(account_id.eq(account))
+            .get_result::<Self>(&conn)?;
+
+        Ok(result)
+    }
+
+    /// Invites made here have no usable token, use utils::create to get one
+    fn insert(new: NewInvite) -> Result<Self, ApiError> {
+        let conn = db::connection()?;
+        let with_id = NewInvite {
+            id: nanoid!(ID_SIZE),
+            ..new
+        };
+        let result = diesel::insert_into(invites)
+            .values(&with_id)
+            .get_result::<Self>(&conn)?;
+
+        Ok(result)
+    }
+
+    fn update(target: String, new_vals: UpdateInvite) -> Result<Self, ApiError> {
+        let conn = db::connection()?;
+        let result = diesel::update(invites)
+            .filter(id.eq(target))
+            .set(new_vals)
+            .get_result(&conn)?;
+
+        Ok(result)
+    }
+
+    fn delete(target: String) -> Result<usize, ApiError> {
+        let conn = db::connection()?;
+        let result = diesel::delete(invites.filter(id.eq(target))).execute(&conn)?;
+
+        Ok(result)
+    }
+}
@@ -0,0 +1,241 @@
+use actix_web::{get, post, put, web, HttpResponse};
+use auth::{account_scope, outranks, require_cap, ReqUser};
+use bcrypt::hash;
+use diesel::prelude::*;
+use models::types::Capability;
+use models::{Account, Invite, ListQuery, Model, NewInvite, NewUser, User, Validate};
+
+use super::{utils, InviteAcceptance, InviteRequest};
+use crate::audit::{self, utils::audited, Actor, Change};
+use crate::mail::Email;
+use crate::{
+    api_error::{ApiError, ErrorCode},
+    db, roles, update_usage, AppData, DASHBOARD_URL,
+};
+
+#[utoipa::path(tag = "invites", params(ListQuery), responses((status = 200, body = InvitePage)))]
+#[get("/invites")]
+async fn find_all(
+    query: web::Query<ListQuery>,
+    req_user: Option<ReqUser>,
+) -> Result<HttpResponse, ApiError> {
+    if !require_cap(&req_user, Capability::ManageUsers) {
+        return Err(ApiError::forbidden());
+    }
+
+    let account = account_scope(&req_user);
+    let invites = web::block(move || Invite::find_page(account, query.into_inner())).await??;
+
+    Ok(HttpResponse::Ok().json(invites))
+}
+
+/// Emails a single use link to join the inviter's account
+#[utoipa::path(tag = "invites", responses((status = 200, body = Invite)))]
+#[post("/invites")]
+async fn create(
+    body: web::Json<InviteRequest>,
+    req_user: Option<ReqUser>,
+    actor: Actor,
+    app_data: web::Data<AppData>,
+) -> Result<HttpResponse, ApiError> {
+    // invites are sent by a user, for their account
+    let req_user = req_user.ok_or_else(ApiError::forbidden)?;
+    let inviter = Some(req_user.clone());
+    if !require_cap(&inviter, Capability::ManageUsers) {
+        return Err(ApiError::forbidden());
+    }
+
+    let body = body.into_inner();
+    let new_role = body
+        .role_id
+        .unwrap_or_else(|| String::from(models::types::Role::User));
+
+    // must outrank the role the invitee will get, same as creating them directly
+    let (role_account, role_target) = (req_user.account_id.clone(), new_role.clone());
+    let new_capabilities = web::block(move || {
+        let conn = db::connection()?;
+        roles::utils::capabilities_of(&conn, &role_account, &role_target)
+    })
+    .await??;
+    if !outranks(&inviter, &new_capabilities) {
+        return Err(ApiError::forbidden());
+    }
+
+    let new_invite = NewInvite {
+        id: String::new(),
+        account_id: req_user.account_id.clone(),
+        email: body.email,
+        role_id: new_role,
+        instances: body.instances,
+        create_perms: body.create_perms,
+        update_perms: body.update_perms,
+        delete_perms: body.delete_perms,
+        invited_by: req_user.id.clone(),
+        token_hash: String::new(),
+        expires_at: chrono::Utc::now().naive_utc(),
+        accepted_at: None,
+        revoked_at: None,
+        user_id: None,
+    };
+    new_invite.validate()?;
+
+    let mailer = app_data.mailer.clone();
+    let invite = web::block(move || {
+        let account_id = new_invite.account_id.clone();
+        audited(&actor, &account_id, |conn| {
+            let (invite, token) = utils::create(conn, new_invite)?;
+            // failing to send rolls the invite back so it can be sent again
+            mailer.send(Email {
+                to: invite.email.clone(),
+                subject: "You've been invited".into(),
+                body: format!(
+                    "Hi,\n\nYou've been invited to join your team. Use this link to pick a username and password, it expires in {} hours:\n{}/accept-invite?token={}",
+                    *crate::auth::INVITE_EXPIRY / 3_600_000,
+                    *DASHBOARD_URL,
+                    token
+                ),
+            })?;
+            let change = Change::new("create", "invite", &invite.id).after(&invite);
+
+            Ok((invite, change))
+        })
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(invite))
+}
+
+#[utoipa::path(tag = "invites", responses((status = 200, body = Invite)))]
+#[put("/invites/{id}/revoke")]
+async fn revoke(
+    id: web::Path<String>,
+    req_user: Option<ReqUser>,
+    actor: Actor,
+) -> Result<HttpResponse, ApiError> {
+    if !require_cap(&req_user, Capability::ManageUsers) {
+        return Err(ApiError::forbidden());
+    }
+    let account = account_scope(&req_user);
+    let invite = web::block(move || Invite::find_scoped(account, id.into_inner())).await??;
+    if invite.accepted_at.is_some() {
+        return Err(ApiError::new(400, "Invite was already accepted.".into()));
+    }
+
+    let invite = web::block(move || {
+        audited(&actor, &invite.account_id, |conn| {
+            let revoked = utils::revoke(conn, &invite.id)?;
+            let change = Change::new("revoke", "invite", &invite.id)
+                .before(&invite)
+                .after(&revoked);
+
+            Ok((revoked, change))
+        })
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(invite))
+}
+
+/// Creates the invited user with the role and perms from their invite, the link stops working after
+#[utoipa::path(tag = "invites", responses((status = 200, body = User)))]
+#[post("/invites/accept")]
+async fn accept(body: web::Json<InviteAcceptance>, actor: Actor) -> Result<HttpResponse, ApiError> {
+    use models::users::dsl::*;
+    let body = body.into_inner();
+
+    // must get these manually so tests can pass when we have only 1 connection
+    let token = body.token.clone();
+    let (invite, owner) = web::block::<_, Result<(Invite, Account), ApiError>>(move || {
+        let conn = db::connection()?;
+        let invite = utils::find_pending(&conn, &token)?;
+        let owner = models::accounts::table
+            .find(&invite.account_id)
+            .first::<Account>(&conn)?;
+
+        Ok((invite, owner))
+    })
+    .await??;
+
+    let new_user = NewUser {
+        id: nanoid!(10),
+        account_id: invite.account_id.clone(),
+        username: body.username,
+        first_name: body.first_name,
+        last_name: body.last_name,
+        password: body.password,
+        active: true,
+        instances: invite.instances.clone(),
+        create_perms: invite.create_perms.clone(),
+        update_perms: invite.update_perms.clone(),
+        delete_perms: invite.delete_perms.clone(),
+        role: roles::utils::legacy_role(&invite.role_id),
+        role_id: invite.role_id.clone(),
+        notes: None,
+        failed_logins: 0,
+        locked_until: None,
+        email: Some(invite.email.clone()),
+    };
+    // validate before hashing so the password policy sees the real password
+    new_user.validate()?;
+    let plain = new_user.password.clone();
+    let hashed = web::block(move || hash(plain, bcrypt::DEFAULT_COST)).await??;
+    let new_user = NewUser {
+        password: hashed,
+        ..new_user
+    };
+
+    let conn = db::connection()?;
+    let user = web::block(move || {
+        conn.transaction::<_, ApiError, _>(|| {
+            if owner.sub_id.is_none() {
+                return Err(ApiError::not_subbed());
+            }
+
+            let user = diesel::insert_into(users)
+                .values(&new_user)
+                .get_result::<User>(&conn)
+                .map_err(|err| match ApiError::from(err) {
+                    // there was a conflict only possibility is username
+                    err if err.status_code == 409 => {
+                        ApiError::new(409, "Username must be unique.".into())
+                            .with_code(ErrorCode::AlreadyExists)
+                    }
+                    err => err,
+                })?;
+            let accepted = utils::accept(&conn, &invite.id, &user.id)?;
+
+            let num_user = users
+                .count()
+                .filter(account_id.eq(owner.id.clone()))
+                .filter(deleted_at.is_null())
+                .get_result::<i64>(&conn)?;
+            let res = update_usage(&owner, "users".into(), num_user)?;
+            if res.error_for_status().is_err() {
+                return Err(ApiError::new(
+                    500,
+                    "Failed to update user subscription with Stripe.".into(),
+                ));
+            }
+
+            let change = Change::new("accept", "invite", &invite.id)
+                .before(&invite)
+                .after(&accepted);
+            audit::utils::record(&conn, &actor, &owner.id, change)?;
+            let change = Change::new("create", "user", &user.id).after(&user);
+            audit::utils::record(&conn, &actor, &owner.id, change)?;
+
+            Ok(user)
+        })
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(user))
+}
+
+pub fn init_routes(config: &mut web::ServiceConfig) {
+    config.service(find_all);
+    config.service(create);
+    // before the id routes so accept isn't read as an id
+    config.service(accept);
+    config.service(revoke);
+}
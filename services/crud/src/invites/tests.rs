@@ -0,0 +1,165 @@
+use actix_http::StatusCode;
+use actix_web::test;
+use auth::ReqUser;
+use diesel::prelude::*;
+use models::{types::Role, Invite, Page, User};
+
+use crate::{db, tests};
+
+fn admin(test_name: &str) -> String {
+    with_role(test_name, Role::Admin)
+}
+
+fn with_role(test_name: &str, role: Role) -> String {
+    serde_json::to_string(&ReqUser {
+        id: test_name.into(),
+        account_id: "test".into(),
+        role,
+        create_perms: vec![],
+        update_perms: vec![],
+        delete_perms: vec![],
+        capabilities: None,
+    })
+    .unwrap()
+}
+
+fn token_for(address: &str) -> String {
+    let sent = crate::mail::outbox::take(address);
+    assert_eq!(sent.len(), 1);
+    sent[0]
+        .body
+        .split("token=")
+        .nth(1)
+        .and_then(|rest| rest.split_whitespace().next())
+        .expect("No token in email")
+        .to_string()
+}
+
+#[actix_web::test]
+async fn invite_and_accept() {
+    actix_web::rt::spawn(tests::mock_payments());
+    let app = tests::init(super::routes::init_routes).await;
+    let address = "invitee@invites.test";
+    let invite = |role: &str| {
+        test::TestRequest::post()
+            .uri("/invites")
+            .insert_header(("user", admin("invites-create")))
+            .set_json(serde_json::json!({ "email": address, "roleId": role }))
+            .to_request()
+    };
+
+    // can't invite someone into a role above your own
+    let resp = test::call_service(&app, invite("owner")).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    let first: Invite = test::call_and_read_body_json(&app, invite("moderator")).await;
+    let first_token = token_for(address);
+    assert_eq!(first.account_id, "test");
+    assert_eq!(first.invited_by, "invites-create");
+
+    // sending again replaces the earlier invite
+    let second: Invite = test::call_and_read_body_json(&app, invite("moderator")).await;
+    let token = token_for(address);
+
+    let req = test::TestRequest::get()
+        .uri("/invites?active=true")
+        .insert_header(("user", admin("invites-create")))
+        .to_request();
+    let page: Page<Invite> = test::call_and_read_body_json(&app, req).await;
+    assert!(page.items.iter().all(|pending| pending.id != first.id));
+    assert!(page.items.iter().any(|pending| pending.id == second.id));
+
+    let accept = |token: &str| {
+        test::TestRequest::post()
+            .uri("/invites/accept")
+            .set_json(serde_json::json!({
+                "token": token,
+                "username": "invites-accept",
+                "firstName": "Invited",
+                "lastName": "User",
+                "password": "Invited-password1",
+            }))
+            .to_request()
+    };
+    let resp = test::call_service(&app, accept(&first_token)).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    // the password policy applies to invited users too
+    let req = test::TestRequest::post()
+        .uri("/invites/accept")
+        .set_json(serde_json::json!({
+            "token": token,
+            "username": "invites-accept",
+            "firstName": "Invited",
+            "lastName": "User",
+            "password": "weak",
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let user: User = test::call_and_read_body_json(&app, accept(&token)).await;
+    assert_eq!(user.account_id, "test");
+    assert_eq!(user.role_id, "moderator");
+    assert_eq!(user.role, Role::Moderator);
+    assert_eq!(user.email.as_deref(), Some(address));
+
+    // links are single use
+    let resp = test::call_service(&app, accept(&token)).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let conn = db::connection().unwrap();
+    remove(&user.id, &conn);
+}
+
+#[actix_web::test]
+async fn revoke() {
+    let app = tests::init(super::routes::init_routes).await;
+    let address = "revoked@invites.test";
+
+    let req = test::TestRequest::post()
+        .uri("/invites")
+        .insert_header(("user", admin("invites-revoke")))
+        .set_json(serde_json::json!({ "email": address }))
+        .to_request();
+    let invite: Invite = test::call_and_read_body_json(&app, req).await;
+    let token = token_for(address);
+    assert_eq!(invite.role_id, "user");
+
+    // can't tell which invites exist without being allowed to revoke them
+    for target in [invite.id.as_str(), "not-an-invite"] {
+        let req = test::TestRequest::put()
+            .uri(&format!("/invites/{}/revoke", target))
+            .insert_header(("user", with_role("invites-revoke", Role::User)))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    }
+
+    let req = test::TestRequest::put()
+        .uri(&format!("/invites/{}/revoke", invite.id))
+        .insert_header(("user", admin("invites-revoke")))
+        .to_request();
+    let revoked: Invite = test::call_and_read_body_json(&app, req).await;
+    assert!(revoked.revoked_at.is_some());
+
+    let req = test::TestRequest::post()
+        .uri("/invites/accept")
+        .set_json(serde_json::json!({
+            "token": token,
+            "username": "invites-revoked",
+            "firstName": "Revoked",
+            "lastName": "User",
+            "password": "Revoked-password1",
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+fn remove(user: &str, conn: &db::PoolConn) {
+    diesel::delete(models::invites::table.filter(models::invites::user_id.eq(user)))
+        .execute(conn)
+        .expect("couldn't delete test invite from table");
+    crate::users::tests::remove(user.into(), conn);
+}
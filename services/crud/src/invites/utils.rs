@@ -0,0 +1,83 @@
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use models::invites::dsl::*;
+use models::{Invite, NewInvite};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    api_error::{ApiError, ErrorCode},
+    auth, ID_SIZE,
+};
+
+fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+fn invalid_token() -> ApiError {
+    ApiError::new(400, "Invalid or expired invite link.".into()).with_code(ErrorCode::InvalidToken)
+}
+
+/// Creates invite, returns it with the signed token to send the invitee
+///
+/// Only a hash of the token is stored, earlier pending invites to the same email stop working
+pub fn create(conn: &PgConnection, new: NewInvite) -> Result<(Invite, String), ApiError> {
+    let now = Utc::now().naive_utc();
+    diesel::update(invites.filter(account_id.eq(&new.account_id)))
+        .filter(email.eq(&new.email))
+        .filter(accepted_at.is_null())
+        .filter(revoked_at.is_null())
+        .set(revoked_at.eq(now))
+        .execute(conn)?;
+
+    let invite_id = nanoid!(ID_SIZE);
+    let token = auth::sign_invite(invite_id.clone()).map_err(|err| {
+        error!("Failed to sign invite token: {:?}", err);
+        ApiError::server_err()
+    })?;
+
+    let invite = diesel::insert_into(invites)
+        .values(NewInvite {
+            id: invite_id,
+            token_hash: hash_token(&token),
+            expires_at: now + Duration::milliseconds(*auth::INVITE_EXPIRY),
+            accepted_at: None,
+            revoked_at: None,
+            user_id: None,
+            ..new
+        })
+        .get_result::<Invite>(conn)?;
+
+    Ok((invite, token))
+}
+
+/// Finds the invite token was issued for, as long as it can still be accepted
+pub fn find_pending(conn: &PgConnection, token: &str) -> Result<Invite, ApiError> {
+    let claim = auth::verify_invite(token).map_err(|_| invalid_token())?;
+
+    invites
+        .filter(id.eq(claim.iid))
+        .filter(token_hash.eq(hash_token(token)))
+        .filter(accepted_at.is_null())
+        .filter(revoked_at.is_null())
+        .filter(expires_at.gt(Utc::now().naive_utc()))
+        .get_result::<Invite>(conn)
+        .optional()?
+        .ok_or_else(invalid_token)
+}
+
+/// Marks invite as accepted by user, the token can't be used again after this
+pub fn accept(conn: &PgConnection, target: &str, user: &str) -> Result<Invite, ApiError> {
+    diesel::update(invites.filter(id.eq(target)))
+        .filter(accepted_at.is_null())
+        .filter(revoked_at.is_null())
+        .set((accepted_at.eq(Utc::now().naive_utc()), user_id.eq(user)))
+        .get_result::<Invite>(conn)
+        .optional()?
+        .ok_or_else(invalid_token)
+}
+
+pub fn revoke(conn: &PgConnection, target: &str) -> Result<Invite, ApiError> {
+    Ok(diesel::update(invites.filter(id.eq(target)))
+        .set(revoked_at.eq(Utc::now().naive_utc()))
+        .get_result::<Invite>(conn)?)
+}
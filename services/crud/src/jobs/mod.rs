@@ -0,0 +1,222 @@
+//! Delayed work saved in the database, so it still runs when crud restarts before it's due
+use actix_web::web;
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use models::jobs::dsl::*;
+use models::{Job, NewJob};
+use serde::{Deserialize, Serialize};
+
+use crate::notifications::{Destination, Notification};
+use crate::{accounts, api_error::ApiError, auth, db, instances, purge, AppData, ID_SIZE};
+
+lazy_static! {
+    /// Seconds between looking for due jobs, set with JOBS_INTERVAL_SECONDS
+    static ref INTERVAL_SECONDS: u64 = std::env::var("JOBS_INTERVAL_SECONDS")
+        .ok()
+        .and_then(|seconds| seconds.parse::<u64>().ok())
+        .unwrap_or(30);
+}
+
+/// Jobs claimed at once by a runner
+const BATCH_SIZE: i64 = 20;
+/// Minutes a claimed job is left to its runner before another one can take it
+const LEASE_MINUTES: i64 = 10;
+
+/// Everything the runner knows how to do, saved as a job's kind and payload
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "payload", rename_all = "snake_case")]
+pub enum Task {
+    /// Fails the instance if it's still deploying
+    EnsureDeployment { instance_id: String },
+    /// Emails the user a password reset link, if they exist and can reset
+    EmailPasswordReset {
+        account_id: String,
+        username: String,
+    },
+    /// Builds an account export's archive
+    BuildExport { export_id: String },
+    /// Hard deletes what was deleted long enough ago, then schedules the next purge
+    Purge,
+    /// Removes an instance's alias once its environment is terminated
+    DeleteDns { url: String, target: String },
+    /// Delivers notification to one of the places it goes
+    Notify {
+        to: Destination,
+        notification: Notification,
+    },
+}
+
+impl Task {
+    fn from_job(job: &Job) -> Result<Task, ApiError> {
+        let tagged = serde_json::json!({ "kind": job.kind, "payload": job.payload });
+        serde_json::from_value(tagged)
+            .map_err(|err| ApiError::new(500, format!("Can't run {} job: {}", job.kind, err)))
+    }
+
+    async fn run(self, app_data: AppData) -> Result<(), ApiError> {
+        match self {
+            Task::EnsureDeployment { instance_id } => {
+                web::block(move || instances::utils::check_deployment(instance_id)).await?
+            }
+            Task::EmailPasswordReset {
+                account_id,
+                username,
+            } => {
+                web::block(move || {
+                    auth::password_reset::send(app_data.mailer.as_ref(), account_id, username)
+                })
+                .await?
+            }
+            Task::BuildExport { export_id } => accounts::export::run(export_id).await,
+            Task::Purge => purge::run().await,
+            Task::DeleteDns { url, target } => {
+                instances::aws::delete_dns(&app_data.aws, &url, &target).await
+            }
+            Task::Notify { to, notification } => {
+                web::block(move || app_data.notifier.deliver(&to, &notification)).await?
+            }
+        }
+    }
+}
+
+/// Saves task to be run once delay has passed, in conn's transaction if it has one
+pub fn schedule(conn: &PgConnection, task: Task, delay: Duration) -> Result<Job, ApiError> {
+    let tagged = serde_json::to_value(&task)?;
+
+    Ok(diesel::insert_into(jobs)
+        .values(NewJob {
+            id: nanoid!(ID_SIZE),
+            kind: tagged["kind"].as_str().unwrap_or_default().into(),
+            payload: tagged["payload"].clone(),
+            run_at: Utc::now().naive_utc() + delay,
+            attempts: 0,
+            max_attempts: 5,
+            locked_until: None,
+            last_error: None,
+            completed_at: None,
+        })
+        .get_result::<Job>(conn)?)
+}
+
+/// Saves task like schedule unless the same task is already waiting to run, for tasks that reschedule themselves
+pub fn schedule_once(
+    conn: &PgConnection,
+    task: Task,
+    delay: Duration,
+) -> Result<Option<Job>, ApiError> {
+    let tagged = serde_json::to_value(&task)?;
+    let waiting = jobs
+        .count()
+        .filter(kind.eq(tagged["kind"].as_str().unwrap_or_default()))
+        .filter(payload.eq(&tagged["payload"]))
+        .filter(completed_at.is_null())
+        .filter(attempts.lt(max_attempts))
+        .get_result::<i64>(conn)?;
+
+    if waiting > 0 {
+        return Ok(None);
+    }
+    Ok(Some(schedule(conn, task, delay)?))
+}
+
+/// Takes the jobs that are due and not held by another runner, counting an attempt for each
+fn claim() -> Result<Vec<Job>, ApiError> {
+    let conn = db::connection()?;
+    let now = Utc::now().naive_utc();
+
+    conn.transaction(|| {
+        let due = jobs
+            .select(id)
+            .filter(completed_at.is_null())
+            .filter(run_at.le(now))
+            .filter(attempts.lt(max_attempts))
+            .filter(locked_until.is_null().or(locked_until.lt(now)))
+            .order(run_at)
+            .limit(BATCH_SIZE)
+            .for_update()
+            .skip_locked()
+            .load::<String>(&conn)?;
+
+        Ok(diesel::update(jobs.filter(id.eq_any(due)))
+            .set((
+                attempts.eq(attempts + 1),
+                locked_until.eq(Some(now + Duration::minutes(LEASE_MINUTES))),
+            ))
+            .get_results::<Job>(&conn)?)
+    })
+}
+
+/// Marks job done, or puts it back with a growing delay until it runs out of attempts
+fn finish(job: &Job, result: Result<(), ApiError>) -> Result<(), ApiError> {
+    let conn = db::connection()?;
+    let now = Utc::now().naive_utc();
+
+    match result {
+        Ok(()) => diesel::update(jobs.find(&job.id))
+            .set((
+                completed_at.eq(Some(now)),
+                locked_until.eq(None::<chrono::NaiveDateTime>),
+                last_error.eq(None::<String>),
+            ))
+            .execute(&conn)?,
+        Err(err) => {
+            if job.attempts >= job.max_attempts {
+                error!(
+                    "Giving up on job {} after {} attempts.",
+                    job.id, job.attempts
+                );
+            }
+
+            diesel::update(jobs.find(&job.id))
+                .set((
+                    run_at.eq(now + Duration::minutes(i64::from(job.attempts.pow(2)))),
+                    locked_until.eq(None::<chrono::NaiveDateTime>),
+                    last_error.eq(Some(err.0.message)),
+                ))
+                .execute(&conn)?
+        }
+    };
+
+    Ok(())
+}
+
+/// Runs every job that's due, returns how many were run
+pub async fn run_due(app_data: &AppData) -> Result<usize, ApiError> {
+    let claimed = web::block(claim).await??;
+
+    for job in &claimed {
+        let result = match Task::from_job(job) {
+            Ok(task) => task.run(app_data.clone()).await,
+            Err(err) => Err(err),
+        };
+
+        let finished = job.clone();
+        if !matches!(
+            web::block(move || finish(&finished, result)).await,
+            Ok(Ok(()))
+        ) {
+            error!("Failed to save the outcome of job {}.", job.id);
+        }
+    }
+
+    Ok(claimed.len())
+}
+
+/// Runs due jobs on an interval while the server runs, jobs left by a previous run are picked up too
+pub fn spawn(app_data: AppData) {
+    actix_web::rt::spawn(async move {
+        let every = std::time::Duration::from_secs(*INTERVAL_SECONDS);
+        let mut interval = actix_web::rt::time::interval(every);
+
+        loop {
+            interval.tick().await;
+
+            if run_due(&app_data).await.is_err() {
+                error!("Failed to run due jobs.");
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests;
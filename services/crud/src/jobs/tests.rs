@@ -0,0 +1,92 @@
+use chrono::Duration;
+use diesel::prelude::*;
+use models::{jobs::dsl::jobs, types::InstanceStatus, Instance, Job, Model};
+
+use super::{run_due, schedule, schedule_once, Task};
+use crate::{db, instances::tests::defaults, tests};
+
+fn reload(job: &Job) -> Job {
+    let conn = db::connection().unwrap();
+    jobs.find(&job.id).get_result::<Job>(&conn).unwrap()
+}
+
+#[actix_web::test]
+async fn runs_due_jobs() {
+    tests::init(|_| {}).await;
+
+    let (_, deploying) = defaults("jobs".into());
+    let (instance, due, later, unknown) = {
+        let conn = db::connection().unwrap();
+        let instance = diesel::insert_into(models::instances::table)
+            .values(&deploying)
+            .get_result::<Instance>(&conn)
+            .unwrap();
+        let task = Task::EnsureDeployment {
+            instance_id: instance.id.clone(),
+        };
+        let due = schedule(&conn, task.clone(), Duration::zero()).unwrap();
+        let later = schedule(&conn, task, Duration::minutes(15)).unwrap();
+
+        // left by a newer version of crud, or of a kind that was removed since
+        let missing = Task::EnsureDeployment {
+            instance_id: "missing".into(),
+        };
+        let unknown = schedule(&conn, missing, Duration::zero()).unwrap();
+        let unknown = diesel::update(jobs.find(&unknown.id))
+            .set(models::jobs::kind.eq("removed_kind"))
+            .get_result::<Job>(&conn)
+            .unwrap();
+
+        (instance, due, later, unknown)
+    };
+    assert_eq!(due.kind, "ensure_deployment");
+
+    assert!(run_due(&tests::app_data()).await.unwrap() >= 2);
+
+    let instance = Instance::find_by_id(instance.id).unwrap();
+    assert_eq!(instance.status, InstanceStatus::Failed);
+    let due = reload(&due);
+    assert!(due.completed_at.is_some());
+    assert_eq!(due.attempts, 1);
+
+    // not due yet, so untouched
+    let later = reload(&later);
+    assert!(later.completed_at.is_none());
+    assert_eq!(later.attempts, 0);
+
+    // failed jobs are kept with why and tried again later
+    let unknown = reload(&unknown);
+    assert!(unknown.completed_at.is_none());
+    assert!(unknown.locked_until.is_none());
+    assert_eq!(unknown.attempts, 1);
+    assert!(unknown.last_error.unwrap().contains("removed_kind"));
+    assert!(unknown.run_at > chrono::Utc::now().naive_utc());
+}
+
+#[actix_web::test]
+async fn purge_reschedules_itself() {
+    tests::init(|_| {}).await;
+    let waiting_purge = || {
+        jobs.filter(models::jobs::kind.eq("purge"))
+            .filter(models::jobs::completed_at.is_null())
+    };
+
+    let first = {
+        let conn = db::connection().unwrap();
+        // left waiting by an earlier test run
+        diesel::delete(waiting_purge()).execute(&conn).unwrap();
+
+        let first = schedule_once(&conn, Task::Purge, Duration::zero()).unwrap();
+        // only ever one waiting, however often crud starts
+        let second = schedule_once(&conn, Task::Purge, Duration::zero()).unwrap();
+        assert!(second.is_none());
+        first.unwrap()
+    };
+
+    run_due(&tests::app_data()).await.unwrap();
+    assert!(reload(&first).completed_at.is_some());
+
+    let conn = db::connection().unwrap();
+    let next = waiting_purge().get_result::<Job>(&conn).unwrap();
+    assert!(next.run_at > chrono::Utc::now().naive_utc() + Duration::hours(23));
+}
@@ -0,0 +1,18 @@
+use crate::api_error::ApiError;
+
+/// Orders a boxed query by column, in the direction requested by the list query
+macro_rules! sort_by {
+    ($query:expr, $column:expr, $list_query:expr) => {
+        if $list_query.descending() {
+            $query.order($column.desc())
+        } else {
+            $query.order($column.asc())
+        }
+    };
+}
+
+pub(crate) use sort_by;
+
+pub fn invalid_sort(field: &str) -> ApiError {
+    ApiError::new(400, format!("Cannot sort by field: {}", field))
+}
@@ -0,0 +1,90 @@
+use serde::Serialize;
+use std::{fmt::Debug, sync::Arc};
+
+use crate::api_error::ApiError;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Delivers emails, blocking so call from inside web::block
+pub trait Mailer: Debug + Send + Sync {
+    fn send(&self, email: Email) -> Result<(), ApiError>;
+}
+
+/// Writes emails to the log instead of sending them, for dev
+#[derive(Debug)]
+pub struct LogMailer;
+
+impl Mailer for LogMailer {
+    fn send(&self, email: Email) -> Result<(), ApiError> {
+        info!(
+            "Email to {}, subject: {}\n{}",
+            email.to, email.subject, email.body
+        );
+        Ok(())
+    }
+}
+
+/// Posts emails as json to a mail relay
+#[derive(Debug)]
+pub struct HttpMailer {
+    uri: String,
+}
+
+impl Mailer for HttpMailer {
+    fn send(&self, email: Email) -> Result<(), ApiError> {
+        let res = reqwest::blocking::Client::new()
+            .post(&self.uri)
+            .json(&email)
+            .send()?;
+
+        if !res.status().is_success() {
+            error!("Mail relay responded with {}", res.status());
+            return Err(ApiError::server_err());
+        }
+        Ok(())
+    }
+}
+
+/// Mailer to use, sends through the relay at MAIL_URI if it is set otherwise only logs
+pub fn from_env() -> Arc<dyn Mailer> {
+    match std::env::var("MAIL_URI") {
+        Ok(uri) => Arc::new(HttpMailer { uri }),
+        Err(_) => Arc::new(LogMailer),
+    }
+}
+
+#[cfg(test)]
+pub mod outbox {
+    use std::sync::Mutex;
+
+    use super::{Email, Mailer};
+    use crate::api_error::ApiError;
+
+    lazy_static! {
+        static ref SENT: Mutex<Vec<Email>> = Mutex::new(vec![]);
+    }
+
+    /// Keeps emails so tests can read them back
+    #[derive(Debug)]
+    pub struct OutboxMailer;
+
+    impl Mailer for OutboxMailer {
+        fn send(&self, email: Email) -> Result<(), ApiError> {
+            SENT.lock().unwrap().push(email);
+            Ok(())
+        }
+    }
+
+    /// Takes every email sent to address so far
+    pub fn take(address: &str) -> Vec<Email> {
+        let mut sent = SENT.lock().unwrap();
+        let (taken, kept) = sent.drain(..).partition(|email| email.to == address);
+        *sent = kept;
+        taken
+    }
+}
@@ -13,9 +13,19 @@ mod api_error;
 mod auth;
 mod db;
 mod json;
+mod jobs;
+mod list;
+mod mail;
+mod notifications;
+mod openapi;
+mod purge;
 
 mod accounts;
+mod api_keys;
+mod audit;
 mod instances;
+mod invites;
+mod roles;
 mod users;
 
 use actix_web::{
@@ -24,18 +34,17 @@ use actix_web::{
     App, HttpResponse, HttpServer,
 };
 use api_error::ApiError;
+use crud_client::accounts::{RegisterBody, RegisterResponse};
 use dotenv::dotenv;
 use models::{Account, Validate};
 use payments_lib::routes::create_usage_record;
-use serde::{Deserialize, Serialize};
 
-use crate::json::ErrorBody;
 
 #[derive(Debug, Clone)]
 struct AppData {
-    pub eb_client: aws_sdk_elasticbeanstalk::Client,
-    pub r53_client: aws_sdk_route53::Client,
-    pub sns_client: aws_sdk_sns::Client,
+    pub aws: cloud::Aws,
+    pub mailer: std::sync::Arc<dyn mail::Mailer>,
+    pub notifier: std::sync::Arc<dyn notifications::Notifier>,
 }
 
 #[actix_web::main]
@@ -45,17 +54,18 @@ async fn main() -> std::io::Result<()> {
     dotenv::from_filename(".env.local").ok();
     env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
     db::init();
+    if purge::start().is_err() {
+        error!("Failed to schedule purging deleted rows.");
+    }
 
-    let aws_creds = aws_config::load_from_env().await;
-    let eb_client = aws_sdk_elasticbeanstalk::Client::new(&aws_creds);
-    let r53_client = aws_sdk_route53::Client::new(&aws_creds);
-    let sns_client = aws_sdk_sns::Client::new(&aws_creds);
-
+    let mailer = mail::from_env();
     let app_data = AppData {
-        eb_client,
-        r53_client,
-        sns_client,
+        aws: cloud::Aws::from_env().await,
+        notifier: notifications::from_env(mailer.clone()),
+        mailer,
     };
+    jobs::spawn(app_data.clone());
+    instances::health::spawn();
 
     info!("Starting HTTP server at http://localhost:8080");
 
@@ -68,6 +78,12 @@ async fn main() -> std::io::Result<()> {
             .configure(accounts::routes::init_routes)
             .configure(users::routes::init_routes)
             .configure(instances::routes::init_routes)
+            .configure(api_keys::routes::init_routes)
+            .configure(audit::routes::init_routes)
+            .configure(roles::routes::init_routes)
+            .configure(invites::routes::init_routes)
+            .configure(notifications::routes::init_routes)
+            .configure(openapi::init_routes)
             .app_data(web::Data::new(app_data.clone()))
     })
     .bind((if *PROD { "0.0.0.0" } else { "127.0.0.1" }, 8080))?
@@ -79,30 +95,26 @@ lazy_static! {
     pub static ref PAYMENTS_URI: String =
         std::env::var("PAYMENTS_URI").unwrap_or("http://127.0.0.1:6000".into());
     static ref PROD: bool = std::env::var("RUST_ENV").unwrap_or("dev".into()) == "prod";
+    /// Where links in emails point to
+    static ref DASHBOARD_URL: String = std::env::var("DASHBOARD_URL").unwrap_or(if *PROD {
+        "https://dashboard.milkyweb.app".into()
+    } else {
+        "http://localhost:3000".into()
+    });
 }
 
-#[derive(Debug, Deserialize)]
-struct RegisterBody {
-    account: models::NewAccount,
-    user: models::NewUser,
-}
-
-#[derive(Debug, Serialize)]
-struct RegisterResponse {
-    account: models::Account,
-    user: models::User,
-}
-
+#[utoipa::path(tag = "accounts", responses((status = 200, body = RegisterResponse)))]
 #[post("/register")]
-async fn register(data: Json<RegisterBody>) -> Result<HttpResponse, ApiError> {
+async fn register(data: Json<RegisterBody>, actor: audit::Actor) -> Result<HttpResponse, ApiError> {
     use diesel::prelude::*;
     let data = data.into_inner();
     let conn = db::connection()?;
 
     if data.account.id != data.user.account_id {
-        return Ok(
-            HttpResponse::BadRequest().json(ErrorBody::new("Account and user id do not match."))
-        );
+        return Err(ApiError::new(
+            400,
+            "Account and user id do not match.".into(),
+        ));
     }
 
     conn.transaction::<HttpResponse, ApiError, _>(|| {
@@ -115,18 +127,33 @@ async fn register(data: Json<RegisterBody>) -> Result<HttpResponse, ApiError> {
             .values(&with_id)
             .get_result::<models::Account>(&conn)?;
 
+        // validate before hashing so the password policy sees the real password
+        data.user.validate()?;
         let with_hash = models::NewUser {
             id: nanoid!(10),
             password: bcrypt::hash(data.user.password, bcrypt::DEFAULT_COST)?,
             role: models::types::Role::Owner,
+            role_id: models::types::Role::Owner.into(),
             account_id: account.id.clone(),
             ..data.user
         };
-        with_hash.validate()?;
         let user: models::User = diesel::insert_into(models::users::table)
             .values(&with_hash)
             .get_result::<models::User>(&conn)?;
 
+        audit::utils::record(
+            &conn,
+            &actor,
+            &account.id,
+            audit::Change::new("create", "account", &account.id).after(&account),
+        )?;
+        audit::utils::record(
+            &conn,
+            &actor,
+            &account.id,
+            audit::Change::new("create", "user", &user.id).after(&user),
+        )?;
+
         Ok(HttpResponse::Ok().json(RegisterResponse { account, user }))
     })
 }
@@ -0,0 +1,343 @@
+//! Telling accounts when something goes wrong, by email or webhook as their preferences ask
+pub mod routes;
+
+use std::{
+    fmt::Debug,
+    fs::OpenOptions,
+    io::Write,
+    net::{IpAddr, SocketAddr, ToSocketAddrs},
+    path::PathBuf,
+    sync::Arc,
+    time::Duration as StdDuration,
+};
+
+use actix_web::web;
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use models::{
+    types::{NotificationKind, Role},
+    Instance, NotificationPreference,
+};
+use reqwest::{redirect::Policy, Url};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api_error::ApiError,
+    db,
+    jobs::{self, Task},
+    mail::{Email, Mailer},
+};
+
+pub use crud_client::notifications::{Notification, OPERATOR};
+
+#[cfg(test)]
+mod tests;
+
+/// Tells the instance's account it didn't deploy
+pub fn deployment_failed(instance: &Instance, reason: &str) -> Notification {
+    Notification {
+        account_id: instance.account_id.clone(),
+        kind: NotificationKind::DeploymentFailed,
+        subject: format!("{} failed to deploy", instance.name),
+        body: format!(
+            "Deploying your instance {} failed, {}. Deploy it again to retry.",
+            instance.name, reason
+        ),
+        instance_id: Some(instance.id.clone()),
+    }
+}
+
+/// Tells the instance's account its health checks keep failing
+pub fn unhealthy(instance: &Instance) -> Notification {
+    Notification {
+        account_id: instance.account_id.clone(),
+        kind: NotificationKind::InstanceUnhealthy,
+        subject: format!("{} is unhealthy", instance.name),
+        body: format!(
+            "Your instance {} has failed its last few health checks.",
+            instance.name
+        ),
+        instance_id: Some(instance.id.clone()),
+    }
+}
+
+/// Tells us stripe is billing account for the wrong number of instances
+pub fn usage_sync_failed(account: &str, instances: i64) -> Notification {
+    Notification {
+        account_id: OPERATOR.into(),
+        kind: NotificationKind::UsageSyncFailed,
+        subject: "Instance usage wasn't sent to Stripe".into(),
+        body: format!(
+            "Account {} should be billed for {} instances, update its usage with Stripe by hand.",
+            account, instances
+        ),
+        instance_id: None,
+    }
+}
+
+/// Where one notification is delivered
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "channel", content = "address", rename_all = "snake_case")]
+pub enum Destination {
+    Email(String),
+    Webhook(String),
+}
+
+/// Delivers notifications, blocking so call from inside web::block
+pub trait Notifier: Debug + Send + Sync {
+    fn deliver(&self, to: &Destination, notification: &Notification) -> Result<(), ApiError>;
+}
+
+/// Emails through the mailer and posts json to webhooks
+#[derive(Debug)]
+pub struct Channels {
+    mailer: Arc<dyn Mailer>,
+}
+
+impl Channels {
+    fn email(&self, address: &str, notification: &Notification) -> Result<(), ApiError> {
+        self.mailer.send(Email {
+            to: address.into(),
+            subject: notification.subject.clone(),
+            body: notification.body.clone(),
+        })
+    }
+
+    fn webhook(&self, url: &str, notification: &Notification) -> Result<(), ApiError> {
+        let (host, addr) = webhook_address(url)?
+            .ok_or_else(|| ApiError::new(502, "Webhook host doesn't resolve.".into()))?;
+        let res = reqwest::blocking::Client::builder()
+            .timeout(StdDuration::from_secs(10))
+            // a redirect could point anywhere, including back at us
+            .redirect(Policy::none())
+            // connect to the address that was checked, the host could resolve somewhere else next time
+            .resolve(&host, addr)
+            .build()?
+            .post(url)
+            .json(notification)
+            .send()?;
+
+        if !res.status().is_success() {
+            return Err(ApiError::new(
+                502,
+                format!("Webhook responded with {}", res.status()),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Notifier for Channels {
+    fn deliver(&self, to: &Destination, notification: &Notification) -> Result<(), ApiError> {
+        match to {
+            Destination::Email(address) => self.email(address, notification),
+            Destination::Webhook(url) => self.webhook(url, notification),
+        }
+    }
+}
+
+/// Writes notifications to a file, or the log without one, instead of delivering them, for dev
+#[derive(Debug)]
+pub struct SinkNotifier {
+    path: Option<PathBuf>,
+}
+
+impl Notifier for SinkNotifier {
+    fn deliver(&self, to: &Destination, notification: &Notification) -> Result<(), ApiError> {
+        let line = serde_json::json!({ "to": to, "notification": notification });
+
+        match &self.path {
+            Some(path) => {
+                let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+                writeln!(file, "{}", line)?;
+            }
+            None => info!("Notification {}", line),
+        }
+        Ok(())
+    }
+}
+
+/// Notifier to use, NOTIFICATIONS_SINK set to a file path or `log` keeps notifications local
+pub fn from_env(mailer: Arc<dyn Mailer>) -> Arc<dyn Notifier> {
+    match std::env::var("NOTIFICATIONS_SINK") {
+        Ok(sink) if sink == "log" => Arc::new(SinkNotifier { path: None }),
+        Ok(path) => Arc::new(SinkNotifier {
+            path: Some(path.into()),
+        }),
+        Err(_) => Arc::new(Channels { mailer }),
+    }
+}
+
+/// Whether ip is somewhere anyone could reach, rather than our own network or machine
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            // 100.64.0.0/10 is shared by carriers and cloud providers
+            let shared = ip.octets()[0] == 100 && ip.octets()[1] & 0xc0 == 64;
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || shared)
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(mapped) => is_public(mapped.into()),
+            None => {
+                let unique_local = ip.segments()[0] & 0xfe00 == 0xfc00;
+                let link_local = ip.segments()[0] & 0xffc0 == 0xfe80;
+                !(ip.is_loopback() || ip.is_unspecified() || unique_local || link_local)
+            }
+        },
+    }
+}
+
+/// Checks url is https to a public address, so a webhook can't reach crud or anything else on our network
+///
+/// Returns its host and the address that resolved to, None if it doesn't resolve yet, blocking so call from inside web::block
+pub fn webhook_address(url: &str) -> Result<Option<(String, SocketAddr)>, ApiError> {
+    let invalid = |reason: &str| ApiError::new(400, format!("Webhook url {}.", reason));
+    let parsed = Url::parse(url).map_err(|_| invalid("isn't a valid url"))?;
+    if parsed.scheme() != "https" {
+        return Err(invalid("must use https"));
+    }
+
+    let host = parsed.host_str().ok_or_else(|| invalid("needs a host"))?;
+    let port = parsed.port_or_known_default().unwrap_or(443);
+    // ipv6 hosts keep their brackets
+    let literal = host.trim_start_matches('[').trim_end_matches(']');
+    let resolved: Vec<SocketAddr> = match literal.parse::<IpAddr>() {
+        Ok(ip) => vec![SocketAddr::new(ip, port)],
+        Err(_) => (host, port)
+            .to_socket_addrs()
+            .map(|addrs| addrs.collect())
+            .unwrap_or_default(),
+    };
+    if resolved.iter().any(|addr| !is_public(addr.ip())) {
+        return Err(invalid("must point at a public address"));
+    }
+
+    Ok(resolved
+        .into_iter()
+        .next()
+        .map(|addr| (host.to_string(), addr)))
+}
+
+/// Account's preferences, the defaults if it never saved any
+pub fn preferences(conn: &PgConnection, account: &str) -> Result<NotificationPreference, ApiError> {
+    use models::notification_preferences::dsl::*;
+
+    let saved = notification_preferences
+        .filter(account_id.eq(account))
+        .first::<NotificationPreference>(conn)
+        .optional()?;
+
+    Ok(saved.unwrap_or_else(|| {
+        let now = Utc::now().naive_utc();
+        NotificationPreference {
+            id: String::new(),
+            created_at: now,
+            updated_at: now,
+            account_id: account.into(),
+            email: true,
+            webhook_url: None,
+            muted: vec![],
+        }
+    }))
+}
+
+/// Everywhere notification should go, nowhere if its account muted the kind
+fn destinations(
+    conn: &PgConnection,
+    notification: &Notification,
+) -> Result<Vec<Destination>, ApiError> {
+    let preferences = preferences(conn, &notification.account_id)?;
+    if preferences.muted.contains(&notification.kind) {
+        return Ok(vec![]);
+    }
+
+    let mut to = vec![];
+    if preferences.email {
+        use models::users::dsl::*;
+        let owners = users
+            .select(email)
+            .filter(account_id.eq(&notification.account_id))
+            .filter(role_id.eq::<String>(Role::Owner.into()))
+            .filter(deleted_at.is_null())
+            .load::<Option<String>>(conn)?;
+
+        to.extend(owners.into_iter().flatten().map(Destination::Email));
+    }
+    if let Some(url) = preferences.webhook_url {
+        to.push(Destination::Webhook(url));
+    }
+
+    Ok(to)
+}
+
+/// Saves a job for each place notification goes, in conn's transaction if it has one, returns how many
+///
+/// Delivery happens in the job runner, so a webhook that's down is retried
+pub fn queue(conn: &PgConnection, notification: Notification) -> Result<usize, ApiError> {
+    let destinations = destinations(conn, &notification)?;
+
+    for to in &destinations {
+        let task = Task::Notify {
+            to: to.clone(),
+            notification: notification.clone(),
+        };
+        jobs::schedule(conn, task, Duration::zero())?;
+    }
+
+    Ok(destinations.len())
+}
+
+/// Queues notification on its own connection, for code that's already handling a failure
+pub async fn send(notification: Notification) {
+    let kind = notification.kind;
+    let queued = web::block(move || {
+        let conn = db::connection()?;
+        queue(&conn, notification)
+    })
+    .await;
+
+    if !matches!(queued, Ok(Ok(_))) {
+        error!("Failed to queue {:?} notification.", kind);
+    }
+}
+
+#[cfg(test)]
+pub mod outbox {
+    use std::sync::Mutex;
+
+    use super::{Destination, Notification, Notifier};
+    use crate::api_error::ApiError;
+
+    lazy_static! {
+        static ref DELIVERED: Mutex<Vec<(Destination, Notification)>> = Mutex::new(vec![]);
+    }
+
+    /// Keeps notifications so tests can read them back
+    #[derive(Debug)]
+    pub struct OutboxNotifier;
+
+    impl Notifier for OutboxNotifier {
+        fn deliver(&self, to: &Destination, notification: &Notification) -> Result<(), ApiError> {
+            DELIVERED
+                .lock()
+                .unwrap()
+                .push((to.clone(), notification.clone()));
+            Ok(())
+        }
+    }
+
+    /// Takes every notification delivered for account so far
+    pub fn take(account: &str) -> Vec<(Destination, Notification)> {
+        let mut delivered = DELIVERED.lock().unwrap();
+        let (taken, kept) = delivered
+            .drain(..)
+            .partition(|(_, notification)| notification.account_id == account);
+        *delivered = kept;
+        taken
+    }
+}
@@ -0,0 +1,124 @@
+use actix_web::{get, post, put, web, HttpResponse};
+use auth::{account_scope, belongs_to_account, require_cap, ReqUser};
+use diesel::prelude::*;
+use models::types::Capability;
+use models::{
+    Account, Model, NewNotificationPreference, NotificationPreference,
+    UpdateNotificationPreference, Validate,
+};
+
+use super::{preferences, queue, webhook_address, Notification, OPERATOR};
+use crate::audit::{utils::audited, Actor, Change};
+use crate::{api_error::ApiError, db, ID_SIZE};
+
+/// Lets other services notify accounts, such as payments when a subscription is past due
+#[utoipa::path(tag = "notifications", responses((status = 200)))]
+#[post("/notifications")]
+async fn send(
+    notification: web::Json<Notification>,
+    req_user: Option<ReqUser>,
+) -> Result<HttpResponse, ApiError> {
+    // only internal requests and the admin account can notify accounts
+    if !belongs_to_account(&req_user, OPERATOR) {
+        return Err(ApiError::forbidden());
+    }
+
+    let notification = notification.into_inner();
+    web::block(move || {
+        Account::find_by_id(notification.account_id.clone())?;
+        let conn = db::connection()?;
+        conn.transaction(|| queue(&conn, notification))
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[utoipa::path(tag = "notifications", responses((status = 200, body = NotificationPreference)))]
+#[get("/accounts/{id}/notification-preferences")]
+async fn find_preferences(
+    target: web::Path<String>,
+    req_user: Option<ReqUser>,
+) -> Result<HttpResponse, ApiError> {
+    let target = target.into_inner();
+    let account = account_scope(&req_user);
+    let preferences = web::block(move || {
+        // make sure account exists and is visible to user
+        Account::find_scoped(account, target.clone())?;
+        let conn = db::connection()?;
+        preferences(&conn, &target)
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(preferences))
+}
+
+/// Changes how the account is notified, saving the defaults first if it never changed them
+#[utoipa::path(tag = "notifications", responses((status = 200, body = NotificationPreference)))]
+#[put("/accounts/{id}/notification-preferences")]
+async fn update_preferences(
+    target: web::Path<String>,
+    changes: web::Json<UpdateNotificationPreference>,
+    req_user: Option<ReqUser>,
+    actor: Actor,
+) -> Result<HttpResponse, ApiError> {
+    let target = target.into_inner();
+    let for_find = target.clone();
+    let account = account_scope(&req_user);
+    web::block(move || Account::find_scoped(account, for_find)).await??;
+    if !require_cap(&req_user, Capability::ManageBilling) {
+        return Err(ApiError::forbidden());
+    }
+
+    let changes = changes.into_inner();
+    // hosts that don't resolve yet are saved, delivery checks again before every post
+    if let Some(Some(url)) = changes.webhook_url.clone() {
+        web::block(move || webhook_address(&url)).await??;
+    }
+
+    let saved = web::block(move || {
+        audited(&actor, &target, |conn| {
+            use models::notification_preferences::dsl::*;
+            let before = preferences(conn, &target)?;
+            let merged = NewNotificationPreference {
+                id: nanoid!(ID_SIZE),
+                account_id: target.clone(),
+                email: changes.email.unwrap_or(before.email),
+                webhook_url: changes
+                    .webhook_url
+                    .unwrap_or_else(|| before.webhook_url.clone()),
+                muted: changes.muted.unwrap_or_else(|| before.muted.clone()),
+            };
+            merged.validate()?;
+
+            // the defaults aren't saved until they're first changed
+            let saved = if before.id.is_empty() {
+                diesel::insert_into(notification_preferences)
+                    .values(&merged)
+                    .get_result::<NotificationPreference>(conn)?
+            } else {
+                diesel::update(notification_preferences.find(&before.id))
+                    .set((
+                        email.eq(merged.email),
+                        webhook_url.eq(&merged.webhook_url),
+                        muted.eq(&merged.muted),
+                    ))
+                    .get_result::<NotificationPreference>(conn)?
+            };
+            let change = Change::new("update", "notification_preferences", &saved.id)
+                .before(&before)
+                .after(&saved);
+
+            Ok((saved, change))
+        })
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(saved))
+}
+
+pub fn init_routes(config: &mut web::ServiceConfig) {
+    config.service(send);
+    config.service(find_preferences);
+    config.service(update_preferences);
+}
@@ -0,0 +1,178 @@
+use actix_http::StatusCode;
+use actix_web::test;
+use auth::ReqUser;
+use diesel::prelude::*;
+use models::{
+    types::{NotificationKind, Role},
+    NewUser, NotificationPreference, User,
+};
+
+use super::{outbox, webhook_address, Channels, Destination, Notification, Notifier};
+use crate::{db, jobs, tests, users};
+
+fn req_user(test_name: &str, role: Role) -> String {
+    serde_json::to_string(&ReqUser {
+        id: test_name.into(),
+        account_id: "test".into(),
+        role,
+        create_perms: vec![],
+        update_perms: vec![],
+        delete_perms: vec![],
+        capabilities: None,
+    })
+    .unwrap()
+}
+
+fn notification(kind: NotificationKind) -> Notification {
+    Notification {
+        account_id: "test".into(),
+        kind,
+        subject: "Something happened".into(),
+        body: "Here's what happened.".into(),
+        instance_id: None,
+    }
+}
+
+#[actix_web::test]
+async fn delivers_by_preferences() {
+    let (default1, _) = users::tests::defaults("notify");
+    let app = tests::init(super::routes::init_routes).await;
+    let conn = db::connection().unwrap();
+    let owner = diesel::insert_into(models::users::table)
+        .values(&NewUser {
+            role: Role::Owner,
+            role_id: Role::Owner.into(),
+            email: Some("owner@notify.test".into()),
+            ..default1
+        })
+        .get_result::<User>(&conn)
+        .expect("couldn't insert");
+    drop(conn);
+
+    // accounts get every notification by email until they change it
+    let req = test::TestRequest::get()
+        .uri("/accounts/test/notification-preferences")
+        .insert_header(("user", req_user("notify", Role::User)))
+        .to_request();
+    let defaults: NotificationPreference = test::call_and_read_body_json(&app, req).await;
+    assert!(defaults.email);
+    assert!(defaults.muted.is_empty());
+
+    let req = test::TestRequest::put()
+        .uri("/accounts/test/notification-preferences")
+        .insert_header(("user", req_user("notify", Role::User)))
+        .set_json(serde_json::json!({ "muted": ["paymentPastDue"] }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    // webhooks can't reach crud or anything else on our network
+    for url in [
+        "not a url",
+        "http://hooks.notify.test/crud",
+        "https://localhost:8080/notifications",
+        "https://127.0.0.1/notifications",
+        "https://10.0.0.5/crud",
+        "https://169.254.169.254/latest/meta-data",
+        "https://[::1]/notifications",
+        "https://[::ffff:192.168.0.1]/crud",
+    ] {
+        let req = test::TestRequest::put()
+            .uri("/accounts/test/notification-preferences")
+            .insert_header(("user", req_user("notify", Role::Owner)))
+            .set_json(serde_json::json!({ "webhookUrl": url }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{} was saved", url);
+    }
+
+    let req = test::TestRequest::put()
+        .uri("/accounts/test/notification-preferences")
+        .insert_header(("user", req_user("notify", Role::Owner)))
+        .set_json(serde_json::json!({
+            "webhookUrl": "https://hooks.notify.test/crud",
+            "muted": ["paymentPastDue"],
+        }))
+        .to_request();
+    let saved: NotificationPreference = test::call_and_read_body_json(&app, req).await;
+    assert!(saved.email);
+    assert_eq!(saved.muted, vec![NotificationKind::PaymentPastDue]);
+
+    // only fields sent are changed once they're saved
+    let req = test::TestRequest::put()
+        .uri("/accounts/test/notification-preferences")
+        .insert_header(("user", req_user("notify", Role::Owner)))
+        .set_json(serde_json::json!({ "email": false }))
+        .to_request();
+    let changed: NotificationPreference = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(changed.id, saved.id);
+    assert!(!changed.email);
+    assert_eq!(changed.webhook_url, saved.webhook_url);
+
+    let req = test::TestRequest::put()
+        .uri("/accounts/test/notification-preferences")
+        .insert_header(("user", req_user("notify", Role::Owner)))
+        .set_json(serde_json::json!({ "email": true }))
+        .to_request();
+    test::call_service(&app, req).await;
+
+    // users can't notify accounts themselves
+    let req = test::TestRequest::post()
+        .uri("/notifications")
+        .insert_header(("user", req_user("notify", Role::Owner)))
+        .set_json(notification(NotificationKind::DeploymentFailed))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    for kind in [
+        NotificationKind::PaymentPastDue,
+        NotificationKind::DeploymentFailed,
+    ] {
+        let req = test::TestRequest::post()
+            .uri("/notifications")
+            .set_json(notification(kind))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    jobs::run_due(&tests::app_data()).await.unwrap();
+    let delivered = outbox::take("test");
+    let sent = notification(NotificationKind::DeploymentFailed);
+    assert!(delivered.contains(&(Destination::Email("owner@notify.test".into()), sent.clone())));
+    assert!(delivered.contains(&(
+        Destination::Webhook("https://hooks.notify.test/crud".into()),
+        sent
+    )));
+    // muted kinds aren't sent anywhere
+    assert!(delivered
+        .iter()
+        .all(|(_, notification)| notification.kind != NotificationKind::PaymentPastDue));
+
+    let conn = db::connection().unwrap();
+    diesel::delete(models::users::table.find(&owner.id))
+        .execute(&conn)
+        .unwrap();
+    diesel::delete(models::notification_preferences::table.find(&saved.id))
+        .execute(&conn)
+        .unwrap();
+}
+
+#[actix_web::test]
+async fn webhooks_only_reach_public_addresses() {
+    let public = webhook_address("https://93.184.216.34/hook").unwrap();
+    assert_eq!(public.unwrap().1, "93.184.216.34:443".parse().unwrap());
+    assert!(webhook_address("https://100.64.0.1/hook").is_err());
+    assert!(webhook_address("https://[fd00::1]/hook").is_err());
+
+    // nothing is sent to a webhook that fails the check
+    let channels = Channels {
+        mailer: std::sync::Arc::new(crate::mail::outbox::OutboxMailer),
+    };
+    let to = Destination::Webhook("https://127.0.0.1:8080/notifications".into());
+    let err = channels
+        .deliver(&to, &notification(NotificationKind::DeploymentFailed))
+        .unwrap_err();
+    assert_eq!(err.0.status_code, 400);
+}
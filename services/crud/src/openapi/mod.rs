@@ -0,0 +1,175 @@
+use actix_web::{get, web, HttpResponse};
+use errors::openapi::ErrorResponses;
+use utoipa::{Modify, OpenApi};
+
+use crate::{
+    accounts, api_keys, audit, auth, instances, invites, json, notifications, roles, users,
+};
+
+#[cfg(test)]
+mod tests;
+
+/// Every route with the models it takes and responds with, generated from the route attributes
+///
+/// Add new routes to `paths` and anything they take or respond with to `schemas`, the tests check both
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        crate::register,
+        accounts::routes::find_all,
+        accounts::routes::find,
+        accounts::routes::find_users,
+        accounts::routes::find_instances,
+        accounts::routes::usage,
+        accounts::routes::find_by_sub,
+        accounts::routes::find_by_customer,
+        accounts::routes::create,
+        accounts::routes::update,
+        accounts::routes::delete,
+        accounts::routes::find_closure,
+        accounts::routes::export,
+        accounts::routes::restore,
+        users::routes::find_all,
+        users::routes::find,
+        users::routes::create,
+        users::routes::import,
+        users::routes::update,
+        users::routes::change_password,
+        users::routes::delete,
+        users::routes::restore,
+        users::routes::toggle_status,
+        users::routes::transfer_owner,
+        instances::routes::find_all,
+        instances::routes::find,
+        instances::routes::create,
+        instances::routes::update,
+        instances::routes::delete,
+        instances::routes::restore,
+        instances::routes::deactivate,
+        instances::routes::deploy,
+        instances::routes::callback,
+        instances::routes::fail_callback,
+        instances::routes::health,
+        instances::routes::history,
+        instances::routes::uptime,
+        auth::routes::login,
+        auth::routes::login_mfa,
+        auth::routes::authenticate,
+        auth::routes::verify,
+        auth::routes::verify_key,
+        auth::routes::refresh,
+        auth::routes::logout,
+        auth::routes::request_password_reset,
+        auth::routes::confirm_password_reset,
+        auth::routes::enroll_mfa,
+        auth::routes::verify_mfa,
+        auth::routes::disable_mfa,
+        auth::routes::verify_deploy,
+        invites::routes::find_all,
+        invites::routes::create,
+        invites::routes::revoke,
+        invites::routes::accept,
+        roles::routes::find_all,
+        roles::routes::find,
+        roles::routes::create,
+        roles::routes::update,
+        roles::routes::delete,
+        api_keys::routes::find_all,
+        api_keys::routes::create,
+        api_keys::routes::revoke,
+        audit::routes::find_by_account,
+        audit::routes::create,
+        notifications::routes::send,
+        notifications::routes::find_preferences,
+        notifications::routes::update_preferences,
+    ),
+    components(schemas(
+        models::Account,
+        models::NewAccount,
+        models::UpdateAccount,
+        models::User,
+        models::NewUser,
+        models::UpdateUser,
+        models::Instance,
+        models::NewInstance,
+        models::UpdateInstance,
+        models::InstanceStatusChange,
+        models::InstanceHealthCheck,
+        models::AccountRole,
+        models::NewAccountRole,
+        models::UpdateAccountRole,
+        models::ApiKey,
+        models::NewApiKey,
+        models::Invite,
+        models::AuditEvent,
+        models::NewAuditEvent,
+        models::NotificationPreference,
+        models::UpdateNotificationPreference,
+        models::AccountClosure,
+        models::AccountExport,
+        models::AccountPage,
+        models::UserPage,
+        models::InstancePage,
+        models::AccountRolePage,
+        models::ApiKeyPage,
+        models::InvitePage,
+        models::AuditEventPage,
+        models::SortOrder,
+        models::types::Capability,
+        models::types::ClosureStep,
+        models::types::InstanceEvent,
+        models::types::InstanceStatus,
+        models::types::NotificationKind,
+        models::types::Resource,
+        models::types::Role,
+        ::auth::ReqUser,
+        crate::RegisterBody,
+        crate::RegisterResponse,
+        json::DeleteBody,
+        accounts::utils::Usage,
+        users::PasswordChange,
+        users::import::ImportReport,
+        users::import::RowError,
+        instances::routes::CallbackParams,
+        instances::health::Uptime,
+        auth::Login,
+        auth::PasswordResetRequest,
+        auth::PasswordResetConfirm,
+        auth::MfaCode,
+        auth::MfaRequired,
+        auth::MfaEnrollment,
+        auth::RecoveryCodes,
+        auth::VerifiedUser,
+        invites::InviteRequest,
+        invites::InviteAcceptance,
+        api_keys::CreatedApiKey,
+        notifications::Notification,
+    )),
+    modifiers(&OperationIds, &ErrorResponses)
+)]
+pub struct ApiDoc;
+
+/// Handlers in different modules share names, so operation ids are prefixed with their tag
+struct OperationIds;
+
+impl Modify for OperationIds {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        for item in openapi.paths.paths.values_mut() {
+            for operation in item.operations.values_mut() {
+                let tag = operation.tags.as_ref().and_then(|tags| tags.first());
+                if let (Some(tag), Some(id)) = (tag, operation.operation_id.as_mut()) {
+                    *id = format!("{}_{}", tag.replace('-', "_"), id);
+                }
+            }
+        }
+    }
+}
+
+#[get("/openapi.json")]
+async fn spec() -> HttpResponse {
+    HttpResponse::Ok().json(ApiDoc::openapi())
+}
+
+pub fn init_routes(config: &mut web::ServiceConfig) {
+    config.service(spec);
+}
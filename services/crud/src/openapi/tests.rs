@@ -0,0 +1,110 @@
+use std::path::{Path, PathBuf};
+
+use actix_web::test::{call_and_read_body_json, TestRequest};
+use utoipa::openapi::PathItemType;
+use utoipa::OpenApi;
+
+use super::ApiDoc;
+use crate::tests;
+
+fn committed_spec() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("openapi.json")
+}
+
+/// Method and path of every actix route attribute in dir, written out like "get /users"
+fn route_attributes(dir: &Path) -> Vec<(PathItemType, String)> {
+    let mut found = vec![];
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            found.extend(route_attributes(&path));
+            continue;
+        }
+
+        for line in std::fs::read_to_string(&path).unwrap().lines() {
+            let methods = [
+                ("get", PathItemType::Get),
+                ("post", PathItemType::Post),
+                ("put", PathItemType::Put),
+                ("delete", PathItemType::Delete),
+            ];
+            for (name, method) in methods {
+                let attribute = format!("#[{}(\"", name);
+                if let Some(rest) = line.trim().strip_prefix(&attribute) {
+                    let path = rest.split('"').next().unwrap();
+                    found.push((method, format!("{} {}", name, path)));
+                }
+            }
+        }
+    }
+    found
+}
+
+/// The dashboard generates its types from the committed spec
+#[test]
+fn spec_is_current() {
+    let spec = ApiDoc::openapi().to_pretty_json().unwrap() + "\n";
+    if std::env::var("UPDATE_OPENAPI").is_ok() {
+        std::fs::write(committed_spec(), &spec).unwrap();
+    }
+
+    let committed = std::fs::read_to_string(committed_spec()).unwrap_or_default();
+    assert!(
+        committed == spec,
+        "openapi.json is out of date, run the crud tests with UPDATE_OPENAPI=1 to regenerate it"
+    );
+}
+
+#[test]
+fn documents_every_route() {
+    let spec = ApiDoc::openapi();
+    let src = Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+
+    let missing: Vec<String> = route_attributes(&src)
+        .into_iter()
+        .filter(|(_, route)| route != "get /openapi.json")
+        .filter(|(method, route)| {
+            let path = route.split_once(' ').unwrap().1;
+            !spec
+                .paths
+                .paths
+                .get(path)
+                .is_some_and(|item| item.operations.contains_key(method))
+        })
+        .map(|(_, route)| route)
+        .collect();
+    assert!(
+        missing.is_empty(),
+        "routes missing from ApiDoc: {:?}",
+        missing
+    );
+}
+
+#[test]
+fn unique_operation_ids() {
+    let spec = ApiDoc::openapi();
+    let mut ids: Vec<&String> = spec
+        .paths
+        .paths
+        .values()
+        .flat_map(|item| item.operations.values())
+        .filter_map(|operation| operation.operation_id.as_ref())
+        .collect();
+    let documented = ids.len();
+    ids.sort();
+    ids.dedup();
+
+    assert_eq!(ids.len(), documented);
+}
+
+#[actix_web::test]
+async fn serves_spec() {
+    let app = tests::init(super::init_routes).await;
+
+    let req = TestRequest::get().uri("/openapi.json").to_request();
+    let resp: serde_json::Value = call_and_read_body_json(&app, req).await;
+
+    assert_eq!(resp, serde_json::to_value(ApiDoc::openapi()).unwrap());
+    assert!(resp["components"]["schemas"]["NewUser"].is_object());
+    assert!(resp["components"]["schemas"]["UpdateInstance"].is_object());
+}
@@ -0,0 +1,53 @@
+use actix_web::web;
+use chrono::{Duration, NaiveDateTime, Utc};
+use models::{Account, Instance, SoftDelete, User};
+
+use crate::{
+    api_error::ApiError,
+    db,
+    instances::health,
+    jobs::{self, Task},
+};
+
+lazy_static! {
+    /// Days deleted rows are kept so they can be restored, set with PURGE_RETENTION_DAYS
+    static ref RETENTION_DAYS: i64 = std::env::var("PURGE_RETENTION_DAYS")
+        .ok()
+        .and_then(|days| days.parse::<i64>().ok())
+        .unwrap_or(30);
+    /// Hours between purges, set with PURGE_INTERVAL_HOURS
+    static ref INTERVAL_HOURS: i64 = std::env::var("PURGE_INTERVAL_HOURS")
+        .ok()
+        .and_then(|hours| hours.parse::<i64>().ok())
+        .unwrap_or(24);
+}
+
+/// Hard deletes everything which was deleted before cutoff, returns how many rows were removed
+pub fn purge(cutoff: NaiveDateTime) -> Result<usize, ApiError> {
+    // children first, an account would take them with it but wouldn't count them
+    Ok(Instance::purge(cutoff)? + User::purge(cutoff)? + Account::purge(cutoff)?)
+}
+
+/// Purges rows deleted longer than the retention period ago and old health checks, then schedules the next purge
+pub async fn run() -> Result<(), ApiError> {
+    let cutoff = Utc::now().naive_utc() - Duration::days(*RETENTION_DAYS);
+    let purged = web::block(move || {
+        // health checks are kept for as long as uptime needs them instead
+        let purged = purge(cutoff)? + health::purge()?;
+        let conn = db::connection()?;
+        jobs::schedule(&conn, Task::Purge, Duration::hours(*INTERVAL_HOURS))?;
+
+        Ok::<_, ApiError>(purged)
+    })
+    .await??;
+
+    info!("Purged {} deleted rows.", purged);
+    Ok(())
+}
+
+/// Queues a purge now unless one is already waiting, the purge job keeps scheduling the next one after that
+pub fn start() -> Result<(), ApiError> {
+    let conn = db::connection()?;
+    jobs::schedule_once(&conn, Task::Purge, Duration::zero())?;
+    Ok(())
+}
@@ -0,0 +1,6 @@
+pub mod model;
+pub mod routes;
+pub mod utils;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,113 @@
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use models::roles::{self, dsl::*};
+use models::{AccountRole, ListQuery, Model, NewAccountRole, Page, UpdateAccountRole};
+
+use crate::{
+    api_error::ApiError,
+    db,
+    list::{invalid_sort, sort_by},
+    ID_SIZE,
+};
+
+/// Account's own roles and the built in ones
+fn filtered(account: &Option<String>, query: &ListQuery) -> roles::BoxedQuery<'static, Pg> {
+    let mut filtered = roles.into_boxed();
+
+    if let Some(account) = account {
+        filtered = filtered.filter(account_id.is_null().or(account_id.eq(account.clone())));
+    }
+    if let Some(start) = query.created_after {
+        filtered = filtered.filter(created_at.ge(start));
+    }
+    if let Some(end) = query.created_before {
+        filtered = filtered.filter(created_at.lt(end));
+    }
+
+    filtered
+}
+
+impl Model<String, NewAccountRole, UpdateAccountRole, ApiError> for AccountRole {
+    fn find_all() -> Result<Vec<Self>, ApiError> {
+        let conn = db::connection()?;
+        let result = roles.load::<Self>(&conn)?;
+
+        Ok(result)
+    }
+
+    fn find_page(account: Option<String>, query: ListQuery) -> Result<Page<Self>, ApiError> {
+        let conn = db::connection()?;
+        let total = filtered(&account, &query)
+            .count()
+            .get_result::<i64>(&conn)?;
+
+        let page = filtered(&account, &query);
+        let page = match query.sort.as_deref().unwrap_or("createdAt") {
+            "createdAt" => sort_by!(page, created_at, query),
+            "updatedAt" => sort_by!(page, updated_at, query),
+            "name" => sort_by!(page, name, query),
+            field => return Err(invalid_sort(field)),
+        };
+        let result = page
+            .then_order_by(id.asc())
+            .limit(query.limit())
+            .offset(query.offset())
+            .load::<Self>(&conn)?;
+
+        Ok(Page::new(result, total, &query))
+    }
+
+    fn find_by_id(target: String) -> Result<Self, ApiError> {
+        let conn = db::connection()?;
+        let result = roles.filter(id.eq(target)).get_result::<Self>(&conn)?;
+
+        Ok(result)
+    }
+
+    /// Includes the built in roles since every account can use them
+    fn find_all_in(account: String) -> Result<Vec<Self>, ApiError> {
+        let conn = db::connection()?;
+        let result = roles
+            .filter(account_id.is_null().or(account_id.eq(account)))
+            .load::<Self>(&conn)?;
+
+        Ok(result)
+    }
+
+    fn find_in(account: String, target: String) -> Result<Self, ApiError> {
+        let conn = db::connection()?;
+        let result = super::utils::find_usable(&conn, &account, &target)?;
+
+        Ok(result)
+    }
+
+    fn insert(new: NewAccountRole) -> Result<Self, ApiError> {
+        let conn = db::connection()?;
+        let with_id = NewAccountRole {
+            id: nanoid!(ID_SIZE),
+            ..new
+        };
+        let result = diesel::insert_into(roles)
+            .values(&with_id)
+            .get_result::<Self>(&conn)?;
+
+        Ok(result)
+    }
+
+    fn update(target: String, new_vals: UpdateAccountRole) -> Result<Self, ApiError> {
+        let conn = db::connection()?;
+        let result = diesel::update(roles)
+            .filter(id.eq(target))
+            .set(new_vals)
+            .get_result(&conn)?;
+
+        Ok(result)
+    }
+
+    fn delete(target: String) -> Result<usize, ApiError> {
+        let conn = db::connection()?;
+        let result = diesel::delete(roles.filter(id.eq(target))).execute(&conn)?;
+
+        Ok(result)
+    }
+}
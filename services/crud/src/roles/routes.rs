@@ -0,0 +1,173 @@
+use actix_web::{delete, get, post, put, web, HttpResponse};
+use auth::{account_scope, covers, require_cap, ReqUser};
+use diesel::prelude::*;
+use models::types::Capability;
+use models::{AccountRole, ListQuery, Model, NewAccountRole, UpdateAccountRole, Validate};
+
+use crate::audit::{utils::audited, Actor, Change};
+use crate::{api_error::ApiError, json::DeleteBody, ID_SIZE};
+
+fn built_in() -> ApiError {
+    ApiError::new(400, "Built in roles can't be changed.".into())
+}
+
+#[utoipa::path(tag = "roles", params(ListQuery), responses((status = 200, body = AccountRolePage)))]
+#[get("/roles")]
+async fn find_all(
+    query: web::Query<ListQuery>,
+    req_user: Option<ReqUser>,
+) -> Result<HttpResponse, ApiError> {
+    // users see the built in roles and their account's own
+    let account = account_scope(&req_user);
+    let roles = web::block(move || AccountRole::find_page(account, query.into_inner())).await??;
+
+    Ok(HttpResponse::Ok().json(roles))
+}
+
+#[utoipa::path(tag = "roles", responses((status = 200, body = AccountRole)))]
+#[get("/roles/{id}")]
+async fn find(id: web::Path<String>, req_user: Option<ReqUser>) -> Result<HttpResponse, ApiError> {
+    let account = account_scope(&req_user);
+    let role = web::block(move || AccountRole::find_scoped(account, id.into_inner())).await??;
+
+    Ok(HttpResponse::Ok().json(role))
+}
+
+#[utoipa::path(tag = "roles", responses((status = 200, body = AccountRole)))]
+#[post("/roles")]
+async fn create(
+    new_role: web::Json<NewAccountRole>,
+    req_user: Option<ReqUser>,
+    actor: Actor,
+) -> Result<HttpResponse, ApiError> {
+    // can't make a role more powerful than your own
+    if !require_cap(&req_user, Capability::ManageRoles)
+        || !covers(&req_user, &new_role.capabilities)
+    {
+        return Err(ApiError::forbidden());
+    }
+
+    let new_role = new_role.into_inner();
+    // roles are always made for an account, only migrations add built in ones
+    let account_id = match req_user.map(|req_user| req_user.account_id) {
+        Some(account_id) => account_id,
+        None => new_role
+            .account_id
+            .clone()
+            .ok_or_else(|| ApiError::new(400, "Roles must belong to an account.".into()))?,
+    };
+    let new_role = NewAccountRole {
+        id: nanoid!(ID_SIZE),
+        account_id: Some(account_id.clone()),
+        ..new_role
+    };
+    new_role.validate()?;
+
+    let role = web::block(move || {
+        audited(&actor, &account_id, |conn| {
+            let role = diesel::insert_into(models::roles::table)
+                .values(&new_role)
+                .get_result::<AccountRole>(conn)?;
+            let change = Change::new("create", "role", &role.id).after(&role);
+
+            Ok((role, change))
+        })
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(role))
+}
+
+#[utoipa::path(tag = "roles", responses((status = 200, body = AccountRole)))]
+#[put("/roles/{id}")]
+async fn update(
+    id: web::Path<String>,
+    role: web::Json<UpdateAccountRole>,
+    req_user: Option<ReqUser>,
+    actor: Actor,
+) -> Result<HttpResponse, ApiError> {
+    let id = id.into_inner();
+    let for_find_to_be_updated = id.clone();
+    let account = account_scope(&req_user);
+    let to_be_updated =
+        web::block(move || AccountRole::find_scoped(account, for_find_to_be_updated)).await??;
+    let owner_id = to_be_updated.account_id.clone().ok_or_else(built_in)?;
+
+    let update_set = UpdateAccountRole {
+        account_id: None,
+        ..role.into_inner()
+    };
+    if !require_cap(&req_user, Capability::ManageRoles)
+        || !covers(&req_user, &to_be_updated.capabilities)
+        || !covers(
+            &req_user,
+            update_set.capabilities.as_deref().unwrap_or_default(),
+        )
+    {
+        return Err(ApiError::forbidden());
+    }
+    update_set.validate()?;
+
+    // users with the role pick up the change the next time their token is verified
+    let role = web::block(move || {
+        audited(&actor, &owner_id, |conn| {
+            let role = diesel::update(models::roles::table.find(&id))
+                .set(update_set)
+                .get_result::<AccountRole>(conn)?;
+            let change = Change::new("update", "role", &id)
+                .before(&to_be_updated)
+                .after(&role);
+
+            Ok((role, change))
+        })
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(role))
+}
+
+#[utoipa::path(tag = "roles", responses((status = 200, body = DeleteBody)))]
+#[delete("/roles/{id}")]
+async fn delete(
+    id: web::Path<String>,
+    req_user: Option<ReqUser>,
+    actor: Actor,
+) -> Result<HttpResponse, ApiError> {
+    let account = account_scope(&req_user);
+    let deleted = web::block(move || AccountRole::find_scoped(account, id.into_inner())).await??;
+    let owner_id = deleted.account_id.clone().ok_or_else(built_in)?;
+    if !require_cap(&req_user, Capability::ManageRoles) {
+        return Err(ApiError::forbidden());
+    }
+
+    let affected = web::block(move || {
+        audited(&actor, &owner_id, |conn| {
+            let assigned = models::users::table
+                .filter(models::users::role_id.eq(&deleted.id))
+                .count()
+                .get_result::<i64>(conn)?;
+            if assigned > 0 {
+                return Err(ApiError::new(
+                    409,
+                    "Role is still assigned to users, give them another role first.".into(),
+                ));
+            }
+
+            let affected = diesel::delete(models::roles::table.find(&deleted.id)).execute(conn)?;
+            let change = Change::new("delete", "role", &deleted.id).before(&deleted);
+
+            Ok((affected, change))
+        })
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(DeleteBody::new(affected as i32)))
+}
+
+pub fn init_routes(config: &mut web::ServiceConfig) {
+    config.service(find_all);
+    config.service(find);
+    config.service(create);
+    config.service(update);
+    config.service(delete);
+}
@@ -0,0 +1,121 @@
+use actix_http::StatusCode;
+use actix_web::test;
+use auth::ReqUser;
+use diesel::prelude::*;
+use models::{types::Role, AccountRole, Page, User};
+
+use crate::{db, json::DeleteBody, tests, users};
+
+fn req_user(test_name: &str, role: Role) -> String {
+    serde_json::to_string(&ReqUser {
+        id: test_name.into(),
+        account_id: "test".into(),
+        role,
+        create_perms: vec![],
+        update_perms: vec![],
+        delete_perms: vec![],
+        capabilities: None,
+    })
+    .unwrap()
+}
+
+#[actix_web::test]
+async fn custom_roles() {
+    let (default1, _) = users::tests::defaults("roles-custom");
+    let app = tests::init(|config| {
+        super::routes::init_routes(config);
+        users::routes::init_routes(config);
+    })
+    .await;
+    let conn = db::connection().unwrap();
+    let user = diesel::insert_into(models::users::table)
+        .values(models::NewUser {
+            role: Role::User,
+            role_id: Role::User.into(),
+            ..default1
+        })
+        .get_result::<User>(&conn)
+        .expect("couldn't insert");
+    drop(conn);
+
+    let create = |role: Role, body: serde_json::Value| {
+        test::TestRequest::post()
+            .uri("/roles")
+            .insert_header(("user", req_user("roles-custom", role)))
+            .set_json(body)
+            .to_request()
+    };
+
+    // moderators can't manage roles, admins can't make roles that can do more than them
+    let dispatcher = serde_json::json!({
+        "name": "Dispatcher",
+        "capabilities": ["manageInstances", "viewAudit"],
+    });
+    let resp = test::call_service(&app, create(Role::Moderator, dispatcher.clone())).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    let billing = serde_json::json!({ "name": "Billing", "capabilities": ["manageBilling"] });
+    let resp = test::call_service(&app, create(Role::Admin, billing)).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    let role: AccountRole =
+        test::call_and_read_body_json(&app, create(Role::Admin, dispatcher)).await;
+    assert_eq!(role.account_id.as_deref(), Some("test"));
+
+    // built in roles are listed with the account's own
+    let req = test::TestRequest::get()
+        .uri("/roles?limit=100")
+        .insert_header(("user", req_user("roles-custom", Role::User)))
+        .to_request();
+    let page: Page<AccountRole> = test::call_and_read_body_json(&app, req).await;
+    assert!(page.items.iter().any(|found| found.id == role.id));
+    assert!(page.items.iter().any(|found| found.id == "owner"));
+
+    let req = test::TestRequest::put()
+        .uri("/roles/owner")
+        .insert_header(("user", req_user("roles-custom", Role::Owner)))
+        .set_json(serde_json::json!({ "name": "Boss" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    // moderators don't outrank the custom role, admins do
+    let assign = |role: Role, role_id: &str| {
+        test::TestRequest::put()
+            .uri(&format!("/users/{}", user.id))
+            .insert_header(("user", req_user("roles-custom", role)))
+            .set_json(serde_json::json!({ "roleId": role_id }))
+            .to_request()
+    };
+    let resp = test::call_service(&app, assign(Role::Moderator, &role.id)).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    let updated: User = test::call_and_read_body_json(&app, assign(Role::Admin, &role.id)).await;
+    assert_eq!(updated.role_id, role.id);
+    assert_eq!(updated.role, Role::User);
+
+    // can't delete a role that is still in use
+    let delete = || {
+        test::TestRequest::delete()
+            .uri(&format!("/roles/{}", role.id))
+            .insert_header(("user", req_user("roles-custom", Role::Admin)))
+            .to_request()
+    };
+    let resp = test::call_service(&app, delete()).await;
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+    let updated: User = test::call_and_read_body_json(&app, assign(Role::Admin, "user")).await;
+    assert_eq!(updated.role_id, "user");
+    let deleted: DeleteBody = test::call_and_read_body_json(&app, delete()).await;
+    assert_eq!(deleted.affected, 1);
+
+    let conn = db::connection().unwrap();
+    users::tests::remove(user.id.clone(), &conn);
+    diesel::delete(
+        models::audit_events::table.filter(
+            models::audit_events::target_id
+                .eq(&user.id)
+                .or(models::audit_events::target_id.eq(&role.id)),
+        ),
+    )
+    .execute(&conn)
+    .expect("couldn't delete test audit events");
+}
@@ -0,0 +1,32 @@
+use diesel::prelude::*;
+use models::roles::dsl::*;
+use models::types::{Capability, Role};
+use models::AccountRole;
+
+use crate::api_error::ApiError;
+
+/// Finds role if account can assign it, either one of its own or a built in role
+pub fn find_usable(
+    conn: &PgConnection,
+    account: &str,
+    target: &str,
+) -> Result<AccountRole, ApiError> {
+    Ok(roles
+        .filter(id.eq(target))
+        .filter(account_id.is_null().or(account_id.eq(account)))
+        .get_result::<AccountRole>(conn)?)
+}
+
+/// Capabilities of a role the account can use
+pub fn capabilities_of(
+    conn: &PgConnection,
+    account: &str,
+    target: &str,
+) -> Result<Vec<Capability>, ApiError> {
+    Ok(find_usable(conn, account, target)?.capabilities)
+}
+
+/// Value for the legacy `users.role` column, custom roles are treated as the lowest
+pub fn legacy_role(role: &str) -> Role {
+    Role::built_in(role).unwrap_or(Role::User)
+}
@@ -2,51 +2,62 @@ use actix_web::{
     dev::{Service, ServiceResponse},
     test, web, App, HttpResponse, HttpServer,
 };
+use cloud::FakeAws;
 use diesel::prelude::*;
 use models::{Account, NewAccount};
-use payments_lib::routes::create_usage_record;
+use payments_lib::routes::{cancel_subscription, create_usage_record, invoices};
 use std::sync::{Arc, Mutex};
 
 use crate::{api_error::ApiError, auth, db};
 
 lazy_static! {
     static ref INITIATED: Arc<Mutex<bool>> = Arc::new(Mutex::new(false));
+    /// What every test app calls instead of AWS
+    pub static ref AWS: Arc<FakeAws> = Arc::new(FakeAws::default());
 }
 
 pub async fn init(
     init_routes: impl FnOnce(&mut web::ServiceConfig),
+) -> impl Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error> {
+    init_with(app_data(), init_routes).await
+}
+
+/// Like init with its own services, for tests that make them fail
+pub async fn init_with(
+    app_data: crate::AppData,
+    init_routes: impl FnOnce(&mut web::ServiceConfig),
 ) -> impl Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error> {
     let mut initiated = INITIATED.lock().unwrap();
     if *initiated == false {
         dotenv::dotenv().ok();
+        // tests log in wrong on purpose, throttle_backs_off covers the waits
+        std::env::set_var("THROTTLE_BACKOFF_MS", "0");
         env_logger::init();
         db::init();
         test_account();
         *initiated = true;
     }
 
-    let aws_creds = aws_config::load_from_env().await;
-    let eb_client = aws_sdk_elasticbeanstalk::Client::new(&aws_creds);
-    let r53_client = aws_sdk_route53::Client::new(&aws_creds);
-    let sns_client = aws_sdk_sns::Client::new(&aws_creds);
-
-    let app_data = crate::AppData {
-        eb_client,
-        r53_client,
-        sns_client,
-    };
-
     // serialize password when doing tests
     models::dont_skip_pass();
     test::init_service(
         App::new()
-            .app_data(web::Data::new(app_data.clone()))
+            .app_data(web::Data::new(app_data))
             .wrap(auth::middleware::Authorize)
             .configure(init_routes),
     )
     .await
 }
 
+/// Services every test app and job runs against, faked so nothing leaves the process
+pub fn app_data() -> crate::AppData {
+    crate::AppData {
+        aws: cloud::Aws::fake(AWS.clone()),
+        mailer: Arc::new(crate::mail::outbox::OutboxMailer),
+        notifier: Arc::new(crate::notifications::outbox::OutboxNotifier),
+    }
+}
+
 pub async fn mock_payments() -> std::io::Result<()> {
     std::env::set_var("PAYMENTS_URI", "http://127.0.0.1:6666");
 
@@ -58,6 +69,31 @@ pub async fn mock_payments() -> std::io::Result<()> {
         Ok(HttpResponse::Ok().finish())
     }
 
+    async fn cancel_subscription_handler() -> Result<HttpResponse, ApiError> {
+        Ok(HttpResponse::Ok().finish())
+    }
+
+    async fn invoices_handler() -> Result<HttpResponse, ApiError> {
+        Ok(HttpResponse::Ok().json(vec![invoices::Invoice {
+            id: "in_test".into(),
+            number: Some("TEST-0001".into()),
+            status: Some("paid".into()),
+            currency: Some("usd".into()),
+            amount_due: Some(1500),
+            amount_paid: Some(1500),
+            total: Some(1500),
+            created: Some(1660000000),
+            hosted_invoice_url: None,
+            lines: vec![invoices::InvoiceLine {
+                description: Some("3 × Users".into()),
+                quantity: Some(3),
+                amount: 1500,
+                period_start: Some(1657000000),
+                period_end: Some(1660000000),
+            }],
+        }]))
+    }
+
     HttpServer::new(|| {
         App::new()
             .wrap(actix_web::middleware::Logger::default())
@@ -66,6 +102,11 @@ pub async fn mock_payments() -> std::io::Result<()> {
                 web::post().to(create_usage_record_handler),
             )
             .route("/customer/{id}", web::put().to(update_customer))
+            .route(
+                cancel_subscription::ROUTE,
+                web::post().to(cancel_subscription_handler),
+            )
+            .route(invoices::ROUTE, web::get().to(invoices_handler))
     })
     .bind(("127.0.0.1", 6666))?
     .run()
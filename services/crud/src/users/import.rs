@@ -0,0 +1,181 @@
+use std::collections::{HashMap, HashSet};
+
+use auth::{outranks, ReqUser};
+use diesel::prelude::*;
+use models::types::{Capability, Resource, Role};
+use models::{NewUser, Validate};
+use serde::Deserialize;
+
+use crate::{api_error::ApiError, roles};
+
+pub use crud_client::users::{ImportQuery, ImportReport, RowError};
+
+/// One line of an import, list columns are separated by semicolons
+#[derive(Debug, Deserialize)]
+struct ImportRow {
+    username: String,
+    first_name: String,
+    last_name: String,
+    password: String,
+    #[serde(default)]
+    email: Option<String>,
+    /// Id of a built in or account defined role, plain user when empty
+    #[serde(default)]
+    role: Option<String>,
+    #[serde(default)]
+    active: Option<bool>,
+    #[serde(default)]
+    notes: Option<String>,
+    #[serde(default)]
+    instances: Option<String>,
+    #[serde(default)]
+    create_perms: Option<String>,
+    #[serde(default)]
+    update_perms: Option<String>,
+    #[serde(default)]
+    delete_perms: Option<String>,
+}
+
+fn list(column: Option<String>) -> Vec<String> {
+    column
+        .unwrap_or_default()
+        .split(';')
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+fn perms(column: Option<String>) -> Result<Vec<Resource>, String> {
+    list(column)
+        .into_iter()
+        .map(|perm| {
+            serde_json::from_value(serde_json::Value::String(perm.clone()))
+                .map_err(|_| format!("Unknown resource '{}'.", perm))
+        })
+        .collect()
+}
+
+/// Turns csv into users for account, checking each one like creating it would
+///
+/// Every row is checked even after one fails so they can all be fixed at once
+pub fn check(
+    conn: &PgConnection,
+    account: &str,
+    req_user: &Option<ReqUser>,
+    csv: &[u8],
+) -> Result<(Vec<NewUser>, Vec<RowError>), ApiError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(csv);
+    let mut checked = vec![];
+    let mut errors = vec![];
+    let mut role_capabilities: HashMap<String, Option<Vec<Capability>>> = HashMap::new();
+    let mut seen = HashSet::new();
+
+    for (index, record) in reader.deserialize::<ImportRow>().enumerate() {
+        let row = index as u64 + 2;
+        let mut fail = |message: String| errors.push(RowError { row, message });
+
+        let record = match record {
+            Ok(record) => record,
+            Err(err) => {
+                fail(format!("Couldn't read row: {}.", err));
+                continue;
+            }
+        };
+
+        let role_id = record
+            .role
+            .clone()
+            .filter(|role| !role.is_empty())
+            .unwrap_or_else(|| String::from(Role::User));
+        let capabilities = role_capabilities
+            .entry(role_id.clone())
+            .or_insert_with(|| roles::utils::capabilities_of(conn, account, &role_id).ok());
+        match capabilities {
+            None => {
+                fail(format!("Unknown role '{}'.", role_id));
+                continue;
+            }
+            Some(capabilities) if !outranks(req_user, capabilities) => {
+                fail(format!("Cannot give users the role '{}'.", role_id));
+                continue;
+            }
+            _ => {}
+        }
+
+        let (create_perms, update_perms, delete_perms) = match (
+            perms(record.create_perms),
+            perms(record.update_perms),
+            perms(record.delete_perms),
+        ) {
+            (Ok(create), Ok(update), Ok(delete)) => (create, update, delete),
+            (Err(message), _, _) | (_, Err(message), _) | (_, _, Err(message)) => {
+                fail(message);
+                continue;
+            }
+        };
+
+        let user = NewUser {
+            id: String::new(),
+            account_id: account.into(),
+            username: record.username,
+            first_name: record.first_name,
+            last_name: record.last_name,
+            password: record.password,
+            active: record.active.unwrap_or(true),
+            instances: list(record.instances),
+            create_perms,
+            update_perms,
+            delete_perms,
+            role: roles::utils::legacy_role(&role_id),
+            notes: record.notes.filter(|notes| !notes.is_empty()),
+            failed_logins: 0,
+            locked_until: None,
+            email: record.email.filter(|email| !email.is_empty()),
+            role_id,
+        };
+
+        if let Err(invalid) = user.validate() {
+            let mut fields: Vec<_> = invalid.field_errors().into_keys().collect();
+            fields.sort_unstable();
+            fail(format!("Invalid {}.", fields.join(", ")));
+            continue;
+        }
+        if !seen.insert(user.username.clone()) {
+            fail(format!(
+                "Username '{}' is used more than once.",
+                user.username
+            ));
+            continue;
+        }
+
+        checked.push((row, user));
+    }
+
+    // usernames are unique across every account, deleted users included
+    let taken: HashSet<String> = {
+        use models::users::dsl::*;
+        users
+            .select(username)
+            .filter(username.eq_any(seen))
+            .load::<String>(conn)?
+            .into_iter()
+            .collect()
+    };
+
+    let mut to_import = vec![];
+    for (row, user) in checked {
+        if taken.contains(&user.username) {
+            errors.push(RowError {
+                row,
+                message: format!("Username '{}' is already taken.", user.username),
+            });
+        } else {
+            to_import.push(user);
+        }
+    }
+    errors.sort_by_key(|err| err.row);
+
+    Ok((to_import, errors))
+}
@@ -1,5 +1,8 @@
+pub mod import;
 pub mod model;
 pub mod routes;
 
+pub use crud_client::users::PasswordChange;
+
 #[cfg(test)]
 pub mod tests;
@@ -1,22 +1,97 @@
 use bcrypt::{hash, DEFAULT_COST};
+use chrono::{NaiveDateTime, Utc};
+use diesel::pg::Pg;
 use diesel::prelude::*;
-use models::{types::Role, Model, NewUser};
+use models::{types::Role, ListQuery, Model, NewUser, Page, SoftDelete};
 use models::{User, UpdateUser};
-use models::users::dsl::*;
+use models::users::{self, dsl::*};
 
-use crate::{api_error::ApiError, db};
+use crate::{
+    api_error::ApiError,
+    db,
+    list::{invalid_sort, sort_by},
+};
+
+fn filtered(account: &Option<String>, query: &ListQuery) -> users::BoxedQuery<'static, Pg> {
+    let mut filtered = users.filter(deleted_at.is_null()).into_boxed();
+
+    if let Some(account) = account {
+        filtered = filtered.filter(account_id.eq(account.clone()));
+    }
+    if let Some(after) = query.created_after {
+        filtered = filtered.filter(created_at.ge(after));
+    }
+    if let Some(before) = query.created_before {
+        filtered = filtered.filter(created_at.lt(before));
+    }
+    if let Some(target_role) = query.role.clone() {
+        filtered = filtered.filter(role.eq(target_role));
+    }
+    if let Some(target_active) = query.active {
+        filtered = filtered.filter(active.eq(target_active));
+    }
+
+    filtered
+}
 
 impl Model<String, NewUser, UpdateUser, ApiError> for User {
     fn find_all() -> Result<Vec<Self>, ApiError> {
         let conn = db::connection()?;
-        let result = users.load::<Self>(&conn)?;
+        let result = users.filter(deleted_at.is_null()).load::<Self>(&conn)?;
 
         Ok(result)
     }
 
+    fn find_page(account: Option<String>, query: ListQuery) -> Result<Page<Self>, ApiError> {
+        let conn = db::connection()?;
+        let total = filtered(&account, &query).count().get_result::<i64>(&conn)?;
+
+        let page = filtered(&account, &query);
+        let page = match query.sort.as_deref().unwrap_or("createdAt") {
+            "createdAt" => sort_by!(page, created_at, query),
+            "updatedAt" => sort_by!(page, updated_at, query),
+            "username" => sort_by!(page, username, query),
+            "firstName" => sort_by!(page, first_name, query),
+            "lastName" => sort_by!(page, last_name, query),
+            "role" => sort_by!(page, role, query),
+            field => return Err(invalid_sort(field)),
+        };
+        let result = page
+            .then_order_by(id.asc())
+            .limit(query.limit())
+            .offset(query.offset())
+            .load::<Self>(&conn)?;
+
+        Ok(Page::new(result, total, &query))
+    }
+
     fn find_by_id(target: String) -> Result<Self, ApiError> {
         let conn = db::connection()?;
-        let result = users.filter(id.eq(target)).get_result::<Self>(&conn)?;
+        let result = users
+            .filter(id.eq(target))
+            .filter(deleted_at.is_null())
+            .get_result::<Self>(&conn)?;
+
+        Ok(result)
+    }
+
+    fn find_all_in(account: String) -> Result<Vec<Self>, ApiError> {
+        let conn = db::connection()?;
+        let result = users
+            .filter(account_id.eq(account))
+            .filter(deleted_at.is_null())
+            .load::<Self>(&conn)?;
+
+        Ok(result)
+    }
+
+    fn find_in(account: String, target: String) -> Result<Self, ApiError> {
+        let conn = db::connection()?;
+        let result = users
+            .filter(id.eq(target))
+            .filter(account_id.eq(account))
+            .filter(deleted_at.is_null())
+            .get_result::<Self>(&conn)?;
 
         Ok(result)
     }
@@ -27,6 +102,7 @@ impl Model<String, NewUser, UpdateUser, ApiError> for User {
             id: nanoid!(10),
             password: hash(new.password, DEFAULT_COST)?,
             role: Role::User,
+            role_id: Role::User.into(),
             ..new
         };
         let result = diesel::insert_into(users)
@@ -39,6 +115,7 @@ impl Model<String, NewUser, UpdateUser, ApiError> for User {
     fn update(target: String, new_vals: UpdateUser) -> Result<Self, ApiError> {
         let conn = db::connection()?;
         let result = diesel::update(users.filter(id.eq(target)))
+            .filter(deleted_at.is_null())
             .set(new_vals)
             .get_result(&conn)?;
 
@@ -47,7 +124,41 @@ impl Model<String, NewUser, UpdateUser, ApiError> for User {
 
     fn delete(target: String) -> Result<usize, ApiError> {
         let conn = db::connection()?;
-        let result = diesel::delete(users.filter(id.eq(target))).execute(&conn)?;
+        let result = diesel::update(users.filter(id.eq(target)))
+            .filter(deleted_at.is_null())
+            .set(deleted_at.eq(Utc::now().naive_utc()))
+            .execute(&conn)?;
+
+        Ok(result)
+    }
+}
+
+impl SoftDelete<String, ApiError> for User {
+    fn find_deleted(account: Option<String>, target: String) -> Result<Self, ApiError> {
+        let conn = db::connection()?;
+        let mut query = users
+            .filter(id.eq(target))
+            .filter(deleted_at.is_not_null())
+            .into_boxed();
+        if let Some(account) = account {
+            query = query.filter(account_id.eq(account));
+        }
+
+        Ok(query.get_result::<Self>(&conn)?)
+    }
+
+    fn restore(target: String) -> Result<Self, ApiError> {
+        let conn = db::connection()?;
+        let result = diesel::update(users.filter(id.eq(target)))
+            .set(deleted_at.eq(None::<NaiveDateTime>))
+            .get_result(&conn)?;
+
+        Ok(result)
+    }
+
+    fn purge(cutoff: NaiveDateTime) -> Result<usize, ApiError> {
+        let conn = db::connection()?;
+        let result = diesel::delete(users.filter(deleted_at.lt(cutoff))).execute(&conn)?;
 
         Ok(result)
     }
@@ -1,79 +1,91 @@
-use actix_web::{delete, get, post, put, web, HttpResponse};
-use auth::{belongs_to_account, higher_role, require_role, ReqUser};
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse};
+use auth::{account_scope, belongs_to_account, outranks, require_cap, ReqUser};
 use bcrypt::hash;
+use chrono::{NaiveDateTime, Utc};
 use diesel::prelude::*;
-use models::types::Role;
-use models::{Account, Model, NewUser, UpdateUser, User, Validate};
-
-use crate::update_usage;
-use crate::{api_error::ApiError, db, json::DeleteBody};
-
+use models::types::{Capability, Role};
+use models::{
+    Account, ListQuery, Model, NewUser, SoftDelete, UpdateUser, User, Validate, ValidationErrors,
+};
+
+use super::import::{ImportQuery, ImportReport};
+use super::PasswordChange;
+use crate::audit::{self, utils::audited, Actor, Change};
+use crate::auth::{session_id, sessions};
+use crate::{roles, update_usage};
+use crate::{api_error::{ApiError, ErrorCode}, db, json::DeleteBody};
+
+#[utoipa::path(tag = "users", params(ListQuery), responses((status = 200, body = UserPage)))]
 #[get("/users")]
-async fn find_all(req_user: Option<ReqUser>) -> Result<HttpResponse, ApiError> {
-    let users = web::block(User::find_all).await??;
-
-    let users = if let Some(req_user) = req_user {
-        // if filter func returns true item will be allowed into the iterator
-        // so if the account ids match or if the user is admin of site they will see the user
-        users
-            .into_iter()
-            .filter(|x| x.account_id == req_user.account_id || req_user.account_id == "admin")
-            .collect()
-    } else {
-        users
-    };
+async fn find_all(
+    query: web::Query<ListQuery>,
+    req_user: Option<ReqUser>,
+) -> Result<HttpResponse, ApiError> {
+    // users outside of the admin account will only see users in their account
+    let account = account_scope(&req_user);
+    let users = web::block(move || User::find_page(account, query.into_inner())).await??;
 
     Ok(HttpResponse::Ok().json(users))
 }
 
+#[utoipa::path(tag = "users", responses((status = 200, body = User)))]
 #[get("/users/{id}")]
 async fn find(id: web::Path<String>, req_user: Option<ReqUser>) -> Result<HttpResponse, ApiError> {
-    let user = web::block(move || User::find_by_id(id.into_inner())).await??;
-
-    if !belongs_to_account(&req_user, &user.account_id) {
-        return Err(ApiError::forbidden());
-    }
+    let account = account_scope(&req_user);
+    let user = web::block(move || User::find_scoped(account, id.into_inner())).await??;
 
     Ok(HttpResponse::Ok().json(user))
 }
 
+#[utoipa::path(tag = "users", responses((status = 200, body = User)))]
 #[post("/users")]
 async fn create(
     new_user: web::Json<NewUser>,
     req_user: Option<ReqUser>,
+    actor: Actor,
 ) -> Result<HttpResponse, ApiError> {
     if !belongs_to_account(&req_user, &new_user.account_id)
-        || !require_role(&req_user, Role::Moderator)
+        || !require_cap(&req_user, Capability::ManageUsers)
     {
         return Err(ApiError::forbidden());
     }
     use models::users::dsl::*;
 
     let new_user = new_user.into_inner();
+    // If req came from user, use their account id instead of whatever they set
+    let new_account = match &req_user {
+        Some(req_user) => req_user.account_id.clone(),
+        None => new_user.account_id.clone(),
+    };
+    // new users are plain users unless given a role
+    let new_role = if new_user.role_id.is_empty() {
+        String::from(Role::User)
+    } else {
+        new_user.role_id.clone()
+    };
 
-    // must have higher role than user you are trying to create
-    if !higher_role(&req_user, new_user.role.clone()) {
+    // must outrank the role you are giving the new user
+    let (role_account, role_target) = (new_account.clone(), new_role.clone());
+    let new_capabilities = web::block(move || {
+        let conn = db::connection()?;
+        roles::utils::capabilities_of(&conn, &role_account, &role_target)
+    })
+    .await??;
+    if !outranks(&req_user, &new_capabilities) {
         return Err(ApiError::forbidden());
     }
 
     new_user.validate()?;
     let hashed_pass = web::block(move || hash(new_user.password, bcrypt::DEFAULT_COST)).await??;
-    // If req came from user, use their account id instead of whatever they set
-    let with_hash = if let Some(req_account_id) = req_user.map(|req_user| req_user.account_id) {
-        NewUser {
-            id: nanoid!(10),
-            password: hashed_pass,
-            role: Role::User,
-            account_id: req_account_id,
-            ..new_user
-        }
-    } else {
-        NewUser {
-            id: nanoid!(10),
-            password: hashed_pass,
-            role: Role::User,
-            ..new_user
-        }
+    let with_hash = NewUser {
+        id: nanoid!(10),
+        password: hashed_pass,
+        role: roles::utils::legacy_role(&new_role),
+        role_id: new_role,
+        account_id: new_account,
+        failed_logins: 0,
+        locked_until: None,
+        ..new_user
     };
 
     let owner_id = with_hash.account_id.clone();
@@ -109,12 +121,16 @@ async fn create(
                         let num_user = users
                             .count()
                             .filter(account_id.eq(owner.id.clone()))
+                            .filter(deleted_at.is_null())
                             .get_result::<i64>(&conn)?;
 
                         let res = update_usage(&owner, "users".into(), num_user)?;
 
                         println!("{:?}", res);
 
+                        let change = Change::new("create", "user", &user.id).after(&user);
+                        audit::utils::record(&conn, &actor, &owner.id, change)?;
+
                         match res.error_for_status() {
                             Ok(_) => Ok(user),
                             Err(_) => Err(ApiError::new(
@@ -128,7 +144,8 @@ async fn create(
                 Err(err) => {
                     if err.status_code == 409 {
                         // there was a conflict only possibility is username
-                        Err(ApiError::new(409, "Username must be unique.".into()))
+                        Err(ApiError::new(409, "Username must be unique.".into())
+                            .with_code(ErrorCode::AlreadyExists))
                     } else {
                         Err(err)
                     }
@@ -144,38 +161,177 @@ async fn create(
     }
 }
 
+/// Creates every user in a csv at once, only checking the rows when dryRun is set
+///
+/// Nothing is created unless every row is valid, and usage is updated once for the whole file
+#[utoipa::path(
+    tag = "users",
+    params(ImportQuery),
+    request_body(content = String, content_type = "text/csv"),
+    responses(
+        (status = 200, body = ImportReport),
+        (
+            status = 400,
+            description = "Some rows are invalid, nothing was imported",
+            body = ImportReport,
+        ),
+    ),
+)]
+#[post("/accounts/{id}/users/import")]
+async fn import(
+    id: web::Path<String>,
+    query: web::Query<ImportQuery>,
+    csv: web::Bytes,
+    req_user: Option<ReqUser>,
+    actor: Actor,
+) -> Result<HttpResponse, ApiError> {
+    if !belongs_to_account(&req_user, &id) || !require_cap(&req_user, Capability::ManageUsers) {
+        return Err(ApiError::forbidden());
+    }
+
+    let (target, checker) = (id.into_inner(), req_user.clone());
+    let (owner, to_import, errors) = web::block(move || {
+        let conn = db::connection()?;
+        let owner = {
+            use models::accounts::dsl::*;
+            accounts
+                .filter(id.eq(&target))
+                .filter(deleted_at.is_null())
+                .first::<Account>(&conn)?
+        };
+        let (to_import, errors) = super::import::check(&conn, &owner.id, &checker, &csv)?;
+
+        Ok::<_, ApiError>((owner, to_import, errors))
+    })
+    .await??;
+
+    let mut report = ImportReport {
+        dry_run: query.dry_run,
+        rows: to_import.len() + errors.len(),
+        imported: 0,
+        errors,
+    };
+    if report.rows == 0 {
+        return Err(ApiError::new(400, "There are no users to import.".into()));
+    }
+    if report.dry_run {
+        return Ok(HttpResponse::Ok().json(report));
+    }
+    if !report.errors.is_empty() {
+        return Ok(HttpResponse::BadRequest().json(report));
+    }
+    if owner.sub_id.is_none() {
+        return Err(ApiError::not_subbed());
+    }
+
+    let hashed = web::block(move || {
+        to_import
+            .into_iter()
+            .map(|user| {
+                Ok(NewUser {
+                    id: nanoid!(10),
+                    password: hash(&user.password, bcrypt::DEFAULT_COST)?,
+                    ..user
+                })
+            })
+            .collect::<Result<Vec<NewUser>, ApiError>>()
+    })
+    .await??;
+
+    let imported = web::block(move || {
+        use models::users::dsl::*;
+        let conn = db::connection()?;
+
+        conn.transaction(|| {
+            let created = diesel::insert_into(users)
+                .values(&hashed)
+                .get_results::<User>(&conn)
+                .map_err(|err| match ApiError::from(err) {
+                    // someone took a username since the rows were checked
+                    err if err.status_code == 409 => {
+                        ApiError::new(409, "Username must be unique.".into())
+                            .with_code(ErrorCode::AlreadyExists)
+                    }
+                    err => err,
+                })?;
+
+            for user in &created {
+                let change = Change::new("create", "user", &user.id).after(user);
+                audit::utils::record(&conn, &actor, &owner.id, change)?;
+            }
+
+            let num_user = users
+                .count()
+                .filter(account_id.eq(&owner.id))
+                .filter(deleted_at.is_null())
+                .get_result::<i64>(&conn)?;
+            // err variant causes rollback
+            match update_usage(&owner, "users".into(), num_user)?.error_for_status() {
+                Ok(_) => Ok(created.len()),
+                Err(_) => Err(ApiError::new(
+                    500,
+                    "Failed to update user subscription with Stripe.".into(),
+                )),
+            }
+        })
+    })
+    .await??;
+    report.imported = imported;
+
+    Ok(HttpResponse::Ok().json(report))
+}
+
+#[utoipa::path(tag = "users", responses((status = 200, body = User)))]
 #[put("/users/{id}")]
 async fn update(
     id: web::Path<String>,
     user: web::Json<UpdateUser>,
     req_user: Option<ReqUser>,
+    actor: Actor,
 ) -> Result<HttpResponse, ApiError> {
     let id = id.into_inner();
     let updated_id = id.clone();
-    let to_be_updated = web::block(move || User::find_by_id(updated_id)).await??;
-    if !belongs_to_account(&req_user, &to_be_updated.account_id)
-        || !require_role(&req_user, Role::Moderator)
-    {
+    let account = account_scope(&req_user);
+    let to_be_updated = web::block(move || User::find_scoped(account, updated_id)).await??;
+    if !require_cap(&req_user, Capability::ManageUsers) {
         return Err(ApiError::forbidden());
     }
 
+    // validate before hashing so the password policy sees the real password
+    user.validate()?;
     let hashing_pass = user.password.clone();
     // if password is being updated, hash it
     let hashed_pass = web::block(move || {
         hashing_pass.map(|password| hash(password, bcrypt::DEFAULT_COST).unwrap())
     })
     .await?;
-    // TODO log user out when updated
-    let update_set: UpdateUser = UpdateUser {
+    let mut update_set: UpdateUser = UpdateUser {
         account_id: None,
         password: hashed_pass,
+        // only login can change these
+        failed_logins: None,
+        locked_until: None,
         ..user.into_inner()
     };
 
-    // must have higher role than what you want to update to
-    // except owner can change anyone, including themselves
-    if let Some(new_role) = update_set.role.clone() {
-        if !require_role(&req_user, Role::Owner) && !higher_role(&req_user, new_role.clone()) {
+    // role id wins, legacy clients only send role
+    let new_role = update_set
+        .role_id
+        .clone()
+        .or_else(|| update_set.role.clone().map(String::from));
+    if let Some(new_role) = new_role {
+        let (role_account, role_target) = (to_be_updated.account_id.clone(), new_role.clone());
+        let new_capabilities = web::block(move || {
+            let conn = db::connection()?;
+            roles::utils::capabilities_of(&conn, &role_account, &role_target)
+        })
+        .await??;
+
+        // must outrank the role you want to update to
+        // except owner can change anyone, including themselves
+        if !require_cap(&req_user, Capability::TransferOwnership)
+            && !outranks(&req_user, &new_capabilities)
+        {
             return Err(ApiError::forbidden());
         }
         // user cannot change their own role
@@ -184,27 +340,101 @@ async fn update(
                 return Err(ApiError::forbidden());
             }
         }
+
+        update_set.role = Some(roles::utils::legacy_role(&new_role));
+        update_set.role_id = Some(new_role);
     }
 
-    update_set.validate()?;
+    // changing any of these should make the user log in again
+    let end_sessions = update_set.password.is_some()
+        || update_set.role.is_some()
+        || update_set.active.is_some();
+
+    let user = web::block(move || {
+        audited(&actor, &to_be_updated.account_id, |conn| {
+            let user = diesel::update(models::users::table.find(&id))
+                .set(update_set)
+                .get_result::<User>(conn)?;
+            if end_sessions {
+                sessions::revoke_all(conn, &id)?;
+            }
+            let change = Change::new("update", "user", &id)
+                .before(&to_be_updated)
+                .after(&user);
 
-    let user = web::block(move || User::update(id, update_set)).await??;
+            Ok((user, change))
+        })
+    })
+    .await??;
 
     Ok(HttpResponse::Ok().json(user))
 }
 
+/// Lets users change their own password, logs them out of every other session
+#[utoipa::path(tag = "users", responses((status = 200)))]
+#[put("/users/me/password")]
+async fn change_password(
+    change: web::Json<PasswordChange>,
+    req_user: Option<ReqUser>,
+    req: HttpRequest,
+    actor: Actor,
+) -> Result<HttpResponse, ApiError> {
+    // only users have a "me"
+    let req_user = req_user.ok_or_else(ApiError::forbidden)?;
+    let change = change.into_inner();
+
+    if let Err(err) = models::validate_password(&change.new_password) {
+        let mut errors = ValidationErrors::new();
+        errors.add("newPassword", err);
+        return Err(errors.into());
+    }
+
+    let account = req_user.account_id.clone();
+    let user = web::block(move || User::find_in(account, req_user.id)).await??;
+
+    let current_hash = user.password.clone();
+    let matches = web::block(move || bcrypt::verify(change.current_password, &current_hash))
+        .await??;
+    if !matches {
+        return Err(ApiError::new(403, "Current password is incorrect.".into()));
+    }
+
+    let hashed_pass =
+        web::block(move || hash(change.new_password, bcrypt::DEFAULT_COST)).await??;
+    let current_session = session_id(&req);
+
+    web::block(move || {
+        use models::users::dsl::*;
+        audited(&actor, &user.account_id, |conn| {
+            diesel::update(users.filter(id.eq(&user.id)))
+                .set(password.eq(hashed_pass))
+                .execute(conn)?;
+
+            match current_session {
+                Some(current_session) => sessions::revoke_others(conn, &user.id, &current_session)?,
+                None => sessions::revoke_all(conn, &user.id)?,
+            };
+
+            Ok(((), Change::new("change_password", "user", &user.id)))
+        })
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[utoipa::path(tag = "users", responses((status = 200, body = DeleteBody)))]
 #[delete("/users/{id}")]
 async fn delete(
     target: web::Path<String>,
     req_user: Option<ReqUser>,
+    actor: Actor,
 ) -> Result<HttpResponse, ApiError> {
     let target = target.into_inner();
 
     let deleted_id = target.clone();
-    let user = web::block(move || User::find_by_id(deleted_id)).await??;
-    if !belongs_to_account(&req_user, &user.account_id) {
-        return Err(ApiError::forbidden());
-    }
+    let account = account_scope(&req_user);
+    let user = web::block(move || User::find_scoped(account, deleted_id)).await??;
     use models::users::dsl::*;
 
     let owner_id = user.account_id.clone();
@@ -223,7 +453,13 @@ async fn delete(
 
     let result: Result<usize, ApiError> = web::block(move || {
         conn.transaction(|| {
-            let affected = diesel::delete(users.filter(id.eq(target))).execute(&conn)?;
+            // hidden until purged, they can't log in meanwhile
+            let affected = diesel::update(users.filter(id.eq(&target)))
+                .set(deleted_at.eq(Utc::now().naive_utc()))
+                .execute(&conn)?;
+            sessions::revoke_all(&conn, &target)?;
+            let change = Change::new("delete", "user", &target).before(&user);
+            audit::utils::record(&conn, &actor, &user.account_id, change)?;
 
             match owner {
                 Ok(owner) => {
@@ -235,6 +471,7 @@ async fn delete(
                     let num_user = users
                         .count()
                         .filter(account_id.eq(owner.id.clone()))
+                        .filter(deleted_at.is_null())
                         .get_result::<i64>(&conn)?;
 
                     let res = update_usage(&owner, "users".into(), num_user)?;
@@ -259,17 +496,80 @@ async fn delete(
     }
 }
 
+/// Brings back a deleted user, they count towards the subscription again
+#[utoipa::path(tag = "users", responses((status = 200, body = User)))]
+#[post("/users/{id}/restore")]
+async fn restore(
+    target: web::Path<String>,
+    req_user: Option<ReqUser>,
+    actor: Actor,
+) -> Result<HttpResponse, ApiError> {
+    let account = account_scope(&req_user);
+    let user = web::block(move || User::find_deleted(account, target.into_inner())).await??;
+    if !require_cap(&req_user, Capability::RestoreDeleted) {
+        return Err(ApiError::forbidden());
+    }
+    use models::users::dsl::*;
+
+    let owner_id = user.account_id.clone();
+    // must get it manually so tests can pass when we have only 1 connection
+    let owner = web::block(move || Account::find_by_id(owner_id)).await??;
+    if owner.sub_id.is_none() {
+        return Err(ApiError::not_subbed());
+    }
+
+    let conn = db::connection()?;
+    let restored = web::block(move || {
+        conn.transaction::<_, ApiError, _>(|| {
+            let restored = diesel::update(users.filter(id.eq(&user.id)))
+                .set(deleted_at.eq(None::<NaiveDateTime>))
+                .get_result::<User>(&conn)?;
+            let change = Change::new("restore", "user", &user.id)
+                .before(&user)
+                .after(&restored);
+            audit::utils::record(&conn, &actor, &owner.id, change)?;
+
+            let num_user = users
+                .count()
+                .filter(account_id.eq(owner.id.clone()))
+                .filter(deleted_at.is_null())
+                .get_result::<i64>(&conn)?;
+            let res = update_usage(&owner, "users".into(), num_user)?;
+            if res.error_for_status().is_err() {
+                // err variant causes rollback
+                return Err(ApiError::new(
+                    500,
+                    "Failed to update user subscription with Stripe.".into(),
+                ));
+            }
+
+            Ok(restored)
+        })
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(restored))
+}
+
+#[utoipa::path(tag = "users", responses((status = 200, body = User)))]
 #[put("/users/{id}/toggle-status")]
 async fn toggle_status(
     id: web::Path<String>,
     req_user: Option<ReqUser>,
+    actor: Actor,
 ) -> Result<HttpResponse, ApiError> {
     let id = id.into_inner();
     let updated_id = id.clone();
-    let to_be_updated = web::block(move || User::find_by_id(updated_id)).await??;
-    if !belongs_to_account(&req_user, &to_be_updated.account_id)
-        || !higher_role(&req_user, to_be_updated.role)
-    {
+    let account = account_scope(&req_user);
+    let to_be_updated = web::block(move || User::find_scoped(account, updated_id)).await??;
+    let role_account = to_be_updated.account_id.clone();
+    let role_target = to_be_updated.role_id.clone();
+    let capabilities = web::block(move || {
+        let conn = db::connection()?;
+        roles::utils::capabilities_of(&conn, &role_account, &role_target)
+    })
+    .await??;
+    if !outranks(&req_user, &capabilities) {
         return Err(ApiError::forbidden());
     }
 
@@ -279,48 +579,69 @@ async fn toggle_status(
     };
     update_set.validate()?;
 
-    let user = web::block(move || User::update(id, update_set)).await??;
+    let user = web::block(move || {
+        audited(&actor, &to_be_updated.account_id, |conn| {
+            let user = diesel::update(models::users::table.find(&id))
+                .set(update_set)
+                .get_result::<User>(conn)?;
+            // log them out when deactivated, harmless when activated
+            sessions::revoke_all(conn, &id)?;
+            let change = Change::new("toggle_status", "user", &id)
+                .before(&to_be_updated)
+                .after(&user);
+
+            Ok((user, change))
+        })
+    })
+    .await??;
 
     Ok(HttpResponse::Ok().json(user))
 }
 
+#[utoipa::path(tag = "users", responses((status = 200)))]
 #[put("/users/{id}/transfer-owner")]
 async fn transfer_owner(
     target: web::Path<String>,
     req_user: Option<ReqUser>,
+    actor: Actor,
 ) -> Result<HttpResponse, ApiError> {
     let target = target.into_inner();
     let updated_id = target.clone();
-    let to_be_updated = web::block(move || User::find_by_id(updated_id)).await??;
-    if !belongs_to_account(&req_user, &to_be_updated.account_id)
-        || !require_role(&req_user, Role::Owner)
-    {
+    let account = account_scope(&req_user);
+    let new_owner = web::block(move || User::find_scoped(account, updated_id)).await??;
+    if !require_cap(&req_user, Capability::TransferOwnership) {
         return Err(ApiError::forbidden());
     }
 
-    web::block::<_, Result<(), ApiError>>(move || {
+    web::block(move || {
         use models::users::dsl::*;
-        let conn = db::connection()?;
         // update both users in transaction
-        conn.transaction::<(), ApiError, _>(|| {
-            // TODO log both users out
-            diesel::update(users.filter(id.eq(target)))
+        audited(&actor, &new_owner.account_id, |conn| {
+            diesel::update(users.filter(id.eq(&target)))
                 .set(UpdateUser {
                     role: Some(Role::Owner),
+                    role_id: Some(Role::Owner.into()),
                     ..Default::default()
                 })
-                .execute(&conn)?;
+                .execute(conn)?;
+            // roles changed, so both users must log in again
+            sessions::revoke_all(conn, &target)?;
 
-            if let Some(req_user) = req_user {
-                diesel::update(users.filter(id.eq(req_user.id)))
+            if let Some(req_user) = &req_user {
+                diesel::update(users.filter(id.eq(&req_user.id)))
                     .set(UpdateUser {
                         role: Some(Role::Admin),
+                        role_id: Some(Role::Admin.into()),
                         ..Default::default()
                     })
-                    .execute(&conn)?;
+                    .execute(conn)?;
+                sessions::revoke_all(conn, &req_user.id)?;
             }
 
-            Ok(())
+            let change = Change::new("transfer_owner", "user", &target)
+                .before(&serde_json::json!({ "roleId": new_owner.role_id }))
+                .after(&serde_json::json!({ "roleId": "owner" }));
+            Ok(((), change))
         })
     })
     .await??;
@@ -332,8 +653,11 @@ pub fn init_routes(config: &mut web::ServiceConfig) {
     config.service(find_all);
     config.service(find);
     config.service(create);
+    config.service(import);
+    config.service(change_password);
     config.service(update);
     config.service(delete);
+    config.service(restore);
     config.service(toggle_status);
     config.service(transfer_owner);
 }
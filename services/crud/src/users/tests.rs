@@ -1,6 +1,7 @@
-use models::{User, NewUser, users::dsl::*, UpdateUser};
-use crate::{db, json::DeleteBody, tests};
-use actix_web::test;
+use models::{User, NewUser, users::dsl::*, Page, UpdateUser};
+use crate::{auth::sessions, db, json::DeleteBody, tests};
+use actix_http::StatusCode;
+use actix_web::{cookie::Cookie, test};
 use diesel::prelude::*;
 use models::types::Role;
 
@@ -19,7 +20,7 @@ pub fn defaults(test_name: &str) -> (NewUser, NewUser) {
             username: test_name.into(),
             first_name: "Test".into(),
             last_name: "User".into(),
-            password: "pretend this is hashed".into(),
+            password: "Pretend this is hashed1".into(),
             active: true,
             instances: vec!["hatfield".into()],
             create_perms: vec!["load".into()],
@@ -27,6 +28,10 @@ pub fn defaults(test_name: &str) -> (NewUser, NewUser) {
             delete_perms: vec!["load".into()],
             role: Role::Admin,
             notes: None,
+            failed_logins: 0,
+            locked_until: None,
+            email: None,
+            role_id: Role::Admin.into(),
         },
         NewUser {
             id: "2".to_string() + test_name,
@@ -34,7 +39,7 @@ pub fn defaults(test_name: &str) -> (NewUser, NewUser) {
             username: format!("{}2", test_name),
             first_name: "Test2".into(),
             last_name: "User2".into(),
-            password: "pretend this is hashed2".into(),
+            password: "Pretend this is hashed2".into(),
             active: false,
             instances: vec!["log gh".into()],
             create_perms: vec!["carrier".into()],
@@ -42,10 +47,28 @@ pub fn defaults(test_name: &str) -> (NewUser, NewUser) {
             delete_perms: vec!["carrier".into()],
             role: Role::User,
             notes: Some("good employee".into()),
+            failed_logins: 0,
+            locked_until: None,
+            email: None,
+            role_id: Role::User.into(),
         },
     )
 }
 
+/// Request user header for someone in the test account with role
+fn as_role(test_name: &str, user_role: Role) -> String {
+    serde_json::to_string(&auth::ReqUser {
+        id: test_name.into(),
+        account_id: "test".into(),
+        role: user_role,
+        create_perms: vec![],
+        update_perms: vec![],
+        delete_perms: vec![],
+        capabilities: None,
+    })
+    .unwrap()
+}
+
 pub fn remove(target: String, conn: &db::PoolConn) {
     diesel::delete(users.filter(id.eq(target)))
         .execute(conn)
@@ -79,13 +102,56 @@ async fn get() {
 
     compare(&resp, &default1);
 
+    // users from other accounts shouldn't be able to find it
+    let req = test::TestRequest::get()
+        .uri(&format!("/users/{}", result1.id))
+        .insert_header((
+            "user",
+            serde_json::to_string(&auth::ReqUser {
+                id: "someone".into(),
+                account_id: "another-account".into(),
+                role: Role::Owner,
+                create_perms: vec![],
+                update_perms: vec![],
+                delete_perms: vec![],
+                capabilities: None,
+            })
+            .unwrap(),
+        ))
+        .to_request();
+
+    let resp = test::call_service(&app, req).await;
+
+    assert_eq!(resp.status(), actix_http::StatusCode::NOT_FOUND);
+
     // Check for both inserted records with the get all route
     let req = test::TestRequest::get().uri("/users").to_request();
 
-    let resp: Vec<User> = test::call_and_read_body_json(&app, req).await;
+    let resp: Page<User> = test::call_and_read_body_json(&app, req).await;
+
+    assert!(resp.items.len() >= 2);
+
+    // filter down to the inactive user, one per page
+    let req = test::TestRequest::get()
+        .uri("/users?active=false&limit=1&sort=username&order=desc")
+        .to_request();
+
+    let resp: Page<User> = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(resp.items.len(), 1);
+    assert!(resp.items.iter().all(|x| !x.active));
+    assert_eq!(resp.next_cursor.is_some(), resp.total > 1);
+
+    // unknown fields can't be sorted by
+    let req = test::TestRequest::get()
+        .uri("/users?sort=password")
+        .to_request();
+
+    let resp = test::call_service(&app, req).await;
+
     let conn = db::connection().unwrap();
 
-    assert!(resp.len() >= 2);
+    assert_eq!(resp.status(), actix_http::StatusCode::BAD_REQUEST);
 
     remove(result1.id, &conn);
     remove(result2.id, &conn);
@@ -184,6 +250,51 @@ async fn delete() {
 
     assert_eq!(resp.affected, 1);
 
+    // hidden until restored or purged
+    let deleted: User = users
+        .filter(id.eq(&result1.id))
+        .get_result(&conn)
+        .expect("deleted user was removed");
+    assert!(deleted.deleted_at.is_some());
+    drop(conn);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/users/{}", result1.id))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    // only owners can restore
+    let restore = |restorer: Role| {
+        test::TestRequest::post()
+            .uri(&format!("/users/{}/restore", result1.id))
+            .insert_header(("user", as_role("users-delete", restorer)))
+            .to_request()
+    };
+    let resp = test::call_service(&app, restore(Role::Admin)).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    let restored: User = test::call_and_read_body_json(&app, restore(Role::Owner)).await;
+    assert_eq!(restored.deleted_at, None);
+
+    let req = test::TestRequest::delete()
+        .uri(&format!("/users/{}", result1.id))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    // purged once it's been deleted longer than the retention period, backdated so
+    // rows other tests deleted aren't purged under them
+    let long_ago = chrono::NaiveDate::from_ymd(2000, 1, 1).and_hms(0, 0, 0);
+    let conn = db::connection().unwrap();
+    diesel::update(users.find(&result1.id))
+        .set(deleted_at.eq(long_ago))
+        .execute(&conn)
+        .unwrap();
+    drop(conn);
+    crate::purge::purge(long_ago + chrono::Duration::seconds(1)).unwrap();
+
+    let conn = db::connection().unwrap();
     let result1: Option<User> = users
         .filter(id.eq(result1.id))
         .get_result(&conn)
@@ -192,3 +303,149 @@ async fn delete() {
 
     assert!(result1 == None);
 }
+
+#[actix_web::test]
+async fn change_password() {
+    let (default1, _default2) = defaults("users-change-password");
+
+    let app = tests::init(super::routes::init_routes).await;
+    let conn = db::connection().unwrap();
+
+    let result1: User = diesel::insert_into(users)
+        .values(NewUser {
+            password: bcrypt::hash(default1.password.clone(), bcrypt::DEFAULT_COST).unwrap(),
+            ..default1.clone()
+        })
+        .get_result::<User>(&conn)
+        .expect("couldn't insert");
+    let (current, _) = sessions::create(&conn, result1.id.clone(), result1.account_id.clone())
+        .expect("couldn't create session");
+    let (other, _) = sessions::create(&conn, result1.id.clone(), result1.account_id.clone())
+        .expect("couldn't create session");
+
+    drop(conn);
+    let change = |current_password: &str, new_password: &str| {
+        test::TestRequest::put()
+            .uri("/users/me/password")
+            .insert_header((
+                "user",
+                serde_json::to_string(&auth::ReqUser {
+                    id: result1.id.clone(),
+                    account_id: result1.account_id.clone(),
+                    role: Role::Admin,
+                    create_perms: vec![],
+                    update_perms: vec![],
+                    delete_perms: vec![],
+                    capabilities: None,
+                })
+                .unwrap(),
+            ))
+            .cookie(Cookie::new(
+                "at",
+                crate::auth::sign(result1.id.clone(), result1.account_id.clone(), current.id.clone())
+                    .unwrap(),
+            ))
+            .set_json(serde_json::json!({
+                "currentPassword": current_password,
+                "newPassword": new_password,
+            }))
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, change("wrong", "Brand new passw0rd")).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    // has to follow the password policy
+    let resp = test::call_service(&app, change(&default1.password, "short")).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let resp = test::call_service(&app, change(&default1.password, "Brand new passw0rd")).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let conn = db::connection().unwrap();
+    let changed: User = users.find(&result1.id).get_result(&conn).unwrap();
+    assert!(bcrypt::verify("Brand new passw0rd", &changed.password).unwrap());
+
+    // only the session that made the change survives
+    assert!(sessions::find_active(&conn, &current.id).unwrap().is_some());
+    assert!(sessions::find_active(&conn, &other.id).unwrap().is_none());
+
+    remove(result1.id, &conn);
+}
+
+#[actix_web::test]
+async fn import() {
+    actix_web::rt::spawn(crate::tests::mock_payments());
+    let app = tests::init(super::routes::init_routes).await;
+
+    let import = |dry_run: bool, csv: &str, user_role: Role| {
+        test::TestRequest::post()
+            .uri(&format!("/accounts/test/users/import?dryRun={}", dry_run))
+            .insert_header(("user", as_role("users-import", user_role)))
+            .insert_header(("Content-Type", "text/csv"))
+            .set_payload(csv.to_string())
+            .to_request()
+    };
+    let header = "username,first_name,last_name,password,email,role,instances,create_perms";
+    let valid = [
+        "users-import1,Test,User,Dispatch passw0rd,one@example.com,,hatfield;logh,load;carrier",
+        "users-import2,Test,User2,Dispatch passw0rd,,admin,,",
+    ];
+
+    // every bad row is reported and nothing is created
+    let csv = [
+        header,
+        valid[0],
+        "users-import3,Test,User3,short,,,,",
+        valid[0],
+        "users-import4,Test,User4,Dispatch passw0rd,,,,trucks",
+    ]
+    .join("\n");
+    let report: super::import::ImportReport =
+        test::call_and_read_body_json(&app, import(true, &csv, Role::Owner)).await;
+    assert_eq!(report.rows, 4);
+    assert_eq!(report.imported, 0);
+    assert_eq!(
+        report.errors.iter().map(|err| err.row).collect::<Vec<_>>(),
+        vec![3, 4, 5]
+    );
+
+    let resp = test::call_service(&app, import(false, &csv, Role::Owner)).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    // admins can't hand out their own role
+    let csv = [header, valid[1]].join("\n");
+    let report: super::import::ImportReport =
+        test::call_and_read_body_json(&app, import(true, &csv, Role::Admin)).await;
+    assert_eq!(report.errors.len(), 1);
+
+    let conn = db::connection().unwrap();
+    let found = users
+        .filter(username.like("users-import%"))
+        .count()
+        .get_result::<i64>(&conn)
+        .unwrap();
+    assert_eq!(found, 0);
+    drop(conn);
+
+    let csv = [header, valid[0], valid[1]].join("\n");
+    let report: super::import::ImportReport =
+        test::call_and_read_body_json(&app, import(false, &csv, Role::Owner)).await;
+    assert_eq!(report.imported, 2);
+    assert!(report.errors.is_empty());
+
+    let conn = db::connection().unwrap();
+    let imported: Vec<User> = users
+        .filter(username.like("users-import%"))
+        .order(username.asc())
+        .load(&conn)
+        .unwrap();
+    assert_eq!(imported.len(), 2);
+    assert_eq!(imported[0].instances, vec!["hatfield", "logh"]);
+    assert_eq!(imported[1].role, Role::Admin);
+    assert!(bcrypt::verify("Dispatch passw0rd", &imported[0].password).unwrap());
+
+    for user in imported {
+        remove(user.id, &conn);
+    }
+}
@@ -1,5 +1,6 @@
 import { useUser } from '@/utils/authUtils';
 import { Instance } from '@/types/Instance';
+import { Page } from '@/types/utils';
 import { api } from '@/utils/apiHelpers';
 import fetcher from '@/utils/swrFetcher';
 import { Group, Loader, MultiSelect, MultiSelectProps, Text } from '@mantine/core';
@@ -30,10 +31,11 @@ const Item = forwardRef<HTMLDivElement, ItemProps>(({ label, ...props }, ref) =>
 
 const InstanceSelect = forwardRef<HTMLInputElement, Props>(({ create, ...props }, ref) => {
 	const { user } = useUser();
-	const { data: instances } = useSWR<Instance[]>(
+	const { data: page } = useSWR<Page<Instance>>(
 		user ? api(`accounts/${user.accountId}/instances`) : null,
 		fetcher,
 	);
+	const instances = page?.items;
 	if (!user)
 		return (
 			<Group>
@@ -2,6 +2,7 @@ import { InstanceDisplay } from '@/components/InstanceDisplay';
 import { useUser } from '@/utils/authUtils';
 import { Instance } from '@/types/Instance';
 import { Role } from '@/types/User';
+import { Page } from '@/types/utils';
 import { api, ssrFetch } from '@/utils/apiHelpers';
 import { isAuthed, redirect, requireRole } from '@/utils/authUtils';
 import fetcher from '@/utils/swrFetcher';
@@ -13,17 +14,18 @@ import Link from 'next/link';
 import useSWR from 'swr';
 
 interface Props {
-	initialInstances: Instance[];
+	initialInstances: Page<Instance>;
 }
 
 const Instances = ({ initialInstances }: Props) => {
 	const { user } = useUser();
 	const { isSubbed } = useSubStatus();
-	const { data: instances, error } = useSWR<Instance[]>(
+	const { data: page, error } = useSWR<Page<Instance>>(
 		user ? api(`accounts/${user.accountId}/instances`) : null,
 		fetcher,
 		{ fallbackData: initialInstances },
 	);
+	const instances = page?.items;
 	const isAdmin = requireRole(user?.role, Role.Admin);
 
 	if (!instances || !user) return <Loader />;
@@ -61,7 +63,7 @@ export const getServerSideProps: GetServerSideProps<Props> = async (ctx) => {
 	if (user) {
 		const instancesRes = await ssrFetch(api(`accounts/${user.accountId}/instances`), ctx);
 		if (instancesRes.ok) {
-			const initialInstances: Instance[] = await instancesRes.json();
+			const initialInstances: Page<Instance> = await instancesRes.json();
 
 			return {
 				props: {
@@ -1,6 +1,7 @@
 import { UserDisplay } from '@/components/UserDisplay';
 import { useUser } from '@/utils/authUtils';
 import { Role, User } from '@/types/User';
+import { Page } from '@/types/utils';
 import { api, ssrFetch } from '@/utils/apiHelpers';
 import { isAuthed, redirect, requireRole } from '@/utils/authUtils';
 import fetcher from '@/utils/swrFetcher';
@@ -12,17 +13,18 @@ import Link from 'next/link';
 import useSWR from 'swr';
 
 interface Props {
-	initialUsers: User[];
+	initialUsers: Page<User>;
 }
 
 const Users = ({ initialUsers }: Props) => {
 	const { user } = useUser();
 	const { isSubbed } = useSubStatus();
-	const { data: users, error } = useSWR<User[]>(
+	const { data: page, error } = useSWR<Page<User>>(
 		user ? api(`accounts/${user.accountId}/users`) : null,
 		fetcher,
 		{ fallbackData: initialUsers },
 	);
+	const users = page?.items;
 	const isMod = requireRole(user?.role, Role.Moderator);
 
 	if (!users || !user) return <Loader />;
@@ -60,7 +62,7 @@ export const getServerSideProps: GetServerSideProps<Props> = async (ctx) => {
 	if (user) {
 		const res = await ssrFetch(api(`accounts/${user.accountId}/users`), ctx);
 		if (res.ok) {
-			const initialUsers: User[] = await res.json();
+			const initialUsers: Page<User> = await res.json();
 
 			return {
 				props: {
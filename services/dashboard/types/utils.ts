@@ -2,6 +2,15 @@ import { Account } from './Account';
 import { Instance } from './Instance';
 import { User } from './User';
 
+/** Envelope returned by list routes */
+export interface Page<T> {
+	items: T[];
+	/** Number of items matching the filters across all pages */
+	total: number;
+	/** Cursor for the next page, null if this is the last page */
+	nextCursor: number | null;
+}
+
 export type RegisterAccount = Omit<
 	Account,
 	'id' | 'createdAt' | 'updatedAt' | 'stripeId' | 'subId'
@@ -1,11 +1,12 @@
+mod openapi;
 mod services;
 
 #[macro_use(lazy_static)]
 extern crate lazy_static;
 
 use axum::http::{HeaderValue, Request, Response};
+use errors::{ApiError, ErrorCode};
 use hyper::{
-    body,
     client::HttpConnector,
     server::conn::AddrStream,
     service::{make_service_fn, service_fn},
@@ -80,8 +81,13 @@ async fn handle(
     mut req: Request<Body>,
 ) -> Result<Response<Body>, Infallible> {
     let path = req.uri().path().to_owned();
+    // only the gateway says who's asking, and internal services who a change was made for
+    req.headers_mut().remove("user");
+    req.headers_mut().remove(auth::ACTOR_HEADER);
 
-    if path.starts_with(payments::PATH_BASE) {
+    if path == "/openapi.json" {
+        Ok(openapi::spec(&client).await)
+    } else if path.starts_with(payments::PATH_BASE) {
         let path_query = service_path_query("/payments", &mut req, path);
 
         let uri = format!("http://127.0.0.1:6000{}", path_query);
@@ -89,18 +95,15 @@ async fn handle(
         *req.uri_mut() = Uri::try_from(uri).unwrap();
 
         // TODO make payments service
-        payments::proxy(client_ip, client, req, path_query).await
+        payments::proxy(client_ip, req, path_query).await
     } else if path.starts_with(crud::PATH_BASE) {
         // will forward requests to crud/auth service
-        crud::proxy(client_ip, client, req, path).await
+        crud::proxy(client_ip, req, path).await
     } else {
-        Ok(Response::builder()
-            .status(StatusCode::NOT_FOUND)
-            .body(Body::from(format!(
-                r#"{{"message": "uri: {} is not valid"}}"#,
-                path
-            )))
-            .unwrap())
+        Ok(error_response(ApiError::new(
+            404,
+            format!("uri: {} is not valid", path),
+        )))
     }
 }
 
@@ -130,31 +133,28 @@ pub async fn proxy_call(
 ) -> Response<Body> {
     match hyper_reverse_proxy::call(client_ip, forward_uri, request).await {
         Ok(response) => response,
-        _ => Response::builder()
-            .status(StatusCode::INTERNAL_SERVER_ERROR)
-            .body(Body::empty())
-            .unwrap(),
+        _ => error_response(
+            ApiError::new(502, "The service couldn't be reached.".into())
+                .with_code(ErrorCode::ServiceUnavailable),
+        ),
     }
 }
 
-pub async fn authorize_req(client: &Client, req: &Request<Body>) -> Option<auth::ReqUser> {
-    let req_cookies = req.headers().get("Cookie");
-
-    let req_cookies = req_cookies?;
-    let auth_req = Request::builder()
-        .method(Method::GET)
-        .uri(crud::URI.clone() + "/verify")
-        .header("Cookie", req_cookies)
-        .body(Body::empty())
-        .unwrap();
-
-    match client.request(auth_req).await {
-        Ok(res) => {
-            let body = body::to_bytes(res.into_body()).await.unwrap();
-            let opt = serde_json::from_slice(&body);
-            opt.ok()
-        }
-        _ => None,
+pub async fn authorize_req(req: &Request<Body>) -> Option<auth::ReqUser> {
+    let api_key = req
+        .headers()
+        .get("Authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    // api keys are used by scripts, everything else logs in for a cookie
+    // deactivated users and ended sessions fail verification
+    if let Some(api_key) = api_key {
+        crud::CLIENT.with_api_key(api_key).verify_key().await.ok()
+    } else {
+        let req_cookies = req.headers().get("Cookie")?.to_str().ok()?;
+        let verified = crud::CLIENT.with_cookie(req_cookies).verify().await.ok()?;
+        Some(verified.into())
     }
 }
 
@@ -172,7 +172,7 @@ pub async fn corsify(mut res: Response<Body>) -> Result<Response<Body>, Infallib
     );
     new_headers.append(
         "Access-Control-Allow-Headers",
-        "Cookie, Content-Type".parse::<HeaderValue>().unwrap(),
+        "Cookie, Content-Type, Authorization".parse::<HeaderValue>().unwrap(),
     );
     new_headers.append(
         "Access-Control-Allow-Methods",
@@ -196,8 +196,13 @@ pub fn log(uri: Uri, method: Method, ua_header: Option<&HeaderValue>, res: &Resp
     tracing::info!(r#"[Logger] {} {} {} "{}""#, res.status(), method, uri, ua);
 }
 
-pub fn error_body(message: impl Into<String>) -> String {
-    format!(r#"{{"message": {}}}"#, message.into())
+/// Responds with the same error body the services use
+pub fn error_response(err: ApiError) -> Response<Body> {
+    Response::builder()
+        .status(err.status_code)
+        .header("Content-Type", "application/json")
+        .body(Body::from(err.to_json()))
+        .unwrap()
 }
 
 #[cfg(test)]
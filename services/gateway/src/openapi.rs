@@ -0,0 +1,55 @@
+use errors::{ApiError, ErrorCode};
+use hyper::{body, Body, Method, Request, Response};
+use utoipa::openapi::{InfoBuilder, OpenApi};
+
+use crate::{
+    error_response,
+    services::{crud, payments},
+    Client,
+};
+
+/// Puts together the spec of every service the gateway routes to
+///
+/// Each service describes its own routes, the gateway only knows where they're mounted and which
+/// ones it doesn't let through
+pub async fn spec(client: &Client) -> Response<Body> {
+    let specs = tokio::try_join!(
+        fetch(client, crud::URI.as_str()),
+        fetch(client, payments::URI.as_str())
+    );
+    let (mut spec, payments_spec) = match specs {
+        Ok(specs) => specs,
+        Err(_) => {
+            return error_response(
+                ApiError::new(502, "The service couldn't be reached.".into())
+                    .with_code(ErrorCode::ServiceUnavailable),
+            )
+        }
+    };
+
+    spec.merge(payments::public_spec(payments_spec));
+    spec.info = InfoBuilder::new()
+        .title("milkyweb")
+        .version(env!("CARGO_PKG_VERSION"))
+        .build();
+
+    Response::builder()
+        .header("Content-Type", "application/json")
+        .body(Body::from(spec.to_json().unwrap()))
+        .unwrap()
+}
+
+async fn fetch(client: &Client, uri: &str) -> Result<OpenApi, ()> {
+    let req = Request::builder()
+        .method(Method::GET)
+        .uri(uri.to_string() + "/openapi.json")
+        .body(Body::empty())
+        .unwrap();
+
+    let res = client.request(req).await.map_err(|_| ())?;
+    if !res.status().is_success() {
+        return Err(());
+    }
+    let body = body::to_bytes(res.into_body()).await.map_err(|_| ())?;
+    serde_json::from_slice(&body).map_err(|_| ())
+}
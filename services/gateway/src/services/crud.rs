@@ -1,22 +1,31 @@
 use std::{convert::Infallible, net::IpAddr};
 
 use axum::http::{HeaderValue, Request, Response};
-use hyper::{Body, StatusCode};
+use crud_client::CrudClient;
+use errors::ApiError;
+use hyper::Body;
 use regex::RegexSet;
 use serde::{Deserialize, Serialize};
 
-use crate::{authorize_req, error_body, proxy_call, Client};
+use crate::{authorize_req, error_response, proxy_call};
 
 pub const PATH_BASE: &str = "/";
 
 lazy_static! {
     pub static ref URI: String =
         std::env::var("CRUD_URI").unwrap_or("http://127.0.0.1:8080".into());
+    pub static ref CLIENT: CrudClient = CrudClient::new(URI.as_str());
     pub static ref PUBLIC_PATH_RE: RegexSet = RegexSet::new(&[
         "^/verify/?$",
+        "^/verify-key/?$",
         "^/login/?$",
+        "^/login/mfa/?$",
         "^/authenticate/?$",
+        "^/refresh/?$",
+        "^/logout/?$",
+        "^/password-reset/(request|confirm)/?$",
         "^/register/?$",
+        "^/invites/accept/?$",
         r"^/instances/\S*/callback/?$",
         r"^/instances/\S*/fail-callback/?$",
     ]).unwrap();
@@ -32,7 +41,6 @@ pub enum Resource {
 
 pub async fn proxy(
     client_ip: IpAddr,
-    client: Client,
     mut req: Request<Body>,
     path: String,
 ) -> Result<Response<Body>, Infallible> {
@@ -40,22 +48,20 @@ pub async fn proxy(
         // do not authorize request
         return Ok(proxy_call(client_ip, URI.as_str(), req).await);
     } else {
-        return match authorize_req(&client, &req).await {
+        return match authorize_req(&req).await {
             // request was authed
             Some(user) => {
-                req.headers_mut().append(
+                req.headers_mut().insert(
                     "user",
                     HeaderValue::from_str(&serde_json::to_string(&user).unwrap()).unwrap(),
                 );
                 Ok(proxy_call(client_ip, URI.as_str(), req).await)
             }
             // request was not authed
-            _ => Ok(Response::builder()
-                .status(StatusCode::UNAUTHORIZED)
-                .body(Body::from(error_body(
-                    "You are not authorized! Login before accessing this resource.",
-                )))
-                .unwrap()),
+            _ => Ok(error_response(ApiError::new(
+                401,
+                "You are not authorized! Login before accessing this resource.".into(),
+            ))),
         };
     }
 }
@@ -1,10 +1,12 @@
 use std::{convert::Infallible, net::IpAddr};
 
 use axum::http::{HeaderValue, Request, Response};
-use hyper::{Body, StatusCode};
+use errors::ApiError;
+use hyper::Body;
 use regex::RegexSet;
+use utoipa::openapi::{OpenApi, PathsBuilder};
 
-use crate::{authorize_req, error_body, proxy_call, Client};
+use crate::{authorize_req, error_response, proxy_call};
 
 pub const PATH_BASE: &str = "/payments";
 
@@ -12,8 +14,13 @@ lazy_static! {
     /// Paths which don't need to be authenticated
     static ref PUBLIC_PATH_RE: RegexSet = RegexSet::new(&["^/webhooks/?$"]).unwrap();
     /// Paths which can only be accessed by other services
-    static ref PRIVATE_PATH_RE: RegexSet =
-        RegexSet::new(&["^/create-usage-record/?$", r"^/customer/.*/?$"]).unwrap();
+    static ref PRIVATE_PATH_RE: RegexSet = RegexSet::new(&[
+        "^/create-usage-record/?$",
+        "^/cancel-subscription/?$",
+        "^/invoices/?$",
+        r"^/customer/.*/?$",
+    ])
+    .unwrap();
 }
 
 lazy_static! {
@@ -23,38 +30,44 @@ lazy_static! {
 
 pub async fn proxy(
     client_ip: IpAddr,
-    client: Client,
     mut req: Request<Body>,
     path: String,
 ) -> Result<Response<Body>, Infallible> {
     if PUBLIC_PATH_RE.is_match(path.as_str()) {
         Ok(proxy_call(client_ip, URI.as_str(), req).await)
     } else if !PRIVATE_PATH_RE.is_match(path.as_str()) {
-        return match authorize_req(&client, &req).await {
+        return match authorize_req(&req).await {
             // request was authed
             Some(user) => {
-                req.headers_mut().append(
+                req.headers_mut().insert(
                     "user",
                     HeaderValue::from_str(&serde_json::to_string(&user).unwrap()).unwrap(),
                 );
                 Ok(proxy_call(client_ip, URI.as_str(), req).await)
             }
             // request was not authed
-            _ => Ok(Response::builder()
-                .status(StatusCode::UNAUTHORIZED)
-                .body(Body::from(error_body(
-                    "You are not authorized! Login before accessing this resource.",
-                )))
-                .unwrap()),
+            _ => Ok(error_response(ApiError::new(
+                401,
+                "You are not authorized! Login before accessing this resource.".into(),
+            ))),
         };
     } else {
         // Only reqs from internal services (crud, instance-deploy, etc.) allowed
-        Ok(Response::builder()
-            .status(StatusCode::NOT_FOUND)
-            .body(Body::from(format!(
-                r#"{{"message": "uri: {} is not valid"}}"#,
-                path
-            )))
-            .unwrap())
+        Ok(error_response(ApiError::new(
+            404,
+            format!("uri: {} is not valid", path),
+        )))
     }
 }
+
+/// Mounts the payments spec under [`PATH_BASE`], without the paths only services can call
+pub fn public_spec(mut spec: OpenApi) -> OpenApi {
+    let paths = std::mem::take(&mut spec.paths.paths)
+        .into_iter()
+        .filter(|(path, _)| !PRIVATE_PATH_RE.is_match(path))
+        .fold(PathsBuilder::new(), |paths, (path, item)| {
+            paths.path(format!("{}{}", PATH_BASE, path), item)
+        });
+    spec.paths = paths.build();
+    spec
+}
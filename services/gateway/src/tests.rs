@@ -7,9 +7,9 @@ use axum::{
     routing::{get, post},
     Json, Router,
 };
-use hyper::{body, Body, Method, Request, StatusCode};
+use hyper::{body, Body, HeaderMap, Method, Request, StatusCode};
 use models::types::Role;
-use serde_json::from_slice;
+use serde_json::{from_slice, json, Value};
 
 use crate::{app, Client};
 
@@ -26,13 +26,23 @@ fn init() {
     }
 }
 
-fn init_e2e() {
+async fn init_e2e() {
     // start mock servers
     tokio::spawn(mock_crud());
     tokio::spawn(mock_payments());
 
     // start main
     tokio::spawn(app(4001));
+
+    // they bind in the background, so wait until they all answer
+    for port in [8081, 6001, 4001] {
+        while tokio::net::TcpStream::connect(("127.0.0.1", port))
+            .await
+            .is_err()
+        {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+    }
 }
 
 async fn mock_crud() {
@@ -42,17 +52,27 @@ async fn mock_crud() {
         .route(
             "/verify",
             get(|| async {
-                Json(auth::ReqUser {
-                    id: "10".into(),
-                    account_id: "account_id".into(),
-                    role: Role::User,
-                })
+                Json(json!({
+                    "id": "10",
+                    "createdAt": "2022-06-01T00:00:00",
+                    "updatedAt": "2022-06-01T00:00:00",
+                    "accountId": "account_id",
+                    "username": "user",
+                    "firstName": "First",
+                    "lastName": "Last",
+                    "active": true,
+                    "instances": [],
+                    "createPerms": [],
+                    "updatePerms": [],
+                    "deletePerms": [],
+                    "role": Role::User,
+                    "capabilities": [],
+                }))
             }),
         )
-        .route(
-            "/users",
-            get(|| async { Json::<Vec<auth::ReqUser>>(vec![]) }),
-        );
+        .route("/users", get(req_users))
+        .route("/login", post(req_users))
+        .route("/openapi.json", get(|| async { Json(mock_spec(&["/users"])) }));
 
     let addr = SocketAddr::from(test_addr.parse::<SocketAddr>().unwrap());
     println!("server listening on {}", addr);
@@ -62,6 +82,16 @@ async fn mock_crud() {
         .unwrap();
 }
 
+/// Every user the gateway said is asking
+async fn req_users(headers: HeaderMap) -> Json<Vec<auth::ReqUser>> {
+    let users = headers
+        .get_all("user")
+        .iter()
+        .map(|user| serde_json::from_slice(user.as_bytes()).unwrap())
+        .collect();
+    Json(users)
+}
+
 const WEBHOOK_BODY: &str = "Web hooked";
 
 async fn mock_payments() {
@@ -72,7 +102,11 @@ async fn mock_payments() {
             "/subscriptions",
             get(|| async { Json(Vec::<String>::new()) }),
         )
-        .route("/webhooks", post(|| async { WEBHOOK_BODY }));
+        .route("/webhooks", post(|| async { WEBHOOK_BODY }))
+        .route(
+            "/openapi.json",
+            get(|| async { Json(mock_spec(&["/webhooks", "/invoices"])) }),
+        );
 
     let addr = SocketAddr::from(test_addr.parse::<SocketAddr>().unwrap());
     println!("server listening on {}", addr);
@@ -82,18 +116,61 @@ async fn mock_payments() {
         .unwrap();
 }
 
+fn mock_spec(paths: &[&str]) -> Value {
+    let paths: serde_json::Map<String, Value> = paths
+        .iter()
+        .map(|path| (path.to_string(), json!({ "get": { "responses": {} } })))
+        .collect();
+    json!({
+        "openapi": "3.0.3",
+        "info": { "title": "mock", "version": "0.1.0" },
+        "paths": paths,
+    })
+}
+
 #[tokio::test]
 async fn e2e() {
     init();
-    init_e2e();
+    init_e2e().await;
     let client = Client::new();
 
-    // expect unverified
+    let verified = auth::ReqUser {
+        id: "10".into(),
+        account_id: "account_id".into(),
+        role: Role::User,
+        create_perms: vec![],
+        update_perms: vec![],
+        delete_perms: vec![],
+        capabilities: Some(vec![]),
+    };
+
+    // a user header sent by the client never reaches crud
     tracing::info!("Testing crud users.");
+    let forged = serde_json::to_string(&auth::ReqUser {
+        id: "forged".into(),
+        role: Role::Admin,
+        ..verified.clone()
+    })
+    .unwrap();
     let req = Request::builder()
         .method(Method::GET)
         .uri("http://localhost:4001/users")
         .header("Cookie", "noop=noop")
+        .header("user", &forged)
+        .body(Body::empty())
+        .unwrap();
+    let res = client.request(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body_json: Vec<auth::ReqUser> =
+        from_slice(&body::to_bytes(res.into_body()).await.unwrap()).unwrap();
+
+    assert_eq!(body_json, vec![verified.clone()]);
+
+    // not even on paths that skip verification
+    let req = Request::builder()
+        .method(Method::POST)
+        .uri("http://localhost:4001/login")
+        .header("user", &forged)
         .body(Body::empty())
         .unwrap();
     let res = client.request(req).await.unwrap();
@@ -113,14 +190,7 @@ async fn e2e() {
     let body_json: auth::ReqUser =
         from_slice(&body::to_bytes(res.into_body()).await.unwrap()).unwrap();
 
-    assert_eq!(
-        body_json,
-        auth::ReqUser {
-            id: "10".into(),
-            account_id: "account_id".into(),
-            role: Role::User
-        }
-    );
+    assert_eq!(body_json, verified);
 
     // test payments
     tracing::info!("Testing payments webhooks.");
@@ -135,4 +205,18 @@ async fn e2e() {
     let body = &body::to_bytes(res.into_body()).await.unwrap();
 
     assert_eq!(body, WEBHOOK_BODY);
+
+    // test the merged spec
+    tracing::info!("Testing openapi spec.");
+    let res = client
+        .get("http://localhost:4001/openapi.json".try_into().unwrap())
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let spec: Value = from_slice(&body::to_bytes(res.into_body()).await.unwrap()).unwrap();
+
+    assert!(spec["paths"]["/users"].is_object());
+    assert!(spec["paths"]["/payments/webhooks"].is_object());
+    // only other services can call it so it isn't documented
+    assert!(spec["paths"]["/payments/invoices"].is_null());
 }
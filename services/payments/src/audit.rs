@@ -0,0 +1,31 @@
+use auth::ReqUser;
+use crud_client::CrudClient;
+
+/// Records a change made outside of crud, such as with Stripe
+///
+/// Only logs failures since the change has already happened
+pub async fn record(
+    crud: &CrudClient,
+    req_user: &Option<ReqUser>,
+    account_id: &str,
+    action: &str,
+    target_type: &str,
+    target_id: &str,
+) {
+    let event = models::NewAuditEvent {
+        id: String::new(),
+        account_id: account_id.into(),
+        actor_id: req_user.as_ref().map(|user| user.id.clone()),
+        actor_role: req_user.as_ref().map(|user| user.role.clone()),
+        action: action.into(),
+        target_type: target_type.into(),
+        target_id: target_id.into(),
+        before: None,
+        after: None,
+        ip: None,
+    };
+
+    if crud.create_audit_event(&event).await.is_err() {
+        tracing::error!("Failed to record {} of {} {}", action, target_type, target_id);
+    }
+}
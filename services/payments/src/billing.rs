@@ -0,0 +1,487 @@
+//! Stripe behind a trait, so the routes run against the real api or an in memory fake
+use std::{
+    fmt::Debug,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use serde::Serialize;
+use stripe::{
+    AttachPaymentMethod, CancelSubscription, CreateCustomer, CreateSubscription, CreateUsageRecord,
+    Customer, CustomerId, Expandable, Invoice, InvoiceId, InvoiceLineItem, InvoiceLineItemId, List,
+    ListInvoices, PaymentMethod, PaymentMethodId, Price, PriceId, Subscription, SubscriptionId,
+    SubscriptionItem, SubscriptionItemId, SubscriptionStatus, UpdateCustomer, UsageRecord,
+    UsageRecordAction,
+};
+
+use crate::{error::ApiError, OFFLINE, STRIPE_KEY};
+
+pub type BillingClient = Arc<dyn Billing>;
+
+/// The parts of stripe payments uses
+#[async_trait]
+pub trait Billing: Debug + Send + Sync {
+    async fn create_customer(&self, params: CreateCustomer<'_>) -> Result<Customer, ApiError>;
+
+    async fn retrieve_customer(&self, id: &CustomerId) -> Result<Customer, ApiError>;
+
+    async fn update_customer(
+        &self,
+        id: &CustomerId,
+        params: UpdateCustomer<'_>,
+    ) -> Result<Customer, ApiError>;
+
+    async fn attach_payment_method(
+        &self,
+        id: &PaymentMethodId,
+        customer: &CustomerId,
+    ) -> Result<(), ApiError>;
+
+    async fn create_subscription(
+        &self,
+        params: CreateSubscription<'_>,
+    ) -> Result<Subscription, ApiError>;
+
+    async fn retrieve_subscription(
+        &self,
+        id: &SubscriptionId,
+        expand: &[&str],
+    ) -> Result<Subscription, ApiError>;
+
+    /// Cancels right away instead of at the end of the period
+    async fn cancel_subscription(&self, id: &SubscriptionId) -> Result<Subscription, ApiError>;
+
+    /// Replaces the item's usage from now on
+    async fn set_usage(&self, item: &SubscriptionItemId, quantity: u64) -> Result<(), ApiError>;
+
+    /// One page of the customer's invoices, newest first
+    async fn list_invoices(
+        &self,
+        customer: &CustomerId,
+        starting_after: Option<InvoiceId>,
+    ) -> Result<List<Invoice>, ApiError>;
+
+    /// One page of the invoice's lines, invoices only come with the first page
+    async fn list_invoice_lines(
+        &self,
+        invoice: &InvoiceId,
+        starting_after: Option<InvoiceLineItemId>,
+    ) -> Result<List<InvoiceLineItem>, ApiError>;
+}
+
+/// Stripe with STRIPE_KEY, or a fresh fake if OFFLINE is set
+pub fn from_env() -> BillingClient {
+    if *OFFLINE {
+        tracing::info!("OFFLINE is set, stripe is faked in memory");
+        Arc::new(FakeBilling::default())
+    } else {
+        Arc::new(StripeBilling(stripe::Client::new(STRIPE_KEY.as_str())))
+    }
+}
+
+#[derive(Clone)]
+pub struct StripeBilling(pub stripe::Client);
+
+impl Debug for StripeBilling {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("StripeBilling")
+    }
+}
+
+#[async_trait]
+impl Billing for StripeBilling {
+    async fn create_customer(&self, params: CreateCustomer<'_>) -> Result<Customer, ApiError> {
+        Ok(Customer::create(&self.0, params).await?)
+    }
+
+    async fn retrieve_customer(&self, id: &CustomerId) -> Result<Customer, ApiError> {
+        Ok(Customer::retrieve(&self.0, id, &[]).await?)
+    }
+
+    async fn update_customer(
+        &self,
+        id: &CustomerId,
+        params: UpdateCustomer<'_>,
+    ) -> Result<Customer, ApiError> {
+        Ok(Customer::update(&self.0, id, params).await?)
+    }
+
+    async fn attach_payment_method(
+        &self,
+        id: &PaymentMethodId,
+        customer: &CustomerId,
+    ) -> Result<(), ApiError> {
+        PaymentMethod::attach(
+            &self.0,
+            id,
+            AttachPaymentMethod {
+                customer: customer.clone(),
+            },
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn create_subscription(
+        &self,
+        params: CreateSubscription<'_>,
+    ) -> Result<Subscription, ApiError> {
+        Ok(Subscription::create(&self.0, params).await?)
+    }
+
+    async fn retrieve_subscription(
+        &self,
+        id: &SubscriptionId,
+        expand: &[&str],
+    ) -> Result<Subscription, ApiError> {
+        Ok(Subscription::retrieve(&self.0, id, expand).await?)
+    }
+
+    async fn cancel_subscription(&self, id: &SubscriptionId) -> Result<Subscription, ApiError> {
+        Ok(Subscription::cancel(&self.0, id, CancelSubscription::new()).await?)
+    }
+
+    async fn set_usage(&self, item: &SubscriptionItemId, quantity: u64) -> Result<(), ApiError> {
+        let record = UsageRecord::create(
+            &self.0,
+            item,
+            CreateUsageRecord {
+                action: Some(UsageRecordAction::Set),
+                quantity,
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        tracing::info!("{:?}", record);
+        Ok(())
+    }
+
+    async fn list_invoices(
+        &self,
+        customer: &CustomerId,
+        starting_after: Option<InvoiceId>,
+    ) -> Result<List<Invoice>, ApiError> {
+        let mut params = ListInvoices::new();
+        params.customer = Some(customer.clone());
+        params.limit = Some(100);
+        params.starting_after = starting_after;
+
+        Ok(Invoice::list(&self.0, &params).await?)
+    }
+
+    async fn list_invoice_lines(
+        &self,
+        invoice: &InvoiceId,
+        starting_after: Option<InvoiceLineItemId>,
+    ) -> Result<List<InvoiceLineItem>, ApiError> {
+        // stripe-rs has no params for this list
+        #[derive(Serialize)]
+        struct ListLines {
+            limit: u64,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            starting_after: Option<InvoiceLineItemId>,
+        }
+
+        let path = format!("/invoices/{}/lines", invoice);
+        let params = ListLines {
+            limit: 100,
+            starting_after,
+        };
+        Ok(self.0.get_query(&path, params).await?)
+    }
+}
+
+#[derive(Debug, Default)]
+struct FakeState {
+    created: usize,
+    customers: Vec<Customer>,
+    subscriptions: Vec<Subscription>,
+    /// Subscription item id and its quantity
+    usage: Vec<(String, u64)>,
+    invoices: Vec<Invoice>,
+    /// Every line of each invoice, the invoices only hold the first page
+    invoice_lines: Vec<(InvoiceId, InvoiceLineItem)>,
+}
+
+impl FakeState {
+    fn next_id(&mut self, prefix: &str) -> String {
+        self.created += 1;
+        format!("{}_fake{:06}", prefix, self.created)
+    }
+
+    fn subscription(&mut self, id: &SubscriptionId) -> Result<&mut Subscription, ApiError> {
+        self.subscriptions
+            .iter_mut()
+            .find(|sub| &sub.id == id)
+            .ok_or_else(|| no_such("subscription", id.as_str()))
+    }
+}
+
+fn no_such(kind: &str, id: &str) -> ApiError {
+    ApiError::new(404, format!("No such {}: '{}'", kind, id))
+}
+
+/// Keeps customers and subscriptions in memory, never charges so there are only the invoices tests add
+#[derive(Debug, Default)]
+pub struct FakeBilling {
+    state: Mutex<FakeState>,
+}
+
+impl FakeBilling {
+    /// Quantity last set on the subscription item
+    #[cfg(test)]
+    pub fn usage(&self, item: &SubscriptionItemId) -> Option<u64> {
+        let state = self.state.lock().unwrap();
+        state
+            .usage
+            .iter()
+            .find(|(id, _)| id == item.as_str())
+            .map(|(_, quantity)| *quantity)
+    }
+
+    /// Bills customer for a line per amount
+    #[cfg(test)]
+    pub fn add_invoice(&self, customer: &CustomerId, amounts: &[i64]) -> Result<Invoice, ApiError> {
+        let mut state = self.state.lock().unwrap();
+        let id = InvoiceId::from_str(&state.next_id("in"))?;
+
+        let mut lines = vec![];
+        for amount in amounts {
+            let line = InvoiceLineItem {
+                id: InvoiceLineItemId::from_str(&state.next_id("il"))?,
+                amount: *amount,
+                ..Default::default()
+            };
+            state.invoice_lines.push((id.clone(), line.clone()));
+            lines.push(line);
+        }
+
+        let has_more = lines.len() > FAKE_PAGE_SIZE;
+        lines.truncate(FAKE_PAGE_SIZE);
+        let invoice = Invoice {
+            id: id.clone(),
+            customer: Some(Expandable::Id(customer.clone())),
+            lines: List {
+                data: lines,
+                has_more,
+                url: format!("/v1/invoices/{}/lines", id),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        state.invoices.push(invoice.clone());
+        Ok(invoice)
+    }
+}
+
+/// Small so tests page through lists
+const FAKE_PAGE_SIZE: usize = 2;
+
+#[async_trait]
+impl Billing for FakeBilling {
+    async fn create_customer(&self, params: CreateCustomer<'_>) -> Result<Customer, ApiError> {
+        let mut state = self.state.lock().unwrap();
+        let customer = Customer {
+            id: CustomerId::from_str(&state.next_id("cus"))?,
+            name: params.name.map(|name| name.into()),
+            email: params.email.map(|email| email.into()),
+            phone: params.phone.map(|phone| phone.into()),
+            address: params.address,
+            ..Default::default()
+        };
+
+        state.customers.push(customer.clone());
+        Ok(customer)
+    }
+
+    async fn retrieve_customer(&self, id: &CustomerId) -> Result<Customer, ApiError> {
+        let state = self.state.lock().unwrap();
+        let mut customer = state
+            .customers
+            .iter()
+            .find(|customer| &customer.id == id)
+            .cloned()
+            .ok_or_else(|| no_such("customer", id.as_str()))?;
+
+        customer.subscriptions.data = state
+            .subscriptions
+            .iter()
+            .filter(|sub| sub.customer.id() == *id)
+            .cloned()
+            .collect();
+        Ok(customer)
+    }
+
+    async fn update_customer(
+        &self,
+        id: &CustomerId,
+        params: UpdateCustomer<'_>,
+    ) -> Result<Customer, ApiError> {
+        let mut state = self.state.lock().unwrap();
+        let customer = state
+            .customers
+            .iter_mut()
+            .find(|customer| &customer.id == id)
+            .ok_or_else(|| no_such("customer", id.as_str()))?;
+
+        if let Some(name) = params.name {
+            customer.name = Some(name.into());
+        }
+        if let Some(email) = params.email {
+            customer.email = Some(email.into());
+        }
+        if let Some(phone) = params.phone {
+            customer.phone = Some(phone.into());
+        }
+        if let Some(address) = params.address {
+            customer.address = Some(address);
+        }
+        Ok(customer.clone())
+    }
+
+    async fn attach_payment_method(
+        &self,
+        _id: &PaymentMethodId,
+        customer: &CustomerId,
+    ) -> Result<(), ApiError> {
+        let state = self.state.lock().unwrap();
+        if !state
+            .customers
+            .iter()
+            .any(|existing| &existing.id == customer)
+        {
+            return Err(no_such("customer", customer.as_str()));
+        }
+        Ok(())
+    }
+
+    async fn create_subscription(
+        &self,
+        params: CreateSubscription<'_>,
+    ) -> Result<Subscription, ApiError> {
+        let mut state = self.state.lock().unwrap();
+        if !state
+            .customers
+            .iter()
+            .any(|customer| customer.id == params.customer)
+        {
+            return Err(no_such("customer", params.customer.as_str()));
+        }
+
+        let id = SubscriptionId::from_str(&state.next_id("sub"))?;
+        let mut items = vec![];
+        for item in params.items.unwrap_or_default() {
+            let price = item.price.as_deref().map(PriceId::from_str).transpose()?;
+            items.push(SubscriptionItem {
+                id: SubscriptionItemId::from_str(&state.next_id("si"))?,
+                price: price.map(|id| Price {
+                    id,
+                    ..Default::default()
+                }),
+                quantity: item.quantity,
+                subscription: Some(id.to_string()),
+                ..Default::default()
+            });
+        }
+
+        let sub = Subscription {
+            items: List {
+                data: items,
+                url: format!("/v1/subscription_items?subscription={}", id),
+                ..Default::default()
+            },
+            id,
+            customer: Expandable::Id(params.customer),
+            status: SubscriptionStatus::Active,
+            ..Default::default()
+        };
+
+        state.subscriptions.push(sub.clone());
+        Ok(sub)
+    }
+
+    async fn retrieve_subscription(
+        &self,
+        id: &SubscriptionId,
+        _expand: &[&str],
+    ) -> Result<Subscription, ApiError> {
+        let mut state = self.state.lock().unwrap();
+        Ok(state.subscription(id)?.clone())
+    }
+
+    async fn cancel_subscription(&self, id: &SubscriptionId) -> Result<Subscription, ApiError> {
+        let mut state = self.state.lock().unwrap();
+        let sub = state.subscription(id)?;
+        if sub.status == SubscriptionStatus::Canceled {
+            return Err(ApiError::new(
+                400,
+                format!("Subscription {} is already canceled.", id),
+            ));
+        }
+
+        sub.status = SubscriptionStatus::Canceled;
+        Ok(sub.clone())
+    }
+
+    async fn set_usage(&self, item: &SubscriptionItemId, quantity: u64) -> Result<(), ApiError> {
+        let mut state = self.state.lock().unwrap();
+        let exists = state
+            .subscriptions
+            .iter()
+            .flat_map(|sub| sub.items.data.iter())
+            .any(|existing| &existing.id == item);
+        if !exists {
+            return Err(no_such("subscription item", item.as_str()));
+        }
+
+        state.usage.retain(|(id, _)| id != item.as_str());
+        state.usage.push((item.to_string(), quantity));
+        Ok(())
+    }
+
+    async fn list_invoices(
+        &self,
+        customer: &CustomerId,
+        _starting_after: Option<InvoiceId>,
+    ) -> Result<List<Invoice>, ApiError> {
+        let state = self.state.lock().unwrap();
+        let data = state
+            .invoices
+            .iter()
+            .filter(|invoice| matches!(&invoice.customer, Some(billed) if &billed.id() == customer))
+            .cloned()
+            .collect();
+
+        Ok(List {
+            data,
+            url: format!("/v1/invoices?customer={}", customer),
+            ..Default::default()
+        })
+    }
+
+    async fn list_invoice_lines(
+        &self,
+        invoice: &InvoiceId,
+        starting_after: Option<InvoiceLineItemId>,
+    ) -> Result<List<InvoiceLineItem>, ApiError> {
+        let state = self.state.lock().unwrap();
+        let lines: Vec<_> = state
+            .invoice_lines
+            .iter()
+            .filter(|(on, _)| on == invoice)
+            .map(|(_, line)| line.clone())
+            .skip_while(|line| matches!(&starting_after, Some(after) if &line.id != after))
+            .skip(usize::from(starting_after.is_some()))
+            .collect();
+
+        Ok(List {
+            has_more: lines.len() > FAKE_PAGE_SIZE,
+            data: lines.into_iter().take(FAKE_PAGE_SIZE).collect(),
+            url: format!("/v1/invoices/{}/lines", invoice),
+            ..Default::default()
+        })
+    }
+}
@@ -1,4 +1,7 @@
+mod audit;
+mod billing;
 mod error;
+mod openapi;
 mod routes;
 mod webhooks;
 
@@ -9,29 +12,22 @@ use axum::{
     routing::{get, post},
     Extension, Router,
 };
-use hyper::{client::HttpConnector, Body};
+use crud_client::CrudClient;
 use std::net::SocketAddr;
 use tower_http::trace::TraceLayer;
 
-pub type Client = hyper::client::Client<HttpConnector, Body>;
-
 #[tokio::main]
 async fn main() {
     dotenv::dotenv().ok();
     std::env::set_var("RUST_LOG", "error,info");
     tracing_subscriber::fmt::init();
 
-    let http_client = Client::new();
-    let stripe = stripe::Client::new(STRIPE_KEY.to_string());
+    let crud = CrudClient::new(CRUD_URI.as_str());
+    let billing = billing::from_env();
 
-    let app = Router::new()
-        .nest("/subscription", routes::subscription::init())
-        .nest("/customer", routes::customer::init())
-        .route("/create-usage-record", post(routes::create_usage_record))
-        .route("/sub-status", get(routes::sub_status))
-        .route("/webhooks", post(webhooks::handler))
-        .layer(Extension(http_client))
-        .layer(Extension(stripe))
+    let app = router()
+        .layer(Extension(crud))
+        .layer(Extension(billing))
         .layer(TraceLayer::new_for_http());
 
     let addr = SocketAddr::from((if *PROD { [0, 0, 0, 0] } else { [127, 0, 0, 1] }, 6000));
@@ -42,19 +38,38 @@ async fn main() {
         .unwrap();
 }
 
+/// Every route, without the extensions they need
+fn router() -> Router {
+    Router::new()
+        .nest("/subscription", routes::subscription::init())
+        .nest("/customer", routes::customer::init())
+        .route("/create-usage-record", post(routes::create_usage_record))
+        .route("/cancel-subscription", post(routes::cancel_subscription))
+        .route("/sub-status", get(routes::sub_status))
+        .route("/invoices", get(routes::invoices))
+        .route("/webhooks", post(webhooks::handler))
+        .route("/openapi.json", get(openapi::spec))
+}
+
 lazy_static! {
     pub static ref STRIPE_KEY: String = std::env::var("STRIPE_KEY").unwrap();
     pub static ref STRIPE_WEBHOOK_KEY: String = std::env::var("STRIPE_WEBHOOK_KEY").unwrap();
     static ref PROD: bool = std::env::var("RUST_ENV").unwrap_or("dev".into()) == "prod";
+    /// Fakes stripe so payments runs without an account
+    pub static ref OFFLINE: bool = std::env::var_os("OFFLINE").is_some();
     pub static ref CRUD_URI: String =
         std::env::var("CRUD_URI").unwrap_or("http://127.0.0.1:8080".into());
-    pub static ref INSTANCE_PRICE_ID: String = if STRIPE_KEY.contains("test") {
+    pub static ref INSTANCE_PRICE_ID: String = if *OFFLINE {
+        "price_offline_instances".to_string()
+    } else if STRIPE_KEY.contains("test") {
         "price_1LP8pMAMMTQqCw55f1MxzIjC".to_string()
     } else {
         // TODO prod ids
         "".to_string()
     };
-    pub static ref USER_PRICE_ID: String = if STRIPE_KEY.contains("test") {
+    pub static ref USER_PRICE_ID: String = if *OFFLINE {
+        "price_offline_users".to_string()
+    } else if STRIPE_KEY.contains("test") {
         "price_1LP8mmAMMTQqCw55U0urmth4".to_string()
     } else {
         "".to_string()
@@ -0,0 +1,45 @@
+use axum::Json;
+use errors::openapi::ErrorResponses;
+use models::{Account, UpdateAccount};
+use payments_lib::routes::{
+    cancel_subscription::CancelSubscriptionParams,
+    create_usage_record::CreateUsageRecordParams,
+    invoices::{Invoice, InvoiceLine},
+    subscription::CreateSubscriptionParams,
+};
+use utoipa::OpenApi;
+
+use crate::{routes, webhooks};
+
+/// Every route with what it takes and responds with, the types come from models and payments-lib
+///
+/// Add new routes to `paths` and anything they take or respond with to `schemas`, the tests check both
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        routes::customer::create_customer,
+        routes::customer::update_customer,
+        routes::subscription::subscribe,
+        routes::subscription::update_subscription,
+        routes::create_usage_record,
+        routes::sub_status,
+        routes::cancel_subscription,
+        routes::invoices,
+        webhooks::handler,
+    ),
+    components(schemas(
+        Account,
+        UpdateAccount,
+        CreateSubscriptionParams,
+        CreateUsageRecordParams,
+        CancelSubscriptionParams,
+        Invoice,
+        InvoiceLine,
+    )),
+    modifiers(&ErrorResponses)
+)]
+pub struct ApiDoc;
+
+pub async fn spec() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
@@ -1,44 +1,51 @@
 use std::str::FromStr;
 
-use auth::{belongs_to_account, require_role, ExtractReqUser};
+use auth::{belongs_to_account, require_cap, ExtractReqUser};
 use axum::{
     extract::Path,
     routing::{post, put},
     Extension, Json, Router,
 };
-use hyper::{Body, Method, Request, Response, StatusCode, Uri, body};
-use models::{types::Role, Account};
+use crud_client::CrudClient;
+use hyper::{Body, Response, StatusCode};
+use models::types::Capability;
 use payments_lib::routes::customer;
-use stripe::{Address, CreateCustomer, Customer, CustomerId, UpdateCustomer};
+use stripe::{Address, CreateCustomer, CustomerId, UpdateCustomer};
 
-use crate::{error::ApiError, Client, CRUD_URI};
+use crate::{billing::BillingClient, error::ApiError};
 
+#[utoipa::path(
+    post,
+    path = "/customer",
+    tag = "customer",
+    request_body = Account,
+    responses((
+        status = 200,
+        description = "Id of the new stripe customer",
+        body = String,
+        content_type = "text/plain",
+    )),
+)]
 async fn create_customer(
     Json(account): Json<customer::CustomerParams>,
-    Extension(stripe): Extension<stripe::Client>,
-    Extension(client): Extension<Client>,
+    Extension(billing): Extension<BillingClient>,
+    Extension(crud): Extension<CrudClient>,
     ExtractReqUser(req_user): ExtractReqUser,
 ) -> Result<Response<Body>, ApiError> {
-    if !belongs_to_account(&req_user, &account.id) || !require_role(&req_user, Role::Owner)  {
-        return Ok(Response::builder()
-            .status(StatusCode::FORBIDDEN)
-            .body(Body::from(
-                r#"{"message":"You cannot access this resource."}"#,
-            ))
-            .unwrap());
+    if !belongs_to_account(&req_user, &account.id) || !require_cap(&req_user, Capability::ManageBilling)  {
+        return Err(ApiError::forbidden());
     }
 
     if account.stripe_id.is_some() {
         return Err(ApiError::new(
-            StatusCode::BAD_REQUEST,
-            "Cannot create a customer that already exists.",
+            400,
+            "Cannot create a customer that already exists.".into(),
         ));
     }
 
     tracing::info!("Creating customer for account: {}", account.id);
-    let customer = Customer::create(
-        &stripe,
-        CreateCustomer {
+    let customer = billing
+        .create_customer(CreateCustomer {
             name: Some(&account.business_name),
             email: Some(&account.email),
             address: Some(Address {
@@ -52,30 +59,21 @@ async fn create_customer(
             }),
             phone: Some(&account.phone_number),
             ..Default::default()
-        },
-    )
-    .await?;
+        })
+        .await?;
 
     tracing::info!("Updating account: {}", account.id);
-    let req = Request::builder()
-        .uri(format!("{}/accounts/{}", CRUD_URI.as_str(), account.id))
-        .method(Method::PUT)
-        .header("Content-Type", "application/json")
-        .body(Body::from(serde_json::to_string(&models::UpdateAccount {
-            stripe_id: Some(Some(customer.id.to_string())),
-            ..Default::default()
-        })?))
-        .unwrap();
-
-    let res = client.request(req).await?;
+    let update = models::UpdateAccount {
+        stripe_id: Some(Some(customer.id.to_string())),
+        ..Default::default()
+    };
+    let res = crud.with_actor(&req_user).update_account(&account.id, &update).await;
 
-    if !res.status().is_success() {
-        return Ok(Response::builder()
-            .status(StatusCode::INTERNAL_SERVER_ERROR)
-            .body(Body::from(
-                r#"{"message": "Failed to update account with customer id."}"#,
-            ))
-            .unwrap());
+    if res.is_err() {
+        return Err(ApiError::new(
+            500,
+            "Failed to update account with customer id.".into(),
+        ));
     }
 
     Ok(Response::builder()
@@ -84,50 +82,50 @@ async fn create_customer(
         .unwrap())
 }
 
+#[utoipa::path(
+    put,
+    path = "/customer/{id}",
+    tag = "customer",
+    params(("id" = String, Path, description = "Stripe customer id")),
+    request_body = UpdateAccount,
+    responses((status = 200)),
+)]
 async fn update_customer(
     Json(account): Json<customer::UpdateCustomerParams>,
     Path(id): Path<String>,
-    Extension(stripe): Extension<stripe::Client>,
-    Extension(client): Extension<Client>,
+    Extension(billing): Extension<BillingClient>,
+    Extension(crud): Extension<CrudClient>,
     ExtractReqUser(req_user): ExtractReqUser,
 ) -> Result<Response<Body>, ApiError> {
-    let res = client
-        .get(Uri::try_from(CRUD_URI.to_string() + "/accounts/by-customer/" + id.as_str()).unwrap())
-        .await?;
-    if !res.status().is_success() {
-        return Err(ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, "Failed to retrieve account information."))
-    }
-    let to_be_updated = serde_json::from_slice::<Account>(&body::to_bytes(res.into_body()).await.unwrap())?;
+    let to_be_updated = crud
+        .find_account_by_customer(&id)
+        .await
+        .map_err(|_| ApiError::new(500, "Failed to retrieve account information.".into()))?;
 
-    if !belongs_to_account(&req_user, &to_be_updated.id) || !require_role(&req_user, Role::Owner) {
-        return Ok(Response::builder()
-            .status(StatusCode::FORBIDDEN)
-            .body(Body::from(
-                r#"{"message":"You cannot access this resource."}"#,
-            ))
-            .unwrap());
+    if !belongs_to_account(&req_user, &to_be_updated.id) || !require_cap(&req_user, Capability::ManageBilling) {
+        return Err(ApiError::forbidden());
     }
 
     let parsed_id = CustomerId::from_str(id.as_str())?;
-    Customer::update(
-        &stripe,
-        &parsed_id,
-        UpdateCustomer {
-            name: account.business_name.as_deref(),
-            email: account.email.as_deref(),
-            address: Some(Address {
-                city: account.city,
-                line1: account.address1,
-                line2: account.address2.unwrap_or_default(),
-                postal_code: account.zip_code,
-                state: account.state,
+    billing
+        .update_customer(
+            &parsed_id,
+            UpdateCustomer {
+                name: account.business_name.as_deref(),
+                email: account.email.as_deref(),
+                address: Some(Address {
+                    city: account.city,
+                    line1: account.address1,
+                    line2: account.address2.unwrap_or_default(),
+                    postal_code: account.zip_code,
+                    state: account.state,
+                    ..Default::default()
+                }),
+                phone: account.phone_number.as_deref(),
                 ..Default::default()
-            }),
-            phone: account.phone_number.as_deref(),
-            ..Default::default()
-        },
-    )
-    .await?;
+            },
+        )
+        .await?;
 
     Ok(Response::builder()
         .status(StatusCode::OK)
@@ -5,31 +5,37 @@ use std::str::FromStr;
 
 use axum::{extract::Query, Extension, Json};
 use hyper::{Body, Response, StatusCode};
-use payments_lib::routes::{create_usage_record, sub_status::IsSubbedQuery};
-use stripe::{
-    CreateUsageRecord, Subscription, SubscriptionId, UsageRecord,
-    UsageRecordAction,
+use payments_lib::routes::{
+    cancel_subscription::CancelSubscriptionParams, create_usage_record, invoices,
+    sub_status::IsSubbedQuery,
 };
+use stripe::{CustomerId, SubscriptionId, SubscriptionStatus};
 
-use crate::{error::ApiError, INSTANCE_PRICE_ID, USER_PRICE_ID};
+use crate::{billing::BillingClient, error::ApiError, INSTANCE_PRICE_ID, USER_PRICE_ID};
 
+#[utoipa::path(
+    post,
+    path = "/create-usage-record",
+    tag = "usage",
+    request_body = CreateUsageRecordParams,
+    responses((status = 200)),
+)]
 pub async fn create_usage_record(
     Json(data): Json<create_usage_record::CreateUsageRecordParams>,
-    Extension(stripe): Extension<stripe::Client>,
+    Extension(billing): Extension<BillingClient>,
 ) -> Result<Response<Body>, ApiError> {
     if data.resource != "users" && data.resource != "instances" {
-        return Ok(Response::builder()
-            .status(StatusCode::BAD_REQUEST)
-            .body(Body::from(
-                "'resource' must be one of 'instances' or 'users'.",
-            ))
-            .unwrap());
+        return Err(ApiError::new(
+            400,
+            "'resource' must be one of 'instances' or 'users'.".into(),
+        ));
     };
 
     tracing::info!("{:?}", data);
 
-    let subscription =
-        Subscription::retrieve(&stripe, &SubscriptionId::from_str(&data.sub_id)?, &[]).await?;
+    let subscription = billing
+        .retrieve_subscription(&SubscriptionId::from_str(&data.sub_id)?, &[])
+        .await?;
 
     let sub_item = subscription.items.data.iter().find(|&item| {
         if let Some(price) = item.price.as_ref() {
@@ -45,17 +51,7 @@ pub async fn create_usage_record(
     });
 
     // new subs always have at least one user
-    let record = UsageRecord::create(
-        &stripe,
-        &sub_item.unwrap().id,
-        CreateUsageRecord {
-            action: Some(UsageRecordAction::Set),
-            quantity: data.number,
-            ..Default::default()
-        },
-    ).await?;
-
-    tracing::info!("{:?}", record);
+    billing.set_usage(&sub_item.unwrap().id, data.number).await?;
 
     Ok(Response::builder()
         .status(StatusCode::OK)
@@ -63,15 +59,117 @@ pub async fn create_usage_record(
         .unwrap())
 }
 
+#[utoipa::path(
+    get,
+    path = "/sub-status",
+    tag = "subscription",
+    params(IsSubbedQuery),
+    responses((
+        status = 200,
+        description = "Status of the stripe subscription",
+        body = String,
+        content_type = "text/plain",
+    )),
+)]
 pub async fn sub_status(
-    stripe: Extension<stripe::Client>,
+    Extension(billing): Extension<BillingClient>,
     query: Query<IsSubbedQuery>,
 ) -> Result<Response<Body>, ApiError> {
     let sub_id = SubscriptionId::from_str(&query.sub_id)?;
-    let sub = Subscription::retrieve(&stripe, &sub_id, &[]).await?;
+    let sub = billing.retrieve_subscription(&sub_id, &[]).await?;
 
     Ok(Response::builder()
         .status(StatusCode::OK)
         .body(Body::from(sub.status.as_str()))
         .unwrap())
 }
+
+/// Cancels a subscription right away, succeeds if it was already canceled so closures can retry
+#[utoipa::path(
+    post,
+    path = "/cancel-subscription",
+    tag = "subscription",
+    request_body = CancelSubscriptionParams,
+    responses((status = 200)),
+)]
+pub async fn cancel_subscription(
+    Json(data): Json<CancelSubscriptionParams>,
+    Extension(billing): Extension<BillingClient>,
+) -> Result<Response<Body>, ApiError> {
+    let sub_id = SubscriptionId::from_str(&data.sub_id)?;
+    let sub = billing.retrieve_subscription(&sub_id, &[]).await?;
+
+    if sub.status != SubscriptionStatus::Canceled {
+        billing.cancel_subscription(&sub_id).await?;
+        tracing::info!("Canceled subscription {}", sub_id);
+    }
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .body(Body::empty())
+        .unwrap())
+}
+
+/// Lists every invoice for a customer with all of its lines, following stripe's pages
+#[utoipa::path(
+    get,
+    path = "/invoices",
+    tag = "usage",
+    params(invoices::InvoicesQuery),
+    responses((status = 200, body = [Invoice])),
+)]
+pub async fn invoices(
+    Extension(billing): Extension<BillingClient>,
+    query: Query<invoices::InvoicesQuery>,
+) -> Result<Response<Body>, ApiError> {
+    let customer_id = CustomerId::from_str(&query.customer_id)?;
+    let mut found: invoices::InvoicesResponse = vec![];
+
+    loop {
+        let starting_after = found.last().map(|last| last.id.parse()).transpose()?;
+        let page = billing.list_invoices(&customer_id, starting_after).await?;
+        for invoice in page.data {
+            let mut lines = invoice.lines;
+            while lines.has_more {
+                let starting_after = lines.data.last().map(|last| last.id.clone());
+                let more = billing
+                    .list_invoice_lines(&invoice.id, starting_after)
+                    .await?;
+                lines.data.extend(more.data);
+                lines.has_more = more.has_more;
+            }
+
+            found.push(invoices::Invoice {
+                id: invoice.id.to_string(),
+                number: invoice.number,
+                status: invoice.status.map(|status| status.as_str().to_string()),
+                currency: invoice.currency.map(|currency| currency.to_string()),
+                amount_due: invoice.amount_due,
+                amount_paid: invoice.amount_paid,
+                total: invoice.total,
+                created: invoice.created,
+                hosted_invoice_url: invoice.hosted_invoice_url,
+                lines: lines
+                    .data
+                    .into_iter()
+                    .map(|line| invoices::InvoiceLine {
+                        description: line.description,
+                        quantity: line.quantity,
+                        amount: line.amount,
+                        period_start: line.period.as_ref().and_then(|period| period.start),
+                        period_end: line.period.as_ref().and_then(|period| period.end),
+                    })
+                    .collect(),
+            });
+        }
+
+        if !page.has_more {
+            break;
+        }
+    }
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .body(Body::from(serde_json::to_string(&found)?))
+        .unwrap())
+}
@@ -1,89 +1,65 @@
 use std::str::FromStr;
 
-use auth::{belongs_to_account, require_role, ExtractReqUser};
+use auth::{belongs_to_account, require_cap, ExtractReqUser};
 use axum::{routing::post, Extension, Json, Router};
-use hyper::{body, Body, Method, Request, Response, StatusCode};
-use models::types::Role;
+use crud_client::CrudClient;
+use hyper::{Body, Response, StatusCode};
+use models::types::Capability;
 use payments_lib::routes::subscription::{CreateSubscriptionParams, UpdateSubscriptionParams};
-use serde::Deserialize;
 use stripe::{
-    AttachPaymentMethod, CreateSubscription, CreateSubscriptionItems,
-    CreateSubscriptionPaymentSettings, CreateSubscriptionPaymentSettingsSaveDefaultPaymentMethod,
-    CreateUsageRecord, Customer, CustomerId, CustomerInvoiceSettings, PaymentMethod,
-    PaymentMethodId, Subscription, SubscriptionId, SubscriptionStatus, UpdateCustomer, UsageRecord,
-    UsageRecordAction,
+    CreateSubscription, CreateSubscriptionItems, CreateSubscriptionPaymentSettings,
+    CreateSubscriptionPaymentSettingsSaveDefaultPaymentMethod, CustomerId,
+    CustomerInvoiceSettings, PaymentMethodId, SubscriptionId, SubscriptionStatus, UpdateCustomer,
 };
 
-use crate::{error::ApiError, Client, CRUD_URI, INSTANCE_PRICE_ID, USER_PRICE_ID};
+use crate::{audit, billing::BillingClient, error::ApiError, INSTANCE_PRICE_ID, USER_PRICE_ID};
 
+#[utoipa::path(
+    post,
+    path = "/subscription",
+    tag = "subscription",
+    request_body = CreateSubscriptionParams,
+    responses((status = 200, description = "The stripe subscription", body = Object)),
+)]
 async fn subscribe(
     Json(data): Json<CreateSubscriptionParams>,
-    Extension(stripe): Extension<stripe::Client>,
-    Extension(client): Extension<Client>,
+    Extension(billing): Extension<BillingClient>,
+    Extension(crud): Extension<CrudClient>,
     ExtractReqUser(req_user): ExtractReqUser,
 ) -> Result<Response<Body>, ApiError> {
-    if !belongs_to_account(&req_user, &data.account.id) || !require_role(&req_user, Role::Owner) {
-        return Ok(Response::builder()
-            .status(StatusCode::FORBIDDEN)
-            .body(Body::from(
-                r#"{"message":"You cannot access this resource."}"#,
-            ))
-            .unwrap());
+    if !belongs_to_account(&req_user, &data.account.id) || !require_cap(&req_user, Capability::ManageBilling) {
+        return Err(ApiError::forbidden());
     }
 
     if data.account.stripe_id == None {
-        return Ok(Response::builder()
-            .status(StatusCode::BAD_REQUEST)
-            .body(Body::from(
-                r#"{"message":"Account does not have a customer id."}"#,
-            ))
-            .unwrap());
+        return Err(ApiError::new(
+            400,
+            "Account does not have a customer id.".into(),
+        ));
     }
 
-    let req = Request::builder()
-        .uri(format!(
-            "{}/accounts/{}/usage",
-            CRUD_URI.as_str(),
-            data.account.id
-        ))
-        .method(Method::GET)
-        .body(Body::empty())
-        .unwrap();
-
-    let res = client.request(req).await?;
-    // TODO define api for crud
-    #[derive(Deserialize)]
-    struct Usage {
-        pub users: u64,
-        pub instances: u64,
-    }
-    let usage = serde_json::from_slice::<Usage>(&body::to_bytes(res.into_body()).await.unwrap())?;
+    let usage = crud.account_usage(&data.account.id).await?;
 
     let parsed_payment_id = PaymentMethodId::from_str(&data.payment_method_id)?;
     let customer_id = data.account.stripe_id.unwrap();
     let parsed_customer_id = CustomerId::from_str(&customer_id)?;
-    let customer = Customer::retrieve(&stripe, &parsed_customer_id, &[]).await?;
+    let customer = billing.retrieve_customer(&parsed_customer_id).await?;
     let expansions = &["pending_setup_intent", "latest_invoice.payment_intent"];
-    PaymentMethod::attach(
-        &stripe,
-        &parsed_payment_id,
-        AttachPaymentMethod {
-            customer: parsed_customer_id.clone(),
-        },
-    )
-    .await?;
-    Customer::update(
-        &stripe,
-        &parsed_customer_id,
-        UpdateCustomer {
-            invoice_settings: Some(CustomerInvoiceSettings {
-                default_payment_method: Some(parsed_payment_id.to_string()),
+    billing
+        .attach_payment_method(&parsed_payment_id, &parsed_customer_id)
+        .await?;
+    billing
+        .update_customer(
+            &parsed_customer_id,
+            UpdateCustomer {
+                invoice_settings: Some(CustomerInvoiceSettings {
+                    default_payment_method: Some(parsed_payment_id.to_string()),
+                    ..Default::default()
+                }),
                 ..Default::default()
-            }),
-            ..Default::default()
-        },
-    )
-    .await?;
+            },
+        )
+        .await?;
     // get or create subscription
     let subscription = if data.account.sub_id == None && customer.subscriptions.data.len() < 1 {
         let mut params = CreateSubscription::new(CustomerId::from_str(&customer_id)?);
@@ -105,14 +81,14 @@ async fn subscribe(
         });
         params.expand = expansions;
 
-        Subscription::create(&stripe, params).await?
+        billing.create_subscription(params).await?
     } else {
-        Subscription::retrieve(
-            &stripe,
-            &SubscriptionId::from_str(&data.account.sub_id.clone().unwrap())?,
-            expansions,
-        )
-        .await?
+        billing
+            .retrieve_subscription(
+                &SubscriptionId::from_str(&data.account.sub_id.clone().unwrap())?,
+                expansions,
+            )
+            .await?
     };
 
     let user_sub_item = subscription.items.data.iter().find(|&item| {
@@ -131,52 +107,28 @@ async fn subscribe(
         }
     });
 
-    UsageRecord::create(
-        &stripe,
-        &user_sub_item.unwrap().id,
-        CreateUsageRecord {
-            action: Some(UsageRecordAction::Set),
-            quantity: usage.users,
-            ..Default::default()
-        },
-    )
-    .await?;
-
-    UsageRecord::create(
-        &stripe,
-        &instance_sub_item.unwrap().id,
-        CreateUsageRecord {
-            action: Some(UsageRecordAction::Set),
-            quantity: usage.instances,
-            ..Default::default()
-        },
-    )
-    .await?;
+    billing
+        .set_usage(&user_sub_item.unwrap().id, usage.users.try_into().unwrap())
+        .await?;
+    billing
+        .set_usage(
+            &instance_sub_item.unwrap().id,
+            usage.instances.try_into().unwrap(),
+        )
+        .await?;
 
     if data.account.sub_id == None {
-        let req = Request::builder()
-            .uri(format!(
-                "{}/accounts/{}",
-                CRUD_URI.as_str(),
-                data.account.id
-            ))
-            .method(Method::PUT)
-            .header("Content-Type", "application/json")
-            .body(Body::from(serde_json::to_string(&models::UpdateAccount {
-                sub_id: Some(Some(subscription.id.to_string())),
-                ..Default::default()
-            })?))
-            .unwrap();
-
-        let res = client.request(req).await?;
-
-        if !res.status().is_success() {
-            return Ok(Response::builder()
-                .status(StatusCode::INTERNAL_SERVER_ERROR)
-                .body(Body::from(
-                    r#"{"message":"Failed to update account subscription id."}"#,
-                ))
-                .unwrap());
+        let update = models::UpdateAccount {
+            sub_id: Some(Some(subscription.id.to_string())),
+            ..Default::default()
+        };
+        let res = crud.with_actor(&req_user).update_account(&data.account.id, &update).await;
+
+        if res.is_err() {
+            return Err(ApiError::new(
+                500,
+                "Failed to update account subscription id.".into(),
+            ));
         }
     }
 
@@ -190,27 +142,32 @@ async fn subscribe(
         .unwrap())
 }
 
+#[utoipa::path(
+    put,
+    path = "/subscription",
+    tag = "subscription",
+    request_body = CreateSubscriptionParams,
+    responses((
+        status = 200,
+        description = "Missed invoice if the subscription is past due, empty otherwise",
+        body = Object,
+    )),
+)]
 async fn update_subscription(
     Json(data): Json<UpdateSubscriptionParams>,
-    Extension(stripe): Extension<stripe::Client>,
+    Extension(billing): Extension<BillingClient>,
+    Extension(crud): Extension<CrudClient>,
     ExtractReqUser(req_user): ExtractReqUser,
 ) -> Result<Response<Body>, ApiError> {
-    if !belongs_to_account(&req_user, &data.account.id) || !require_role(&req_user, Role::Owner) {
-        return Ok(Response::builder()
-            .status(StatusCode::FORBIDDEN)
-            .body(Body::from(
-                r#"{"message":"You cannot access this resource."}"#,
-            ))
-            .unwrap());
+    if !belongs_to_account(&req_user, &data.account.id) || !require_cap(&req_user, Capability::ManageBilling) {
+        return Err(ApiError::forbidden());
     }
 
     if data.account.stripe_id == None || data.account.sub_id == None {
-        return Ok(Response::builder()
-            .status(StatusCode::BAD_REQUEST)
-            .body(Body::from(
-                r#"{"message":"Account does not have a customer or subscription id."}"#,
-            ))
-            .unwrap());
+        return Err(ApiError::new(
+            400,
+            "Account does not have a customer or subscription id.".into(),
+        ));
     }
 
     let parsed_payment_id = PaymentMethodId::from_str(&data.payment_method_id)?;
@@ -218,31 +175,36 @@ async fn update_subscription(
     let parsed_customer_id = CustomerId::from_str(&customer_id)?;
 
     // update payment method
-    PaymentMethod::attach(
-        &stripe,
-        &parsed_payment_id,
-        AttachPaymentMethod {
-            customer: parsed_customer_id.clone(),
-        },
-    )
-    .await?;
-    Customer::update(
-        &stripe,
-        &parsed_customer_id,
-        UpdateCustomer {
-            invoice_settings: Some(CustomerInvoiceSettings {
-                default_payment_method: Some(parsed_payment_id.to_string()),
+    billing
+        .attach_payment_method(&parsed_payment_id, &parsed_customer_id)
+        .await?;
+    billing
+        .update_customer(
+            &parsed_customer_id,
+            UpdateCustomer {
+                invoice_settings: Some(CustomerInvoiceSettings {
+                    default_payment_method: Some(parsed_payment_id.to_string()),
+                    ..Default::default()
+                }),
                 ..Default::default()
-            }),
-            ..Default::default()
-        },
+            },
+        )
+        .await?;
+
+    audit::record(
+        &crud,
+        &req_user,
+        &data.account.id,
+        "update_payment_method",
+        "customer",
+        &customer_id,
     )
-    .await?;
+    .await;
 
     let sub_id = data.account.sub_id.unwrap();
     let parsed_sub_id = SubscriptionId::from_str(&sub_id)?;
     let expansions = &["pending_setup_intent", "latest_invoice.payment_intent"];
-    let sub = Subscription::retrieve(&stripe, &parsed_sub_id, expansions).await?;
+    let sub = billing.retrieve_subscription(&parsed_sub_id, expansions).await?;
 
     if sub.status == SubscriptionStatus::PastDue || sub.status == SubscriptionStatus::Unpaid {
         // get missed invoice
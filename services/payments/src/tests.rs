@@ -1,2 +1,176 @@
+use std::sync::Arc;
+
+use axum::{Extension, Router};
+use hyper::{Body, Method, Request, StatusCode};
+use payments_lib::routes::{
+    cancel_subscription::CancelSubscriptionParams,
+    create_usage_record::CreateUsageRecordParams,
+    invoices::InvoicesResponse,
+};
+use stripe::{CreateCustomer, CreateSubscription, CreateSubscriptionItems, Subscription};
+use tower::Service;
+use utoipa::{openapi::PathItemType, OpenApi};
+
+use crate::{
+    billing::{BillingClient, FakeBilling},
+    openapi::ApiDoc,
+    router, INSTANCE_PRICE_ID, USER_PRICE_ID,
+};
+
 #[test]
 fn noop() {}
+
+/// Regenerate with `UPDATE_OPENAPI=1 cargo test -p payments`
+#[test]
+fn spec_is_current() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/openapi.json");
+    let spec = ApiDoc::openapi().to_pretty_json().unwrap() + "\n";
+    if std::env::var_os("UPDATE_OPENAPI").is_some() {
+        std::fs::write(path, &spec).unwrap();
+    }
+    let committed = std::fs::read_to_string(path).unwrap_or_default();
+    assert!(
+        committed == spec,
+        "openapi.json is out of date, run `UPDATE_OPENAPI=1 cargo test -p payments`"
+    );
+}
+
+#[tokio::test]
+async fn documents_mounted_routes() {
+    for (path, item) in ApiDoc::openapi().paths.paths {
+        for method in item.operations.keys() {
+            let method = match method {
+                PathItemType::Get => Method::GET,
+                PathItemType::Post => Method::POST,
+                PathItemType::Put => Method::PUT,
+                PathItemType::Delete => Method::DELETE,
+                _ => unreachable!("payments only routes get, post, put and delete"),
+            };
+            let req = Request::builder()
+                .method(&method)
+                .uri(path.replace("{id}", "cus_test"))
+                .body(Body::empty())
+                .unwrap();
+            let res = router().call(req).await.unwrap();
+            assert!(
+                res.status() != StatusCode::NOT_FOUND
+                    && res.status() != StatusCode::METHOD_NOT_ALLOWED,
+                "{} {} is documented but not routed",
+                method,
+                path
+            );
+        }
+    }
+}
+
+#[tokio::test]
+async fn serves_spec() {
+    let req = Request::get("/openapi.json").body(Body::empty()).unwrap();
+    let res = router().call(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+    let spec: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert!(spec["components"]["schemas"]["UpdateAccount"].is_object());
+    assert!(spec["paths"]["/invoices"]["get"].is_object());
+}
+
+/// Customer with a subscription to both prices, and the app billing it
+async fn subscribed(fake: &Arc<FakeBilling>) -> (Subscription, Router) {
+    dotenv::dotenv().ok();
+    let billing: BillingClient = fake.clone();
+    let customer = billing.create_customer(CreateCustomer::new()).await.unwrap();
+
+    let mut params = CreateSubscription::new(customer.id);
+    params.items = Some(vec![
+        CreateSubscriptionItems {
+            price: Some(INSTANCE_PRICE_ID.clone()),
+            ..Default::default()
+        },
+        CreateSubscriptionItems {
+            price: Some(USER_PRICE_ID.clone()),
+            ..Default::default()
+        },
+    ]);
+    let sub = billing.create_subscription(params).await.unwrap();
+
+    (sub, router().layer(Extension(billing)))
+}
+
+fn post_json(uri: &str, body: &impl serde::Serialize) -> Request<Body> {
+    Request::post(uri)
+        .header("Content-Type", "application/json")
+        .body(Body::from(serde_json::to_string(body).unwrap()))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn records_usage() {
+    let fake = Arc::new(FakeBilling::default());
+    let (sub, mut app) = subscribed(&fake).await;
+
+    let req = post_json(
+        "/create-usage-record",
+        &CreateUsageRecordParams {
+            sub_id: sub.id.to_string(),
+            resource: "users".into(),
+            number: 3,
+        },
+    );
+    let res = app.call(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let users = sub
+        .items
+        .data
+        .iter()
+        .find(|item| item.price.as_ref().unwrap().id == USER_PRICE_ID.as_str())
+        .unwrap();
+    assert_eq!(fake.usage(&users.id), Some(3));
+}
+
+#[tokio::test]
+async fn cancels_once() {
+    let fake = Arc::new(FakeBilling::default());
+    let (sub, mut app) = subscribed(&fake).await;
+    let params = CancelSubscriptionParams {
+        sub_id: sub.id.to_string(),
+    };
+
+    // a retried closure cancels again, which has to succeed
+    for _ in 0..2 {
+        let res = app
+            .call(post_json("/cancel-subscription", &params))
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    let req = Request::get(format!("/sub-status?subId={}", sub.id))
+        .body(Body::empty())
+        .unwrap();
+    let res = app.call(req).await.unwrap();
+    let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+    assert_eq!(&body[..], b"canceled");
+}
+
+#[tokio::test]
+async fn lists_every_invoice_line() {
+    let fake = Arc::new(FakeBilling::default());
+    let (sub, mut app) = subscribed(&fake).await;
+    let customer = sub.customer.id();
+    let billed = fake.add_invoice(&customer, &[100, 200, 300, 400, 500]).unwrap();
+    assert!(billed.lines.has_more);
+
+    let req = Request::get(format!("/invoices?customerId={}", customer))
+        .body(Body::empty())
+        .unwrap();
+    let res = app.call(req).await.unwrap();
+    assert_eq!(res.status(), StatusCode::OK);
+    let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+    let found: InvoicesResponse = serde_json::from_slice(&body).unwrap();
+
+    assert_eq!(found.len(), 1);
+    let amounts: Vec<i64> = found[0].lines.iter().map(|line| line.amount).collect();
+    assert_eq!(amounts, vec![100, 200, 300, 400, 500]);
+}
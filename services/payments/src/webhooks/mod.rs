@@ -1,16 +1,35 @@
-use hyper::{body, Body, Request, Response, StatusCode, Method};
-use models::UpdateAccount;
-use serde::Deserialize;
-use stripe::{
-    CreateUsageRecord, EventObject, EventType, Subscription, SubscriptionStatus, UsageRecord,
-    UsageRecordAction, Webhook,
+use axum::Extension;
+use crud_client::{
+    notifications::{Notification, OPERATOR},
+    CrudClient,
 };
+use hyper::{body, Body, Request, Response, StatusCode};
+use models::{types::NotificationKind, ListQuery, UpdateAccount};
+use stripe::{EventObject, EventType, Subscription, SubscriptionStatus, Webhook};
 
 use crate::{
-    error::ApiError, Client, CRUD_URI, INSTANCE_PRICE_ID, STRIPE_WEBHOOK_KEY, USER_PRICE_ID,
+    billing::BillingClient, error::ApiError, INSTANCE_PRICE_ID, STRIPE_WEBHOOK_KEY, USER_PRICE_ID,
 };
 
-pub async fn handler(req: Request<Body>) -> Response<Body> {
+/// Receives events from stripe, always responds with success so stripe doesn't retry
+#[utoipa::path(
+    post,
+    path = "/webhooks",
+    tag = "webhooks",
+    operation_id = "stripe_webhook",
+    params((
+        "Stripe-Signature" = String,
+        Header,
+        description = "Signs the payload with the webhook secret"
+    )),
+    request_body(content = String, description = "Stripe event", content_type = "application/json"),
+    responses((status = 200)),
+)]
+pub async fn handler(
+    Extension(crud): Extension<CrudClient>,
+    Extension(billing): Extension<BillingClient>,
+    req: Request<Body>,
+) -> Response<Body> {
     let (head, body) = req.into_parts();
     let payload_str = std::str::from_utf8(&body::to_bytes(body).await.unwrap())
         .unwrap()
@@ -24,16 +43,15 @@ pub async fn handler(req: Request<Body>) -> Response<Body> {
 
     if let Ok(event) = Webhook::construct_event(&payload_str, stripe_signature, &STRIPE_WEBHOOK_KEY)
     {
-        let client = Client::new();
         match event.event_type {
             EventType::CustomerSubscriptionDeleted => {
                 if let EventObject::Subscription(sub) = event.data.object {
-                    tokio::spawn(handle_sub_delete(client.clone(), sub));
+                    tokio::spawn(handle_sub_delete(crud.clone(), sub));
                 }
             }
             EventType::CustomerSubscriptionUpdated => {
                 if let EventObject::Subscription(sub) = event.data.object {
-                    tokio::spawn(handle_sub_update(client.clone(), sub));
+                    tokio::spawn(handle_sub_update(crud.clone(), billing.clone(), sub));
                 }
             }
             _ => {
@@ -55,45 +73,61 @@ pub async fn handler(req: Request<Body>) -> Response<Body> {
         .unwrap()
 }
 
-async fn handle_sub_delete(client: Client, sub: Subscription) {
-    let account = sub_user(&client, &sub).await;
+async fn handle_sub_delete(crud: CrudClient, sub: Subscription) {
+    let account = sub_user(&crud, &sub).await;
     if let Some(account) = account {
-        let req = Request::builder()
-            .uri(CRUD_URI.to_string() + "/accounts/" + account.id.as_str())
-            .body(Body::from(
-                serde_json::to_string(&UpdateAccount {
-                    sub_id: Some(None),
-                    ..Default::default()
-                })
-                .unwrap(),
-            ))
-            .unwrap();
+        let update = UpdateAccount {
+            sub_id: Some(None),
+            ..Default::default()
+        };
 
-        let res = client.request(req).await;
-
-        if let Ok(res) = res {
-            if res.status().is_success() {
-                tracing::info!("Successfully canceled sub for {}", account.business_name);
-            } else {
-                tracing::error!("Failed to update Account and cancel subscription.");
-                // TODO some kinda notification for me
-            }
+        if crud.update_account(&account.id, &update).await.is_ok() {
+            tracing::info!("Successfully canceled sub for {}", account.business_name);
         } else {
             tracing::error!("Failed to update Account and cancel subscription.");
-            // TODO some kinda notification for me
+            let notification = Notification {
+                // only we can fix it, the account still has access it isn't paying for
+                account_id: OPERATOR.into(),
+                kind: NotificationKind::SubscriptionSyncFailed,
+                subject: "A canceled subscription is still active in crud".into(),
+                body: format!(
+                    "Subscription {} for account {} ended but the account couldn't be updated, remove its sub id by hand.",
+                    sub.id, account.id
+                ),
+                instance_id: None,
+            };
+            if crud.send_notification(&notification).await.is_err() {
+                tracing::error!("Failed to notify about subscription {}.", sub.id);
+            }
         }
     }
 }
 
-async fn handle_sub_update(client: Client, sub: Subscription) -> Result<(), ApiError> {
+async fn handle_sub_update(
+    crud: CrudClient,
+    billing: BillingClient,
+    sub: Subscription,
+) -> Result<(), ApiError> {
     if sub.status == SubscriptionStatus::Canceled || sub.status == SubscriptionStatus::Unpaid {
         // Their subscription is bad so we revoke access 😈
-        handle_sub_delete(client, sub).await;
+        handle_sub_delete(crud, sub).await;
     } else if sub.status == SubscriptionStatus::PastDue {
-        // TODO notify account holder of subscription is past due
+        if let Some(account) = sub_user(&crud, &sub).await {
+            let notification = Notification {
+                account_id: account.id.clone(),
+                kind: NotificationKind::PaymentPastDue,
+                subject: "Your payment is past due".into(),
+                body: format!(
+                    "The latest payment for {} didn't go through. Update your payment method to keep your instances running.",
+                    account.business_name
+                ),
+                instance_id: None,
+            };
+            crud.send_notification(&notification).await?;
+        }
     } else if sub.status == SubscriptionStatus::Active {
         // Make sure usage records are up to date with current usage
-        let account = sub_user(&client, &sub).await;
+        let account = sub_user(&crud, &sub).await;
         if let Some(account) = account {
             let user_sub_item = sub.items.data.iter().find(|&item| {
                 if let Some(price) = item.price.as_ref() {
@@ -103,29 +137,17 @@ async fn handle_sub_update(client: Client, sub: Subscription) -> Result<(), ApiE
                 }
             });
 
-            let stripe = stripe::Client::new(crate::STRIPE_KEY.as_str());
-            let req = Request::builder()
-                .method(Method::GET)
-                .uri(CRUD_URI.to_string() + "/accounts/" + account.id.as_str() + "/users")
-                .body(Body::empty())
-                .unwrap();
-
-            let res = client.request(req).await?;
-            #[derive(Deserialize)]
-            struct MinUser {}
-            let users =
-                serde_json::from_slice::<Vec<MinUser>>(&body::to_bytes(res.into_body()).await?)?;
+            // only need the total from the page
+            let first = ListQuery {
+                limit: Some(1),
+                ..Default::default()
+            };
+            let users = crud.find_account_users(&account.id, &first).await?;
 
             // make sure usage is up to date for user
-            UsageRecord::create(
-                &stripe,
-                &user_sub_item.unwrap().id,
-                CreateUsageRecord {
-                    action: Some(UsageRecordAction::Set),
-                    quantity: users.len().try_into().unwrap(),
-                    ..Default::default()
-                },
-            );
+            billing
+                .set_usage(&user_sub_item.unwrap().id, users.total.try_into().unwrap())
+                .await?;
 
             let instance_sub_item = sub.items.data.iter().find(|&item| {
                 if let Some(price) = item.price.as_ref() {
@@ -135,47 +157,20 @@ async fn handle_sub_update(client: Client, sub: Subscription) -> Result<(), ApiE
                 }
             });
 
-            let stripe = stripe::Client::new(crate::STRIPE_KEY.as_str());
-            let req = Request::builder()
-                .method(Method::GET)
-                .uri(CRUD_URI.to_string() + "/accounts/" + account.id.as_str() + "/instances")
-                .body(Body::empty())
-                .unwrap();
+            let instances = crud.find_account_instances(&account.id, &first).await?;
 
-            let res = client.request(req).await?;
-            #[derive(Deserialize)]
-            struct MinInstance {}
-            let users = serde_json::from_slice::<Vec<MinInstance>>(
-                &body::to_bytes(res.into_body()).await?,
-            )?;
-
-            UsageRecord::create(
-                &stripe,
-                &instance_sub_item.unwrap().id,
-                CreateUsageRecord {
-                    action: Some(UsageRecordAction::Set),
-                    quantity: users.len().try_into().unwrap(),
-                    ..Default::default()
-                },
-            );
+            billing
+                .set_usage(
+                    &instance_sub_item.unwrap().id,
+                    instances.total.try_into().unwrap(),
+                )
+                .await?;
         }
     };
 
     Ok(())
 }
 
-async fn sub_user(client: &Client, sub: &Subscription) -> Option<models::Account> {
-    let req = Request::builder()
-        .method(Method::GET)
-        .uri(CRUD_URI.to_string() + "/accounts/by-sub/" + sub.id.as_str())
-        .body(Body::empty())
-        .unwrap();
-    let res = client.request(req).await.ok();
-
-    if let Some(res) = res {
-        serde_json::from_slice::<models::Account>(&body::to_bytes(res.into_body()).await.unwrap())
-            .ok()
-    } else {
-        None
-    }
+async fn sub_user(crud: &CrudClient, sub: &Subscription) -> Option<models::Account> {
+    crud.find_account_by_sub(sub.id.as_str()).await.ok()
 }
@@ -1,5 +1,6 @@
 use std::time::Duration;
 
+use cloud::{Aws, Beanstalk};
 use itertools::Itertools;
 use lambda_runtime::{run, service_fn, LambdaEvent};
 
@@ -19,40 +20,23 @@ struct Response {
     exit_code: i32,
 }
 
-async fn function_handler(event: LambdaEvent<Payload>) -> Result<Response, Error> {
+async fn function_handler(
+    beanstalk: &dyn Beanstalk,
+    event: LambdaEvent<Payload>,
+) -> Result<Response, Error> {
     let (message, _context) = event.into_parts();
-    let aws_config = aws_config::load_from_env().await;
-    let eb_client = aws_sdk_elasticbeanstalk::Client::new(&aws_config);
 
-    let app_version_label = eb_client
-        .describe_application_versions()
-        .application_name(&message.app_name)
-        .send()
+    let app_version_label = beanstalk
+        .version_labels(&message.app_name)
         .await?
-        .application_versions()
-        .map(|x| x.to_owned())
-        .ok_or(Error::new(format!(
-            "Error getting application versions for application: {}",
-            &message.app_name
-        )))?
-        .get(0)
-        .map(|x| x.clone())
+        .into_iter()
+        .next()
         .ok_or(Error::new(format!(
             "No applications versions for application: {}",
             &message.app_name
-        )))?
-        .version_label()
-        .map(|x| x.to_owned())
-        .ok_or(Error::new("No version label?!?!?!"))?;
-
-    let environments = eb_client
-        .describe_environments()
-        .application_name(&message.app_name)
-        .send()
-        .await?
-        .environments()
-        .ok_or(Error::new("No environments found."))?
-        .to_owned();
+        )))?;
+
+    let environments = beanstalk.environments(&message.app_name).await?;
 
     if environments.len() == 0 {
         info!("No environments to update.");
@@ -74,17 +58,14 @@ async fn function_handler(event: LambdaEvent<Payload>) -> Result<Response, Error
     for env_chunk in env_chunks.into_iter() {
         // update all envs in a chunk then wait 200ms so no rate limit
         for env in env_chunk.into_iter() {
-            let update_result = eb_client
-                .update_environment()
-                .environment_id(env.environment_id().unwrap())
-                .version_label(&app_version_label)
-                .send()
+            let update_result = beanstalk
+                .update_environment(&env.id, &app_version_label)
                 .await;
 
             if update_result.is_err() {
                 error!(
                     "failed to update environment {} for app {}.",
-                    env.environment_name().unwrap(),
+                    env.name,
                     message.app_name
                 );
             }
@@ -106,5 +87,9 @@ async fn main() -> Result<(), lambda_runtime::Error> {
         .without_time()
         .init();
 
-    run(service_fn(function_handler)).await
+    let aws = Aws::from_env().await;
+    run(service_fn(|event| function_handler(aws.beanstalk.as_ref(), event))).await
 }
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,82 @@
+use std::sync::Arc;
+
+use cloud::{beanstalk::NewEnvironment, Aws, FakeAws};
+use lambda_runtime::{Context, LambdaEvent};
+
+use crate::{function_handler, Payload};
+
+fn new_environment(application: &str, name: &str) -> NewEnvironment {
+    NewEnvironment {
+        application_name: application.into(),
+        environment_name: name.into(),
+        version_label: "v1".into(),
+        solution_stack: "docker".into(),
+        options: vec![],
+    }
+}
+
+#[tokio::test]
+async fn updates_running_environments() {
+    let fake = Arc::new(FakeAws::default());
+    let aws = Aws::fake(fake.clone());
+
+    for name in ["first", "second"] {
+        aws.beanstalk
+            .create_environment(new_environment("pudo", name))
+            .await
+            .unwrap();
+    }
+    let terminated = aws
+        .beanstalk
+        .create_environment(new_environment("pudo", "terminated"))
+        .await
+        .unwrap();
+    aws.beanstalk
+        .terminate_environment(&terminated.id)
+        .await
+        .unwrap();
+    aws.beanstalk
+        .create_environment(new_environment("other", "other"))
+        .await
+        .unwrap();
+
+    let event = LambdaEvent::new(
+        Payload {
+            app_name: "pudo".into(),
+        },
+        Context::default(),
+    );
+    let response = function_handler(aws.beanstalk.as_ref(), event)
+        .await
+        .unwrap();
+    assert_eq!(response.exit_code, 0);
+
+    let labels: Vec<(String, String)> = fake
+        .environments()
+        .into_iter()
+        .map(|env| (env.environment.name, env.version_label))
+        .collect();
+    assert_eq!(
+        labels,
+        vec![
+            ("first".into(), "fake-version".into()),
+            ("second".into(), "fake-version".into()),
+            ("terminated".into(), "v1".into()),
+            ("other".into(), "v1".into()),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn fails_without_versions() {
+    let fake = Arc::new(FakeAws::default());
+    fake.fail_on("version_labels");
+
+    let event = LambdaEvent::new(
+        Payload {
+            app_name: "pudo".into(),
+        },
+        Context::default(),
+    );
+    assert!(function_handler(fake.as_ref(), event).await.is_err());
+}
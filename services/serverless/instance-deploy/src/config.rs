@@ -5,17 +5,12 @@ mod types;
 use std::time::Duration;
 
 use aws_lambda_events::sqs::SqsEvent;
-use aws_sdk_elasticloadbalancingv2::model::{
-    Action, ActionTypeEnum, Certificate, ProtocolEnum, RedirectActionConfig,
-    RedirectActionStatusCodeEnum,
-};
-use aws_sdk_route53::model::{
-    AliasTarget, Change, ChangeAction, ChangeBatch, ResourceRecordSet, RrType,
-};
+use cloud::{dns::AliasRecord, Aws};
 use lambda_runtime::{service_fn, LambdaEvent};
 use serde::Serialize;
 
 use common::{CRUD_URI, DOMAIN_NAME, ELB_ZONE_ID, HOSTED_ZONE_ID};
+use crud_client::{instances::CallbackParams, CrudClient};
 use error::Error;
 use types::ConfigMessage;
 
@@ -45,11 +40,7 @@ async fn main() -> Result<(), lambda_runtime::Error> {
 }
 
 async fn func(event: LambdaEvent<SqsEvent>) -> Result<Response, Error> {
-    let aws_config = aws_config::load_from_env().await;
-    let eb_client = aws_sdk_elasticbeanstalk::Client::new(&aws_config);
-    let elb_client = aws_sdk_elasticloadbalancingv2::Client::new(&aws_config);
-    let r53_client = aws_sdk_route53::Client::new(&aws_config);
-    let sqs_client = aws_sdk_sqs::Client::new(&aws_config);
+    let aws = Aws::from_env().await;
 
     let http_client = reqwest::Client::builder()
         .use_rustls_tls()
@@ -57,6 +48,7 @@ async fn func(event: LambdaEvent<SqsEvent>) -> Result<Response, Error> {
         .timeout(Duration::from_secs(2))
         .build()
         .unwrap();
+    let crud = CrudClient::with_client(http_client, CRUD_URI.as_str());
 
     let (event, _context) = event.into_parts();
 
@@ -67,14 +59,7 @@ async fn func(event: LambdaEvent<SqsEvent>) -> Result<Response, Error> {
         .map(|record| {
             let message: ConfigMessage = serde_json::from_str(&record.body.unwrap()).unwrap();
             (
-                tokio::spawn(handle_message(
-                    message,
-                    eb_client.clone(),
-                    elb_client.clone(),
-                    r53_client.clone(),
-                    sqs_client.clone(),
-                    http_client.clone(),
-                )),
+                tokio::spawn(handle_message(message, aws.clone(), crud.clone())),
                 record.message_id.unwrap(),
             )
         })
@@ -112,214 +97,101 @@ async fn func(event: LambdaEvent<SqsEvent>) -> Result<Response, Error> {
     })
 }
 
-async fn handle_message(
-    message: ConfigMessage,
-    eb_client: aws_sdk_elasticbeanstalk::Client,
-    elb_client: aws_sdk_elasticloadbalancingv2::Client,
-    r53_client: aws_sdk_route53::Client,
-    sqs_client: aws_sdk_sqs::Client,
-    http_client: reqwest::Client,
-) -> Result<(), Error> {
-    let result: Result<String, Error> = async {let balancer_arn = eb_client
-        .describe_environment_resources()
-        .environment_id(&message.env_id)
-        .send()
-        .await?
-        .environment_resources()
-        .ok_or(Error::new("No resources found."))?
-        .load_balancers()
-        .ok_or(Error::new("No load balancers yet"))?
-        .get(0)
-        .ok_or(Error::new("No load balancer?!"))?
-        .name()
-        .ok_or(Error::new("Load balancer doesn't have a name!?"))?
-        .to_string();
-
-    let http_listener_arn = elb_client
-        .describe_listeners()
-        .load_balancer_arn(&balancer_arn)
-        .send()
-        .await?
-        .listeners()
-        .ok_or(Error::new("No listeners found"))?
-        .get(0)
-        .ok_or(Error::new("No listener?!?!"))?
-        .listener_arn()
-        .ok_or(Error::new("Listener has no arn?!"))?
-        .to_string();
-
-    let target_group_arn = elb_client
-        .describe_target_groups()
-        .load_balancer_arn(&balancer_arn)
-        .send()
-        .await?
-        .target_groups()
-        .ok_or(Error::new("No target groups found"))?
-        .get(0)
-        .ok_or(Error::new("No target group in arr?!?!"))?
-        .target_group_arn()
-        .ok_or(Error::new("Target group has no arn!?"))?
-        .to_string();
-
-    // adding an https listener to the load balancer
-    elb_client
-        .create_listener()
-        .load_balancer_arn(&balancer_arn)
-        .port(443)
-        .protocol(ProtocolEnum::Https)
-        .ssl_policy("ELBSecurityPolicy-2016-08")
-        .default_actions(
-            Action::builder()
-                .r#type(ActionTypeEnum::Forward)
-                .target_group_arn(&target_group_arn)
-                .build(),
-        )
-        .certificates(
-            Certificate::builder()
-            .certificate_arn("arn:aws:acm:us-east-1:262246349843:certificate/67e2142a-df92-424b-b92c-f5af04d12952")
-            .build()
-        )
-        .send()
-        .await?;
-
-    // change http listener to redirect to the https port
-    elb_client
-        .modify_listener()
-        .listener_arn(&http_listener_arn)
-        .default_actions(
-            Action::builder()
-                .r#type(ActionTypeEnum::Redirect)
-                .redirect_config(
-                    RedirectActionConfig::builder()
-                        .status_code(RedirectActionStatusCodeEnum::Http301)
-                        .port("443")
-                        .protocol("HTTPS")
-                        .build(),
-                )
-                .build(),
-        )
-        .send()
-        .await?;
-
-    elb_client
-        .set_security_groups()
-        .load_balancer_arn(&balancer_arn)
-        .security_groups("sg-0e949ec585c11b34a")
-        .send()
-        .await?;
-
-    let cname = eb_client
-        .describe_environments()
-        .application_name(&message.application_name)
-        .environment_ids(&message.env_id)
-        .send()
-        .await?
-        .environments()
-        .ok_or(Error::new("No envs??"))?
-        .get(0)
-        .ok_or(Error::new("No env??"))?
-        .cname()
-        .ok_or(Error::new("No CNAME????"))?
-        .to_string();
-
-    let dns_name = format!("{}.{}", message.env_name, DOMAIN_NAME);
-
-    // add dns record & link to the environment
-    r53_client
-        .change_resource_record_sets()
-        .hosted_zone_id(HOSTED_ZONE_ID)
-        .change_batch(
-            ChangeBatch::builder()
-                .changes(
-                    Change::builder()
-                        .action(aws_sdk_route53::model::ChangeAction::Create)
-                        .resource_record_set(
-                            ResourceRecordSet::builder()
-                                .r#type(aws_sdk_route53::model::RrType::A)
-                                .name(&dns_name)
-                                .alias_target(
-                                    AliasTarget::builder()
-                                        .dns_name(&cname)
-                                        // us east 1
-                                        .hosted_zone_id(ELB_ZONE_ID)
-                                        .evaluate_target_health(false)
-                                        .build(),
-                                )
-                                .build(),
-                        )
-                        .build(),
-                )
-                .build(),
-        )
-        .send()
-        .await?;
+async fn handle_message(message: ConfigMessage, aws: Aws, crud: CrudClient) -> Result<(), Error> {
+    let result: Result<AliasRecord, Error> = async {
+        let balancer_arn = aws
+            .beanstalk
+            .load_balancers(&message.env_id)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or(Error::new("No load balancers yet"))?;
+
+        let http_listener_arn = aws
+            .load_balancers
+            .listeners(&balancer_arn)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or(Error::new("No listener?!?!"))?;
+
+        let target_group_arn = aws
+            .load_balancers
+            .target_groups(&balancer_arn)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or(Error::new("No target group in arr?!?!"))?;
+
+        // adding an https listener to the load balancer
+        aws.load_balancers
+            .create_https_listener(
+                &balancer_arn,
+                &target_group_arn,
+                "arn:aws:acm:us-east-1:262246349843:certificate/67e2142a-df92-424b-b92c-f5af04d12952",
+            )
+            .await?;
 
-        Ok(dns_name)}.await;
+        // change http listener to redirect to the https port
+        aws.load_balancers
+            .redirect_to_https(&http_listener_arn)
+            .await?;
 
-    if let Ok(dns_name) = result {
-        let callback_res = http_client
-            .post(format!(
-                "{}instances/{}/callback",
-                *CRUD_URI, message.instance_id
-            ))
-            .header("jwt", &message.jwt)
-            .json(&serde_json::json!({
-                "envId": &message.env_id,
-                "accountId": &message.account_id,
-                "url": &dns_name,
-            }))
-            .send()
+        aws.load_balancers
+            .set_security_groups(&balancer_arn, &["sg-0e949ec585c11b34a".to_string()])
             .await?;
 
-        match callback_res.error_for_status() {
+        let cname = aws
+            .beanstalk
+            .find_environment(&message.application_name, &message.env_id)
+            .await?
+            .ok_or(Error::new("No env??"))?
+            .cname
+            .ok_or(Error::new("No CNAME????"))?;
+
+        // add dns record & link to the environment
+        let record = AliasRecord {
+            hosted_zone_id: HOSTED_ZONE_ID.into(),
+            name: format!("{}.{}", message.env_name, DOMAIN_NAME),
+            target_dns_name: cname,
+            // us east 1
+            target_zone_id: ELB_ZONE_ID.into(),
+        };
+        aws.dns.create_alias(&record).await?;
+
+        Ok(record)
+    }
+    .await;
+
+    if let Ok(record) = result {
+        let callback_res = crud
+            .with_jwt(&message.jwt)
+            .instance_callback(
+                &message.instance_id,
+                &CallbackParams {
+                    env_id: message.env_id.clone(),
+                    account_id: message.account_id.clone(),
+                    url: record.name.clone(),
+                },
+            )
+            .await;
+
+        match callback_res {
             Ok(_) => Ok(()),
             Err(_) => {
                 // failed to tell central that instance is deployed
                 // delete env and domain records and send to the fail queue
-
-                let env = eb_client
-                    .terminate_environment()
-                    .environment_id(&message.env_id)
-                    .force_terminate(true)
-                    .send()
-                    .await?;
-
-                r53_client
-                    .change_resource_record_sets()
-                    .hosted_zone_id("Z0898550109O7ZB98C1FF")
-                    .change_batch(
-                        ChangeBatch::builder()
-                            .changes(
-                                Change::builder()
-                                    .action(ChangeAction::Delete)
-                                    .resource_record_set(
-                                        ResourceRecordSet::builder()
-                                            .name(&dns_name)
-                                            .r#type(RrType::A)
-                                            .alias_target(
-                                                AliasTarget::builder()
-                                                    .dns_name(env.cname().unwrap())
-                                                    .evaluate_target_health(false)
-                                                    .hosted_zone_id("Z117KPS5GTRQ2G")
-                                                    .build(),
-                                            )
-                                            .build(),
-                                    )
-                                    .build(),
-                            )
-                            .build(),
-                    )
-                    .send()
+                aws.beanstalk
+                    .terminate_environment(&message.env_id)
                     .await?;
+                aws.dns.delete_alias(&record).await?;
 
                 // send to fail queue
-                sqs_client
-                    .send_message()
-                    .queue_url(
+                aws.queue
+                    .send_message(
                         "https://sqs.us-east-1.amazonaws.com/262246349843/DeadInstanceDeploy",
+                        serde_json::to_string(&message).unwrap(),
                     )
-                    .message_body(&serde_json::to_string(&message).unwrap())
-                    .send()
                     .await?;
 
                 // return ok so that it doesn't go back into queue
@@ -5,15 +5,16 @@ mod types;
 use std::time::Duration;
 
 use aws_lambda_events::sns::SnsEvent;
-use aws_sdk_elasticbeanstalk::{
-    model::{ConfigurationOptionSetting, EnvironmentTier},
-    output::CreateEnvironmentOutput,
+use cloud::{
+    beanstalk::{ConfigOption, Environment, NewEnvironment},
+    Aws,
 };
 use lambda_runtime::{service_fn, LambdaEvent};
 use nanoid::nanoid;
 use serde::Serialize;
 
 use common::CRUD_URI;
+use crud_client::CrudClient;
 use error::Error;
 use types::{ConfigMessage, DeployMessage};
 
@@ -37,9 +38,7 @@ async fn main() -> Result<(), lambda_runtime::Error> {
 }
 
 async fn func(event: LambdaEvent<SnsEvent>) -> Result<Response, Error> {
-    let aws_config = aws_config::load_from_env().await;
-    let eb_client = aws_sdk_elasticbeanstalk::Client::new(&aws_config);
-    let sqs_client = aws_sdk_sqs::Client::new(&aws_config);
+    let aws = Aws::from_env().await;
 
     let http_client = reqwest::Client::builder()
         .use_rustls_tls()
@@ -47,43 +46,28 @@ async fn func(event: LambdaEvent<SnsEvent>) -> Result<Response, Error> {
         .timeout(Duration::from_secs(10))
         .build()
         .unwrap();
+    let crud = CrudClient::with_client(http_client, CRUD_URI.as_str());
 
     let (event, _context) = event.into_parts();
     let message: DeployMessage = serde_json::from_str(&event.records[0].sns.message).unwrap();
     // eventually get this from the payload
     let application_name = "pudo";
 
-    let result: Result<CreateEnvironmentOutput, Error> = async {
-        let app_version_label = eb_client
-            .describe_application_versions()
-            .application_name(application_name)
-            .send()
+    let result: Result<Environment, Error> = async {
+        let app_version_label = aws
+            .beanstalk
+            .version_labels(application_name)
             .await?
-            .application_versions()
-            .map(|x| x.to_owned())
-            .ok_or(Error::new(format!(
-                "Error getting application versions for application: {}",
-                application_name
-            )))?
-            .get(0)
-            .map(|x| x.clone())
+            .into_iter()
+            .next()
             .ok_or(Error::new(format!(
                 "No applications versions for application: {}",
                 application_name
-            )))?
-            .version_label()
-            .map(|x| x.to_owned())
-            .ok_or(Error::new("No version label?!?!?!"))?;
+            )))?;
 
         println!("ver label gotten");
 
-        let solutions_stacks = eb_client
-            .list_available_solution_stacks()
-            .send()
-            .await?
-            .solution_stacks()
-            .map(|x| x.to_owned())
-            .ok_or(Error::new("No solution stacks found?????"))?;
+        let solutions_stacks = aws.beanstalk.solution_stacks().await?;
 
         let docker_stack = solutions_stacks
             .into_iter()
@@ -94,37 +78,32 @@ async fn func(event: LambdaEvent<SnsEvent>) -> Result<Response, Error> {
         let db_pass = nanoid!(36);
         let jwt_secret = nanoid!(36);
 
-        let env_info = eb_client
-            .create_environment()
-            .application_name(application_name)
-            .environment_name(env_name)
-            .tier(
-                EnvironmentTier::builder()
-                    .name("WebServer")
-                    .r#type("Standard")
-                    .build(),
-            )
-            .version_label(app_version_label)
-            .solution_stack_name(docker_stack)
-            .set_option_settings(Some({
-                let mut options = no_config_options();
-
-                options.extend(
-                    [
-                        set_db("DBPassword", &db_pass),
-                        set_env("KEY", &message.key),
-                        set_env("ID", &message.instance_id),
-                        set_env("ACCOUNT_ID", &message.account_id),
-                        set_env("NEXT_PUBLIC_NAME", &message.name),
-                        set_env("NAME", &message.name),
-                        set_env("JWT_SECRET", &jwt_secret),
-                    ]
-                    .into_iter(),
-                );
-
-                options
-            }))
-            .send()
+        let env_info = aws
+            .beanstalk
+            .create_environment(NewEnvironment {
+                application_name: application_name.to_string(),
+                environment_name: env_name,
+                version_label: app_version_label,
+                solution_stack: docker_stack,
+                options: {
+                    let mut options = no_config_options();
+
+                    options.extend(
+                        [
+                            set_db("DBPassword", &db_pass),
+                            set_env("KEY", &message.key),
+                            set_env("ID", &message.instance_id),
+                            set_env("ACCOUNT_ID", &message.account_id),
+                            set_env("NEXT_PUBLIC_NAME", &message.name),
+                            set_env("NAME", &message.name),
+                            set_env("JWT_SECRET", &jwt_secret),
+                        ]
+                        .into_iter(),
+                    );
+
+                    options
+                },
+            })
             .await?;
 
         Ok(env_info)
@@ -132,20 +111,9 @@ async fn func(event: LambdaEvent<SnsEvent>) -> Result<Response, Error> {
     .await;
 
     if let Ok(env_info) = result {
-        let env_id = env_info
-            .environment_id()
-            .ok_or(Error::new("No env id??"))?
-            .to_string();
-
-        let env_name = env_info
-            .environment_name()
-            .ok_or(Error::new("No env name??"))?
-            .to_string();
-
-        sqs_client
-            .send_message()
-            .queue_url("https://sqs.us-east-1.amazonaws.com/262246349843/CentralInstanceConfig")
-            .message_body(
+        aws.queue
+            .send_message(
+                "https://sqs.us-east-1.amazonaws.com/262246349843/CentralInstanceConfig",
                 serde_json::to_string(&ConfigMessage {
                     account_id: message.account_id.clone(),
                     instance_id: message.instance_id.clone(),
@@ -154,32 +122,26 @@ async fn func(event: LambdaEvent<SnsEvent>) -> Result<Response, Error> {
                     jwt: message.jwt,
                     application_name: application_name.to_string(),
                     // add necessary data from environment
-                    env_id,
-                    env_name,
+                    env_id: env_info.id,
+                    env_name: env_info.name,
                 })
                 .unwrap(),
             )
-            .send()
             .await?;
 
         Ok(Response {
             message: "Deployed".into(),
         })
     } else {
-        http_client
-            .post(format!(
-                "{}instances/{}/fail-callback",
-                *CRUD_URI, message.instance_id
-            ))
-            .header("jwt", message.jwt)
-            .send()
+        crud.with_jwt(&message.jwt)
+            .instance_fail_callback(&message.instance_id)
             .await?;
 
         Err(result.err().unwrap())
     }
 }
 
-type SetterFunction = &'static (dyn Sync + Fn(&str, &str) -> ConfigurationOptionSetting);
+type SetterFunction = &'static (dyn Sync + Fn(&str, &str) -> ConfigOption);
 
 #[allow(non_upper_case_globals)]
 static set_db: SetterFunction = &create_option_setter("aws:rds:dbinstance");
@@ -187,8 +149,8 @@ static set_db: SetterFunction = &create_option_setter("aws:rds:dbinstance");
 static set_env: SetterFunction =
     &create_option_setter("aws:elasticbeanstalk:application:environment");
 
-fn no_config_options() -> Vec<ConfigurationOptionSetting> {
-    let options: Vec<ConfigurationOptionSetting> = vec![
+fn no_config_options() -> Vec<ConfigOption> {
+    let options: Vec<ConfigOption> = vec![
         set_db("DBAllocatedStorage", "10"),
         set_db("DBDeletionPolicy", "Delete"),
         set_db("DBEngine", "postgres"),
@@ -232,17 +194,11 @@ fn no_config_options() -> Vec<ConfigurationOptionSetting> {
     options
 }
 
-fn option(namespace: &str, key: &str, value: &str) -> ConfigurationOptionSetting {
-    ConfigurationOptionSetting::builder()
-        .namespace(namespace)
-        .option_name(key)
-        .value(value)
-        .build()
+fn option(namespace: &str, key: &str, value: &str) -> ConfigOption {
+    ConfigOption::new(namespace, key, value)
 }
 
-const fn create_option_setter(
-    namespace: &'static str,
-) -> impl Fn(&str, &str) -> ConfigurationOptionSetting {
+const fn create_option_setter(namespace: &'static str) -> impl Fn(&str, &str) -> ConfigOption {
     {
         move |k, v| option(namespace, k, v)
     }
@@ -19,12 +19,6 @@ impl std::fmt::Display for Error {
 
 impl std::error::Error for Error {}
 
-impl<E: std::fmt::Debug> From<aws_sdk_elasticbeanstalk::types::SdkError<E>> for Error {
-    fn from(err: aws_sdk_elasticbeanstalk::types::SdkError<E>) -> Self {
-        Error::new(format!("[Elastic Beanstalk]: {:?}", err))
-    }
-}
-
 impl<T: std::error::Error + Send + Sync + 'static> From<Box<T>> for Error {
     fn from(err: Box<T>) -> Self {
         Error::new(format!("[Error]: {:?}", err))
@@ -32,6 +26,12 @@ impl<T: std::error::Error + Send + Sync + 'static> From<Box<T>> for Error {
     }
 }
 
+impl From<errors::ApiError> for Error {
+    fn from(err: errors::ApiError) -> Self {
+        Error::new(err)
+    }
+}
+
 impl From<reqwest::Error> for Error {
     fn from(err: reqwest::Error) -> Self {
         Error::new(format!("[Request Error] {:?}", err))
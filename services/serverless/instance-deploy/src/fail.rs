@@ -5,10 +5,9 @@ mod types;
 use std::time::Duration;
 
 use aws_lambda_events::sqs::SqsEvent;
-use aws_sdk_route53::model::{
-    AliasTarget, Change, ChangeAction, ChangeBatch, ResourceRecordSet, RrType,
-};
+use cloud::{dns::AliasRecord, Aws};
 use common::{CRUD_URI, DOMAIN_NAME, ELB_ZONE_ID, HOSTED_ZONE_ID};
+use crud_client::CrudClient;
 use lambda_runtime::{service_fn, LambdaEvent};
 use serde::Serialize;
 
@@ -41,9 +40,7 @@ async fn main() -> Result<(), lambda_runtime::Error> {
 }
 
 async fn func(event: LambdaEvent<SqsEvent>) -> Result<Response, Error> {
-    let aws_config = aws_config::load_from_env().await;
-    let eb_client = aws_sdk_elasticbeanstalk::Client::new(&aws_config);
-    let r53_client = aws_sdk_route53::Client::new(&aws_config);
+    let aws = Aws::from_env().await;
 
     let http_client = reqwest::Client::builder()
         .use_rustls_tls()
@@ -51,6 +48,7 @@ async fn func(event: LambdaEvent<SqsEvent>) -> Result<Response, Error> {
         .timeout(Duration::from_secs(2))
         .build()
         .unwrap();
+    let crud = CrudClient::with_client(http_client, CRUD_URI.as_str());
 
     let (event, _context) = event.into_parts();
 
@@ -61,12 +59,7 @@ async fn func(event: LambdaEvent<SqsEvent>) -> Result<Response, Error> {
         .map(|record| {
             let message: FailMessage = serde_json::from_str(&record.body.unwrap()).unwrap();
             (
-                tokio::spawn(handle_message(
-                    message,
-                    eb_client.clone(),
-                    r53_client.clone(),
-                    http_client.clone(),
-                )),
+                tokio::spawn(handle_message(message, aws.clone(), crud.clone())),
                 record.message_id.unwrap(),
             )
         })
@@ -104,63 +97,27 @@ async fn func(event: LambdaEvent<SqsEvent>) -> Result<Response, Error> {
     })
 }
 
-async fn handle_message(
-    message: FailMessage,
-    eb_client: aws_sdk_elasticbeanstalk::Client,
-    r53_client: aws_sdk_route53::Client,
-    http_client: reqwest::Client,
-) -> Result<(), Error> {
+async fn handle_message(message: FailMessage, aws: Aws, crud: CrudClient) -> Result<(), Error> {
     if let Some(env_id) = message.env_id {
         if let Some(env_name) = message.env_name {
-            let term_result = eb_client
-                .terminate_environment()
-                .environment_id(&env_id)
-                .force_terminate(true)
-                .send()
-                .await;
+            let term_result = aws.beanstalk.terminate_environment(&env_id).await;
 
             if let Ok(env) = term_result {
-                r53_client
-                    .change_resource_record_sets()
-                    .hosted_zone_id(HOSTED_ZONE_ID)
-                    .change_batch(
-                        ChangeBatch::builder()
-                            .changes(
-                                Change::builder()
-                                    .action(ChangeAction::Delete)
-                                    .resource_record_set(
-                                        ResourceRecordSet::builder()
-                                            .name(&format!("{}.{}", env_name, DOMAIN_NAME))
-                                            .r#type(RrType::A)
-                                            .alias_target(
-                                                AliasTarget::builder()
-                                                    .dns_name(env.cname().unwrap())
-                                                    .evaluate_target_health(false)
-                                                    .hosted_zone_id(ELB_ZONE_ID)
-                                                    .build(),
-                                            )
-                                            .build(),
-                                    )
-                                    .build(),
-                            )
-                            .build(),
-                    )
-                    .send()
+                aws.dns
+                    .delete_alias(&AliasRecord {
+                        hosted_zone_id: HOSTED_ZONE_ID.into(),
+                        name: format!("{}.{}", env_name, DOMAIN_NAME),
+                        target_dns_name: env.cname.unwrap_or_default(),
+                        target_zone_id: ELB_ZONE_ID.into(),
+                    })
                     .await
                     .ok();
             }
         }
     }
 
-    http_client
-        .post(format!(
-            "{}instances/{}/fail-callback",
-            *CRUD_URI, message.instance_id
-        ))
-        .header("jwt", message.jwt)
-        .send()
-        .await?
-        .error_for_status()
-        .map(|_| ())
+    crud.with_jwt(&message.jwt)
+        .instance_fail_callback(&message.instance_id)
+        .await
         .map_err(|e| e.into())
 }
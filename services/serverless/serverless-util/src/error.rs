@@ -35,6 +35,12 @@ impl<T: std::error::Error + Send + Sync + 'static> From<Box<T>> for Error {
     }
 }
 
+impl From<errors::ApiError> for Error {
+    fn from(err: errors::ApiError) -> Self {
+        Error::new(err)
+    }
+}
+
 impl From<reqwest::Error> for Error {
     fn from(err: reqwest::Error) -> Self {
         Error::new(format!("[Request Error] {:?}", err))